@@ -8,27 +8,97 @@ use std::io::prelude::*;
 use std::path::PathBuf;
 use std::time::Instant;
 use structopt::StructOpt;
-use tracing::info;
+use tracing::{info, warn};
 
 #[derive(StructOpt)]
 enum Command {
     /// initialize a catlang project
-    Init {},
+    Init {
+        /// package name; defaults to the directory's name
+        #[structopt(long)]
+        name: Option<String>,
+        /// create a library instead of a program
+        #[structopt(long)]
+        lib: bool,
+        /// create a script instead of a program
+        #[structopt(long, conflicts_with = "lib")]
+        script: bool,
+        /// replace an existing package.yaml
+        #[structopt(short, long)]
+        force: bool,
+        /// directory to create the project in; defaults to the current one
+        #[structopt(name = "PATH", parse(from_os_str))]
+        path: Option<PathBuf>,
+    },
     /// build a catlang project
     Build {
         /// output binary name
         #[structopt(short, long, parse(from_os_str))]
         output: Option<PathBuf>,
-        /// optimization level
-        #[structopt(short = "O", default_value = "2", possible_values = &["0", "1", "2", "3"])]
-        optimization: u8,
+        /// optimization level, or s or z to optimize for size; defaults to 2, or
+        /// for packages to the profile's
+        #[structopt(short = "O", possible_values = &["0", "1", "2", "3", "s", "z"])]
+        optimization: Option<catlang::codegen::optimize::OptLevel>,
+        /// build the package with the release profile
+        #[structopt(long)]
+        release: bool,
+        /// build the package with this profile; defaults to debug
+        #[structopt(long, conflicts_with = "release")]
+        profile: Option<String>,
+        /// features to turn on, separated by commas
+        #[structopt(long, use_delimiter = true)]
+        features: Vec<String>,
+        /// do not turn on the packages' default features
+        #[structopt(long)]
+        no_default_features: bool,
+        /// number of modules to compile at once; defaults to the number of CPUs
+        #[structopt(short, long)]
+        jobs: Option<usize>,
+        /// option to pass on to LLVM, like -print-after-all; can be repeated
+        #[structopt(long, allow_hyphen_values = true, number_of_values = 1)]
+        llvm_args: Vec<String>,
+        /// print each optimization pass as it runs
+        #[structopt(long)]
+        print_passes: bool,
+        /// verify each module after every optimization pass
+        #[structopt(long)]
+        verify_each: bool,
+        /// build only this member of the workspace
+        #[structopt(short, long, conflicts_with = "INPUT")]
+        package: Option<String>,
         /// LLVM target triple
         #[structopt(long, default_value = &catlang::codegen::DEFAULT_TARGET_TRIPLE)]
         target: String,
-        /// application entry point
+        /// what to emit: a binary, or a C header declaring the module's exports
+        #[structopt(long, default_value = "binary", possible_values = &["binary", "c-header"])]
+        emit: String,
+        /// application entry point; defaults to building the package in the current directory
+        #[structopt(name = "INPUT", parse(from_os_str))]
+        input: Option<PathBuf>,
+    },
+    /// generate bindings for calling a catlang module from another language
+    Bindgen {
+        /// generate Rust bindings
+        #[structopt(long)]
+        rust: bool,
+        /// output file name
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+        /// module to generate bindings for
         #[structopt(name = "INPUT", parse(from_os_str))]
         input: PathBuf,
     },
+    /// compile and run a catlang script
+    Run {
+        /// script to run
+        #[structopt(name = "INPUT", parse(from_os_str))]
+        input: PathBuf,
+        /// arguments passed to the script
+        #[structopt(name = "ARGS")]
+        args: Vec<String>,
+    },
+    /// start an interactive catlang session
+    Repl {},
     /// auto-format a catlang project
     Fmt {},
     /// start the catlang language server
@@ -73,6 +143,61 @@ fn main() {
     );
 }
 
+/// Reports every error, one per line.
+fn errors_to_anyhow<E: ToString>(errors: Vec<E>) -> anyhow::Error {
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    anyhow::anyhow!(messages.join("\n"))
+}
+
+/// The workspace the current directory is in.
+fn workspace() -> anyhow::Result<catlang::package::Workspace> {
+    catlang::package::Workspace::discover(".")?
+        .ok_or_else(|| anyhow::anyhow!("no {} found", catlang::package::MANIFEST_FILE))
+}
+
+/// The workspace member named `name`, or else the package the current
+/// directory is in.
+fn package(name: Option<&str>) -> anyhow::Result<catlang::package::Project> {
+    match name {
+        Some(name) => workspace()?
+            .member(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("the workspace has no package named {}", name)),
+        None => catlang::package::Project::discover(".")?
+            .ok_or_else(|| anyhow::anyhow!("no {} found", catlang::package::MANIFEST_FILE)),
+    }
+}
+
+/// Reads REPL inputs from standard input until it ends or `:quit` is
+/// entered. An input continues over several lines while brackets are left
+/// open.
+fn repl() -> anyhow::Result<()> {
+    let context = catlang::Context::create();
+    let mut repl = catlang::codegen::repl::Repl::new(&context);
+    let stdin = std::io::stdin();
+    let mut buffer = String::new();
+    loop {
+        print!("{}", if buffer.is_empty() { "> " } else { ". " });
+        std::io::stdout().flush()?;
+        if stdin.lock().read_line(&mut buffer)? == 0 {
+            return Ok(());
+        }
+        if catlang::codegen::repl::open_brackets(&buffer) > 0 {
+            continue;
+        }
+        let input = std::mem::take(&mut buffer);
+        match input.trim() {
+            "" => {}
+            ":quit" | ":q" => return Ok(()),
+            input => match repl.eval(input) {
+                Ok(Some(output)) => println!("{}", output),
+                Ok(None) => {}
+                Err(e) => eprintln!("error: {}", e),
+            },
+        }
+    }
+}
+
 fn run(opt: &Opt) -> anyhow::Result<()> {
     let verbose_num = opt.verbose + 1;
     let max_log_level = match verbose_num {
@@ -88,33 +213,176 @@ fn run(opt: &Opt) -> anyhow::Result<()> {
             .init();
     }
 
+    // LLVM's options are global, so they are set before anything is built.
+    let passes = match &opt.command {
+        Command::Build {
+            llvm_args,
+            print_passes,
+            verify_each,
+            ..
+        } => {
+            catlang::codegen::optimize::set_llvm_args(llvm_args)?;
+            catlang::codegen::optimize::PassDebugging {
+                print_passes: *print_passes,
+                verify_each: *verify_each,
+            }
+        }
+        _ => catlang::codegen::optimize::PassDebugging::default(),
+    };
+
     match &opt.command {
-        Command::Init {} => {
+        Command::Init {
+            name,
+            lib,
+            script,
+            force,
+            path,
+        } => {
             info!("Initializing...");
+            let template = match (lib, script) {
+                (true, _) => catlang::package::Template::Library,
+                (_, true) => catlang::package::Template::Script,
+                _ => catlang::package::Template::Binary,
+            };
+            let directory = path.clone().unwrap_or_else(|| PathBuf::from("."));
+            let written = catlang::package::init(&directory, name.as_deref(), template, *force)?;
+            for path in written {
+                info!("Wrote {}", path.display());
+            }
         }
         Command::Build {
-            output: _output,
-            optimization,
+            output,
+            emit,
+            package: name,
             input,
+            ..
+        } if emit == "c-header" => {
+            info!("Generating C header...");
+            let input = match input {
+                Some(input) => input.clone(),
+                None => package(name.as_deref())?
+                    .main()
+                    .ok_or_else(|| anyhow::anyhow!("the package has no main file"))?,
+            };
+            let input = &input;
+            let contents = fs::read_to_string(input)?;
+            let program = catlang::syntax::parser::parse(&contents).map_err(errors_to_anyhow)?;
+            let name = input.to_string_lossy();
+            for unit in program.body().iter() {
+                let catlang::syntax::ast::SourceUnit::Module(module) = unit.value;
+                let header = catlang::interop::c_export::generate_header(&name, &module)
+                    .map_err(errors_to_anyhow)?;
+                let path = output.clone().unwrap_or_else(|| input.with_extension("h"));
+                fs::write(&path, header)?;
+                info!("Wrote {}", path.display());
+            }
+        }
+        Command::Build {
+            output,
+            optimization,
+            release,
+            profile,
+            features,
+            no_default_features,
+            jobs,
+            package,
+            input: None,
             target,
+            ..
+        } => {
+            info!("Building package...");
+            let workspace = workspace()?;
+            let profile = match (release, profile) {
+                (_, Some(profile)) => profile,
+                (true, None) => "release",
+                (false, None) => "debug",
+            };
+            let mut profile = catlang::package::Profile::resolve(profile, &workspace.profiles)?;
+            profile.opt_level = optimization.unwrap_or(profile.opt_level);
+            let mut options = catlang::package::BuildOptions::new(profile);
+            options.target = target.clone();
+            options.features = features.clone();
+            options.default_features = !no_default_features;
+            options.jobs = jobs.unwrap_or(options.jobs);
+            options.passes = passes.clone();
+            let artifacts = catlang::package::build(&workspace, package.as_deref(), &options)?;
+            match (output, artifacts.as_slice()) {
+                (Some(output), [artifact]) => {
+                    fs::copy(artifact.path(), output)?;
+                    info!("Wrote {}", output.display());
+                }
+                (Some(_), _) => {
+                    anyhow::bail!("--output needs a single package to build; select one with -p")
+                }
+                (None, artifacts) => {
+                    for artifact in artifacts {
+                        info!("Wrote {}", artifact);
+                    }
+                }
+            }
+        }
+        Command::Build {
+            output,
+            optimization,
+            input: Some(input),
+            target,
+            ..
         } => {
             info!("Building...");
             let file_metadata = fs::metadata(input.clone())?;
             info!("File size: {}", HumanBytes(file_metadata.len()));
-            let mut file = fs::File::open(input)?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
+            let modules = catlang::build::find_modules(std::slice::from_ref(input))?;
             info!("Read file contents");
-            let parsed = catlang::syntax::parser::parse(&contents).unwrap();
-            info!("Parsed file");
-            let body = parsed.body();
-            info!("Body: {:?}", body);
-            // let _out_fname = m.value_of("_output").unwrap_or("out.c");
-            // catlang::syntax::codegen::llvm::codegen(main_block, out_fname);
-
-            // codegen
-            println!("{}", catlang::codegen::run(*optimization, target)?);
+            let mut session = catlang::Session::new();
+            session
+                .opt_level(optimization.unwrap_or(catlang::codegen::optimize::OptLevel::Default))
+                .passes(passes.clone());
+            // Modules come after those they import, so the input comes last;
+            // it is added first for the program to start in it.
+            for path in modules.order.iter().rev() {
+                let source = modules.source(path).unwrap_or_default();
+                session.add_source(path.to_string_lossy(), source);
+            }
+            let output = output.clone().unwrap_or_else(|| input.with_extension("o"));
+            let context = catlang::Context::create();
+            let object = session
+                .write_object(&context, target, &output)
+                .map_err(errors_to_anyhow)?;
+            for warning in &object.warnings {
+                warn!("{}", warning);
+            }
+            println!("{}", object.ir);
+            info!("Wrote {}", output.display());
+        }
+        Command::Bindgen {
+            rust,
+            output,
+            input,
+        } => {
+            if !rust {
+                anyhow::bail!("no language to generate bindings for; use --rust");
+            }
+            info!("Generating Rust bindings...");
+            let contents = fs::read_to_string(input)?;
+            let program = catlang::syntax::parser::parse(&contents).map_err(errors_to_anyhow)?;
+            let name = input.to_string_lossy();
+            for unit in program.body().iter() {
+                let catlang::syntax::ast::SourceUnit::Module(module) = unit.value;
+                let bindings = catlang::interop::rust_bindings::generate_bindings(&name, &module)
+                    .map_err(errors_to_anyhow)?;
+                let path = output.clone().unwrap_or_else(|| input.with_extension("rs"));
+                fs::write(&path, bindings)?;
+                info!("Wrote {}", path.display());
+            }
+        }
+        Command::Run { input, args } => {
+            let contents = fs::read_to_string(input)?;
+            info!("Read file contents");
+            let name = input.to_string_lossy();
+            let exit_code = catlang::codegen::jit::run(&name, &contents, args)?;
+            std::process::exit(exit_code);
         }
+        Command::Repl {} => repl()?,
         Command::Fmt {} => {
             info!("Formatting...");
         }
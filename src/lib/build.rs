@@ -0,0 +1,530 @@
+//! Compiles catlang from Cargo build scripts, like the `cc` crate does for
+//! C:
+//!
+//! ```no_run
+//! // In build.rs's `main`:
+//! catlang::build::Build::new()
+//!     .file("src/shapes.cat")
+//!     .compile("shapes");
+//! ```
+//!
+//! The sources and every catlang module and C header they import, directly
+//! or not, are compiled into a static library in `OUT_DIR`, and Cargo is
+//! told to link it and to rerun the build script when any of them change.
+
+use crate::codegen::module::{exported_items, ExportedItems, ModuleCompiler};
+use crate::codegen::optimize::OptLevel;
+use crate::codegen::{Checks, CodeGen};
+use crate::interop::is_c_header;
+use crate::semantic::cfg::{strip, Config};
+use crate::semantic::entry::initialization_order;
+use crate::syntax::ast::*;
+use crate::syntax::parser::parse;
+use inkwell::context::Context;
+use inkwell::targets::{InitializationConfig, Target};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+use toolshed::Arena;
+
+#[derive(Error, Debug)]
+pub enum BuildError {
+    #[error("environment variable {} is not set; is this running in a build script?", .0)]
+    MissingEnvironmentVariable(&'static str),
+    #[error("invalid value {:?} for {}", value, variable)]
+    InvalidEnvironmentVariable {
+        variable: &'static str,
+        value: String,
+    },
+    #[error("could not read {}: {}", path.display(), source)]
+    CouldNotRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("could not compile {}:\n{}", path.display(), message)]
+    Compile { path: PathBuf, message: String },
+    #[error("could not create the library with {}: {}", archiver, message)]
+    Archive { archiver: String, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, BuildError>;
+
+/// A static library compiled from catlang sources.
+#[derive(Clone, Debug)]
+pub struct Build {
+    files: Vec<PathBuf>,
+    opt_level: Option<OptLevel>,
+    debug: Option<bool>,
+    features: Vec<String>,
+    target: Option<String>,
+    out_dir: Option<PathBuf>,
+    cargo_metadata: bool,
+}
+
+impl Default for Build {
+    fn default() -> Self {
+        Build::new()
+    }
+}
+
+impl Build {
+    /// A build configured by the environment variables Cargo sets for build
+    /// scripts.
+    pub fn new() -> Self {
+        Build {
+            files: vec![],
+            opt_level: None,
+            debug: None,
+            features: vec![],
+            target: None,
+            out_dir: None,
+            cargo_metadata: true,
+        }
+    }
+
+    /// Adds a source file. Modules it imports are found and compiled as
+    /// well, so only the modules Rust calls into need to be added.
+    pub fn file<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn files<P, I>(&mut self, paths: I) -> &mut Self
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = P>,
+    {
+        for path in paths {
+            self.file(path);
+        }
+        self
+    }
+
+    /// Sets the optimization level instead of using Cargo's `OPT_LEVEL`.
+    pub fn opt_level(&mut self, opt_level: OptLevel) -> &mut Self {
+        self.opt_level = Some(opt_level);
+        self
+    }
+
+    /// Whether to compile with assertions and overflow checks, and with the
+    /// `debug` name set for `#[cfg]`, instead of only when Cargo's `PROFILE`
+    /// is `debug`.
+    pub fn debug(&mut self, debug: bool) -> &mut Self {
+        self.debug = Some(debug);
+        self
+    }
+
+    /// Turns on `feature`, for `#[cfg(feature = "...")]`.
+    pub fn feature(&mut self, feature: &str) -> &mut Self {
+        self.features.push(feature.to_string());
+        self
+    }
+
+    /// Sets the LLVM target triple, instead of using Cargo's `TARGET`.
+    pub fn target(&mut self, target: &str) -> &mut Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    /// Sets where the library is written, instead of Cargo's `OUT_DIR`.
+    pub fn out_dir<P: AsRef<Path>>(&mut self, out_dir: P) -> &mut Self {
+        self.out_dir = Some(out_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Whether to print the `cargo:` lines telling Cargo to link the
+    /// library and when to rerun the build script. Enabled by default.
+    pub fn cargo_metadata(&mut self, cargo_metadata: bool) -> &mut Self {
+        self.cargo_metadata = cargo_metadata;
+        self
+    }
+
+    /// Compiles the library `lib<name>.a`, panicking with the compiler's
+    /// errors if it fails, as build scripts usually should.
+    pub fn compile(&self, name: &str) {
+        if let Err(e) = self.try_compile(name) {
+            panic!("\n\nfailed to compile catlang library {}: {}\n\n", name, e);
+        }
+    }
+
+    /// Compiles the library `lib<name>.a`.
+    pub fn try_compile(&self, name: &str) -> Result<()> {
+        let out_dir = match &self.out_dir {
+            Some(out_dir) => out_dir.clone(),
+            None => PathBuf::from(environment("OUT_DIR")?),
+        };
+        let opt_level = match self.opt_level {
+            Some(opt_level) => opt_level,
+            None => opt_level(&environment("OPT_LEVEL")?)?,
+        };
+        let target = match &self.target {
+            Some(target) => target.clone(),
+            None => environment("TARGET")?,
+        };
+        let debug = match self.debug {
+            Some(debug) => debug,
+            None => env::var("PROFILE").ok().as_deref() == Some("debug"),
+        };
+        let mut config = Config::for_target(&target);
+        if debug {
+            config.flag("debug");
+        }
+        for feature in &self.features {
+            config.feature(feature);
+        }
+
+        let modules = find_modules(&self.files)?;
+        let object = out_dir.join(format!("{}.o", name));
+        let checks = match debug {
+            true => Checks::all(),
+            false => Checks::default(),
+        };
+        compile_modules(name, &modules, opt_level, &target, &config, checks, &object)?;
+        let library = out_dir.join(format!("lib{}.a", name));
+        archive(&target, &library, &[&object])?;
+
+        if self.cargo_metadata {
+            println!("cargo:rustc-link-search=native={}", out_dir.display());
+            println!("cargo:rustc-link-lib=static={}", name);
+            for path in modules.order.iter().chain(&modules.headers) {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn environment(variable: &'static str) -> Result<String> {
+    env::var(variable).map_err(|_| BuildError::MissingEnvironmentVariable(variable))
+}
+
+/// Maps Cargo's `OPT_LEVEL`, which has the same levels, onto an
+/// optimization level.
+fn opt_level(value: &str) -> Result<OptLevel> {
+    value
+        .parse()
+        .map_err(|_| BuildError::InvalidEnvironmentVariable {
+            variable: "OPT_LEVEL",
+            value: value.to_string(),
+        })
+}
+
+/// The modules a library is compiled from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Modules {
+    /// Every catlang module, each after the modules it imports.
+    pub order: Vec<PathBuf>,
+    /// Every C header the modules import.
+    pub headers: Vec<PathBuf>,
+    /// The catlang modules each module imports, with the imports naming
+    /// them as written.
+    pub imports: HashMap<PathBuf, Vec<(String, PathBuf)>>,
+    sources: HashMap<PathBuf, String>,
+}
+
+impl Modules {
+    /// The source of the module `path`, as it was read.
+    pub fn source(&self, path: &Path) -> Option<&str> {
+        self.sources.get(path).map(String::as_str)
+    }
+}
+
+/// Finds the modules `files` import, directly or not. Imports are relative
+/// to the importing module, and name `.cat` files with or without their
+/// extension. Imports of modules that are not files, such as the standard
+/// library's, are left to the compiler.
+pub fn find_modules(files: &[PathBuf]) -> Result<Modules> {
+    find_modules_with(files, |_, _| None)
+}
+
+/// Finds the modules `files` import like [`find_modules`], resolving
+/// imports that name no file relative to the importing module with
+/// `resolve`, which is given the importing module and the import.
+pub fn find_modules_with<F>(files: &[PathBuf], resolve: F) -> Result<Modules>
+where
+    F: Fn(&Path, &str) -> Option<PathBuf>,
+{
+    let mut modules = Modules::default();
+    let mut pending = files.to_vec();
+    while let Some(path) = pending.pop() {
+        if modules.sources.contains_key(&path) {
+            continue;
+        }
+        let source = fs::read_to_string(&path).map_err(|source| BuildError::CouldNotRead {
+            path: path.clone(),
+            source,
+        })?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut imported = vec![];
+        for import in import_paths(&path, &source)? {
+            if is_c_header(&import) {
+                let header = normalize(&directory.join(&import));
+                if header.exists() && !modules.headers.contains(&header) {
+                    modules.headers.push(header);
+                }
+                continue;
+            }
+            let mut module = import_path(&path, &import);
+            if !module.exists() {
+                match resolve(&path, &import) {
+                    Some(resolved) => module = resolved,
+                    None => continue,
+                }
+            }
+            pending.push(module.clone());
+            imported.push((import, module));
+        }
+        modules.imports.insert(path.clone(), imported);
+        modules.sources.insert(path, source);
+    }
+
+    let imports: HashMap<&str, Vec<&str>> = modules
+        .imports
+        .iter()
+        .filter_map(|(module, imported)| {
+            let imported = imported.iter().filter_map(|(_, i)| i.to_str()).collect();
+            Some((module.to_str()?, imported))
+        })
+        .collect();
+    for file in files {
+        for module in initialization_order(&file.to_string_lossy(), &imports) {
+            let module = PathBuf::from(module);
+            if !modules.order.contains(&module) {
+                modules.order.push(module);
+            }
+        }
+    }
+    Ok(modules)
+}
+
+/// Removes the `.` components relative imports start with, so that every
+/// import of a module names it the same way.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+/// The path of the catlang module `import` names, relative to the module
+/// `importer`.
+pub(crate) fn import_path(importer: &Path, import: &str) -> PathBuf {
+    let directory = importer.parent().unwrap_or_else(|| Path::new(""));
+    let mut module = normalize(&directory.join(import));
+    if module.extension().is_none() {
+        module.set_extension("cat");
+    }
+    module
+}
+
+/// The paths a module imports, as written.
+fn import_paths(path: &Path, source: &str) -> Result<Vec<String>> {
+    let program = parse(source).map_err(|errors| compile_error(path, &errors))?;
+    let mut paths = vec![];
+    for unit in program.body().iter() {
+        let SourceUnit::Module(module) = unit.value;
+        for element in module.elements.iter() {
+            if let ModuleElement::Import(import) = element.value {
+                paths.push(import.value.path.value.trim_matches('"').to_string());
+            }
+        }
+    }
+    Ok(paths)
+}
+
+fn compile_error<E: ToString>(path: &Path, errors: &[E]) -> BuildError {
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    BuildError::Compile {
+        path: path.to_path_buf(),
+        message: messages.join("\n"),
+    }
+}
+
+/// Compiles `modules` into a single object file, without the declarations
+/// `#[cfg]` disables. Their initializers run before the program linking
+/// them starts.
+fn compile_modules(
+    name: &str,
+    modules: &Modules,
+    opt_level: OptLevel,
+    target: &str,
+    config: &Config,
+    checks: Checks,
+    object: &Path,
+) -> Result<()> {
+    let error = |path: &Path, message: String| BuildError::Compile {
+        path: path.to_path_buf(),
+        message,
+    };
+    Target::initialize_all(&InitializationConfig::default());
+    let context = Context::create();
+    let mut codegen = CodeGen::named(&context, name);
+    codegen.checks = checks;
+    let mut programs = vec![];
+    for path in &modules.order {
+        let source = &modules.sources[path];
+        let program = parse(source).map_err(|errors| compile_error(path, &errors))?;
+        programs.push((path, program));
+    }
+    let arena = Arena::new();
+    let mut initializers = vec![];
+    // Modules come after the modules they import, so what those export is
+    // known by the time they are compiled.
+    let mut exports: HashMap<&Path, Vec<ExportedItems>> = HashMap::new();
+    for (path, program) in &programs {
+        let module_name = path.to_string_lossy();
+        let mut items = vec![];
+        for unit in program.body().iter() {
+            let SourceUnit::Module(module) = unit.value;
+            let module = strip(&arena, &module, config).map_err(|e| error(path, e.to_string()))?;
+            let imports: Vec<(&str, &ExportedItems)> = modules
+                .imports
+                .get(*path)
+                .into_iter()
+                .flatten()
+                .flat_map(|(import, imported)| {
+                    let items = exports.get(imported.as_path()).into_iter().flatten();
+                    items.map(move |items| (import.as_str(), items))
+                })
+                .collect();
+            let compiled = ModuleCompiler::with_imports(&codegen, &module_name, &module, &imports)
+                .and_then(|mut compiler| {
+                    let compiled = compiler.compile(&module)?;
+                    for warning in compiler.warnings() {
+                        println!("cargo:warning={}: {}", path.display(), warning);
+                    }
+                    Ok(compiled)
+                })
+                .and_then(|compiled| {
+                    items.push(exported_items(&module_name, &module, &imports)?);
+                    Ok(compiled)
+                })
+                .map_err(|e| error(path, e.to_string()))?;
+            initializers.extend(compiled.initializer);
+        }
+        exports.insert(path, items);
+    }
+    if !initializers.is_empty() {
+        codegen.build_constructor(&initializers);
+    }
+    codegen
+        .module
+        .verify()
+        .map_err(|e| error(Path::new(name), e.to_string()))?;
+    codegen
+        .write_output_file(opt_level, target, object)
+        .map_err(|e| error(Path::new(name), e.to_string()))
+}
+
+/// Puts `objects` in the static library `library`, with the archiver named
+/// by `AR_<target>` or `AR`, or else `ar`, like the `cc` crate does.
+pub(crate) fn archive<P: AsRef<Path>>(target: &str, library: &Path, objects: &[P]) -> Result<()> {
+    let archiver = env::var(format!("AR_{}", target.replace('-', "_")))
+        .or_else(|_| env::var("AR"))
+        .unwrap_or_else(|_| "ar".to_string());
+    // `ar` adds to existing archives, which may hold stale objects.
+    let _ = fs::remove_file(library);
+    let output = Command::new(&archiver)
+        .arg("crs")
+        .arg(library)
+        .args(objects.iter().map(AsRef::as_ref))
+        .output()
+        .map_err(|e| BuildError::Archive {
+            archiver: archiver.clone(),
+            message: e.to_string(),
+        })?;
+    if !output.status.success() {
+        return Err(BuildError::Archive {
+            archiver,
+            message: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::DEFAULT_TARGET_TRIPLE;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_opt_levels() {
+        assert_eq!(opt_level("0").unwrap(), OptLevel::None);
+        assert_eq!(opt_level("3").unwrap(), OptLevel::Aggressive);
+        assert_eq!(opt_level("s").unwrap(), OptLevel::Size);
+        assert_eq!(opt_level("z").unwrap(), OptLevel::MinSize);
+        assert!(opt_level("fast").is_err());
+    }
+
+    #[test]
+    fn test_transitive_imports() {
+        let directory = env::temp_dir().join("catlang-build-test-transitive-imports");
+        fs::create_dir_all(directory.join("geometry")).unwrap();
+        let write = |path: &str, source: &str| fs::write(directory.join(path), source).unwrap();
+        write(
+            "shapes.cat",
+            "import { area } from \"./geometry/area\";\nimport * as io from \"io\";\nexport area;",
+        );
+        write(
+            "geometry/area.cat",
+            "import { PI } from \"./constants.cat\";\nimport { point } from \"point.h\";\nexport function area(r: f64) -> f64 { return PI * r * r; }",
+        );
+        write("geometry/constants.cat", "export const PI = 3;");
+        write("geometry/point.h", "struct point { int x; int y; };");
+
+        let modules = find_modules(&[directory.join("shapes.cat")]).unwrap();
+        assert_eq!(
+            modules.order,
+            vec![
+                directory.join("geometry/constants.cat"),
+                directory.join("geometry/area.cat"),
+                directory.join("shapes.cat"),
+            ]
+        );
+        assert_eq!(modules.headers, vec![directory.join("geometry/point.h")]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_imported_modules_link() {
+        let directory = env::temp_dir().join("catlang-build-test-imported-modules-link");
+        fs::create_dir_all(&directory).unwrap();
+        let write = |path: &str, source: &str| fs::write(directory.join(path), source).unwrap();
+        write(
+            "counter.cat",
+            "export let base: s32 = 20;\nexport function twice(x: s32) -> s32 { return x * 2; }",
+        );
+        write(
+            "answer.cat",
+            "import { base, twice } from \"./counter\";\nexport twice;\n#[extern(\"C\")]\nexport function answer() -> s32 { return twice(base) + 2; }",
+        );
+        write(
+            "main.c",
+            "int answer(void);\nint main(void) { return answer(); }",
+        );
+
+        Build::new()
+            .file(directory.join("answer.cat"))
+            .opt_level(OptLevel::None)
+            .debug(false)
+            .target(DEFAULT_TARGET_TRIPLE.as_str())
+            .out_dir(&directory)
+            .cargo_metadata(false)
+            .try_compile("answer")
+            .unwrap();
+        let status = Command::new("cc")
+            .arg(directory.join("main.c"))
+            .arg(directory.join("libanswer.a"))
+            .arg("-o")
+            .arg(directory.join("answer"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let status = Command::new(directory.join("answer")).status().unwrap();
+        assert_eq!(status.code(), Some(42));
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
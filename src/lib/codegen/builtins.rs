@@ -0,0 +1,224 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{span, ModuleCompiler, Value};
+use crate::codegen::statements::int_value;
+use crate::semantic::error::Error;
+use crate::semantic::types::Type;
+use crate::syntax::ast::*;
+use inkwell::module::Linkage;
+use inkwell::values::{BasicMetadataValueEnum, FunctionValue};
+use inkwell::AddressSpace;
+
+/// Functions every script can call without declaring them. A function the
+/// module declares itself takes precedence over a builtin of the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Builtin {
+    /// `print(value)` writes a string, number, character or bool followed
+    /// by a newline to standard output.
+    Print,
+    /// `assert(condition)` aborts the program if `condition` is false, in
+    /// builds with assertions. The condition is evaluated either way.
+    Assert,
+}
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "print" => Some(Builtin::Print),
+            "assert" => Some(Builtin::Assert),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    pub(super) fn compile_builtin(
+        &mut self,
+        builtin: Builtin,
+        call: &ExpressionNode<'ast>,
+        arguments: &[Value<'ctx>],
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        match builtin {
+            Builtin::Print => self.compile_print(call, arguments),
+            Builtin::Assert => self.compile_assert(call, arguments),
+        }
+    }
+
+    fn compile_assert(
+        &mut self,
+        call: &ExpressionNode<'ast>,
+        arguments: &[Value<'ctx>],
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let condition = match arguments {
+            [condition] => condition,
+            _ => {
+                let types: Vec<String> = arguments.iter().map(|a| a.ty.to_string()).collect();
+                return Err(Error::NoMatchingOverload {
+                    name: "assert".to_string(),
+                    arguments: types.join(", "),
+                    candidates: vec![],
+                    span: span(call),
+                }
+                .into());
+            }
+        };
+        let boolean = Type::Primitive(PrimitiveType::Bool);
+        let condition = int_value(&self.convert(condition.clone(), &boolean, span(call))?)?;
+        if self.codegen.checks.assertions {
+            let failed = self.codegen.builder.build_not(condition, "failed");
+            self.build_trap_if(failed)?;
+        }
+        Ok(Value {
+            value: None,
+            ty: Type::Primitive(PrimitiveType::CVoid),
+        })
+    }
+
+    fn compile_print(
+        &mut self,
+        call: &ExpressionNode<'ast>,
+        arguments: &[Value<'ctx>],
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let no_overload = |ty: String| -> CodeGenError {
+            Error::NoMatchingOverload {
+                name: "print".to_string(),
+                arguments: ty,
+                candidates: vec![],
+                span: span(call),
+            }
+            .into()
+        };
+        let argument = match arguments {
+            [argument] => argument,
+            _ => {
+                let types: Vec<String> = arguments.iter().map(|a| a.ty.to_string()).collect();
+                return Err(no_overload(types.join(", ")));
+            }
+        };
+        match self.print_value(argument, "")? {
+            Some(()) => Ok(Value {
+                value: None,
+                ty: Type::Primitive(PrimitiveType::CVoid),
+            }),
+            None => Err(no_overload(argument.ty.to_string())),
+        }
+    }
+
+    /// Writes `argument` followed by `suffix` and a newline to standard
+    /// output. Returns `None` without emitting anything if values of its type
+    /// cannot be printed.
+    pub(super) fn print_value(
+        &self,
+        argument: &Value<'ctx>,
+        suffix: &str,
+    ) -> Result<Option<()>, CodeGenError> {
+        let value = match argument.value {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let builder = &self.codegen.builder;
+        let context = self.codegen.context;
+        let i64_type = context.i64_type();
+
+        let (format, values): (&str, Vec<BasicMetadataValueEnum<'ctx>>) = match argument
+            .ty
+            .unqualified()
+        {
+            Type::SizedArray(element) | Type::UnsizedArray(element)
+                if element.unqualified() == &Type::Primitive(PrimitiveType::Char) =>
+            {
+                let slice = value.into_struct_value();
+                let data = builder
+                    .build_extract_value(slice, 0, "data")
+                    .ok_or(CodeGenError::Unknown)?;
+                let length = builder
+                    .build_extract_value(slice, 1, "length")
+                    .ok_or(CodeGenError::Unknown)?;
+                let length = builder.build_int_truncate(
+                    length.into_int_value(),
+                    context.i32_type(),
+                    "length",
+                );
+                ("%.*s", vec![length.into(), data.into()])
+            }
+            Type::Primitive(PrimitiveType::Bool) => {
+                let text_true = builder.build_global_string_ptr("true", "true");
+                let text_false = builder.build_global_string_ptr("false", "false");
+                let text = builder.build_select(
+                    value.into_int_value(),
+                    text_true.as_pointer_value(),
+                    text_false.as_pointer_value(),
+                    "bool",
+                );
+                ("%s", vec![text.into()])
+            }
+            Type::Primitive(PrimitiveType::Char) => {
+                let c =
+                    builder.build_int_z_extend(value.into_int_value(), context.i32_type(), "char");
+                ("%c", vec![c.into()])
+            }
+            Type::Primitive(pt) => match (pt.integer_layout(), pt.float_width()) {
+                (Some((true, _)), _) => {
+                    let v = builder.build_int_s_extend_or_bit_cast(
+                        value.into_int_value(),
+                        i64_type,
+                        "int",
+                    );
+                    ("%lld", vec![v.into()])
+                }
+                (Some((false, _)), _) => {
+                    let v = builder.build_int_z_extend_or_bit_cast(
+                        value.into_int_value(),
+                        i64_type,
+                        "int",
+                    );
+                    ("%llu", vec![v.into()])
+                }
+                (_, Some(_)) => {
+                    let v = builder.build_float_ext(
+                        value.into_float_value(),
+                        context.f64_type(),
+                        "float",
+                    );
+                    ("%g", vec![v.into()])
+                }
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        let printf = self.printf();
+        let format = format!("{}{}\n", format, suffix.replace('%', "%%"));
+        let format = builder.build_global_string_ptr(&format, "format");
+        let mut all: Vec<BasicMetadataValueEnum<'ctx>> = vec![format.as_pointer_value().into()];
+        all.extend(values);
+        builder.build_call(printf, &all, "print");
+        Ok(Some(()))
+    }
+
+    /// Flushes everything printed so far, so that output written by the
+    /// compiler itself appears after it.
+    pub(super) fn flush_output(&self) {
+        let module = &self.codegen.module;
+        let context = self.codegen.context;
+        let stream = context.i8_type().ptr_type(AddressSpace::Generic);
+        let fflush = module.get_function("fflush").unwrap_or_else(|| {
+            let fn_type = context.i32_type().fn_type(&[stream.into()], false);
+            module.add_function("fflush", fn_type, Some(Linkage::External))
+        });
+        // `fflush(NULL)` flushes every open stream.
+        self.codegen
+            .builder
+            .build_call(fflush, &[stream.const_null().into()], "flush");
+    }
+
+    /// The C library's `printf`, declared on first use.
+    fn printf(&self) -> FunctionValue<'ctx> {
+        let module = &self.codegen.module;
+        module.get_function("printf").unwrap_or_else(|| {
+            let context = self.codegen.context;
+            let format = context.i8_type().ptr_type(AddressSpace::Generic);
+            let fn_type = context.i32_type().fn_type(&[format.into()], true);
+            module.add_function("printf", fn_type, Some(Linkage::External))
+        })
+    }
+}
@@ -0,0 +1,232 @@
+use crate::codegen::CodeGen;
+use crate::semantic::entry::arguments_type;
+use crate::semantic::types::Type;
+use crate::syntax::ast::PrimitiveType;
+use inkwell::module::Linkage;
+use inkwell::types::{BasicMetadataTypeEnum, StructType};
+use inkwell::values::{BasicMetadataValueEnum, FunctionValue, IntValue, PointerValue, StructValue};
+use inkwell::AddressSpace;
+
+/// The symbol of the function the system starts programs in.
+pub const ENTRY_POINT: &str = "main";
+
+/// The priority of constructors initializing libraries: the lowest, so they
+/// run after those of C and C++ libraries they may call into.
+const CONSTRUCTOR_PRIORITY: u64 = 65535;
+
+/// The function a program starts in: a `function main`, or the top-level
+/// statements of a script.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Start<'ctx> {
+    pub function: FunctionValue<'ctx>,
+    /// Whether it takes the program's arguments as a `[][]char`.
+    pub takes_arguments: bool,
+    /// The signedness and width of the exit code it returns, if any.
+    pub exit_code: Option<(bool, u32)>,
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Emits C's `main`, which runs the module `initializers` in order and
+    /// then calls `start` with the program's arguments. The exit code is what
+    /// `start` returns, converted to a C `int`, or 0.
+    pub fn build_entry_point(
+        &self,
+        start: &Start<'ctx>,
+        initializers: &[FunctionValue<'ctx>],
+    ) -> Option<FunctionValue<'ctx>> {
+        let i32_type = self.context.i32_type();
+        let argv_type = self
+            .context
+            .i8_type()
+            .ptr_type(AddressSpace::Generic)
+            .ptr_type(AddressSpace::Generic);
+        let fn_type = i32_type.fn_type(&[i32_type.into(), argv_type.into()], false);
+        let function = self
+            .module
+            .add_function(ENTRY_POINT, fn_type, Some(Linkage::External));
+        let block = self.context.append_basic_block(function, "entry");
+        self.builder.position_at_end(block);
+
+        for initializer in initializers {
+            self.builder.build_call(*initializer, &[], "");
+        }
+        let mut arguments: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+        if start.takes_arguments {
+            let argc = function.get_nth_param(0)?.into_int_value();
+            let argv = function.get_nth_param(1)?.into_pointer_value();
+            arguments.push(self.build_arguments(argc, argv)?.into());
+        }
+        let call = self.builder.build_call(start.function, &arguments, "start");
+
+        let exit_code = match start.exit_code {
+            Some((signed, bits)) => {
+                let code = call.try_as_basic_value().left()?.into_int_value();
+                if bits > 32 {
+                    self.builder.build_int_truncate(code, i32_type, "exit_code")
+                } else if signed {
+                    self.builder
+                        .build_int_s_extend_or_bit_cast(code, i32_type, "exit_code")
+                } else {
+                    self.builder
+                        .build_int_z_extend_or_bit_cast(code, i32_type, "exit_code")
+                }
+            }
+            None => i32_type.const_zero(),
+        };
+        self.builder.build_return(Some(&exit_code));
+        Some(function)
+    }
+
+    /// Declares the initializer `symbol` of a module compiled into another
+    /// object, for the entry point or constructor to call.
+    pub fn declare_initializer(&self, symbol: &str) -> FunctionValue<'ctx> {
+        self.module.get_function(symbol).unwrap_or_else(|| {
+            let fn_type = self.context.void_type().fn_type(&[], false);
+            self.module
+                .add_function(symbol, fn_type, Some(Linkage::External))
+        })
+    }
+
+    /// Declares the start function `symbol` of a module compiled into
+    /// another object, for the entry point to call.
+    pub fn declare_start(
+        &self,
+        symbol: &str,
+        takes_arguments: bool,
+        exit_code: Option<(bool, u32)>,
+    ) -> Option<Start<'ctx>> {
+        let mut parameters: Vec<BasicMetadataTypeEnum<'ctx>> = vec![];
+        if takes_arguments {
+            parameters.push(self.llvm_type(&arguments_type()).ok()??.into());
+        }
+        let fn_type = match exit_code {
+            Some((_, bits)) => self
+                .context
+                .custom_width_int_type(bits)
+                .fn_type(&parameters, false),
+            None => self.context.void_type().fn_type(&parameters, false),
+        };
+        let function = self
+            .module
+            .add_function(symbol, fn_type, Some(Linkage::External));
+        Some(Start {
+            function,
+            takes_arguments,
+            exit_code,
+        })
+    }
+
+    /// Emits a function running the module `initializers` in order, and
+    /// registers it in `llvm.global_ctors` so the system runs it before the
+    /// program starts. Libraries use this, since the program linking them
+    /// does not start in catlang.
+    pub fn build_constructor(&self, initializers: &[FunctionValue<'ctx>]) -> FunctionValue<'ctx> {
+        let fn_type = self.context.void_type().fn_type(&[], false);
+        let name = format!("{}::init", self.module.get_name().to_string_lossy());
+        let function = self
+            .module
+            .add_function(&name, fn_type, Some(Linkage::Internal));
+        let block = self.context.append_basic_block(function, "entry");
+        self.builder.position_at_end(block);
+        for initializer in initializers {
+            self.builder.build_call(*initializer, &[], "");
+        }
+        self.builder.build_return(None);
+
+        let i32_type = self.context.i32_type();
+        let data_type = self.context.i8_type().ptr_type(AddressSpace::Generic);
+        let entry_type = self.context.struct_type(
+            &[
+                i32_type.into(),
+                fn_type.ptr_type(AddressSpace::Generic).into(),
+                data_type.into(),
+            ],
+            false,
+        );
+        let entry = entry_type.const_named_struct(&[
+            i32_type.const_int(CONSTRUCTOR_PRIORITY, false).into(),
+            function.as_global_value().as_pointer_value().into(),
+            data_type.const_null().into(),
+        ]);
+        let constructors =
+            self.module
+                .add_global(entry_type.array_type(1), None, "llvm.global_ctors");
+        constructors.set_linkage(Linkage::Appending);
+        constructors.set_initializer(&entry_type.const_array(&[entry]));
+        function
+    }
+
+    /// Collects C's `argc` and `argv` into a `[][]char` on the stack.
+    fn build_arguments(
+        &self,
+        argc: IntValue<'ctx>,
+        argv: PointerValue<'ctx>,
+    ) -> Option<StructValue<'ctx>> {
+        let char_slice = Type::SizedArray(Box::new(Type::Primitive(PrimitiveType::Char)));
+        let string_type = self.llvm_type(&char_slice).ok()??.into_struct_type();
+        let arguments_type = self.llvm_type(&arguments_type()).ok()??.into_struct_type();
+        let i64_type = self.context.i64_type();
+
+        let count = self.builder.build_int_z_extend(argc, i64_type, "count");
+        let data = self
+            .builder
+            .build_array_alloca(string_type, count, "arguments");
+        let exclusive = self.context.bool_type().const_zero();
+        let mut stored = Some(());
+        self.build_counted_loop(
+            i64_type.const_zero(),
+            count,
+            exclusive,
+            false,
+            |_, index| stored = self.build_argument(argv, data, index, string_type),
+        )?;
+        stored?;
+
+        let arguments =
+            self.builder
+                .build_insert_value(arguments_type.get_undef(), data, 0, "arguments")?;
+        let arguments = self
+            .builder
+            .build_insert_value(arguments, count, 1, "arguments")?;
+        Some(arguments.into_struct_value())
+    }
+
+    /// Stores `argv[index]` into `data[index]` as a `[]char`.
+    fn build_argument(
+        &self,
+        argv: PointerValue<'ctx>,
+        data: PointerValue<'ctx>,
+        index: IntValue<'ctx>,
+        string_type: StructType<'ctx>,
+    ) -> Option<()> {
+        let argument = unsafe { self.builder.build_in_bounds_gep(argv, &[index], "argument") };
+        let argument = self
+            .builder
+            .build_load(argument, "argument")
+            .into_pointer_value();
+        let length = self
+            .builder
+            .build_call(self.strlen(), &[argument.into()], "length")
+            .try_as_basic_value()
+            .left()?;
+        let string =
+            self.builder
+                .build_insert_value(string_type.get_undef(), argument, 0, "string")?;
+        let string = self
+            .builder
+            .build_insert_value(string, length, 1, "string")?;
+        let slot = unsafe { self.builder.build_in_bounds_gep(data, &[index], "slot") };
+        self.builder.build_store(slot, string.into_struct_value());
+        Some(())
+    }
+
+    /// The C library's `strlen`, declared on first use.
+    fn strlen(&self) -> FunctionValue<'ctx> {
+        self.module.get_function("strlen").unwrap_or_else(|| {
+            let string = self.context.i8_type().ptr_type(AddressSpace::Generic);
+            let fn_type = self.context.i64_type().fn_type(&[string.into()], false);
+            self.module
+                .add_function("strlen", fn_type, Some(Linkage::External))
+        })
+    }
+}
@@ -1,4 +1,6 @@
+use crate::semantic::error::Error;
 use inkwell::support::LLVMString;
+use std::ops::Range;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -7,13 +9,49 @@ pub enum CodeGenError {
     LlvmError(String),
     #[error("could not create target machine")]
     CouldNotCreateTargetMachine,
+    #[error(transparent)]
+    Semantic(#[from] Error),
+    #[error("vtable slot {} of {} does not hold a function", slot, interface)]
+    InvalidVTableSlot { interface: String, slot: u32 },
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+    SemanticErrors(Vec<Error>),
+    #[error("{} cannot be compiled yet at {}:{}", construct, span.start, span.end)]
+    Unsupported {
+        construct: &'static str,
+        span: Range<usize>,
+    },
+    #[error("generic parameter {} was not substituted", .0)]
+    UnsubstitutedGeneric(String),
+    #[error("the program has neither a `main` function nor top-level statements")]
+    NoEntryPoint,
     #[error("unknown error")]
     Unknown,
 }
 
+impl CodeGenError {
+    /// Where in the source the error is, if it is anywhere in particular.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            CodeGenError::Semantic(error) => error.span(),
+            CodeGenError::Unsupported { span, .. } => Some(span.clone()),
+            _ => None,
+        }
+    }
+}
+
 impl From<LLVMString> for CodeGenError {
     fn from(llvm_str: LLVMString) -> Self {
         let llvm_string = llvm_str.to_string();
         CodeGenError::LlvmError(llvm_string)
     }
 }
+
+impl From<Vec<Error>> for CodeGenError {
+    fn from(mut errors: Vec<Error>) -> Self {
+        if errors.len() == 1 {
+            CodeGenError::Semantic(errors.remove(0))
+        } else {
+            CodeGenError::SemanticErrors(errors)
+        }
+    }
+}
@@ -0,0 +1,1252 @@
+use crate::codegen::builtins::Builtin;
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{span, unsupported, ModuleCompiler, Value, Variable};
+use crate::codegen::operators::is_array;
+use crate::codegen::statements::int_value;
+use crate::semantic::constants::{unescape, Constant};
+use crate::semantic::error::Error;
+use crate::semantic::functions::Overload;
+use crate::semantic::types::{FunctionSignature, Type, TypeDefinition, LOCAL_MODULE};
+use crate::syntax::ast::*;
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValueEnum, FloatValue, FunctionValue, IntValue,
+};
+use inkwell::{FloatPredicate, IntPredicate};
+use std::ops::Range;
+
+/// How the primitive operators treat a type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Numeric {
+    Integer { signed: bool, bits: u32 },
+    Float { bits: u32 },
+    Bool,
+}
+
+fn numeric(ty: &Type) -> Option<Numeric> {
+    match ty.unqualified() {
+        Type::Primitive(PrimitiveType::Bool) => Some(Numeric::Bool),
+        Type::Primitive(pt) => match (pt.integer_layout(), pt.float_width()) {
+            (Some((signed, bits)), _) => Some(Numeric::Integer { signed, bits }),
+            (_, Some(bits)) => Some(Numeric::Float { bits }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The types the arguments of a call with `arity` arguments are expected
+/// to have: literals take on a parameter type all candidate overloads agree
+/// on.
+fn expected_arguments(overloads: &[Overload<'_>], arity: usize) -> Vec<Option<Type>> {
+    (0..arity)
+        .map(|i| {
+            let mut candidates = overloads
+                .iter()
+                .filter(|overload| overload.signature.parameters.len() == arity)
+                .map(|overload| &overload.signature.parameters[i]);
+            let first = candidates.next()?;
+            if candidates.all(|ty| ty == first) {
+                Some(first.clone())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// The type of expressions without a value, such as calls to functions
+/// without a return type.
+pub(super) fn void() -> Type {
+    Type::Primitive(PrimitiveType::CVoid)
+}
+
+fn is_literal(expression: &ExpressionNode<'_>) -> bool {
+    match expression.value {
+        Expression::PrimitiveExpression(_) => true,
+        Expression::PrefixExpression(prefix) => {
+            prefix.operator == PrefixOperator::Minus && is_literal(&prefix.operand)
+        }
+        _ => false,
+    }
+}
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Compiles an expression. Number literals take on the `expected` type
+    /// where it is numeric; otherwise it is only a hint.
+    pub(super) fn compile_expression(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        expected: Option<&Type>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        match expression.value {
+            Expression::PrimitiveExpression(primitive) => {
+                self.compile_literal(expression, primitive, expected)
+            }
+            Expression::IdentifierExpression(identifier) => {
+                if let Some(constant) = self.foreign_constant(expression) {
+                    return self.compile_constant(&constant, expected);
+                }
+                let variable = self.lookup(&identifier)?;
+                self.load(variable, identifier.value)
+            }
+            Expression::BinaryExpression(binary) => {
+                self.compile_binary(expression, &binary, expected)
+            }
+            Expression::PrefixExpression(prefix) => {
+                self.compile_prefix(expression, &prefix, expected)
+            }
+            Expression::PostfixExpression(postfix) => match postfix.operator {
+                PostfixOperator::Increment | PostfixOperator::Decrement => {
+                    let increment = postfix.operator == PostfixOperator::Increment;
+                    self.compile_increment(&postfix.operand, increment, false)
+                }
+                _ => Err(unsupported("optional operators", expression)),
+            },
+            Expression::AssignmentExpression(assignment) => {
+                self.compile_assignment(expression, &assignment)
+            }
+            Expression::TernaryExpression(ternary) => {
+                self.compile_ternary(expression, &ternary, expected)
+            }
+            Expression::CallExpression(call) => self.compile_call(expression, &call),
+            Expression::CastExpression(cast) => {
+                let value = self.compile_expression(&cast.left, None)?;
+                let ty = self.env.resolve(&cast.cast_to, &[])?;
+                if ty.interface_pointer().is_some() {
+                    return self.compile_interface_cast(value, &ty, span(expression));
+                }
+                self.cast(value, &ty)
+            }
+            Expression::IndexExpression(index) => self.compile_index(&index),
+            Expression::MemberAccessExpression(access) => {
+                if let Some(constant) = self.foreign_constant(expression) {
+                    return self.compile_constant(&constant, expected);
+                }
+                if let Some(variable) = self.namespace_variable(expression) {
+                    return self.load(variable?, access.member.value);
+                }
+                match self.compile_variant(expression, &access)? {
+                    Some(variant) => Ok(variant),
+                    None => self.compile_member(&access),
+                }
+            }
+            Expression::ConstructorCallExpression(_) => {
+                Err(unsupported("constructor calls", expression))
+            }
+            Expression::ScopedExpression(scoped) => {
+                if let Some(constant) = self.foreign_constant(expression) {
+                    return self.compile_constant(&constant, expected);
+                }
+                match self.namespace_variable(expression) {
+                    Some(variable) => self.load(variable?, scoped.member.value),
+                    None => Err(unsupported("scoped names", expression)),
+                }
+            }
+            Expression::TryExpression(try_expression) => {
+                self.compile_try(expression, &try_expression)
+            }
+            Expression::LambdaExpression(_) => Err(unsupported("lambdas", expression)),
+        }
+    }
+
+    /// Reads the value of a variable.
+    fn load(&self, variable: Variable<'ctx>, name: &str) -> Result<Value<'ctx>, CodeGenError> {
+        let value = self.codegen.builder.build_load(variable.pointer, name);
+        Ok(Value {
+            value: Some(value),
+            ty: variable.ty,
+        })
+    }
+
+    fn compile_literal(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        primitive: Primitive<'ast>,
+        expected: Option<&Type>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let context = self.codegen.context;
+        let expected_numeric = expected.and_then(numeric);
+        let integer = |digits: &str, radix| {
+            u64::from_str_radix(&digits.replace('_', ""), radix)
+                .map_err(|_| unsupported("integer literals wider than 64 bits", expression))
+        };
+        let value = match primitive {
+            Primitive::Bool(b) => Value {
+                value: Some(context.bool_type().const_int(b as u64, false).into()),
+                ty: Type::Primitive(PrimitiveType::Bool),
+            },
+            Primitive::DecimalNumber(digits) => {
+                self.integer_literal(integer(digits, 10)?, expected)
+            }
+            Primitive::HexadecimalNumber(digits) => {
+                self.integer_literal(integer(&digits[2..], 16)?, expected)
+            }
+            Primitive::OctalNumber(digits) => {
+                self.integer_literal(integer(&digits[2..], 8)?, expected)
+            }
+            Primitive::BinaryNumber(digits) => {
+                self.integer_literal(integer(&digits[2..], 2)?, expected)
+            }
+            Primitive::DecimalFloat(digits) => {
+                let value: f64 = digits
+                    .parse()
+                    .map_err(|_| unsupported("this float literal", expression))?;
+                let ty = match (expected, expected_numeric) {
+                    (Some(expected), Some(Numeric::Float { .. })) => expected.clone(),
+                    _ => Type::Primitive(PrimitiveType::F64),
+                };
+                let float_type = self.codegen.llvm_type(&ty)?.ok_or(CodeGenError::Unknown)?;
+                Value {
+                    value: Some(float_type.into_float_type().const_float(value).into()),
+                    ty,
+                }
+            }
+            Primitive::Char(quoted) => {
+                let bytes = unescape(&quoted[1..quoted.len() - 1]);
+                let byte = bytes.first().copied().unwrap_or_default();
+                Value {
+                    value: Some(context.i8_type().const_int(byte as u64, false).into()),
+                    ty: Type::Primitive(PrimitiveType::Char),
+                }
+            }
+            Primitive::String(quoted) => {
+                let bytes = unescape(&quoted[1..quoted.len() - 1]);
+                let text = String::from_utf8_lossy(&bytes);
+                let data = self.codegen.builder.build_global_string_ptr(&text, "str");
+                let length = context.i64_type().const_int(text.len() as u64, false);
+                let slice =
+                    context.const_struct(&[data.as_pointer_value().into(), length.into()], false);
+                Value {
+                    value: Some(slice.into()),
+                    ty: Type::SizedArray(Box::new(Type::Primitive(PrimitiveType::Char))),
+                }
+            }
+            // `null` is the empty optional, or the `null` member of a union.
+            Primitive::Null => match expected {
+                Some(ty) if matches!(ty.unqualified(), Type::Optional(_)) => {
+                    let llvm_type = self.codegen.llvm_type(ty)?.ok_or(CodeGenError::Unknown)?;
+                    Value {
+                        value: Some(llvm_type.const_zero()),
+                        ty: ty.clone(),
+                    }
+                }
+                Some(ty) if ty.contains(&Type::Primitive(PrimitiveType::Null)) => {
+                    let null = Value {
+                        value: None,
+                        ty: Type::Primitive(PrimitiveType::Null),
+                    };
+                    self.build_union(null, ty, span(expression))?
+                }
+                _ => return Err(unsupported("null outside of optionals", expression)),
+            },
+        };
+        Ok(value)
+    }
+
+    /// Integer literals are `int`, or `s64` if they do not fit, unless a
+    /// numeric type is expected.
+    fn integer_literal(&self, value: u64, expected: Option<&Type>) -> Value<'ctx> {
+        let ty = match (expected, expected.and_then(numeric)) {
+            (Some(expected), Some(Numeric::Integer { .. })) => expected.clone(),
+            (Some(expected), Some(Numeric::Float { .. })) => {
+                let float_type = self
+                    .codegen
+                    .llvm_type(expected)
+                    .ok()
+                    .flatten()
+                    .map(|t| t.into_float_type());
+                if let Some(float_type) = float_type {
+                    return Value {
+                        value: Some(float_type.const_float(value as f64).into()),
+                        ty: expected.clone(),
+                    };
+                }
+                Type::Primitive(PrimitiveType::S32)
+            }
+            _ if value > i32::MAX as u64 => Type::Primitive(PrimitiveType::S64),
+            _ => Type::Primitive(PrimitiveType::S32),
+        };
+        let int_type = self
+            .codegen
+            .llvm_type(&ty)
+            .ok()
+            .flatten()
+            .map(|t| t.into_int_type())
+            .unwrap_or_else(|| self.codegen.context.i32_type());
+        Value {
+            value: Some(int_type.const_int(value, false).into()),
+            ty,
+        }
+    }
+
+    pub(super) fn compile_binary(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        binary: &BinaryExpression<'ast>,
+        expected: Option<&Type>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        match binary.operator {
+            BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => {
+                let left = self.compile_expression(&binary.left, None)?;
+                return self.compile_logical(
+                    binary.operator,
+                    left,
+                    &binary.right,
+                    span(expression),
+                );
+            }
+            BinaryOperator::RangeExclusive | BinaryOperator::RangeInclusive => {
+                return self.compile_range(expression, binary, expected)
+            }
+            BinaryOperator::NullCoalesce => {
+                let left = self.compile_expression(&binary.left, None)?;
+                return self.compile_null_coalesce(left, &binary.right, span(expression));
+            }
+            BinaryOperator::In | BinaryOperator::Cast | BinaryOperator::ForcedCast => {
+                return Err(unsupported("this operator", expression))
+            }
+            _ => {}
+        }
+        let is_comparison = matches!(
+            binary.operator,
+            BinaryOperator::Equals
+                | BinaryOperator::NotEquals
+                | BinaryOperator::LessThan
+                | BinaryOperator::LessThanEquals
+                | BinaryOperator::GreaterThan
+                | BinaryOperator::GreaterThanEquals
+        );
+        let hint = if is_comparison { None } else { expected };
+        let (left, right) = self.compile_operands(&binary.left, &binary.right, hint)?;
+        self.build_overloadable_binary(binary.operator, left, right, span(expression))
+    }
+
+    /// Compiles both operands of a binary operator. A literal operand takes
+    /// on the type of the other one.
+    pub(super) fn compile_operands(
+        &mut self,
+        left: &ExpressionNode<'ast>,
+        right: &ExpressionNode<'ast>,
+        hint: Option<&Type>,
+    ) -> Result<(Value<'ctx>, Value<'ctx>), CodeGenError> {
+        if is_literal(left) && !is_literal(right) {
+            let right = self.compile_expression(right, hint)?;
+            let left = self.compile_expression(left, Some(&right.ty))?;
+            Ok((left, right))
+        } else {
+            let left = self.compile_expression(left, hint)?;
+            let right = self.compile_expression(right, Some(&left.ty))?;
+            Ok((left, right))
+        }
+    }
+
+    /// Converts the operand of the narrower type to the other operand's type.
+    pub(super) fn unify(
+        &self,
+        left: Value<'ctx>,
+        right: Value<'ctx>,
+        span: Range<usize>,
+    ) -> Result<(Value<'ctx>, Value<'ctx>), CodeGenError> {
+        if right.ty.widens_to(&left.ty) {
+            let ty = left.ty.clone();
+            Ok((left, self.convert(right, &ty, span)?))
+        } else {
+            let ty = right.ty.clone();
+            Ok((self.convert(left, &ty, span)?, right))
+        }
+    }
+
+    /// Applies a primitive binary operator.
+    pub(super) fn build_binary(
+        &self,
+        operator: BinaryOperator,
+        left: Value<'ctx>,
+        right: Value<'ctx>,
+        span: Range<usize>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let (left, right) = self.unify(left, right, span.clone())?;
+        let no_overload = || {
+            CodeGenError::from(Error::NoOperatorOverload {
+                operator: format!("{:?}", operator),
+                operands: format!("{}, {}", left.ty, right.ty),
+                span: span.clone(),
+            })
+        };
+        let kind = numeric(&left.ty).ok_or_else(no_overload)?;
+        let builder = &self.codegen.builder;
+        let boolean = Type::Primitive(PrimitiveType::Bool);
+        let (l, r) = match (left.value, right.value) {
+            (Some(l), Some(r)) => (l, r),
+            _ => return Err(no_overload()),
+        };
+
+        if let Numeric::Float { .. } = kind {
+            let (l, r) = (l.into_float_value(), r.into_float_value());
+            let compare = |predicate| -> Value<'ctx> {
+                Value {
+                    value: Some(builder.build_float_compare(predicate, l, r, "cmp").into()),
+                    ty: boolean.clone(),
+                }
+            };
+            let value: FloatValue<'ctx> = match operator {
+                BinaryOperator::Addition => builder.build_float_add(l, r, "add"),
+                BinaryOperator::Subtraction => builder.build_float_sub(l, r, "sub"),
+                BinaryOperator::Multiplication => builder.build_float_mul(l, r, "mul"),
+                BinaryOperator::Division => builder.build_float_div(l, r, "div"),
+                BinaryOperator::Remainder => builder.build_float_rem(l, r, "rem"),
+                BinaryOperator::Equals => return Ok(compare(FloatPredicate::OEQ)),
+                BinaryOperator::NotEquals => return Ok(compare(FloatPredicate::UNE)),
+                BinaryOperator::LessThan => return Ok(compare(FloatPredicate::OLT)),
+                BinaryOperator::LessThanEquals => return Ok(compare(FloatPredicate::OLE)),
+                BinaryOperator::GreaterThan => return Ok(compare(FloatPredicate::OGT)),
+                BinaryOperator::GreaterThanEquals => return Ok(compare(FloatPredicate::OGE)),
+                _ => return Err(no_overload()),
+            };
+            return Ok(Value {
+                value: Some(value.into()),
+                ty: left.ty,
+            });
+        }
+
+        let signed = matches!(kind, Numeric::Integer { signed: true, .. });
+        let checked = self.codegen.checks.overflow;
+        let (l, r) = (l.into_int_value(), r.into_int_value());
+        let compare = |signed_predicate, unsigned_predicate| -> Value<'ctx> {
+            let predicate = if signed {
+                signed_predicate
+            } else {
+                unsigned_predicate
+            };
+            Value {
+                value: Some(builder.build_int_compare(predicate, l, r, "cmp").into()),
+                ty: boolean.clone(),
+            }
+        };
+        let value: IntValue<'ctx> = match (operator, kind) {
+            (BinaryOperator::Equals, _) => return Ok(compare(IntPredicate::EQ, IntPredicate::EQ)),
+            (BinaryOperator::NotEquals, _) => {
+                return Ok(compare(IntPredicate::NE, IntPredicate::NE))
+            }
+            (BinaryOperator::BitAnd, _) => builder.build_and(l, r, "and"),
+            (BinaryOperator::BitOr, _) => builder.build_or(l, r, "or"),
+            (BinaryOperator::BitXor, _) => builder.build_xor(l, r, "xor"),
+            (_, Numeric::Bool) => return Err(no_overload()),
+            (BinaryOperator::LessThan, _) => {
+                return Ok(compare(IntPredicate::SLT, IntPredicate::ULT))
+            }
+            (BinaryOperator::LessThanEquals, _) => {
+                return Ok(compare(IntPredicate::SLE, IntPredicate::ULE))
+            }
+            (BinaryOperator::GreaterThan, _) => {
+                return Ok(compare(IntPredicate::SGT, IntPredicate::UGT))
+            }
+            (BinaryOperator::GreaterThanEquals, _) => {
+                return Ok(compare(IntPredicate::SGE, IntPredicate::UGE))
+            }
+            (BinaryOperator::Addition, _) if checked => self.build_checked("add", l, r, signed)?,
+            (BinaryOperator::Subtraction, _) if checked => {
+                self.build_checked("sub", l, r, signed)?
+            }
+            (BinaryOperator::Multiplication, _) if checked => {
+                self.build_checked("mul", l, r, signed)?
+            }
+            (BinaryOperator::Addition, _) => builder.build_int_add(l, r, "add"),
+            (BinaryOperator::Subtraction, _) => builder.build_int_sub(l, r, "sub"),
+            (BinaryOperator::Multiplication, _) => builder.build_int_mul(l, r, "mul"),
+            (BinaryOperator::Division, _) if signed => builder.build_int_signed_div(l, r, "div"),
+            (BinaryOperator::Division, _) => builder.build_int_unsigned_div(l, r, "div"),
+            (BinaryOperator::Remainder, _) if signed => builder.build_int_signed_rem(l, r, "rem"),
+            (BinaryOperator::Remainder, _) => builder.build_int_unsigned_rem(l, r, "rem"),
+            (BinaryOperator::BitShiftLeft, _) => builder.build_left_shift(l, r, "shl"),
+            (BinaryOperator::BitShiftRight, _) => builder.build_right_shift(l, r, signed, "shr"),
+            _ => return Err(no_overload()),
+        };
+        Ok(Value {
+            value: Some(value.into()),
+            ty: left.ty,
+        })
+    }
+
+    /// Builds the integer `operation`, `add`, `sub` or `mul`, with the
+    /// `llvm.*.with.overflow` intrinsic, trapping if it overflows.
+    fn build_checked(
+        &self,
+        operation: &str,
+        l: IntValue<'ctx>,
+        r: IntValue<'ctx>,
+        signed: bool,
+    ) -> Result<IntValue<'ctx>, CodeGenError> {
+        let context = self.codegen.context;
+        let module = &self.codegen.module;
+        let builder = &self.codegen.builder;
+        let ty = l.get_type();
+        let name = format!(
+            "llvm.{}{}.with.overflow.i{}",
+            if signed { "s" } else { "u" },
+            operation,
+            ty.get_bit_width()
+        );
+        let intrinsic = module.get_function(&name).unwrap_or_else(|| {
+            let result = context.struct_type(&[ty.into(), context.bool_type().into()], false);
+            let fn_type = result.fn_type(&[ty.into(), ty.into()], false);
+            module.add_function(&name, fn_type, None)
+        });
+        let result = builder
+            .build_call(intrinsic, &[l.into(), r.into()], operation)
+            .try_as_basic_value()
+            .left()
+            .ok_or(CodeGenError::Unknown)?
+            .into_struct_value();
+        let value = builder
+            .build_extract_value(result, 0, operation)
+            .ok_or(CodeGenError::Unknown)?;
+        let overflowed = builder
+            .build_extract_value(result, 1, "overflow")
+            .ok_or(CodeGenError::Unknown)?;
+        self.build_trap_if(overflowed.into_int_value())?;
+        Ok(value.into_int_value())
+    }
+
+    /// Compiles `left && right` or `left || right`, evaluating `right` only
+    /// if `left` does not decide the result.
+    fn compile_logical(
+        &mut self,
+        operator: BinaryOperator,
+        left: Value<'ctx>,
+        right: &ExpressionNode<'ast>,
+        span: Range<usize>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let boolean = Type::Primitive(PrimitiveType::Bool);
+        let left = int_value(&self.convert(left, &boolean, span)?)?;
+        let left_block = self.current_block()?;
+        let function = left_block.get_parent().ok_or(CodeGenError::Unknown)?;
+        let context = self.codegen.context;
+        let right_block = context.append_basic_block(function, "logic.rhs");
+        let merge = context.append_basic_block(function, "logic.end");
+        let is_and = operator == BinaryOperator::LogicalAnd;
+        if is_and {
+            self.codegen
+                .builder
+                .build_conditional_branch(left, right_block, merge);
+        } else {
+            self.codegen
+                .builder
+                .build_conditional_branch(left, merge, right_block);
+        }
+
+        self.codegen.builder.position_at_end(right_block);
+        let right = self.compile_condition(right)?;
+        let right_end = self.current_block()?;
+        self.codegen.builder.build_unconditional_branch(merge);
+
+        self.codegen.builder.position_at_end(merge);
+        let phi = self.codegen.builder.build_phi(context.bool_type(), "logic");
+        let decided = context.bool_type().const_int(!is_and as u64, false);
+        phi.add_incoming(&[(&decided, left_block), (&right, right_end)]);
+        Ok(Value {
+            value: Some(phi.as_basic_value()),
+            ty: boolean,
+        })
+    }
+
+    fn compile_prefix(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        prefix: &PrefixExpression<'ast>,
+        expected: Option<&Type>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let operator = prefix.operator;
+        match operator {
+            PrefixOperator::Increment | PrefixOperator::Decrement => {
+                let increment = operator == PrefixOperator::Increment;
+                return self.compile_increment(&prefix.operand, increment, true);
+            }
+            // Pointers to variables that cannot be assigned to point to
+            // constants.
+            PrefixOperator::AddressOf => {
+                let variable = self.compile_address(&prefix.operand)?;
+                let pointee = if variable.mutable {
+                    variable.ty
+                } else {
+                    Type::Const(Box::new(variable.ty))
+                };
+                return Ok(Value {
+                    value: Some(variable.pointer.into()),
+                    ty: Type::Pointer(Box::new(pointee)),
+                });
+            }
+            PrefixOperator::Dereference => {
+                let variable = self.compile_address(expression)?;
+                let value = self.codegen.builder.build_load(variable.pointer, "deref");
+                return Ok(Value {
+                    value: Some(value),
+                    ty: variable.ty,
+                });
+            }
+            _ => {}
+        }
+        let boolean = Type::Primitive(PrimitiveType::Bool);
+        let hint = match operator {
+            PrefixOperator::LogicalNot => Some(&boolean),
+            _ => expected,
+        };
+        let operand = self.compile_expression(&prefix.operand, hint)?;
+        let overload = self.methods.resolve_prefix_operator(
+            &self.interfaces,
+            &self.env,
+            operator,
+            &operand.ty,
+            span(expression),
+        )?;
+        if let Some(call) = overload {
+            return self.build_operator_call(&call, operand, vec![], span(expression));
+        }
+        if operator == PrefixOperator::LogicalNot {
+            let operand = self.convert(operand, &boolean, span(&prefix.operand))?;
+            let not = self.codegen.builder.build_not(int_value(&operand)?, "not");
+            return Ok(Value {
+                value: Some(not.into()),
+                ty: boolean,
+            });
+        }
+        let builder = &self.codegen.builder;
+        let value = operand.value.ok_or(CodeGenError::Unknown)?;
+        let value: BasicValueEnum<'ctx> = match (operator, numeric(&operand.ty)) {
+            (PrefixOperator::Plus, Some(Numeric::Integer { .. }))
+            | (PrefixOperator::Plus, Some(Numeric::Float { .. })) => value,
+            (PrefixOperator::Minus, Some(Numeric::Integer { .. })) => {
+                builder.build_int_neg(value.into_int_value(), "neg").into()
+            }
+            (PrefixOperator::Minus, Some(Numeric::Float { .. })) => builder
+                .build_float_neg(value.into_float_value(), "neg")
+                .into(),
+            (PrefixOperator::BitNot, Some(Numeric::Integer { .. })) => {
+                builder.build_not(value.into_int_value(), "not").into()
+            }
+            _ => {
+                return Err(Error::NoOperatorOverload {
+                    operator: format!("{:?}", operator),
+                    operands: operand.ty.to_string(),
+                    span: span(expression),
+                }
+                .into())
+            }
+        };
+        Ok(Value {
+            value: Some(value),
+            ty: operand.ty,
+        })
+    }
+
+    /// Compiles `++x`, `x++`, `--x` and `x--`, which evaluate to the new or
+    /// the old value respectively.
+    fn compile_increment(
+        &mut self,
+        operand: &ExpressionNode<'ast>,
+        increment: bool,
+        prefix: bool,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let place = self.compile_place(operand)?;
+        let builder = &self.codegen.builder;
+        let old = builder.build_load(place.pointer, "old");
+        let new: BasicValueEnum<'ctx> = match numeric(&place.ty) {
+            Some(Numeric::Integer { .. }) => {
+                let old = old.into_int_value();
+                let one = old.get_type().const_int(1, false);
+                if increment {
+                    builder.build_int_add(old, one, "inc").into()
+                } else {
+                    builder.build_int_sub(old, one, "dec").into()
+                }
+            }
+            Some(Numeric::Float { .. }) => {
+                let old = old.into_float_value();
+                let one = old.get_type().const_float(1.0);
+                if increment {
+                    builder.build_float_add(old, one, "inc").into()
+                } else {
+                    builder.build_float_sub(old, one, "dec").into()
+                }
+            }
+            _ => {
+                return Err(Error::NoOperatorOverload {
+                    operator: if increment { "++" } else { "--" }.to_string(),
+                    operands: place.ty.to_string(),
+                    span: span(operand),
+                }
+                .into())
+            }
+        };
+        builder.build_store(place.pointer, new);
+        Ok(Value {
+            value: Some(if prefix { new } else { old }),
+            ty: place.ty,
+        })
+    }
+
+    /// Compiles an assignment. `a op= b` assigns `a op b`, using the
+    /// overloads of `op`, without evaluating `a` twice unless it is indexed
+    /// through an overload of `[]=`.
+    fn compile_assignment(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        assignment: &AssignmentExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let place = match assignment.left.value {
+            Expression::IndexExpression(index) => {
+                let object = self.compile_address(&index.array)?;
+                if !is_array(&object.ty) {
+                    return self.compile_index_assignment(expression, object, &index, assignment);
+                }
+                self.compile_element_of(object, &index)?
+            }
+            _ => self.compile_place(&assignment.left)?,
+        };
+        let right_span = span(&assignment.right);
+        let value = match assignment.operator.binary_operator() {
+            None => {
+                let value = self.compile_expression(&assignment.right, Some(&place.ty))?;
+                self.convert(value, &place.ty, right_span)?
+            }
+            Some(operator) => {
+                let current = Value {
+                    value: Some(self.codegen.builder.build_load(place.pointer, "current")),
+                    ty: place.ty.clone(),
+                };
+                let value = match operator {
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => self
+                        .compile_logical(
+                            operator,
+                            current,
+                            &assignment.right,
+                            right_span.clone(),
+                        )?,
+                    BinaryOperator::NullCoalesce => {
+                        self.compile_null_coalesce(current, &assignment.right, right_span.clone())?
+                    }
+                    _ => {
+                        let right = self.compile_expression(&assignment.right, Some(&place.ty))?;
+                        self.build_overloadable_binary(
+                            operator,
+                            current,
+                            right,
+                            right_span.clone(),
+                        )?
+                    }
+                };
+                self.convert(value, &place.ty, right_span)?
+            }
+        };
+        let stored = value.value.ok_or(CodeGenError::Unknown)?;
+        self.codegen.builder.build_store(place.pointer, stored);
+        Ok(value)
+    }
+
+    /// The storage an assignment writes to: a mutable variable, an array
+    /// element, a field of either, or what a pointer points to.
+    fn compile_place(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+    ) -> Result<Variable<'ctx>, CodeGenError> {
+        match expression.value {
+            Expression::IdentifierExpression(identifier) => {
+                let variable = self.lookup(&identifier)?;
+                if !variable.mutable {
+                    return Err(Error::AssignToConstant {
+                        name: identifier.value.to_string(),
+                        span: span(expression),
+                    }
+                    .into());
+                }
+                Ok(variable)
+            }
+            Expression::IndexExpression(index) => self.compile_element(&index),
+            Expression::MemberAccessExpression(access) => {
+                let variable = self.compile_address(expression)?;
+                if !variable.mutable {
+                    return Err(Error::AssignToConstant {
+                        name: access.member.value.to_string(),
+                        span: span(expression),
+                    }
+                    .into());
+                }
+                Ok(variable)
+            }
+            Expression::PrefixExpression(PrefixExpression {
+                operator: PrefixOperator::Dereference,
+                operand,
+            }) => {
+                let variable = self.compile_address(expression)?;
+                if !variable.mutable {
+                    return Err(Error::AssignToConstant {
+                        name: match operand.value {
+                            Expression::IdentifierExpression(identifier) => {
+                                format!("*{}", identifier.value)
+                            }
+                            _ => "*".to_string(),
+                        },
+                        span: span(expression),
+                    }
+                    .into());
+                }
+                Ok(variable)
+            }
+            _ => Err(unsupported("assigning to this expression", expression)),
+        }
+    }
+
+    /// The address of an array element. Its mutability is that of the array.
+    fn compile_element(
+        &mut self,
+        index: &IndexExpression<'ast>,
+    ) -> Result<Variable<'ctx>, CodeGenError> {
+        let array = self.compile_address(&index.array)?;
+        self.compile_element_of(array, index)
+    }
+
+    /// The address of an element of the array stored at `array`.
+    pub(super) fn compile_element_of(
+        &mut self,
+        array: Variable<'ctx>,
+        index: &IndexExpression<'ast>,
+    ) -> Result<Variable<'ctx>, CodeGenError> {
+        let element = match array.ty.unqualified() {
+            Type::SizedArray(element) | Type::UnsizedArray(element) | Type::Array(element, _) => {
+                (**element).clone()
+            }
+            ty => {
+                return Err(Error::NoOperatorOverload {
+                    operator: "[]".to_string(),
+                    operands: ty.to_string(),
+                    span: span(&index.array),
+                }
+                .into())
+            }
+        };
+        let mutable = match index.array.value {
+            Expression::IdentifierExpression(_) => array.mutable,
+            _ => true,
+        };
+        let position = self.compile_expression(&index.index, None)?;
+        let position = self.cast(position, &Type::Primitive(PrimitiveType::S64))?;
+        let position = int_value(&position)?;
+        let builder = &self.codegen.builder;
+        // Fixed-size arrays are stored inline; the others point to their
+        // elements.
+        let pointer = match array.ty.unqualified() {
+            Type::Array(..) => {
+                let zero = self.codegen.context.i64_type().const_zero();
+                unsafe { builder.build_in_bounds_gep(array.pointer, &[zero, position], "element") }
+            }
+            _ => {
+                let slice = builder
+                    .build_load(array.pointer, "array")
+                    .into_struct_value();
+                let data = builder
+                    .build_extract_value(slice, 0, "data")
+                    .ok_or(CodeGenError::Unknown)?
+                    .into_pointer_value();
+                unsafe { builder.build_in_bounds_gep(data, &[position], "element") }
+            }
+        };
+        Ok(Variable {
+            pointer,
+            ty: element,
+            mutable,
+        })
+    }
+
+    /// The value of a variant of an enum the module declares, as in
+    /// `Alignment.Left`, if `access` names one.
+    fn compile_variant(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        access: &MemberAccessExpression<'ast>,
+    ) -> Result<Option<Value<'ctx>>, CodeGenError> {
+        let name = match access.object.value {
+            Expression::IdentifierExpression(identifier) if !self.is_variable(identifier.value) => {
+                identifier.value
+            }
+            _ => return Ok(None),
+        };
+        if !matches!(self.env.get(name), Some(TypeDefinition::Enum(_))) {
+            return Ok(None);
+        }
+        let evaluated = self.env.constants().map_err(CodeGenError::from)?.evaluate(
+            LOCAL_MODULE,
+            expression,
+            None,
+        )?;
+        let value = match evaluated {
+            Constant::Integer { value, .. } => value,
+            _ => return Err(CodeGenError::Unknown),
+        };
+        let ty = Type::Enum(name.to_string());
+        let int_type = self
+            .codegen
+            .llvm_type(&ty)?
+            .ok_or(CodeGenError::Unknown)?
+            .into_int_type();
+        Ok(Some(Value {
+            value: Some(int_type.const_int(value as u64, value < 0).into()),
+            ty,
+        }))
+    }
+
+    /// Arrays have a `length`; structs and pointers to them have fields.
+    fn compile_member(
+        &mut self,
+        access: &MemberAccessExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let object = self.compile_address(&access.object)?;
+        let builder = &self.codegen.builder;
+        match (object.ty.unqualified(), access.member.value) {
+            (Type::SizedArray(_), "length") | (Type::UnsizedArray(_), "length") => {
+                let array = builder.build_load(object.pointer, "array");
+                let length = builder
+                    .build_extract_value(array.into_struct_value(), 1, "length")
+                    .ok_or(CodeGenError::Unknown)?;
+                Ok(Value {
+                    value: Some(length),
+                    ty: Type::Primitive(PrimitiveType::S64),
+                })
+            }
+            (Type::Array(_, size), "length") => Ok(Value {
+                value: Some(
+                    self.codegen
+                        .context
+                        .i64_type()
+                        .const_int(*size, false)
+                        .into(),
+                ),
+                ty: Type::Primitive(PrimitiveType::S64),
+            }),
+            _ => {
+                let field = self.compile_field(object, &access.member)?;
+                let value = self
+                    .codegen
+                    .builder
+                    .build_load(field.pointer, access.member.value);
+                Ok(Value {
+                    value: Some(value),
+                    ty: field.ty,
+                })
+            }
+        }
+    }
+
+    fn compile_ternary(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        ternary: &TernaryExpression<'ast>,
+        expected: Option<&Type>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let condition = self.compile_condition(&ternary.condition)?;
+        let function = self
+            .current_block()?
+            .get_parent()
+            .ok_or(CodeGenError::Unknown)?;
+        let context = self.codegen.context;
+        let true_block = context.append_basic_block(function, "cond.true");
+        let false_block = context.append_basic_block(function, "cond.false");
+        let merge = context.append_basic_block(function, "cond.end");
+        self.codegen
+            .builder
+            .build_conditional_branch(condition, true_block, false_block);
+
+        self.codegen.builder.position_at_end(true_block);
+        let when_true = self.compile_expression(&ternary.when_true, expected)?;
+        let true_end = self.current_block()?;
+        self.codegen.builder.position_at_end(false_block);
+        let when_false = self.compile_expression(&ternary.when_false, Some(&when_true.ty))?;
+        let false_end = self.current_block()?;
+
+        // Each side is converted to the common type at the end of its branch.
+        let ty = if when_false.ty.widens_to(&when_true.ty) {
+            when_true.ty.clone()
+        } else {
+            when_false.ty.clone()
+        };
+        let span = span(expression);
+        self.codegen.builder.position_at_end(true_end);
+        let when_true = self.convert(when_true, &ty, span.clone())?;
+        self.codegen.builder.build_unconditional_branch(merge);
+        self.codegen.builder.position_at_end(false_end);
+        let when_false = self.convert(when_false, &ty, span)?;
+        self.codegen.builder.build_unconditional_branch(merge);
+
+        self.codegen.builder.position_at_end(merge);
+        let value = match (when_true.value, when_false.value) {
+            (Some(when_true), Some(when_false)) => {
+                let phi = self.codegen.builder.build_phi(when_true.get_type(), "cond");
+                phi.add_incoming(&[(&when_true, true_end), (&when_false, false_end)]);
+                Some(phi.as_basic_value())
+            }
+            _ => None,
+        };
+        Ok(Value { value, ty })
+    }
+
+    /// Calls the overload of a function that best matches the arguments, or
+    /// a builtin if the module defines no function of that name. Calls of
+    /// members and scoped names call methods.
+    fn compile_call(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        call: &CallExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        if let Some((namespace, function)) = self.foreign_callee(&call.callee) {
+            return self.compile_foreign_call(expression, call, namespace, function);
+        }
+        if let Some((namespace, function)) = self.namespace_member(&call.callee) {
+            return self.compile_namespace_call(expression, call, namespace, function);
+        }
+        let name = match call.callee.value {
+            Expression::IdentifierExpression(identifier) => identifier.value,
+            Expression::MemberAccessExpression(access) => {
+                return self.compile_method_call(expression, call, &access)
+            }
+            Expression::ScopedExpression(scoped) => {
+                return self.compile_static_call(expression, call, &scoped)
+            }
+            _ => return Err(unsupported("calls through values", &call.callee)),
+        };
+        let overloads = self.functions.overloads(name);
+        let user_defined = !overloads.is_empty();
+        let expected = expected_arguments(overloads, call.arguments.iter().count());
+        let mut arguments = vec![];
+        for (argument, expected) in call.arguments.iter().zip(&expected) {
+            arguments.push(self.compile_expression(argument, expected.as_ref())?);
+        }
+
+        if !user_defined {
+            if let Some(builtin) = Builtin::from_name(name) {
+                return self.compile_builtin(builtin, expression, &arguments);
+            }
+        }
+        let types: Vec<Type> = arguments
+            .iter()
+            .map(|argument| argument.ty.clone())
+            .collect();
+        let overload = self
+            .functions
+            .resolve_call(name, &types, span(expression))?;
+        let (symbol, signature) = (overload.symbol.clone(), overload.signature.clone());
+        let function = self
+            .codegen
+            .module
+            .get_function(&symbol)
+            .ok_or_else(|| unsupported("calls to generic functions", expression))?;
+        self.build_call_with(function, &signature, arguments, call)
+    }
+
+    /// Calls a function of a catlang module imported with `import * as`.
+    fn compile_namespace_call(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        call: &CallExpression<'ast>,
+        namespace: IdentifierNode<'ast>,
+        function: IdentifierNode<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let overloads = self
+            .functions
+            .namespace(namespace.value)
+            .map_or(&[][..], |module| module.overloads(function.value));
+        let expected = expected_arguments(overloads, call.arguments.iter().count());
+        let mut arguments = vec![];
+        for (argument, expected) in call.arguments.iter().zip(&expected) {
+            arguments.push(self.compile_expression(argument, expected.as_ref())?);
+        }
+        let types: Vec<Type> = arguments
+            .iter()
+            .map(|argument| argument.ty.clone())
+            .collect();
+        let overload = self
+            .functions
+            .resolve_namespace_call(namespace.value, function, &types, span(expression))?
+            .ok_or(CodeGenError::Unknown)?;
+        let (symbol, signature) = (overload.symbol.clone(), overload.signature.clone());
+        let function = self
+            .codegen
+            .module
+            .get_function(&symbol)
+            .ok_or_else(|| unsupported("calls to generic functions", expression))?;
+        self.build_call_with(function, &signature, arguments, call)
+    }
+
+    /// Calls `function` with the compiled `arguments` of `call`, converted
+    /// to the parameter types of `signature`.
+    pub(super) fn build_call_with(
+        &mut self,
+        function: FunctionValue<'ctx>,
+        signature: &FunctionSignature,
+        arguments: Vec<Value<'ctx>>,
+        call: &CallExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let values = self.convert_arguments(arguments, signature, call)?;
+        let call = self.codegen.builder.build_call(function, &values, "call");
+        Ok(Value {
+            value: call.try_as_basic_value().left(),
+            ty: signature.return_type.clone().unwrap_or_else(void),
+        })
+    }
+
+    /// Converts the compiled `arguments` of `call` to the parameter types of
+    /// `signature`.
+    fn convert_arguments(
+        &self,
+        arguments: Vec<Value<'ctx>>,
+        signature: &FunctionSignature,
+        call: &CallExpression<'ast>,
+    ) -> Result<Vec<BasicMetadataValueEnum<'ctx>>, CodeGenError> {
+        let mut values: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+        let parameters = arguments.into_iter().zip(&signature.parameters);
+        for ((argument, parameter), node) in parameters.zip(call.arguments.iter()) {
+            let argument = self.convert(argument, parameter, span(node))?;
+            values.extend(argument.value.map(BasicMetadataValueEnum::from));
+        }
+        Ok(values)
+    }
+
+    /// Compiles the arguments of a call to the function `name` with a known
+    /// signature, checking that there are as many as it has parameters.
+    pub(super) fn compile_arguments(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        call: &CallExpression<'ast>,
+        name: &str,
+        signature: &FunctionSignature,
+    ) -> Result<Vec<BasicMetadataValueEnum<'ctx>>, CodeGenError> {
+        let mut arguments = vec![];
+        for (argument, parameter) in call.arguments.iter().zip(&signature.parameters) {
+            arguments.push(self.compile_expression(argument, Some(parameter))?);
+        }
+        if call.arguments.iter().count() != signature.parameters.len() {
+            let types: Vec<String> = arguments.iter().map(|a| a.ty.to_string()).collect();
+            let parameters: Vec<String> = signature
+                .parameters
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            return Err(Error::NoMatchingOverload {
+                name: name.to_string(),
+                arguments: types.join(", "),
+                candidates: vec![format!("{}({})", name, parameters.join(", "))],
+                span: span(expression),
+            }
+            .into());
+        }
+        self.convert_arguments(arguments, signature, call)
+    }
+
+    /// Converts a value to `to` if that loses no information. Pointers also
+    /// convert to pointers to constants, and values to optionals.
+    pub(super) fn convert(
+        &self,
+        value: Value<'ctx>,
+        to: &Type,
+        span: Range<usize>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let adds_const = match (value.ty.unqualified(), to.unqualified()) {
+            (Type::Pointer(from), Type::Pointer(to)) => **to == Type::Const(from.clone()),
+            _ => false,
+        };
+        if value.ty.unqualified() == to.unqualified() || adds_const {
+            return Ok(Value {
+                value: value.value,
+                ty: to.clone(),
+            });
+        }
+        if let Type::Union(_) = to.unqualified() {
+            if !matches!(value.ty.unqualified(), Type::Union(_)) {
+                return self.build_union(value, to, span);
+            }
+        }
+        if let Type::Optional(inner) = to.unqualified() {
+            if !matches!(value.ty.unqualified(), Type::Optional(_)) {
+                let value = self.convert(value, inner, span)?;
+                return self.build_optional(value, to);
+            }
+        }
+        if !value.ty.widens_to(to) {
+            return Err(Error::TypeMismatch {
+                expected: to.to_string(),
+                found: value.ty.to_string(),
+                span,
+            }
+            .into());
+        }
+        self.cast(value, to)
+    }
+
+    /// Converts between numeric types, as `as` does. Narrowing conversions
+    /// truncate, and floats are rounded towards zero.
+    pub(super) fn cast(&self, value: Value<'ctx>, to: &Type) -> Result<Value<'ctx>, CodeGenError> {
+        let invalid = |reason| {
+            CodeGenError::from(Error::InvalidCast {
+                from: value.ty.to_string(),
+                to: to.to_string(),
+                reason,
+            })
+        };
+        if value.ty.unqualified() == to.unqualified() {
+            return Ok(Value {
+                value: value.value,
+                ty: to.clone(),
+            });
+        }
+        let (from_kind, to_kind) = match (numeric(&value.ty), numeric(to)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return Err(invalid("only numbers can be converted")),
+        };
+        let target = self.codegen.llvm_type(to)?.ok_or(CodeGenError::Unknown)?;
+        let v = value.value.ok_or(CodeGenError::Unknown)?;
+        let builder = &self.codegen.builder;
+        let converted: BasicValueEnum<'ctx> = match (from_kind, to_kind) {
+            (Numeric::Integer { signed, bits }, Numeric::Integer { bits: to_bits, .. }) => {
+                let (v, t) = (v.into_int_value(), target.into_int_type());
+                if to_bits < bits {
+                    builder.build_int_truncate(v, t, "trunc").into()
+                } else if to_bits == bits {
+                    v.into()
+                } else if signed {
+                    builder.build_int_s_extend(v, t, "sext").into()
+                } else {
+                    builder.build_int_z_extend(v, t, "zext").into()
+                }
+            }
+            (Numeric::Bool, Numeric::Integer { .. }) => builder
+                .build_int_z_extend(v.into_int_value(), target.into_int_type(), "zext")
+                .into(),
+            (Numeric::Integer { signed, .. }, Numeric::Float { .. }) => {
+                let (v, t) = (v.into_int_value(), target.into_float_type());
+                if signed {
+                    builder.build_signed_int_to_float(v, t, "itof").into()
+                } else {
+                    builder.build_unsigned_int_to_float(v, t, "itof").into()
+                }
+            }
+            (Numeric::Float { .. }, Numeric::Integer { signed, .. }) => {
+                let (v, t) = (v.into_float_value(), target.into_int_type());
+                if signed {
+                    builder.build_float_to_signed_int(v, t, "ftoi").into()
+                } else {
+                    builder.build_float_to_unsigned_int(v, t, "ftoi").into()
+                }
+            }
+            (Numeric::Float { bits }, Numeric::Float { bits: to_bits }) => {
+                let (v, t) = (v.into_float_value(), target.into_float_type());
+                if to_bits < bits {
+                    builder.build_float_trunc(v, t, "ftrunc").into()
+                } else {
+                    builder.build_float_ext(v, t, "fext").into()
+                }
+            }
+            _ => return Err(invalid("booleans only convert to integers")),
+        };
+        Ok(Value {
+            value: Some(converted),
+            ty: to.clone(),
+        })
+    }
+}
@@ -0,0 +1,217 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{span, ModuleCompiler, Value};
+use crate::interop::c_header::Symbol;
+use crate::interop::{named_imports, ImportedHeader};
+use crate::semantic::constants::Constant;
+use crate::semantic::patterns::integer_bounds;
+use crate::semantic::types::{FunctionSignature, NamedTypeRef, Type};
+use crate::syntax::ast::*;
+use inkwell::module::Linkage;
+use inkwell::types::BasicTypeEnum;
+use inkwell::values::FunctionValue;
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Declares what a module imports from C headers: the bodies of their
+    /// structs, the functions it imports by name, and its constants, which
+    /// are compiled where they are used like C's macros are.
+    pub(super) fn declare_headers(
+        &mut self,
+        headers: &[ImportedHeader<'ast>],
+    ) -> Result<(), CodeGenError> {
+        for imported in headers {
+            for declared in &imported.header.structs {
+                if let Some(fields) = &declared.fields {
+                    self.define_foreign_struct(&declared.name, fields)?;
+                }
+            }
+        }
+        for (imported, import) in named_imports(headers) {
+            let local_name = import.renamed_to.unwrap_or(import.identifier);
+            match imported.header.lookup(import.identifier.value) {
+                Some(Symbol::Function(function)) => {
+                    self.foreign_function(&function.name, &function.signature)?;
+                }
+                Some(Symbol::Constant(constant)) => {
+                    self.foreign_constants
+                        .insert(local_name.value, constant.clone());
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives a C struct its fields, unless it already has them.
+    fn define_foreign_struct(
+        &self,
+        name: &str,
+        fields: &[(String, Type)],
+    ) -> Result<(), CodeGenError> {
+        let struct_type = self
+            .codegen
+            .llvm_type(&Type::Struct(NamedTypeRef::new(name)))?
+            .ok_or(CodeGenError::Unknown)?
+            .into_struct_type();
+        if !struct_type.is_opaque() {
+            return Ok(());
+        }
+        let mut field_types: Vec<BasicTypeEnum<'ctx>> = vec![];
+        for (_, ty) in fields {
+            field_types.extend(self.codegen.llvm_type(ty)?);
+        }
+        struct_type.set_body(&field_types, false);
+        Ok(())
+    }
+
+    /// Declares (or looks up) a function defined in C.
+    pub(super) fn foreign_function(
+        &self,
+        symbol: &str,
+        signature: &FunctionSignature,
+    ) -> Result<FunctionValue<'ctx>, CodeGenError> {
+        if let Some(function) = self.codegen.module.get_function(symbol) {
+            return Ok(function);
+        }
+        let fn_type = self.codegen.function_type(signature)?;
+        Ok(self
+            .codegen
+            .module
+            .add_function(symbol, fn_type, Some(Linkage::External)))
+    }
+
+    /// The C header `expression` names, if it is the name of a header
+    /// imported with `import * as` that no variable shadows.
+    fn foreign_namespace(&self, expression: &ExpressionNode<'ast>) -> Option<IdentifierNode<'ast>> {
+        match expression.value {
+            Expression::IdentifierExpression(identifier) if !self.is_variable(identifier.value) => {
+                self.functions
+                    .foreign_namespace(identifier.value)
+                    .map(|_| identifier)
+            }
+            _ => None,
+        }
+    }
+
+    pub(super) fn is_variable(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains_key(name)) || self.globals.contains_key(name)
+    }
+
+    /// The namespace and function a call through a C header namespace
+    /// names, as in `cModule.f()` or `cModule::f()`.
+    pub(super) fn foreign_callee(
+        &self,
+        callee: &ExpressionNode<'ast>,
+    ) -> Option<(IdentifierNode<'ast>, IdentifierNode<'ast>)> {
+        match callee.value {
+            Expression::MemberAccessExpression(access) => self
+                .foreign_namespace(&access.object)
+                .map(|namespace| (namespace, access.member)),
+            Expression::ScopedExpression(scoped) => self
+                .functions
+                .foreign_namespace(scoped.scope.value)
+                .map(|_| (scoped.scope, scoped.member)),
+            _ => None,
+        }
+    }
+
+    /// Calls a function of a C header imported with `import * as`.
+    pub(super) fn compile_foreign_call(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        call: &CallExpression<'ast>,
+        namespace: IdentifierNode<'ast>,
+        function: IdentifierNode<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let parameters: Vec<Type> = self
+            .functions
+            .foreign_namespace(namespace.value)
+            .and_then(|header| header.functions.iter().find(|f| f.name == function.value))
+            .map(|declared| declared.signature.parameters.clone())
+            .unwrap_or_default();
+        let mut arguments = vec![];
+        for (index, argument) in call.arguments.iter().enumerate() {
+            arguments.push(self.compile_expression(argument, parameters.get(index))?);
+        }
+        let types: Vec<Type> = arguments
+            .iter()
+            .map(|argument| argument.ty.clone())
+            .collect();
+        let overload = self
+            .functions
+            .resolve_foreign_call(namespace.value, function, &types, span(expression))?
+            .ok_or(CodeGenError::Unknown)?;
+        let value = self.foreign_function(&overload.symbol, &overload.signature)?;
+        self.build_call_with(value, &overload.signature, arguments, call)
+    }
+
+    /// The constant a name refers to: a `#define` or enumerator imported by
+    /// name, or one of a C header namespace, as in `cModule.SIZE`.
+    pub(super) fn foreign_constant(&self, expression: &ExpressionNode<'ast>) -> Option<Constant> {
+        let (namespace, name) = match expression.value {
+            Expression::IdentifierExpression(identifier) if !self.is_variable(identifier.value) => {
+                return self.foreign_constants.get(identifier.value).cloned();
+            }
+            Expression::MemberAccessExpression(access) => {
+                (self.foreign_namespace(&access.object)?, access.member)
+            }
+            Expression::ScopedExpression(scoped) => (scoped.scope, scoped.member),
+            _ => return None,
+        };
+        let header = self.functions.foreign_namespace(namespace.value)?;
+        match header.lookup(name.value) {
+            Some(Symbol::Constant(constant)) => Some(constant.clone()),
+            _ => None,
+        }
+    }
+
+    /// Compiles a constant from a C header. Integers take on the `expected`
+    /// integer type if they fit it, like literals.
+    pub(super) fn compile_constant(
+        &self,
+        constant: &Constant,
+        expected: Option<&Type>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let context = self.codegen.context;
+        let ty = match (constant, expected) {
+            (Constant::Integer { value, .. }, Some(expected @ Type::Primitive(pt)))
+                if pt.integer_layout().is_some() =>
+            {
+                match integer_bounds(expected) {
+                    Some((min, max)) if min <= *value && *value <= max => expected.clone(),
+                    _ => constant.ty(),
+                }
+            }
+            _ => constant.ty(),
+        };
+        let value = match constant {
+            Constant::Integer { value, .. } => {
+                let int_type = self
+                    .codegen
+                    .llvm_type(&ty)?
+                    .ok_or(CodeGenError::Unknown)?
+                    .into_int_type();
+                int_type.const_int(*value as u64, *value < 0).into()
+            }
+            Constant::Float { value, .. } => {
+                let float_type = self
+                    .codegen
+                    .llvm_type(&ty)?
+                    .ok_or(CodeGenError::Unknown)?
+                    .into_float_type();
+                float_type.const_float(*value).into()
+            }
+            Constant::Bool(b) => context.bool_type().const_int(*b as u64, false).into(),
+            Constant::String(text) => {
+                let data = self.codegen.builder.build_global_string_ptr(text, "str");
+                let length = context.i64_type().const_int(text.len() as u64, false);
+                context
+                    .const_struct(&[data.as_pointer_value().into(), length.into()], false)
+                    .into()
+            }
+        };
+        Ok(Value {
+            value: Some(value),
+            ty,
+        })
+    }
+}
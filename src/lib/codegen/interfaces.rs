@@ -0,0 +1,91 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{FunctionBody, ModuleCompiler, Value};
+use crate::semantic::interfaces::Implementation;
+use crate::semantic::types::Type;
+use std::ops::Range;
+
+/// Whether the methods of an `impl` block are compiled. Generic blocks and
+/// blocks for type unions are not yet, since `this` has no single type.
+fn is_compiled(implementation: &Implementation) -> bool {
+    implementation.generic_parameters.is_empty()
+        && !matches!(implementation.implementor, Type::Union(_))
+}
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Declares the methods of the module's `impl` blocks under the symbols
+    /// their vtables refer to.
+    pub(super) fn declare_implementations(
+        &self,
+    ) -> Result<Vec<FunctionBody<'ast, 'ctx>>, CodeGenError> {
+        let mut bodies = vec![];
+        for implementation in self.interfaces.implementations() {
+            if !is_compiled(implementation) {
+                continue;
+            }
+            let signatures = self
+                .interfaces
+                .method_signatures(&self.env, &implementation.interface)?;
+            for (declarator, (name, signature)) in implementation.methods.iter().zip(signatures) {
+                let function = self
+                    .codegen
+                    .declare_method(implementation, name, &signature)?;
+                let receiver = if signature.receiver {
+                    Some(implementation.implementor.clone())
+                } else {
+                    None
+                };
+                bodies.push(FunctionBody {
+                    declarator: *declarator,
+                    function,
+                    signature,
+                    receiver,
+                });
+            }
+        }
+        Ok(bodies)
+    }
+
+    /// Casts a pointer to a pointer to an interface the pointee implements,
+    /// pairing it with the vtable of that implementation.
+    pub(super) fn compile_interface_cast(
+        &self,
+        value: Value<'ctx>,
+        to: &Type,
+        span: Range<usize>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        self.interfaces.check_cast(&self.env, &value.ty, to)?;
+        let pointer = value.value.ok_or(CodeGenError::Unknown)?;
+        let pointee = match value.ty.unqualified() {
+            Type::Pointer(pointee) => pointee.unqualified(),
+            _ => return Err(CodeGenError::Unknown),
+        };
+        // Interface pointers keep their vtable.
+        if let Type::Interface(_) = pointee {
+            return Ok(Value {
+                value: Some(pointer),
+                ty: to.clone(),
+            });
+        }
+        let interface = to.interface_pointer().ok_or(CodeGenError::Unknown)?;
+        let implementation = self
+            .interfaces
+            .find_implementation(pointee, interface)
+            .ok_or(CodeGenError::Unknown)?;
+        if !is_compiled(implementation) {
+            return Err(CodeGenError::Unsupported {
+                construct: "casts through generic implementations",
+                span,
+            });
+        }
+        let fat = self.codegen.build_interface_cast(
+            &self.interfaces,
+            &self.env,
+            implementation,
+            pointer.into_pointer_value(),
+        )?;
+        Ok(Value {
+            value: Some(fat.into()),
+            ty: to.clone(),
+        })
+    }
+}
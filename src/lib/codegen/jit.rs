@@ -0,0 +1,497 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::ModuleCompiler;
+use crate::codegen::{Checks, CodeGen, DEFAULT_TARGET_TRIPLE};
+use crate::semantic::cfg::{strip, Config};
+use crate::syntax::ast::SourceUnit;
+use crate::syntax::parser::parse;
+use anyhow::anyhow;
+use inkwell::context::Context;
+use inkwell::targets::{InitializationConfig, Target};
+use inkwell::OptimizationLevel;
+use toolshed::Arena;
+use tracing::warn;
+
+/// The `#[cfg]` configuration of code compiled in memory: the host's, with
+/// `debug` set since it is not optimized. It is compiled with every check.
+pub(crate) fn config() -> Config {
+    let mut config = Config::for_target(&DEFAULT_TARGET_TRIPLE);
+    config.flag("debug");
+    config
+}
+
+/// Compiles a program in memory and runs it from its `main` function or
+/// top-level code, returning its exit code. `name` is passed to the program
+/// as the first argument, like a program's path, followed by `args`.
+pub fn run(name: &str, source: &str, args: &[String]) -> anyhow::Result<i32> {
+    let program = parse(source).map_err(|errors| {
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        anyhow!(messages.join("\n"))
+    })?;
+
+    Target::initialize_native(&InitializationConfig::default()).map_err(|e| anyhow!(e))?;
+    let context = Context::create();
+    let mut codegen = CodeGen::named(&context, name);
+    codegen.checks = Checks::all();
+    let arena = Arena::new();
+    let config = config();
+    let mut start = None;
+    let mut initializers = vec![];
+    for unit in program.body().iter() {
+        let SourceUnit::Module(module) = unit.value;
+        let module = strip(&arena, &module, &config).map_err(CodeGenError::from)?;
+        let mut compiler = ModuleCompiler::new(&codegen, name, &module)?;
+        let compiled = compiler.compile(&module)?;
+        for warning in compiler.warnings() {
+            warn!("{}: {}", name, warning);
+        }
+        initializers.extend(compiled.initializer);
+        start = compiled.start.or(start);
+    }
+    let start = start.ok_or(CodeGenError::NoEntryPoint)?;
+    let entry = codegen
+        .build_entry_point(&start, &initializers)
+        .ok_or(CodeGenError::Unknown)?;
+    codegen.module.verify().map_err(CodeGenError::from)?;
+
+    let engine = codegen
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .map_err(CodeGenError::from)?;
+    let mut argv = vec![name];
+    argv.extend(args.iter().map(String::as_str));
+    let exit_code = unsafe { engine.run_function_as_main(entry, &argv) };
+    Ok(exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const SHAPES: &str = "
+        interface IShape {
+            function area(this) -> s32;
+            function scale(this, factor: s32);
+        }
+        struct Square { side: s32; }
+        struct Rectangle { width: s32; height: s32; }
+        struct Circle { radius: s32; }
+        impl IShape for Square {
+            function area(this) -> s32 { return this.side * this.side; }
+            function scale(this, factor: s32) { this.side = this.side * factor; }
+        }
+        impl IShape for Rectangle {
+            function area(this) -> s32 { return this.width * this.height; }
+            function scale(this, factor: s32) {
+                this.width = this.width * factor;
+                this.height = this.height * factor;
+            }
+        }
+        function total(a: *IShape, b: *IShape) -> s32 {
+            return a.area() + b.area();
+        }
+    ";
+
+    #[test]
+    fn test_interface_calls_go_through_vtables() {
+        let source = format!(
+            "{}
+            function main() -> s32 {{
+                let square: Square;
+                square.side = 3;
+                let rectangle: Rectangle;
+                rectangle.width = 2;
+                rectangle.height = 5;
+                let shape = (@square) as *IShape;
+                shape.scale(2);
+                return total(shape, (@rectangle) as *IShape) + square.side;
+            }}",
+            SHAPES
+        );
+        assert_eq!(run("shapes", &source, &[]).unwrap(), 36 + 10 + 6);
+    }
+
+    #[test]
+    fn test_casts_need_an_implementation() {
+        let source = format!(
+            "{}
+            function main() -> s32 {{
+                let circle: Circle;
+                return ((@circle) as *IShape).area();
+            }}",
+            SHAPES
+        );
+        assert_eq!(
+            run("shapes", &source, &[]).unwrap_err().to_string(),
+            "cannot cast *Circle to *IShape: the type does not implement the interface"
+        );
+    }
+
+    const COUNTER: &str = "
+        struct Counter { count: s32; }
+        function Counter::starting(count: s32) -> Counter {
+            let counter: Counter;
+            counter.count = count;
+            return counter;
+        }
+        function Counter::add(this, amount: s32) { this.count = this.count + amount; }
+        function Counter::get(this) -> s32 { return this.count; }
+        function bump(counter: *Counter) { counter.add(1); }
+    ";
+
+    #[test]
+    fn test_method_calls() {
+        let source = format!(
+            "{}
+            function main() -> s32 {{
+                let counter = Counter::starting(10);
+                counter.add(5);
+                bump(@counter);
+                return counter.get() + Counter::get(@counter);
+            }}",
+            COUNTER
+        );
+        assert_eq!(run("counter", &source, &[]).unwrap(), 32);
+    }
+
+    #[test]
+    fn test_methods_overloaded_by_arity() {
+        let source = format!(
+            "{}
+            function Counter::add(this) {{ this.add(1); }}
+            function main() -> s32 {{
+                let counter = Counter::starting(10);
+                counter.add();
+                counter.add(5);
+                Counter::add(@counter);
+                return counter.get();
+            }}",
+            COUNTER
+        );
+        assert_eq!(run("counter", &source, &[]).unwrap(), 17);
+    }
+
+    #[test]
+    fn test_generic_methods_are_rejected_where_defined() {
+        let source = "
+            struct Tree<K, V> { key: K; value: V; }
+            function Tree<K, V>::get(this, key: K) -> ?V { return null; }
+            function main() -> s32 { return 0; }
+        ";
+        assert_eq!(
+            run("tree", source, &[]).unwrap_err().to_string(),
+            "generic methods cannot be compiled yet at 86:89"
+        );
+    }
+
+    #[test]
+    fn test_static_methods_are_not_called_on_instances() {
+        let source = format!(
+            "{}
+            function main() -> s32 {{
+                let counter = Counter::starting(10);
+                return counter.starting(1).get();
+            }}",
+            COUNTER
+        );
+        assert_eq!(
+            run("counter", &source, &[]).unwrap_err().to_string(),
+            "static method Counter::starting cannot be called on an instance at 547:555"
+        );
+    }
+
+    #[test]
+    fn test_operator_overloads() {
+        let source = "
+            struct Vector { x: s32; y: s32; }
+            function Vector::operator +(this, rhs: Vector) -> Vector {
+                let sum: Vector;
+                sum.x = this.x + rhs.x;
+                sum.y = this.y + rhs.y;
+                return sum;
+            }
+            function Vector::operator -(this) -> Vector {
+                let negated: Vector;
+                negated.x = -this.x;
+                negated.y = -this.y;
+                return negated;
+            }
+            function Vector::operator ==(this, rhs: Vector) -> bool {
+                return this.x == rhs.x && this.y == rhs.y;
+            }
+            function Vector::operator [](this, index: s32) -> s32 {
+                if (index == 0) { return this.x; }
+                return this.y;
+            }
+            function Vector::operator []=(this, index: s32, value: s32) {
+                if (index == 0) { this.x = value; } else { this.y = value; }
+            }
+            function main() -> s32 {
+                let a: Vector;
+                a.x = 1;
+                a.y = 2;
+                let b = a + a;
+                b += a;
+                b = -b;
+                b[0] = 10;
+                b[1] += 20;
+                let equal = a == a;
+                return b[0] * 100 + b[1] + (equal ? 1000 : 0);
+            }
+        ";
+        assert_eq!(run("vectors", source, &[]).unwrap(), 1000 + 1000 + 14);
+    }
+
+    #[test]
+    fn test_null_coalescing() {
+        let source = "
+            function half(x: s32) -> ?s32 {
+                if (x % 2 == 1) { return null; }
+                return x / 2;
+            }
+            function main() -> s32 {
+                let missing = half(3);
+                missing ??= 7;
+                return (half(8) ?? 100) * 10 + (half(5) ?? 1) * 100 + (missing ?? 0);
+            }
+        ";
+        assert_eq!(run("halves", source, &[]).unwrap(), 40 + 100 + 7);
+    }
+
+    const CLASSIFY: &str = "
+        function classify(x: s32) -> s32 {
+            let class = 0;
+            match (x) {
+                0 => class = 1;
+                1..10 => class = 2;
+                -5 => { class = 3; }
+                _ => class = 4;
+            }
+            return class;
+        }
+        function describe(x: ?s32, limit: s32) -> s32 {
+            let result = 0;
+            match (x) {
+                null => result = -1;
+                n: s32 if n > limit => result = limit;
+                n: s32 => result = n;
+            }
+            return result;
+        }
+    ";
+
+    #[test]
+    fn test_match_statements() {
+        let source = format!(
+            "{}
+            function main() -> s32 {{
+                let switched = classify(0) + classify(7) * 10 + classify(-5) * 100 + classify(42) * 1000;
+                let tested = describe(null, 5) + describe(3, 5) * 10 + describe(9, 5) * 100;
+                return switched - 4321 + tested;
+            }}",
+            CLASSIFY
+        );
+        assert_eq!(run("classify", &source, &[]).unwrap(), -1 + 30 + 500);
+    }
+
+    #[test]
+    fn test_non_exhaustive_matches_are_rejected() {
+        let source = "
+            function main() -> s32 {
+                let x = 3;
+                match (x) {
+                    0 => return 1;
+                    1..10 => return 2;
+                }
+                return 0;
+            }
+        ";
+        let error = run("partial", source, &[]).unwrap_err().to_string();
+        assert!(error.starts_with("match does not handle"), "{}", error);
+    }
+
+    const DIGITS: &str = "
+        struct BadDigit { digit: s32; }
+        impl Error for BadDigit {
+            function message(this) -> []char { return \"not a digit\"; }
+        }
+        let failure: BadDigit;
+        let reached = 0;
+        function digit(value: s32) -> s32 | *Error {
+            if (value > 9) {
+                failure.digit = value;
+                return @failure;
+            }
+            return value;
+        }
+    ";
+
+    #[test]
+    fn test_try_propagates_errors() {
+        let source = format!(
+            "{}
+            function add(a: s32, b: s32) -> s32 | *Error {{
+                let first = try digit(a);
+                let second = try digit(b);
+                reached = reached + first + second;
+                return first + second;
+            }}
+            function main() -> s32 {{
+                let sum = add(2, 3);
+                let failed = add(4, 12);
+                return reached * 100 + failure.digit;
+            }}",
+            DIGITS
+        );
+        assert_eq!(run("digits", &source, &[]).unwrap(), 512);
+    }
+
+    #[test]
+    fn test_try_needs_a_returnable_error() {
+        let source = format!(
+            "{}
+            function main() -> s32 {{
+                return try digit(4);
+            }}",
+            DIGITS
+        );
+        let error = run("digits", &source, &[]).unwrap_err().to_string();
+        assert!(error.starts_with("cannot propagate *Error"), "{}", error);
+    }
+
+    #[test]
+    fn test_for_loops_call_next() {
+        let source = "
+            struct Countdown { left: s32; }
+            function Countdown::next(this) -> ?s32 {
+                if (this.left == 0) {
+                    return null;
+                }
+                this.left = this.left - 1;
+                return this.left + 1;
+            }
+            struct Evens { current: s32; limit: s32; }
+            impl Iterator<s32> for Evens {
+                function next(this) -> ?s32 {
+                    if (this.current >= this.limit) {
+                        return null;
+                    }
+                    this.current = this.current + 2;
+                    return this.current - 2;
+                }
+            }
+            function sum(numbers: *Iterator<s32>) -> s32 {
+                let total = 0;
+                for (number in numbers) {
+                    total = total + number;
+                }
+                return total;
+            }
+            function main() -> s32 {
+                let countdown: Countdown;
+                countdown.left = 4;
+                let digits = 0;
+                for (digit in countdown) {
+                    digits = digits * 10 + digit;
+                }
+                let evens: Evens;
+                evens.current = 0;
+                evens.limit = 10;
+                return countdown.left * 1000000 + digits * 100 + sum((@evens) as *Iterator<s32>);
+            }
+        ";
+        assert_eq!(run("iterators", source, &[]).unwrap(), 4_432_120);
+    }
+
+    #[test]
+    fn test_for_loops_over_range_values() {
+        let source = "
+            function total(range: Range<s32>) -> s32 {
+                let sum = 0;
+                for (i in range) {
+                    sum = sum + i;
+                }
+                return sum;
+            }
+            function main() -> s32 {
+                let inclusive = 1...4;
+                return total(inclusive) * 10 + total(0..3);
+            }
+        ";
+        assert_eq!(run("ranges", source, &[]).unwrap(), 103);
+    }
+
+    #[test]
+    fn test_match_union_members() {
+        let source = "
+            function describe(v: int | bool) -> s32 {
+                match (v) {
+                    n: int => return n;
+                    b: bool => {
+                        if (b) {
+                            return 2;
+                        }
+                        return 3;
+                    }
+                }
+                return 0;
+            }
+            function classify(v: int | bool | null) -> s32 {
+                match (v) {
+                    0...9 => return 1;
+                    null => return 4;
+                    rest: int | bool => return describe(rest);
+                }
+                return 0;
+            }
+            function main() -> s32 {
+                return classify(5) * 100000 + classify(42) * 1000 + classify(true) * 100
+                    + classify(false) * 10 + classify(null);
+            }
+        ";
+        assert_eq!(run("unions", source, &[]).unwrap(), 142_234);
+    }
+
+    #[test]
+    fn test_constants_size_arrays_and_number_variants() {
+        let source = "
+            const SIZE = 2 + 2;
+            enum Level: u8 { Low = SIZE, Mid, High = SIZE << 2 }
+            let squares: [SIZE * 2]s32;
+            let last = SIZE * 2 - 1;
+            function rank(level: Level) -> s32 {
+                match (level) {
+                    Level.Low => return 1;
+                    Level.Mid => return 2;
+                    Level.High => return 3;
+                }
+                return 0;
+            }
+            function main() -> s32 {
+                for (i in 0..8) {
+                    squares[i] = i * i;
+                }
+                let length = squares.length as s32;
+                return rank(Level.High) * 10000 + length * 1000 + rank(Level.Mid) * 100
+                    + squares[last];
+            }
+        ";
+        assert_eq!(run("constants", source, &[]).unwrap(), 38_249);
+    }
+
+    #[test]
+    fn test_for_loops_need_an_iterator() {
+        let source = "
+            struct Point { x: s32; }
+            function main() -> s32 {
+                let point: Point;
+                for (coordinate in point) {
+                    return 1;
+                }
+                return 0;
+            }
+        ";
+        let error = run("points", source, &[]).unwrap_err().to_string();
+        assert!(error.starts_with("cannot iterate over Point"), "{}", error);
+    }
+}
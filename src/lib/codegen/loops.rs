@@ -0,0 +1,197 @@
+use crate::codegen::CodeGen;
+use inkwell::basic_block::BasicBlock;
+use inkwell::values::{BasicValueEnum, IntValue, StructValue};
+use inkwell::IntPredicate;
+
+/// Where `continue` and `break` jump to inside a loop body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoopTargets<'ctx> {
+    pub continue_to: BasicBlock<'ctx>,
+    pub break_to: BasicBlock<'ctx>,
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Counts from `start` to `end`, excluding `end` unless the `inclusive`
+    /// flag is set, and emits `body` once for each value. Stepping past the
+    /// last value is checked before incrementing, so inclusive ranges ending
+    /// at the maximum value of their type do not wrap around.
+    ///
+    /// The builder is left at the end of the loop.
+    pub fn build_counted_loop<F>(
+        &self,
+        start: IntValue<'ctx>,
+        end: IntValue<'ctx>,
+        inclusive: IntValue<'ctx>,
+        signed: bool,
+        body: F,
+    ) -> Option<BasicBlock<'ctx>>
+    where
+        F: FnOnce(LoopTargets<'ctx>, IntValue<'ctx>),
+    {
+        let preheader = self.builder.get_insert_block()?;
+        let function = preheader.get_parent()?;
+        let header = self.context.append_basic_block(function, "for.header");
+        let body_block = self.context.append_basic_block(function, "for.body");
+        let step = self.context.append_basic_block(function, "for.step");
+        let exit = self.context.append_basic_block(function, "for.exit");
+        self.builder.build_unconditional_branch(header);
+
+        self.builder.position_at_end(header);
+        let counter = self.builder.build_phi(start.get_type(), "i");
+        let i = counter.as_basic_value().into_int_value();
+        let less = if signed {
+            IntPredicate::SLT
+        } else {
+            IntPredicate::ULT
+        };
+        let before_end = self.builder.build_int_compare(less, i, end, "before_end");
+        let at_end = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, i, end, "at_end");
+        let at_inclusive_end = self
+            .builder
+            .build_and(inclusive, at_end, "at_inclusive_end");
+        let in_range = self
+            .builder
+            .build_or(before_end, at_inclusive_end, "in_range");
+        self.builder
+            .build_conditional_branch(in_range, body_block, exit);
+
+        self.builder.position_at_end(body_block);
+        body(
+            LoopTargets {
+                continue_to: step,
+                break_to: exit,
+            },
+            i,
+        );
+        self.branch_if_unterminated(step)?;
+
+        self.builder.position_at_end(step);
+        let done = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, i, end, "done");
+        let next = self
+            .builder
+            .build_int_add(i, start.get_type().const_int(1, false), "next");
+        self.builder.build_conditional_branch(done, exit, header);
+        counter.add_incoming(&[(&start, preheader), (&next, step)]);
+
+        self.builder.position_at_end(exit);
+        Some(exit)
+    }
+
+    /// Counts through a `Range<T>` value.
+    pub fn build_range_loop<F>(
+        &self,
+        range: StructValue<'ctx>,
+        signed: bool,
+        body: F,
+    ) -> Option<BasicBlock<'ctx>>
+    where
+        F: FnOnce(LoopTargets<'ctx>, IntValue<'ctx>),
+    {
+        let field = |index, name| {
+            self.builder
+                .build_extract_value(range, index, name)
+                .map(|value| value.into_int_value())
+        };
+        let start = field(0, "start")?;
+        let end = field(1, "end")?;
+        let inclusive = field(2, "inclusive")?;
+        self.build_counted_loop(start, end, inclusive, signed, body)
+    }
+
+    /// Counts through the elements of a sized or growable array, which both
+    /// start with a data pointer and a length.
+    pub fn build_array_loop<F>(&self, array: StructValue<'ctx>, body: F) -> Option<BasicBlock<'ctx>>
+    where
+        F: FnOnce(LoopTargets<'ctx>, BasicValueEnum<'ctx>),
+    {
+        let data = self
+            .builder
+            .build_extract_value(array, 0, "data")?
+            .into_pointer_value();
+        let length = self
+            .builder
+            .build_extract_value(array, 1, "length")?
+            .into_int_value();
+        let index_type = length.get_type();
+        let exclusive = self.context.bool_type().const_zero();
+        self.build_counted_loop(
+            index_type.const_zero(),
+            length,
+            exclusive,
+            false,
+            |targets, index| {
+                let element =
+                    unsafe { self.builder.build_in_bounds_gep(data, &[index], "element") };
+                let element = self.builder.build_load(element, "element");
+                body(targets, element)
+            },
+        )
+    }
+
+    /// Calls `next` until it returns `null`, emitting `body` for each value.
+    /// `next` emits the call and returns the optional it produced: a pointer
+    /// that may be null, or a `{ i1, T }` pair. Both are passed `state`, which
+    /// they would otherwise both have to borrow.
+    pub fn build_iterator_loop<S, N, F>(
+        &self,
+        state: &mut S,
+        next: N,
+        body: F,
+    ) -> Option<BasicBlock<'ctx>>
+    where
+        N: FnOnce(&mut S) -> Option<BasicValueEnum<'ctx>>,
+        F: FnOnce(&mut S, LoopTargets<'ctx>, BasicValueEnum<'ctx>),
+    {
+        let function = self.builder.get_insert_block()?.get_parent()?;
+        let header = self.context.append_basic_block(function, "for.next");
+        let body_block = self.context.append_basic_block(function, "for.body");
+        let exit = self.context.append_basic_block(function, "for.exit");
+        self.builder.build_unconditional_branch(header);
+
+        self.builder.position_at_end(header);
+        let optional = next(state)?;
+        let (has_value, value) = if optional.is_pointer_value() {
+            let pointer = optional.into_pointer_value();
+            let has_value = self.builder.build_is_not_null(pointer, "has_value");
+            (has_value, optional)
+        } else {
+            let optional = optional.into_struct_value();
+            let has_value = self
+                .builder
+                .build_extract_value(optional, 0, "has_value")?
+                .into_int_value();
+            let value = self.builder.build_extract_value(optional, 1, "value")?;
+            (has_value, value)
+        };
+        self.builder
+            .build_conditional_branch(has_value, body_block, exit);
+
+        self.builder.position_at_end(body_block);
+        body(
+            state,
+            LoopTargets {
+                continue_to: header,
+                break_to: exit,
+            },
+            value,
+        );
+        self.branch_if_unterminated(header)?;
+
+        self.builder.position_at_end(exit);
+        Some(exit)
+    }
+
+    /// Falls through to `to` unless the current block already ends in a
+    /// branch or return, e.g. after a `break`.
+    fn branch_if_unterminated(&self, to: BasicBlock<'ctx>) -> Option<()> {
+        let current = self.builder.get_insert_block()?;
+        if current.get_terminator().is_none() {
+            self.builder.build_unconditional_branch(to);
+        }
+        Some(())
+    }
+}
@@ -0,0 +1,334 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{span, unsupported, ModuleCompiler, Value};
+use crate::codegen::CodeGen;
+use crate::semantic::patterns::{
+    check_match, enum_discriminants, integer_value, range_bounds, MatchLowering,
+};
+use crate::semantic::types::{Type, TypeDefinition};
+use crate::syntax::ast::*;
+use inkwell::basic_block::BasicBlock;
+use inkwell::values::{BasicValueEnum, InstructionValue, IntValue};
+use inkwell::IntPredicate;
+use std::collections::HashMap;
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Branches to the arm handling `value` with a `switch` instruction.
+    /// `arms` holds the entry block of each arm, in order. Matches without a
+    /// default arm jump to an `unreachable` block for values no case handles.
+    ///
+    /// Returns `None` if the match has to be lowered as a chain of tests.
+    pub fn build_match_switch(
+        &self,
+        lowering: &MatchLowering,
+        value: IntValue<'ctx>,
+        arms: &[BasicBlock<'ctx>],
+    ) -> Option<InstructionValue<'ctx>> {
+        let (cases, default) = match lowering {
+            MatchLowering::Switch { cases, default } => (cases, default),
+            MatchLowering::Chain => return None,
+        };
+        let otherwise = match default {
+            Some(arm) => arms[*arm],
+            None => {
+                let current = self.builder.get_insert_block()?;
+                let function = current.get_parent()?;
+                let unmatched = self
+                    .context
+                    .append_basic_block(function, "match.unreachable");
+                self.builder.position_at_end(unmatched);
+                self.builder.build_unreachable();
+                self.builder.position_at_end(current);
+                unmatched
+            }
+        };
+        let int_type = value.get_type();
+        let cases: Vec<(IntValue<'ctx>, BasicBlock<'ctx>)> = cases
+            .iter()
+            .map(|(case, arm)| (int_type.const_int(*case as i64 as u64, true), arms[*arm]))
+            .collect();
+        Some(self.builder.build_switch(value, otherwise, &cases))
+    }
+}
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Compiles a `match` statement whose arms `check_match` accepts. Arms
+    /// on integers and enums become a `switch` where they can; otherwise
+    /// each arm's pattern and guard are tested in order.
+    pub(super) fn compile_match(
+        &mut self,
+        statement: &StatementNode<'ast>,
+        matching: &MatchStatement<'ast>,
+    ) -> Result<(), CodeGenError> {
+        let reachable = self.reachable;
+        let scrutinee = self.compile_expression(&matching.scrutinee, None)?;
+        let lowering = check_match(&self.env, &scrutinee.ty, matching, span(statement))?;
+        let function = self
+            .current_block()?
+            .get_parent()
+            .ok_or(CodeGenError::Unknown)?;
+        let context = self.codegen.context;
+        let blocks: Vec<BasicBlock<'ctx>> = matching
+            .arms
+            .iter()
+            .map(|_| context.append_basic_block(function, "match.arm"))
+            .collect();
+        let merge = context.append_basic_block(function, "match.end");
+
+        let switched = match scrutinee.value {
+            Some(BasicValueEnum::IntValue(value)) => {
+                self.codegen.build_match_switch(&lowering, value, &blocks)
+            }
+            _ => None,
+        };
+        let mut merged = false;
+        if switched.is_some() {
+            for (arm, block) in matching.arms.iter().zip(&blocks) {
+                self.codegen.builder.position_at_end(*block);
+                self.reachable = reachable;
+                self.compile_branch(&arm.value.body)?;
+                merged |= self.fall_through(merge);
+            }
+        } else {
+            for (arm, block) in matching.arms.iter().zip(&blocks) {
+                let next = context.append_basic_block(function, "match.next");
+                self.reachable = reachable;
+                // Bindings are in scope in the guard and the body.
+                self.scopes.push(HashMap::new());
+                let matched = self.build_pattern_test(&scrutinee, &arm.value.pattern)?;
+                let guard = match arm.value.guard {
+                    Some(guard) => {
+                        let test = context.append_basic_block(function, "match.guard");
+                        self.build_branch(matched, test, next);
+                        self.codegen.builder.position_at_end(test);
+                        Some(self.compile_condition(&guard)?)
+                    }
+                    None => matched,
+                };
+                self.build_branch(guard, *block, next);
+                self.codegen.builder.position_at_end(*block);
+                let res = self.compile_statement(&arm.value.body);
+                self.scopes.pop();
+                res?;
+                merged |= self.fall_through(merge);
+                self.codegen.builder.position_at_end(next);
+            }
+            // The arms are exhaustive.
+            self.codegen.builder.build_unreachable();
+        }
+
+        self.codegen.builder.position_at_end(merge);
+        self.reachable = merged;
+        Ok(())
+    }
+
+    /// Branches to `then` if `condition` holds, or always without one.
+    fn build_branch(
+        &self,
+        condition: Option<IntValue<'ctx>>,
+        then: BasicBlock<'ctx>,
+        otherwise: BasicBlock<'ctx>,
+    ) {
+        let builder = &self.codegen.builder;
+        match condition {
+            Some(condition) => builder.build_conditional_branch(condition, then, otherwise),
+            None => builder.build_unconditional_branch(then),
+        };
+    }
+
+    /// Whether `scrutinee` matches `pattern`, or `None` if it always does.
+    /// Type patterns bind the value in the innermost scope.
+    fn build_pattern_test(
+        &mut self,
+        scrutinee: &Value<'ctx>,
+        pattern: &PatternNode<'ast>,
+    ) -> Result<Option<IntValue<'ctx>>, CodeGenError> {
+        if let Type::Union(members) = scrutinee.ty.unqualified() {
+            return self.build_union_pattern_test(scrutinee, members, pattern);
+        }
+        let builder = &self.codegen.builder;
+        let value = scrutinee.value.ok_or(CodeGenError::Unknown)?;
+        // Patterns other than type patterns and `null` test the value an
+        // optional holds.
+        let (present, inner) = match scrutinee.ty.unqualified() {
+            Type::Optional(inner) => {
+                let (present, value) = match value {
+                    BasicValueEnum::PointerValue(pointer) => {
+                        (builder.build_is_not_null(pointer, "present"), value)
+                    }
+                    optional => {
+                        let optional = optional.into_struct_value();
+                        let present = builder
+                            .build_extract_value(optional, 0, "present")
+                            .ok_or(CodeGenError::Unknown)?
+                            .into_int_value();
+                        let value = builder
+                            .build_extract_value(optional, 1, "value")
+                            .ok_or(CodeGenError::Unknown)?;
+                        (present, value)
+                    }
+                };
+                (
+                    Some(present),
+                    Value {
+                        value: Some(value),
+                        ty: (**inner).clone(),
+                    },
+                )
+            }
+            _ => (None, scrutinee.clone()),
+        };
+        let and_present = |test: IntValue<'ctx>| match present {
+            Some(present) => builder.build_and(present, test, "matched"),
+            None => test,
+        };
+        let test = match pattern.value {
+            Pattern::Wildcard => return Ok(None),
+            Pattern::Literal(LiteralPattern {
+                value: Primitive::Null,
+                ..
+            }) => {
+                let present = present.ok_or_else(|| unsupported("this pattern", pattern))?;
+                builder.build_not(present, "empty")
+            }
+            Pattern::Literal(literal) => {
+                let expected = integer_value(&inner.ty, &literal)
+                    .ok_or_else(|| unsupported("this pattern", pattern))?;
+                and_present(self.build_equals(&inner, expected)?)
+            }
+            Pattern::Range(range) => {
+                let (start, end) = range_bounds(&inner.ty, &range)
+                    .ok_or_else(|| unsupported("this pattern", pattern))?;
+                let signed = match inner.ty.unqualified() {
+                    Type::Primitive(pt) => matches!(pt.integer_layout(), Some((true, _))),
+                    _ => false,
+                };
+                let (above, below) = if signed {
+                    (IntPredicate::SGE, IntPredicate::SLE)
+                } else {
+                    (IntPredicate::UGE, IntPredicate::ULE)
+                };
+                let value = inner.value.ok_or(CodeGenError::Unknown)?.into_int_value();
+                let int_type = value.get_type();
+                let start = int_type.const_int(start as u64, signed);
+                let end = int_type.const_int(end as u64, signed);
+                let above = builder.build_int_compare(above, value, start, "range.start");
+                let below = builder.build_int_compare(below, value, end, "range.end");
+                and_present(builder.build_and(above, below, "in_range"))
+            }
+            Pattern::EnumVariant(variant) => {
+                let discriminants = match inner.ty.unqualified() {
+                    Type::Enum(name) => match self.env.get(name) {
+                        Some(TypeDefinition::Enum(declarator)) => {
+                            enum_discriminants(&self.env, declarator)
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let expected = discriminants
+                    .and_then(|discriminants| {
+                        discriminants
+                            .into_iter()
+                            .find(|(name, _)| *name == variant.variant.value)
+                    })
+                    .map(|(_, value)| value)
+                    .ok_or_else(|| unsupported("variants with computed values", pattern))?;
+                and_present(self.build_equals(&inner, expected)?)
+            }
+            Pattern::Type(type_pattern) => {
+                let ty = self.env.resolve(&type_pattern.type_expression, &[])?;
+                let (bound, test) = if ty.contains(&scrutinee.ty) {
+                    (scrutinee.clone(), None)
+                } else if present.is_some() && ty.contains(&inner.ty) {
+                    (inner, present)
+                } else {
+                    return Err(unsupported("this pattern", pattern));
+                };
+                let binding = type_pattern.binding.value;
+                let variable = self.declare_local(binding, bound.ty, false)?;
+                let value = bound.value.ok_or(CodeGenError::Unknown)?;
+                self.codegen.builder.build_store(variable.pointer, value);
+                return Ok(test);
+            }
+        };
+        Ok(Some(test))
+    }
+
+    /// Whether the union `scrutinee` of `members` matches `pattern`. Type
+    /// patterns and `null` test the union's tag, and other patterns test
+    /// each member they fit.
+    fn build_union_pattern_test(
+        &mut self,
+        scrutinee: &Value<'ctx>,
+        members: &[Type],
+        pattern: &PatternNode<'ast>,
+    ) -> Result<Option<IntValue<'ctx>>, CodeGenError> {
+        let union = scrutinee
+            .value
+            .ok_or(CodeGenError::Unknown)?
+            .into_struct_value();
+        let tags_of = |fits: &dyn Fn(&Type) -> bool| -> Vec<u32> {
+            (0..members.len() as u32)
+                .filter(|tag| fits(&members[*tag as usize]))
+                .collect()
+        };
+        let test = match pattern.value {
+            Pattern::Wildcard => return Ok(None),
+            Pattern::Type(type_pattern) => {
+                let ty = self.env.resolve(&type_pattern.type_expression, &[])?;
+                let tags = tags_of(&|member| ty.contains(member));
+                let bound = self.build_union_narrowing(union, members, &tags, &ty)?;
+                if let Some(value) = bound.value {
+                    let binding = type_pattern.binding.value;
+                    let variable = self.declare_local(binding, bound.ty, false)?;
+                    self.codegen.builder.build_store(variable.pointer, value);
+                }
+                self.codegen.build_tag_check(union, &tags)
+            }
+            Pattern::Literal(LiteralPattern {
+                value: Primitive::Null,
+                ..
+            }) => {
+                let null = Type::Primitive(PrimitiveType::Null);
+                self.codegen
+                    .build_tag_check(union, &tags_of(&|member| *member == null))
+            }
+            _ => {
+                let fits = |member: &Type| match pattern.value {
+                    Pattern::Literal(literal) => integer_value(member, &literal).is_some(),
+                    Pattern::Range(range) => range_bounds(member, &range).is_some(),
+                    _ => false,
+                };
+                let mut test = None;
+                for tag in tags_of(&fits) {
+                    let member = self.build_union_member(union, members, tag)?;
+                    let matched = self
+                        .build_pattern_test(&member, pattern)?
+                        .ok_or(CodeGenError::Unknown)?;
+                    let builder = &self.codegen.builder;
+                    let held = self.codegen.build_tag_check(union, &[tag]);
+                    let matched = builder.build_and(held, matched, "matched");
+                    test = Some(match test {
+                        Some(test) => builder.build_or(test, matched, "matched"),
+                        None => matched,
+                    });
+                }
+                test.ok_or_else(|| unsupported("this pattern", pattern))?
+            }
+        };
+        Ok(Some(test))
+    }
+
+    fn build_equals(
+        &self,
+        value: &Value<'ctx>,
+        expected: i128,
+    ) -> Result<IntValue<'ctx>, CodeGenError> {
+        let value = value.value.ok_or(CodeGenError::Unknown)?.into_int_value();
+        let expected = value.get_type().const_int(expected as u64, false);
+        Ok(self
+            .codegen
+            .builder
+            .build_int_compare(IntPredicate::EQ, value, expected, "matched"))
+    }
+}
@@ -0,0 +1,183 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::expressions::void;
+use crate::codegen::module::{span, unsupported, FunctionBody, ModuleCompiler, Value, Variable};
+use crate::semantic::methods::{Dispatch, MethodCall, ReceiverAdjustment};
+use crate::semantic::types::{FunctionSignature, NamedTypeRef, Type, TypeDefinition};
+use crate::syntax::ast::*;
+use inkwell::values::{BasicMetadataValueEnum, FunctionValue, PointerValue};
+use std::ops::Range;
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Declares the method a scoped function defines. Methods of generic
+    /// types and generic methods are not compiled yet, and are rejected
+    /// where they are defined rather than where they are called.
+    pub(super) fn declare_scoped_method(
+        &self,
+        declarator: &FunctionDeclarator<'ast>,
+    ) -> Result<FunctionBody<'ast, 'ctx>, CodeGenError> {
+        let method = self
+            .methods
+            .defined_by(declarator)
+            .ok_or(CodeGenError::Unknown)?;
+        if !method.owner_generics.is_empty() || !declarator.generic_parameters.is_empty() {
+            return Err(unsupported("generic methods", &declarator.function_name));
+        }
+        let function = self.codegen.module.add_function(
+            &method.symbol(),
+            self.codegen.function_type(&method.signature)?,
+            None,
+        );
+        let receiver = if method.signature.receiver {
+            Some(self.owner_type(method.owner)?)
+        } else {
+            None
+        };
+        Ok(FunctionBody {
+            declarator: *declarator,
+            function,
+            signature: method.signature.clone(),
+            receiver,
+        })
+    }
+
+    /// The type a method is defined in the scope of.
+    fn owner_type(&self, owner: &str) -> Result<Type, CodeGenError> {
+        match self.env.get(owner) {
+            Some(TypeDefinition::Struct(_)) => Ok(Type::Struct(NamedTypeRef::new(owner))),
+            Some(TypeDefinition::Enum(_)) => Ok(Type::Enum(owner.to_string())),
+            _ => Err(CodeGenError::Unknown),
+        }
+    }
+
+    /// Calls `object.method(...)`. Values are passed by address; interface
+    /// pointers call through their vtable.
+    pub(super) fn compile_method_call(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        call: &CallExpression<'ast>,
+        access: &MemberAccessExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let object = self.compile_address(&access.object)?;
+        let method = access.member;
+        let resolved = self.methods.resolve_instance(
+            &self.interfaces,
+            &self.env,
+            &object.ty,
+            method.value,
+            Some(call.arguments.iter().count()),
+            span(&method),
+        )?;
+        let arguments =
+            self.compile_arguments(expression, call, method.value, &resolved.signature)?;
+        self.build_resolved_call(&resolved, &object, &arguments, span(expression))
+    }
+
+    /// Calls a resolved method on the object stored at `object`, with
+    /// arguments converted to its parameter types.
+    pub(super) fn build_resolved_call(
+        &mut self,
+        resolved: &MethodCall,
+        object: &Variable<'ctx>,
+        arguments: &[BasicMetadataValueEnum<'ctx>],
+        span: Range<usize>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let builder = &self.codegen.builder;
+        let symbol = match &resolved.dispatch {
+            Dispatch::Static(symbol) => symbol,
+            Dispatch::Dynamic { interface, slot } => {
+                // The vtable's layout is needed even where no
+                // implementation's vtable is emitted.
+                if self.codegen.vtable_type(interface).is_opaque() {
+                    let signatures = self.interfaces.method_signatures(&self.env, interface)?;
+                    self.codegen.define_vtable_type(interface, &signatures)?;
+                }
+                let fat = builder
+                    .build_load(object.pointer, "object")
+                    .into_struct_value();
+                let value = self
+                    .codegen
+                    .build_dynamic_call(interface, fat, *slot, arguments)?;
+                return Ok(Value {
+                    value,
+                    ty: resolved.signature.return_type.clone().unwrap_or_else(void),
+                });
+            }
+        };
+        let receiver = match resolved.receiver {
+            ReceiverAdjustment::AutoRef => object.pointer,
+            ReceiverAdjustment::None => builder
+                .build_load(object.pointer, "object")
+                .into_pointer_value(),
+        };
+        let function =
+            self.codegen
+                .module
+                .get_function(symbol)
+                .ok_or(CodeGenError::Unsupported {
+                    construct: "calls to generic methods",
+                    span,
+                })?;
+        let mut values = vec![self.build_receiver(receiver)];
+        values.extend_from_slice(arguments);
+        Ok(self.build_method_call(function, &resolved.signature, &values))
+    }
+
+    /// Calls `Owner::method(...)`. Instance methods called this way take a
+    /// pointer to their receiver as the first argument.
+    pub(super) fn compile_static_call(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        call: &CallExpression<'ast>,
+        scoped: &ScopedExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let method = self
+            .methods
+            .resolve_static_call(
+                scoped.scope.value,
+                scoped.member.value,
+                Some(call.arguments.iter().count()),
+                span(&scoped.member),
+            )?
+            .clone();
+        let function = self
+            .codegen
+            .module
+            .get_function(&method.symbol())
+            .ok_or_else(|| unsupported("calls to generic methods", expression))?;
+        let mut parameters = method.signature.clone();
+        if parameters.receiver {
+            let this = Type::Pointer(Box::new(self.owner_type(method.owner)?));
+            parameters.receiver = false;
+            parameters.parameters.insert(0, this);
+        }
+        let name = format!("{}::{}", method.owner, method.name());
+        let mut arguments = self.compile_arguments(expression, call, &name, &parameters)?;
+        if method.signature.receiver {
+            if let Some(BasicMetadataValueEnum::PointerValue(this)) = arguments.first().copied() {
+                arguments[0] = self.build_receiver(this);
+            }
+        }
+        Ok(self.build_method_call(function, &method.signature, &arguments))
+    }
+
+    /// Passes a pointer as `this`, which methods take as an untyped pointer.
+    fn build_receiver(&self, pointer: PointerValue<'ctx>) -> BasicMetadataValueEnum<'ctx> {
+        self.codegen
+            .builder
+            .build_pointer_cast(pointer, self.codegen.opaque_pointer_type(), "this")
+            .into()
+    }
+
+    fn build_method_call(
+        &self,
+        function: FunctionValue<'ctx>,
+        signature: &FunctionSignature,
+        arguments: &[BasicMetadataValueEnum<'ctx>],
+    ) -> Value<'ctx> {
+        let call = self.codegen.builder.build_call(function, arguments, "call");
+        Value {
+            value: call.try_as_basic_value().left(),
+            ty: signature.return_type.clone().unwrap_or_else(void),
+        }
+    }
+}
@@ -1,12 +1,33 @@
+use crate::codegen::optimize::{OptLevel, PassDebugging};
+use crate::syntax::ast::PrimitiveType;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::module::Module;
 use inkwell::targets::{CodeModel, FileType, RelocMode, Target, TargetMachine, TargetTriple};
-use inkwell::OptimizationLevel;
 use lazy_static::lazy_static;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::path::Path;
 
-mod error;
+mod builtins;
+pub mod entry;
+pub mod error;
+mod expressions;
+mod foreign;
+mod interfaces;
+pub mod jit;
+mod loops;
+mod matching;
+mod methods;
+pub mod module;
+mod operators;
+pub mod optimize;
+pub mod repl;
+mod results;
+mod statements;
+mod structs;
+mod types;
+mod vtable;
 
 lazy_static! {
     pub static ref DEFAULT_TARGET_TRIPLE: String = TargetMachine::get_default_triple()
@@ -19,84 +40,110 @@ pub struct CodeGen<'ctx> {
     pub context: &'ctx Context,
     pub module: Module<'ctx>,
     pub builder: Builder<'ctx>,
+    pub checks: Checks,
+    pub passes: PassDebugging,
+    /// The representation types of the enums defined so far.
+    enums: RefCell<HashMap<String, PrimitiveType>>,
+}
+
+/// The checks compiled into the program, which trap when they fail.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Checks {
+    /// Whether `assert` checks its condition.
+    pub assertions: bool,
+    /// Whether integer `+`, `-` and `*` trap when they overflow.
+    pub overflow: bool,
+}
+
+impl Checks {
+    /// Every check, for unoptimized builds.
+    pub fn all() -> Self {
+        Checks {
+            assertions: true,
+            overflow: true,
+        }
+    }
 }
 
 impl<'ctx> CodeGen<'ctx> {
     pub fn new(context: &'ctx Context) -> Self {
-        let module = context.create_module("example");
+        Self::named(context, "example")
+    }
+
+    /// Creates a code generator for an LLVM module called `name`, without
+    /// checks.
+    pub fn named(context: &'ctx Context, name: &str) -> Self {
+        let module = context.create_module(name);
         let builder = context.create_builder();
 
         CodeGen {
             context,
             module,
             builder,
+            checks: Checks::default(),
+            passes: PassDebugging::default(),
+            enums: RefCell::new(HashMap::new()),
         }
     }
 
-    pub fn compile_example(&mut self) -> Option<()> {
-        let i64_type = self.context.i64_type();
-        let fn_type = i64_type.fn_type(&[i64_type.into(), i64_type.into(), i64_type.into()], false);
-        let function = self.module.add_function("sum", fn_type, None);
-        let basic_block = self.context.append_basic_block(function, "entry");
-
-        self.builder.position_at_end(basic_block);
-
-        let x = function.get_nth_param(0)?.into_int_value();
-        let y = function.get_nth_param(1)?.into_int_value();
-        let z = function.get_nth_param(2)?.into_int_value();
-
-        let sum = self.builder.build_int_add(x, y, "sum");
-        let sum = self.builder.build_int_add(sum, z, "sum");
-
-        self.builder.build_return(Some(&sum));
-
-        Some(())
-    }
-
     pub fn write_to_string(&self) -> String {
         self.module.print_to_string().to_string()
     }
 
+    /// Optimizes the module with the pipeline of `opt_level` and writes it
+    /// to the object file `path`.
     pub fn write_output_file<P: AsRef<Path>>(
         &self,
-        optimization: u8,
+        opt_level: OptLevel,
         target_triple: &str,
         path: P,
     ) -> anyhow::Result<()> {
-        let opt_level = match optimization {
-            0 => OptimizationLevel::None,       // -O0
-            1 => OptimizationLevel::Less,       // -O1
-            2 => OptimizationLevel::Default,    // -O2, -Os
-            _ => OptimizationLevel::Aggressive, // -O3
-        };
-        let target_triple = TargetTriple::create(target_triple);
-        let target = Target::from_triple(&target_triple).map_err(error::CodeGenError::from)?;
-        let target_machine = target
-            .create_target_machine(
-                &target_triple,
-                "generic",
-                "",
-                opt_level,
-                RelocMode::Default,
-                CodeModel::Default,
-            )
-            .ok_or(error::CodeGenError::CouldNotCreateTargetMachine)?;
-
+        let target_machine = target_machine(target_triple, opt_level)?;
+        self.optimize(opt_level, &target_machine)?;
         target_machine
             .write_to_file(&self.module, FileType::Object, path.as_ref())
             .map_err(error::CodeGenError::from)?;
 
         Ok(())
     }
-}
 
-pub fn run(optimization: u8, target_triple: &str) -> anyhow::Result<String> {
-    Target::initialize_all(&Default::default());
-    let context = Context::create();
-    let mut codegen = CodeGen::new(&context);
-    codegen.compile_example();
-
-    codegen.write_output_file(optimization, target_triple, "module.o")?;
+    /// Optimizes the module with the pipeline of `opt_level`, for the target
+    /// `target_machine` generates code for.
+    pub fn optimize(
+        &self,
+        opt_level: OptLevel,
+        target_machine: &TargetMachine,
+    ) -> Result<(), error::CodeGenError> {
+        // The target decides what the optimizer can assume, like the sizes
+        // of types and which vector instructions there are.
+        self.module.set_triple(&target_machine.get_triple());
+        self.module
+            .set_data_layout(&target_machine.get_target_data().get_data_layout());
+        self.module.run_passes(
+            opt_level.pipeline(),
+            target_machine,
+            opt_level.pass_options(&self.passes),
+        )?;
+        Ok(())
+    }
+}
 
-    Ok(codegen.write_to_string())
+/// A machine generating code for `target_triple` at `opt_level`, for
+/// targets that have been initialized.
+pub fn target_machine(
+    target_triple: &str,
+    opt_level: OptLevel,
+) -> Result<TargetMachine, error::CodeGenError> {
+    let target_triple = TargetTriple::create(target_triple);
+    let target = Target::from_triple(&target_triple)?;
+    target
+        .create_target_machine(
+            &target_triple,
+            "generic",
+            "",
+            opt_level.codegen_level(),
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .ok_or(error::CodeGenError::CouldNotCreateTargetMachine)
 }
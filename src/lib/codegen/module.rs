@@ -0,0 +1,994 @@
+use crate::codegen::entry::Start;
+use crate::codegen::error::CodeGenError;
+use crate::codegen::loops::LoopTargets;
+use crate::codegen::CodeGen;
+use crate::interop::{self, ImportedHeader};
+use crate::semantic::constants::Constant;
+use crate::semantic::entry::{arguments_type, find_entry_point, EntryPoint, MAIN};
+use crate::semantic::error::{Error, Warning};
+use crate::semantic::functions::{FunctionTable, Overload};
+use crate::semantic::interfaces::InterfaceTable;
+use crate::semantic::methods::MethodTable;
+use crate::semantic::module_declarations;
+use crate::semantic::prelude::define_prelude;
+use crate::semantic::types::{FunctionSignature, Type, TypeEnvironment, LOCAL_MODULE};
+use crate::syntax::ast::*;
+use inkwell::basic_block::BasicBlock;
+use inkwell::builder::Builder;
+use inkwell::values::{BasicValueEnum, FunctionValue, IntValue, PointerValue};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+/// What a program needs from a compiled module besides its functions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompiledModule<'ctx> {
+    /// Initializes the module's variables. A program runs the initializers
+    /// of its modules in their initialization order before it starts.
+    pub initializer: Option<FunctionValue<'ctx>>,
+    /// Where a program starting in this module begins, if it can.
+    pub start: Option<Start<'ctx>>,
+}
+
+/// The storage of a variable: a stack slot or a global.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable<'ctx> {
+    pub pointer: PointerValue<'ctx>,
+    pub ty: Type,
+    pub mutable: bool,
+}
+
+/// A compiled expression and its type. `value` is `None` for calls to
+/// functions without a return type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Value<'ctx> {
+    pub value: Option<BasicValueEnum<'ctx>>,
+    pub ty: Type,
+}
+
+/// A loop being compiled, and whether anything breaks out of it.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct EnclosingLoop<'ctx> {
+    pub targets: LoopTargets<'ctx>,
+    pub broken: bool,
+}
+
+/// A declared function and what its body is compiled from.
+pub(super) struct FunctionBody<'ast, 'ctx> {
+    pub declarator: FunctionDeclarator<'ast>,
+    pub function: FunctionValue<'ctx>,
+    pub signature: FunctionSignature,
+    /// The type `this` points to in methods.
+    pub receiver: Option<Type>,
+}
+
+/// Lowers the functions and top-level code of a module to LLVM IR.
+///
+/// Free functions, the methods of non-generic types and `impl` blocks are
+/// compiled, along with the vtables of the interfaces cast to. Generic
+/// functions are resolved by the semantic tables but not yet emitted.
+pub struct ModuleCompiler<'a, 'ctx, 'ast> {
+    pub(super) codegen: &'a CodeGen<'ctx>,
+    pub(super) name: String,
+    pub(super) env: TypeEnvironment<'ast>,
+    pub(super) functions: FunctionTable<'ast>,
+    pub(super) interfaces: InterfaceTable<'ast>,
+    pub(super) methods: MethodTable<'ast>,
+    pub(super) globals: HashMap<&'ast str, Variable<'ctx>>,
+    /// Constants imported by name from C headers.
+    pub(super) foreign_constants: HashMap<&'ast str, Constant>,
+    /// The variables of catlang modules imported with `import * as`, by
+    /// namespace and the name they are exported as.
+    pub(super) namespace_variables: HashMap<&'ast str, HashMap<&'ast str, Variable<'ctx>>>,
+    pub(super) warnings: Vec<Warning>,
+
+    // The state of the function being compiled.
+    pub(super) scopes: Vec<HashMap<&'ast str, Variable<'ctx>>>,
+    pub(super) loops: Vec<EnclosingLoop<'ctx>>,
+    pub(super) return_type: Option<Type>,
+    /// Whether control can reach the builder's position. Code after a
+    /// `return`, `break` or `continue` is emitted into a block nothing jumps
+    /// to.
+    pub(super) reachable: bool,
+    /// Positioned in the entry block of the current function, which holds
+    /// its stack slots so that loops do not grow the stack.
+    allocas: Builder<'ctx>,
+    /// The block the entry block falls through to.
+    body: Option<BasicBlock<'ctx>>,
+}
+
+/// A variable whose global is defined in another LLVM module: that of the
+/// module it is imported from, or of an earlier REPL input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalVariable {
+    pub symbol: String,
+    pub ty: Type,
+    pub mutable: bool,
+}
+
+/// What modules importing a module can use from it.
+pub struct ExportedItems<'ast> {
+    /// Its functions, including those it imports from C headers.
+    pub functions: FunctionTable<'ast>,
+    /// The variables it exports, by the name they are exported as.
+    pub variables: HashMap<&'ast str, ExternalVariable>,
+    /// The values of the constants among its variables, which importers
+    /// can use in array sizes and other constants.
+    pub constants: HashMap<&'ast str, Constant>,
+}
+
+/// The symbol of the global that holds the variable `name` of `module`.
+fn global_symbol(module: &str, name: &str) -> String {
+    format!("{}::{}", module, name)
+}
+
+/// The C headers `module` imports, and its types and functions along with
+/// those it imports from them. The constants of the catlang modules that
+/// export `imports` are known to its constant evaluator. `name` identifies
+/// the module in symbols, and is the path headers are found relative to.
+fn resolve_module<'ast>(
+    name: &str,
+    module: &Module<'ast>,
+    imports: &[(&str, &ExportedItems<'ast>)],
+) -> Result<
+    (
+        Vec<ImportedHeader<'ast>>,
+        TypeEnvironment<'ast>,
+        FunctionTable<'ast>,
+    ),
+    CodeGenError,
+> {
+    let directory = Path::new(name).parent().unwrap_or_else(|| Path::new(""));
+    let headers = interop::load_headers(module, directory)?;
+    let mut env = TypeEnvironment::from_module(module).map_err(CodeGenError::from)?;
+    for (import, from) in imports {
+        env.constants()
+            .map_err(CodeGenError::from)?
+            .add_exports(import, &from.constants);
+    }
+    define_prelude(&mut env);
+    interop::import_types(&headers, &mut env)?;
+    let mut functions = FunctionTable::build(name, module, &env).map_err(CodeGenError::from)?;
+    interop::import_functions(&headers, &mut functions)?;
+    Ok((headers, env, functions))
+}
+
+/// What `module` exports, as modules importing it see it, including what
+/// it re-exports from the catlang modules that export `imports`, by the
+/// import naming each. `name` identifies the module in symbols, and is the
+/// path headers are found relative to.
+pub fn exported_items<'ast>(
+    name: &str,
+    module: &Module<'ast>,
+    imports: &[(&str, &ExportedItems<'ast>)],
+) -> Result<ExportedItems<'ast>, CodeGenError> {
+    let (_, env, mut functions) = resolve_module(name, module, imports)?;
+    let exports = export_names(module);
+    let mut variables = exported_variables(name, module, &env, &exports)?;
+    // Constants that fail to evaluate are reported when the module itself
+    // is compiled; importers just cannot fold them.
+    let mut constants = HashMap::new();
+    for (local, exported_as) in &exports {
+        if let Ok(constant) = env
+            .constants()
+            .map_err(CodeGenError::from)?
+            .constant(LOCAL_MODULE, local)
+        {
+            constants.insert(*exported_as, constant);
+        }
+    }
+    for (import, from) in imports {
+        for imported in named_imports(module, import) {
+            let identifier = imported.identifier;
+            let local_name = imported.renamed_to.unwrap_or(identifier);
+            if let Some(variable) = from.variables.get(identifier.value) {
+                for (_, exported_as) in exports
+                    .iter()
+                    .filter(|(local, _)| *local == local_name.value)
+                {
+                    variables.insert(*exported_as, variable.clone());
+                }
+                continue;
+            }
+            functions.import(&from.functions, &imported)?;
+        }
+    }
+    functions.export_functions(module);
+    Ok(ExportedItems {
+        functions,
+        variables,
+        constants,
+    })
+}
+
+/// The names `module` imports by name from the module it names `import`.
+fn named_imports<'m, 'ast: 'm>(
+    module: &'m Module<'ast>,
+    import: &'m str,
+) -> impl Iterator<Item = ImportIdentifier<'ast>> + 'm {
+    module
+        .elements
+        .iter()
+        .filter_map(move |element| match element.value {
+            ModuleElement::Import(node) if node.value.path.value.trim_matches('"') == import => {
+                match node.value.import_list {
+                    ImportList::NamedImportList(named) => Some(named.imports.iter()),
+                    ImportList::GlobImportList(_) => None,
+                }
+            }
+            _ => None,
+        })
+        .flatten()
+        .map(|imported| imported.value)
+}
+
+/// The local names `module` exports variables and functions under, with the
+/// names they are exported as.
+fn export_names<'ast>(module: &Module<'ast>) -> Vec<(&'ast str, &'ast str)> {
+    let mut exports = vec![];
+    for element in module.elements.iter() {
+        if let ModuleElement::Export(export) = element.value {
+            match export.value {
+                Export::Declaration(declaration) => match declaration.value.declarator {
+                    Declarator::Variable(VariableDeclarator { identifier, .. })
+                    | Declarator::Constant(ConstantDeclarator { identifier, .. }) => {
+                        exports.push((identifier.value, identifier.value))
+                    }
+                    _ => {}
+                },
+                Export::Statement(statement) => {
+                    let exported_as = statement.renamed_to.unwrap_or(statement.identifier);
+                    exports.push((statement.identifier.value, exported_as.value));
+                }
+                Export::ReExport(_) => {}
+            }
+        }
+    }
+    exports
+}
+
+/// The variables `module` declares and exports under the names in
+/// `exports`. Importers are compiled without the module, so each needs a
+/// declared type or a constant initializer.
+fn exported_variables<'ast>(
+    name: &str,
+    module: &Module<'ast>,
+    env: &TypeEnvironment<'ast>,
+    exports: &[(&'ast str, &'ast str)],
+) -> Result<HashMap<&'ast str, ExternalVariable>, CodeGenError> {
+    let mut variables = HashMap::new();
+    for declaration in module_declarations(module) {
+        let (identifier, type_expression, expression, mutable) = match declaration.value.declarator
+        {
+            Declarator::Variable(v) => (v.identifier, v.type_expression, v.expression, true),
+            Declarator::Constant(c) => (c.identifier, c.type_expression, Some(c.expression), false),
+            _ => continue,
+        };
+        let exported_as = exports
+            .iter()
+            .filter(|(local, _)| *local == identifier.value)
+            .map(|(_, exported_as)| *exported_as);
+        for exported_as in exported_as {
+            let ty = match (type_expression, expression) {
+                (Some(ty), _) => env.resolve(&ty, &[])?,
+                (None, Some(expression)) => env
+                    .constants()
+                    .map_err(CodeGenError::from)?
+                    .evaluate(LOCAL_MODULE, &expression, None)
+                    .map_err(|_| {
+                        unsupported(
+                            "exported variables without a type or constant value",
+                            &identifier,
+                        )
+                    })?
+                    .ty(),
+                (None, None) => return Err(unsupported("variables without a value", &identifier)),
+            };
+            let variable = ExternalVariable {
+                symbol: global_symbol(name, identifier.value),
+                ty,
+                mutable,
+            };
+            variables.insert(exported_as, variable);
+        }
+    }
+    Ok(variables)
+}
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Resolves the types and functions of `module`, including those it
+    /// imports from C headers. `name` identifies the module in symbols, and
+    /// is the path headers are found relative to.
+    pub fn new(
+        codegen: &'a CodeGen<'ctx>,
+        name: &str,
+        module: &Module<'ast>,
+    ) -> Result<Self, CodeGenError> {
+        Self::with_imports(codegen, name, module, &[])
+    }
+
+    /// Like [`ModuleCompiler::new`], and also imports what `module` imports
+    /// from the catlang modules that export `imports`, by the import naming
+    /// each. Their functions and variables are declared without
+    /// definitions, so the object of this module links against the ones
+    /// they are compiled into, and their constants are folded.
+    pub fn with_imports(
+        codegen: &'a CodeGen<'ctx>,
+        name: &str,
+        module: &Module<'ast>,
+        imports: &[(&str, &ExportedItems<'ast>)],
+    ) -> Result<Self, CodeGenError> {
+        let (headers, env, functions) = resolve_module(name, module, imports)?;
+        let interfaces = InterfaceTable::build(module, &env).map_err(CodeGenError::from)?;
+        let methods = MethodTable::build(module, &env).map_err(CodeGenError::from)?;
+        let mut compiler = ModuleCompiler {
+            codegen,
+            name: name.to_string(),
+            env,
+            functions,
+            interfaces,
+            methods,
+            globals: HashMap::new(),
+            foreign_constants: HashMap::new(),
+            namespace_variables: HashMap::new(),
+            warnings: vec![],
+            scopes: vec![],
+            loops: vec![],
+            return_type: None,
+            reachable: true,
+            allocas: codegen.context.create_builder(),
+            body: None,
+        };
+        compiler.declare_headers(&headers)?;
+        compiler.define_types()?;
+        for (import, from) in imports {
+            compiler.import_module(module, import, from)?;
+        }
+        Ok(compiler)
+    }
+
+    /// The functions of the module, with the symbols they are emitted under.
+    pub fn functions(&self) -> &FunctionTable<'ast> {
+        &self.functions
+    }
+
+    /// What compiling the module found suspicious but not wrong, such as
+    /// discarded result unions.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Imports the functions and variables `module` imports with `import`,
+    /// by name or through a namespace, from the catlang module that exports
+    /// `from`.
+    fn import_module(
+        &mut self,
+        module: &Module<'ast>,
+        import: &str,
+        from: &ExportedItems<'ast>,
+    ) -> Result<(), CodeGenError> {
+        for element in module.elements.iter() {
+            let node = match element.value {
+                ModuleElement::Import(node) => node,
+                _ => continue,
+            };
+            if node.value.path.value.trim_matches('"') != import {
+                continue;
+            }
+            let named = match node.value.import_list {
+                ImportList::NamedImportList(named) => named,
+                ImportList::GlobImportList(glob) => {
+                    self.import_namespace(glob.identifier.value, from)?;
+                    continue;
+                }
+            };
+            for imported in named.imports.iter() {
+                let identifier = imported.value.identifier;
+                let local_name = imported.value.renamed_to.unwrap_or(identifier);
+                if let Some(variable) = from.variables.get(identifier.value) {
+                    self.declare_external(local_name.value, variable)?;
+                    continue;
+                }
+                self.functions.import(&from.functions, &imported.value)?;
+                // Imported overloads are located at the imported name, which
+                // tells them from the module's own.
+                let span = identifier.start as usize..identifier.end as usize;
+                for overload in self.functions.overloads(local_name.value) {
+                    if overload.span == span {
+                        self.declare_imported(overload)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes the functions and variables the module that exports `from`
+    /// exports usable as `name.function()` and `name.variable`.
+    fn import_namespace(
+        &mut self,
+        name: &'ast str,
+        from: &ExportedItems<'ast>,
+    ) -> Result<(), CodeGenError> {
+        self.functions.import_namespace(name, &from.functions);
+        let mut variables = HashMap::new();
+        for (&exported_as, variable) in &from.variables {
+            variables.insert(exported_as, self.external_global(variable)?);
+        }
+        self.namespace_variables.insert(name, variables);
+        let namespace = self
+            .functions
+            .namespace(name)
+            .ok_or(CodeGenError::Unknown)?;
+        for overload in namespace.iter() {
+            self.declare_imported(overload)?;
+        }
+        Ok(())
+    }
+
+    /// Declares an imported overload for calls to link against, unless it
+    /// is generic or already declared.
+    fn declare_imported(&self, overload: &Overload<'ast>) -> Result<(), CodeGenError> {
+        let module = &self.codegen.module;
+        if overload.generic_parameters.is_empty() && module.get_function(&overload.symbol).is_none()
+        {
+            let ty = self.codegen.function_type(&overload.signature)?;
+            module.add_function(&overload.symbol, ty, None);
+        }
+        Ok(())
+    }
+
+    /// The namespace and member `expression` names, as in `name.member` or
+    /// `name::member`, if `name` is a catlang module imported with
+    /// `import * as` that no variable shadows.
+    pub(super) fn namespace_member(
+        &self,
+        expression: &ExpressionNode<'ast>,
+    ) -> Option<(IdentifierNode<'ast>, IdentifierNode<'ast>)> {
+        let (namespace, member) = match expression.value {
+            Expression::MemberAccessExpression(access) => match access.object.value {
+                Expression::IdentifierExpression(identifier)
+                    if !self.is_variable(identifier.value) =>
+                {
+                    (identifier, access.member)
+                }
+                _ => return None,
+            },
+            Expression::ScopedExpression(scoped) => (scoped.scope, scoped.member),
+            _ => return None,
+        };
+        self.functions
+            .namespace(namespace.value)
+            .map(|_| (namespace, member))
+    }
+
+    /// The variable of an imported module `expression` names, as in
+    /// `name.variable`, or `None` if it names no member of a module.
+    pub(super) fn namespace_variable(
+        &self,
+        expression: &ExpressionNode<'ast>,
+    ) -> Option<Result<Variable<'ctx>, CodeGenError>> {
+        let (namespace, member) = self.namespace_member(expression)?;
+        let variable = self
+            .namespace_variables
+            .get(namespace.value)
+            .and_then(|variables| variables.get(member.value))
+            .cloned()
+            .ok_or_else(|| {
+                Error::UnknownVariable {
+                    name: format!("{}.{}", namespace.value, member.value),
+                    span: span(expression),
+                }
+                .into()
+            });
+        Some(variable)
+    }
+
+    /// Makes a variable whose global is defined in another LLVM module
+    /// visible as `name`.
+    pub(super) fn declare_external(
+        &mut self,
+        name: &'ast str,
+        variable: &ExternalVariable,
+    ) -> Result<(), CodeGenError> {
+        let declared = self.external_global(variable)?;
+        self.globals.insert(name, declared);
+        Ok(())
+    }
+
+    /// Declares the global of a variable defined in another LLVM module.
+    fn external_global(&self, variable: &ExternalVariable) -> Result<Variable<'ctx>, CodeGenError> {
+        let llvm_type = self
+            .codegen
+            .llvm_type(&variable.ty)?
+            .ok_or(CodeGenError::Unknown)?;
+        // Without an initializer, the global refers to the one the other
+        // module defines, unless it was compiled into the same LLVM module.
+        let module = &self.codegen.module;
+        let pointer = module
+            .get_global(&variable.symbol)
+            .unwrap_or_else(|| module.add_global(llvm_type, None, &variable.symbol))
+            .as_pointer_value();
+        Ok(Variable {
+            pointer,
+            ty: variable.ty.clone(),
+            mutable: variable.mutable,
+        })
+    }
+
+    /// Compiles every function of `module`, along with an initializer for
+    /// its variables. The entry point of a program starting in the module is
+    /// its `function main` or, for scripts, a function running the top-level
+    /// statements in order, which initializes variables as it reaches them.
+    pub fn compile(&mut self, module: &Module<'ast>) -> Result<CompiledModule<'ctx>, CodeGenError> {
+        let bodies = self.declare_functions(module)?;
+
+        // Variables are created before function bodies are compiled, since
+        // they may use them.
+        let compiled = match find_entry_point(module, &self.functions)? {
+            Some(EntryPoint::Script) => CompiledModule {
+                initializer: None,
+                start: Some(self.compile_script(module)?),
+            },
+            Some(EntryPoint::Main {
+                overload,
+                takes_arguments,
+            }) => {
+                let function = self
+                    .codegen
+                    .module
+                    .get_function(&overload.symbol)
+                    .ok_or(CodeGenError::Unknown)?;
+                let exit_code = match overload.signature.return_type {
+                    Some(Type::Primitive(pt)) => pt.integer_layout(),
+                    _ => None,
+                };
+                CompiledModule {
+                    initializer: self.compile_module_initializer(module)?,
+                    start: Some(Start {
+                        function,
+                        takes_arguments,
+                        exit_code,
+                    }),
+                }
+            }
+            None => CompiledModule {
+                initializer: self.compile_module_initializer(module)?,
+                start: None,
+            },
+        };
+
+        self.compile_functions(bodies)?;
+        Ok(compiled)
+    }
+
+    /// Declares the functions of `module` so they can be called before their
+    /// bodies are compiled.
+    pub(super) fn declare_functions(
+        &self,
+        module: &Module<'ast>,
+    ) -> Result<Vec<FunctionBody<'ast, 'ctx>>, CodeGenError> {
+        let mut bodies = vec![];
+        for declaration in module_declarations(module) {
+            if let Declarator::Function(declarator) = declaration.value.declarator {
+                if declarator.scope.is_some() {
+                    bodies.push(self.declare_scoped_method(&declarator)?);
+                } else if let Some((function, signature)) = self.declare_function(&declarator)? {
+                    bodies.push(FunctionBody {
+                        declarator,
+                        function,
+                        signature,
+                        receiver: None,
+                    });
+                }
+            }
+        }
+        bodies.extend(self.declare_implementations()?);
+        Ok(bodies)
+    }
+
+    pub(super) fn compile_functions(
+        &mut self,
+        bodies: Vec<FunctionBody<'ast, 'ctx>>,
+    ) -> Result<(), CodeGenError> {
+        for body in bodies {
+            self.compile_function(&body)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the LLVM function an overload is compiled to. Returns `None` for
+    /// functions that are not compiled yet.
+    fn declare_function(
+        &self,
+        declarator: &FunctionDeclarator<'ast>,
+    ) -> Result<Option<(FunctionValue<'ctx>, FunctionSignature)>, CodeGenError> {
+        if !declarator.generic_parameters.is_empty() {
+            return Ok(None);
+        }
+        let name = declarator.function_name;
+        let span = name.start as usize..name.end as usize;
+        let overload = self
+            .functions
+            .overloads(name.value)
+            .iter()
+            .find(|overload| overload.span == span)
+            .ok_or(CodeGenError::Unknown)?;
+        let function = self.codegen.module.add_function(
+            &overload.symbol,
+            self.codegen.function_type(&overload.signature)?,
+            None,
+        );
+        Ok(Some((function, overload.signature.clone())))
+    }
+
+    fn compile_function(&mut self, body: &FunctionBody<'ast, 'ctx>) -> Result<(), CodeGenError> {
+        let FunctionBody {
+            declarator,
+            function,
+            signature,
+            receiver,
+        } = body;
+        self.begin_function(*function, signature.return_type.clone());
+        let mut values = function.get_params().into_iter();
+        // `this` arrives as an untyped data pointer.
+        if let Some(receiver) = receiver {
+            let this = Type::Pointer(Box::new(receiver.clone()));
+            let data = values.next().ok_or(CodeGenError::Unknown)?;
+            let pointer = self.codegen.builder.build_pointer_cast(
+                data.into_pointer_value(),
+                self.codegen.pointer_to(receiver)?,
+                "this",
+            );
+            let variable = self.declare_local("this", this, false)?;
+            self.codegen.builder.build_store(variable.pointer, pointer);
+        }
+        let parameters = declarator.parameters.iter().zip(&signature.parameters);
+        for ((parameter, ty), value) in parameters.zip(values) {
+            let variable =
+                self.declare_local(parameter.value.identifier.value, ty.clone(), false)?;
+            self.codegen.builder.build_store(variable.pointer, value);
+        }
+        self.compile_block(&declarator.block)?;
+        let name = declarator.function_name;
+        self.end_function(name.value, name.start as usize..name.end as usize)
+    }
+
+    /// Compiles the top-level code of a script into a function that takes
+    /// the program's arguments, which the script sees as `args`. It returns
+    /// 0 unless a top-level `return` says otherwise.
+    fn compile_script(&mut self, module: &Module<'ast>) -> Result<Start<'ctx>, CodeGenError> {
+        let arguments = arguments_type();
+        let arguments_llvm_type = self
+            .codegen
+            .llvm_type(&arguments)?
+            .ok_or(CodeGenError::Unknown)?;
+        let context = self.codegen.context;
+        let fn_type = context
+            .i32_type()
+            .fn_type(&[arguments_llvm_type.into()], false);
+        let symbol = self.global_symbol(MAIN);
+        let function = self.codegen.module.add_function(&symbol, fn_type, None);
+        self.begin_function(function, Some(Type::Primitive(PrimitiveType::S32)));
+
+        let args =
+            self.codegen
+                .module
+                .add_global(arguments_llvm_type, None, &self.global_symbol("args"));
+        args.set_initializer(&arguments_llvm_type.const_zero());
+        let value = function.get_nth_param(0).ok_or(CodeGenError::Unknown)?;
+        self.codegen
+            .builder
+            .build_store(args.as_pointer_value(), value);
+        let variable = Variable {
+            pointer: args.as_pointer_value(),
+            ty: arguments,
+            mutable: false,
+        };
+        self.globals.insert("args", variable);
+
+        for element in module.elements.iter() {
+            match element.value {
+                ModuleElement::Declaration(declaration) => self.compile_global(&declaration)?,
+                ModuleElement::Export(export) => {
+                    if let Export::Declaration(declaration) = export.value {
+                        self.compile_global(&declaration)?;
+                    }
+                }
+                ModuleElement::Statement(statement) => self.compile_statement(&statement)?,
+                ModuleElement::Import(import) => {
+                    if !interop::is_c_header(import.value.path.value.trim_matches('"')) {
+                        return Err(unsupported("imports in scripts", &import));
+                    }
+                }
+            }
+        }
+        if self.reachable {
+            let success = context.i32_type().const_zero();
+            self.codegen.builder.build_return(Some(&success));
+            self.reachable = false;
+        }
+        self.end_function(&symbol, 0..0)?;
+        Ok(Start {
+            function,
+            takes_arguments: true,
+            exit_code: Some((true, 32)),
+        })
+    }
+
+    /// Compiles a function that initializes the module's variables in the
+    /// order they are declared. Modules without variables need none.
+    fn compile_module_initializer(
+        &mut self,
+        module: &Module<'ast>,
+    ) -> Result<Option<FunctionValue<'ctx>>, CodeGenError> {
+        let variables: Vec<DeclarationNode<'ast>> = module_declarations(module)
+            .into_iter()
+            .filter(|declaration| {
+                matches!(
+                    declaration.value.declarator,
+                    Declarator::Variable(_) | Declarator::Constant(_)
+                )
+            })
+            .collect();
+        if variables.is_empty() {
+            return Ok(None);
+        }
+        let symbol = self.global_symbol("init");
+        let fn_type = self.codegen.context.void_type().fn_type(&[], false);
+        let function = self.codegen.module.add_function(&symbol, fn_type, None);
+        self.begin_function(function, None);
+        for declaration in &variables {
+            self.compile_global(declaration)?;
+        }
+        self.end_function(&symbol, 0..0)?;
+        Ok(Some(function))
+    }
+
+    /// Creates the global of a module variable. Constant initial values are
+    /// stored in the program image; others are stored when this runs.
+    pub(super) fn compile_global(
+        &mut self,
+        declaration: &DeclarationNode<'ast>,
+    ) -> Result<(), CodeGenError> {
+        let folded = self.fold_initializer(declaration)?;
+        let constant = folded.is_some();
+        let initializer = match folded {
+            Some(initializer) => Some(initializer),
+            None => self.compile_initializer(declaration)?,
+        };
+        let (identifier, value, mutable) = match initializer {
+            Some(initializer) => initializer,
+            None => return Ok(()),
+        };
+        let llvm_type = self
+            .codegen
+            .llvm_type(&value.ty)?
+            .ok_or_else(|| unsupported("variables without a value", declaration))?;
+        let symbol = self.global_symbol(identifier.value);
+        let global = self.codegen.module.add_global(llvm_type, None, &symbol);
+        let variable = Variable {
+            pointer: global.as_pointer_value(),
+            ty: value.ty,
+            mutable,
+        };
+        match value.value {
+            Some(value) if constant => global.set_initializer(&value),
+            Some(value) => {
+                global.set_initializer(&llvm_type.const_zero());
+                self.codegen.builder.build_store(variable.pointer, value);
+            }
+            None => global.set_initializer(&llvm_type.const_zero()),
+        }
+        self.globals.insert(identifier.value, variable);
+        Ok(())
+    }
+
+    /// The value of a module variable or constant whose initializer the
+    /// constant evaluator computes, which the global starts out with instead
+    /// of it being stored at startup. Constants must have such a value.
+    fn fold_initializer(
+        &mut self,
+        declaration: &DeclarationNode<'ast>,
+    ) -> Result<Option<(IdentifierNode<'ast>, Value<'ctx>, bool)>, CodeGenError> {
+        let (identifier, type_expression, expression, mutable) = match declaration.value.declarator
+        {
+            Declarator::Variable(VariableDeclarator {
+                identifier,
+                type_expression,
+                expression: Some(expression),
+                ..
+            }) => (identifier, type_expression, expression, true),
+            Declarator::Constant(constant) => (
+                constant.identifier,
+                constant.type_expression,
+                constant.expression,
+                false,
+            ),
+            _ => return Ok(None),
+        };
+        let declared = match type_expression {
+            Some(ty) => Some(self.env.resolve(&ty, &[])?),
+            None => None,
+        };
+        let constant = match self.env.constants().map_err(CodeGenError::from)?.evaluate(
+            LOCAL_MODULE,
+            &expression,
+            declared.as_ref(),
+        ) {
+            Ok(constant) => constant,
+            Err(e) if !mutable => return Err(e.into()),
+            Err(_) => return Ok(None),
+        };
+        let value = self.compile_constant(&constant, declared.as_ref())?;
+        let value = match &declared {
+            Some(declared) => self.convert(value, declared, span(&expression))?,
+            None => value,
+        };
+        Ok(Some((identifier, value, mutable)))
+    }
+
+    /// The symbol of the global that holds a module variable.
+    pub(super) fn global_symbol(&self, name: &str) -> String {
+        global_symbol(&self.name, name)
+    }
+
+    /// Prepares to compile the body of `function`, which starts with a block
+    /// for stack slots.
+    pub(super) fn begin_function(
+        &mut self,
+        function: FunctionValue<'ctx>,
+        return_type: Option<Type>,
+    ) {
+        let context = self.codegen.context;
+        let entry = context.append_basic_block(function, "entry");
+        let body = context.append_basic_block(function, "body");
+        self.allocas.position_at_end(entry);
+        self.codegen.builder.position_at_end(body);
+        self.body = Some(body);
+        self.scopes = vec![HashMap::new()];
+        self.loops = vec![];
+        self.return_type = return_type;
+        self.reachable = true;
+    }
+
+    /// Finishes the current function, returning implicitly from functions
+    /// without a return type.
+    pub(super) fn end_function(
+        &mut self,
+        name: &str,
+        span: Range<usize>,
+    ) -> Result<(), CodeGenError> {
+        if self.reachable {
+            if self.return_type.is_some() {
+                return Err(Error::MissingReturn {
+                    function: name.to_string(),
+                    span,
+                }
+                .into());
+            }
+            self.codegen.builder.build_return(None);
+        } else if self.current_block()?.get_terminator().is_none() {
+            self.codegen.builder.build_unreachable();
+        }
+        // The stack slots are all allocated now, so the entry block can end.
+        let body = self.body.take().ok_or(CodeGenError::Unknown)?;
+        self.allocas.build_unconditional_branch(body);
+        self.scopes.clear();
+        Ok(())
+    }
+
+    pub(super) fn current_block(&self) -> Result<BasicBlock<'ctx>, CodeGenError> {
+        self.codegen
+            .builder
+            .get_insert_block()
+            .ok_or(CodeGenError::Unknown)
+    }
+
+    /// Aborts the program with `llvm.trap` if `condition` holds, and
+    /// otherwise continues in a new block.
+    pub(super) fn build_trap_if(&self, condition: IntValue<'ctx>) -> Result<(), CodeGenError> {
+        let context = self.codegen.context;
+        let module = &self.codegen.module;
+        let builder = &self.codegen.builder;
+        let function = self
+            .current_block()?
+            .get_parent()
+            .ok_or(CodeGenError::Unknown)?;
+        let trap_block = context.append_basic_block(function, "trap");
+        let continue_block = context.append_basic_block(function, "trap.skip");
+        builder.build_conditional_branch(condition, trap_block, continue_block);
+        builder.position_at_end(trap_block);
+        let trap = module.get_function("llvm.trap").unwrap_or_else(|| {
+            let fn_type = context.void_type().fn_type(&[], false);
+            module.add_function("llvm.trap", fn_type, None)
+        });
+        builder.build_call(trap, &[], "");
+        builder.build_unreachable();
+        builder.position_at_end(continue_block);
+        Ok(())
+    }
+
+    /// Continues in a new block after a jump. Nothing branches to it, so the
+    /// code that follows is dead.
+    pub(super) fn continue_unreachable(&mut self) -> Result<(), CodeGenError> {
+        let function = self
+            .current_block()?
+            .get_parent()
+            .ok_or(CodeGenError::Unknown)?;
+        let block = self.codegen.context.append_basic_block(function, "dead");
+        self.codegen.builder.position_at_end(block);
+        self.reachable = false;
+        Ok(())
+    }
+
+    /// Allocates a stack slot for a local variable in the innermost scope.
+    /// Redeclaring a name shadows the earlier variable.
+    pub(super) fn declare_local(
+        &mut self,
+        name: &'ast str,
+        ty: Type,
+        mutable: bool,
+    ) -> Result<Variable<'ctx>, CodeGenError> {
+        let llvm_type = self.codegen.llvm_type(&ty)?.ok_or(CodeGenError::Unknown)?;
+        let pointer = self.allocas.build_alloca(llvm_type, name);
+        let variable = Variable {
+            pointer,
+            ty,
+            mutable,
+        };
+        self.scopes
+            .last_mut()
+            .ok_or(CodeGenError::Unknown)?
+            .insert(name, variable.clone());
+        Ok(variable)
+    }
+
+    /// Stores a value in a stack slot of its own, so that its address can be
+    /// taken.
+    pub(super) fn build_temporary(
+        &self,
+        value: Value<'ctx>,
+    ) -> Result<Variable<'ctx>, CodeGenError> {
+        let stored = value.value.ok_or(CodeGenError::Unknown)?;
+        let pointer = self.allocas.build_alloca(stored.get_type(), "temporary");
+        self.codegen.builder.build_store(pointer, stored);
+        Ok(Variable {
+            pointer,
+            ty: value.ty,
+            mutable: false,
+        })
+    }
+
+    /// Looks a variable up in the enclosing scopes, then among the module's
+    /// variables.
+    pub(super) fn lookup(
+        &self,
+        identifier: &IdentifierNode<'ast>,
+    ) -> Result<Variable<'ctx>, CodeGenError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(identifier.value))
+            .or_else(|| self.globals.get(identifier.value))
+            .cloned()
+            .ok_or_else(|| {
+                Error::UnknownVariable {
+                    name: identifier.value.to_string(),
+                    span: span(identifier),
+                }
+                .into()
+            })
+    }
+}
+
+pub(super) fn span<T>(node: &Node<'_, T>) -> Range<usize> {
+    node.start as usize..node.end as usize
+}
+
+pub(super) fn unsupported<T>(construct: &'static str, node: &Node<'_, T>) -> CodeGenError {
+    CodeGenError::Unsupported {
+        construct,
+        span: span(node),
+    }
+}
@@ -0,0 +1,287 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{span, ModuleCompiler, Value, Variable};
+use crate::semantic::error::Error;
+use crate::semantic::iteration::range_type;
+use crate::semantic::methods::MethodCall;
+use crate::semantic::operators::compound_assignment_value;
+use crate::semantic::prelude::RANGE_STRUCT;
+use crate::semantic::types::Type;
+use crate::syntax::ast::*;
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum};
+use std::ops::Range;
+
+pub(super) fn is_array(ty: &Type) -> bool {
+    matches!(
+        ty.unqualified(),
+        Type::SizedArray(_) | Type::UnsizedArray(_) | Type::Array(..)
+    )
+}
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Applies a binary operator, calling the left operand's overload of it
+    /// where it has one.
+    pub(super) fn build_overloadable_binary(
+        &mut self,
+        operator: BinaryOperator,
+        left: Value<'ctx>,
+        right: Value<'ctx>,
+        span: Range<usize>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let overload = self.methods.resolve_binary_operator(
+            &self.interfaces,
+            &self.env,
+            &left.ty,
+            operator,
+            &right.ty,
+            span.clone(),
+        )?;
+        match overload {
+            Some(call) => self.build_operator_call(&call, left, vec![(right, span.clone())], span),
+            None => self.build_binary(operator, left, right, span),
+        }
+    }
+
+    /// Calls an operator overload on a value, which is stored in a temporary
+    /// to be passed as `this`.
+    pub(super) fn build_operator_call(
+        &mut self,
+        resolved: &MethodCall,
+        operand: Value<'ctx>,
+        arguments: Vec<(Value<'ctx>, Range<usize>)>,
+        span: Range<usize>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let values = self.convert_operands(resolved, arguments)?;
+        let object = self.build_temporary(operand)?;
+        self.build_resolved_call(resolved, &object, &values, span)
+    }
+
+    fn convert_operands(
+        &self,
+        resolved: &MethodCall,
+        arguments: Vec<(Value<'ctx>, Range<usize>)>,
+    ) -> Result<Vec<BasicMetadataValueEnum<'ctx>>, CodeGenError> {
+        let mut values = vec![];
+        let parameters = arguments.into_iter().zip(&resolved.signature.parameters);
+        for ((argument, span), parameter) in parameters {
+            let argument = self.convert(argument, parameter, span)?;
+            values.extend(argument.value.map(BasicMetadataValueEnum::from));
+        }
+        Ok(values)
+    }
+
+    /// Reads `array[index]`: an array element, or what the object's `[]`
+    /// overload returns.
+    pub(super) fn compile_index(
+        &mut self,
+        index: &IndexExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let object = self.compile_address(&index.array)?;
+        if !is_array(&object.ty) {
+            return self.compile_index_overload(object, index);
+        }
+        let element = self.compile_element_of(object, index)?;
+        let value = self.codegen.builder.build_load(element.pointer, "element");
+        Ok(Value {
+            value: Some(value),
+            ty: element.ty,
+        })
+    }
+
+    /// Calls the `[]` overload of the object stored at `object`.
+    pub(super) fn compile_index_overload(
+        &mut self,
+        object: Variable<'ctx>,
+        index: &IndexExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let position = self.compile_expression(&index.index, None)?;
+        let overload = self.methods.resolve_index(
+            &self.interfaces,
+            &self.env,
+            &object.ty,
+            &position.ty,
+            span(&index.array),
+        )?;
+        let call = overload.ok_or_else(|| Error::NoOperatorOverload {
+            operator: "[]".to_string(),
+            operands: object.ty.to_string(),
+            span: span(&index.array),
+        })?;
+        let values = self.convert_operands(&call, vec![(position, span(&index.index))])?;
+        self.build_resolved_call(&call, &object, &values, span(&index.array))
+    }
+
+    /// Compiles an assignment to `array[index]` through the object's `[]=`
+    /// overload. A compound assignment assigns `array[index] op value`,
+    /// which reads the element through `[]`.
+    pub(super) fn compile_index_assignment(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        object: Variable<'ctx>,
+        index: &IndexExpression<'ast>,
+        assignment: &AssignmentExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let position = self.compile_expression(&index.index, None)?;
+        let value = match compound_assignment_value(assignment) {
+            Some(binary) => self.compile_binary(expression, &binary, None)?,
+            None => self.compile_expression(&assignment.right, None)?,
+        };
+        let overload = self.methods.resolve_index_assign(
+            &self.interfaces,
+            &self.env,
+            &object.ty,
+            &position.ty,
+            &value.ty,
+            span(&index.array),
+        )?;
+        let call = overload.ok_or_else(|| Error::NoOperatorOverload {
+            operator: "[]=".to_string(),
+            operands: object.ty.to_string(),
+            span: span(&index.array),
+        })?;
+        let arguments = vec![
+            (position, span(&index.index)),
+            (value.clone(), span(&assignment.right)),
+        ];
+        let values = self.convert_operands(&call, arguments)?;
+        self.build_resolved_call(&call, &object, &values, span(expression))?;
+        Ok(value)
+    }
+
+    /// Compiles `left ?? right`, the value `left` holds or, if it holds
+    /// none, `right`. `right` is only evaluated if it is needed.
+    pub(super) fn compile_null_coalesce(
+        &mut self,
+        left: Value<'ctx>,
+        right: &ExpressionNode<'ast>,
+        span: Range<usize>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let inner = match left.ty.unqualified() {
+            Type::Optional(inner) => (**inner).clone(),
+            ty => {
+                return Err(Error::NoOperatorOverload {
+                    operator: "??".to_string(),
+                    operands: ty.to_string(),
+                    span,
+                }
+                .into())
+            }
+        };
+        let builder = &self.codegen.builder;
+        let optional = left.value.ok_or(CodeGenError::Unknown)?;
+        // Optional pointers are null when empty; other optionals start with
+        // a flag.
+        let (present, value): (_, BasicValueEnum<'ctx>) = match optional {
+            BasicValueEnum::PointerValue(pointer) => (
+                builder.build_is_not_null(pointer, "present"),
+                pointer.into(),
+            ),
+            optional => {
+                let optional = optional.into_struct_value();
+                let present = builder
+                    .build_extract_value(optional, 0, "present")
+                    .ok_or(CodeGenError::Unknown)?
+                    .into_int_value();
+                let value = builder
+                    .build_extract_value(optional, 1, "value")
+                    .ok_or(CodeGenError::Unknown)?;
+                (present, value)
+            }
+        };
+        let left_block = self.current_block()?;
+        let function = left_block.get_parent().ok_or(CodeGenError::Unknown)?;
+        let context = self.codegen.context;
+        let default_block = context.append_basic_block(function, "coalesce.default");
+        let merge = context.append_basic_block(function, "coalesce.end");
+        builder.build_conditional_branch(present, merge, default_block);
+
+        self.codegen.builder.position_at_end(default_block);
+        let default = self.compile_expression(right, Some(&inner))?;
+        let default = self.convert(default, &inner, span)?;
+        let default = default.value.ok_or(CodeGenError::Unknown)?;
+        let default_end = self.current_block()?;
+        self.codegen.builder.build_unconditional_branch(merge);
+
+        self.codegen.builder.position_at_end(merge);
+        let phi = self.codegen.builder.build_phi(value.get_type(), "coalesce");
+        phi.add_incoming(&[(&value, left_block), (&default, default_end)]);
+        Ok(Value {
+            value: Some(phi.as_basic_value()),
+            ty: inner,
+        })
+    }
+
+    /// Compiles `start..end` or `start...end` into a `Range<T>` of the type
+    /// both bounds convert to.
+    pub(super) fn compile_range(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        binary: &BinaryExpression<'ast>,
+        expected: Option<&Type>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let hint = match expected.map(Type::unqualified) {
+            Some(Type::Struct(named)) if named.name == RANGE_STRUCT => {
+                named.generic_arguments.first()
+            }
+            _ => None,
+        };
+        let (start, end) = self.compile_operands(&binary.left, &binary.right, hint)?;
+        let (start, end) = self.unify(start, end, span(expression))?;
+        let ty = range_type(&start.ty);
+        let llvm_type = self.codegen.llvm_type(&ty)?.ok_or(CodeGenError::Unknown)?;
+        let inclusive = binary.operator == BinaryOperator::RangeInclusive;
+        let fields = [
+            start.value.ok_or(CodeGenError::Unknown)?,
+            end.value.ok_or(CodeGenError::Unknown)?,
+            self.codegen
+                .context
+                .bool_type()
+                .const_int(inclusive as u64, false)
+                .into(),
+        ];
+        let mut range = llvm_type.const_zero().into_struct_value();
+        for (index, field) in fields.iter().enumerate() {
+            range = self
+                .codegen
+                .builder
+                .build_insert_value(range, *field, index as u32, "range")
+                .ok_or(CodeGenError::Unknown)?
+                .into_struct_value();
+        }
+        Ok(Value {
+            value: Some(range.into()),
+            ty,
+        })
+    }
+
+    /// Wraps a value in an optional of its type.
+    pub(super) fn build_optional(
+        &self,
+        value: Value<'ctx>,
+        optional: &Type,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let inner = value.value.ok_or(CodeGenError::Unknown)?;
+        let llvm_type = self
+            .codegen
+            .llvm_type(optional)?
+            .ok_or(CodeGenError::Unknown)?;
+        let wrapped: BasicValueEnum<'ctx> = if llvm_type.is_pointer_type() {
+            inner
+        } else {
+            let builder = &self.codegen.builder;
+            let empty = llvm_type.const_zero().into_struct_value();
+            let present = self.codegen.context.bool_type().const_int(1, false);
+            let flagged = builder
+                .build_insert_value(empty, present, 0, "optional")
+                .ok_or(CodeGenError::Unknown)?;
+            builder
+                .build_insert_value(flagged, inner, 1, "optional")
+                .ok_or(CodeGenError::Unknown)?
+                .into_struct_value()
+                .into()
+        };
+        Ok(Value {
+            value: Some(wrapped),
+            ty: optional.clone(),
+        })
+    }
+}
@@ -0,0 +1,248 @@
+//! The LLVM pass pipelines modules are optimized with before they are
+//! written.
+
+use inkwell::passes::PassBuilderOptions;
+use inkwell::OptimizationLevel;
+use serde::de::{self, Deserialize, Deserializer};
+use std::ffi::{CString, NulError};
+use std::fmt;
+use std::os::raw::{c_char, c_int};
+use std::str::FromStr;
+use std::sync::Once;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("{} is not an optimization level; levels are 0 to 3, s and z", .0)]
+pub struct InvalidOptLevel(pub String);
+
+/// How much a module is optimized, like clang's `-O` levels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OptLevel {
+    /// `0`: no optimization, for the fastest builds.
+    None,
+    /// `1`: optimizations that are quick to run.
+    Less,
+    /// `2`: most optimizations, including vectorization.
+    Default,
+    /// `3`: every optimization, even ones that make code larger.
+    Aggressive,
+    /// `s`: `2`, without the optimizations that make code larger.
+    Size,
+    /// `z`: smaller code still, at the cost of speed.
+    MinSize,
+}
+
+impl OptLevel {
+    /// The pass pipeline of the level: LLVM's default one, which promotes
+    /// stack slots to registers, inlines, removes redundant code with GVN,
+    /// and simplifies, unrolls and vectorizes loops, as far as the level
+    /// goes.
+    pub fn pipeline(self) -> &'static str {
+        match self {
+            OptLevel::None => "default<O0>",
+            OptLevel::Less => "default<O1>",
+            OptLevel::Default => "default<O2>",
+            OptLevel::Aggressive => "default<O3>",
+            OptLevel::Size => "default<Os>",
+            OptLevel::MinSize => "default<Oz>",
+        }
+    }
+
+    /// The level instructions are selected and scheduled at, which has no
+    /// size levels.
+    pub fn codegen_level(self) -> OptimizationLevel {
+        match self {
+            OptLevel::None => OptimizationLevel::None,
+            OptLevel::Less => OptimizationLevel::Less,
+            OptLevel::Default | OptLevel::Size | OptLevel::MinSize => OptimizationLevel::Default,
+            OptLevel::Aggressive => OptimizationLevel::Aggressive,
+        }
+    }
+
+    /// The options of the pipeline, which turn on the loop optimizations
+    /// clang runs at the level: unrolling from `2`, and vectorization from
+    /// `2` except at `z`. Size levels also merge identical functions.
+    pub(super) fn pass_options(self, debugging: &PassDebugging) -> PassBuilderOptions {
+        let unroll = matches!(self, OptLevel::Default | OptLevel::Aggressive);
+        let vectorize = matches!(
+            self,
+            OptLevel::Default | OptLevel::Aggressive | OptLevel::Size
+        );
+        let options = PassBuilderOptions::create();
+        options.set_loop_unrolling(unroll);
+        options.set_loop_interleaving(unroll);
+        options.set_loop_vectorization(vectorize);
+        options.set_loop_slp_vectorization(vectorize);
+        options.set_merge_functions(matches!(self, OptLevel::Size | OptLevel::MinSize));
+        options.set_debug_logging(debugging.print_passes);
+        options.set_verify_each(debugging.verify_each);
+        options
+    }
+}
+
+impl FromStr for OptLevel {
+    type Err = InvalidOptLevel;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "0" => Ok(OptLevel::None),
+            "1" => Ok(OptLevel::Less),
+            "2" => Ok(OptLevel::Default),
+            "3" => Ok(OptLevel::Aggressive),
+            "s" => Ok(OptLevel::Size),
+            "z" => Ok(OptLevel::MinSize),
+            _ => Err(InvalidOptLevel(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = match self {
+            OptLevel::None => "0",
+            OptLevel::Less => "1",
+            OptLevel::Default => "2",
+            OptLevel::Aggressive => "3",
+            OptLevel::Size => "s",
+            OptLevel::MinSize => "z",
+        };
+        f.write_str(level)
+    }
+}
+
+/// Levels are written as in `-O`, with `0` to `3` as numbers or strings.
+impl<'de> Deserialize<'de> for OptLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde_derive::Deserialize)]
+        #[serde(untagged)]
+        enum Level {
+            Number(u64),
+            Name(String),
+        }
+        let level = match Level::deserialize(deserializer)? {
+            Level::Number(number) => number.to_string(),
+            Level::Name(name) => name,
+        };
+        level.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Hooks for looking into what the optimizer does to a module.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PassDebugging {
+    /// Whether to print each pass as it runs.
+    pub print_passes: bool,
+    /// Whether to verify the module after each pass, which finds the pass
+    /// that breaks it.
+    pub verify_each: bool,
+}
+
+static LLVM_ARGS: Once = Once::new();
+
+/// Gives LLVM `args` as if they were passed on its command line, such as
+/// `-print-after-all`, `-print-before=gvn`, `-filter-print-funcs=main` or
+/// `-time-passes`. LLVM's options are global and can only be set once, so
+/// later calls do nothing; LLVM exits the process on options it does not
+/// know.
+pub fn set_llvm_args(args: &[String]) -> Result<(), NulError> {
+    let args = Some("catlang")
+        .into_iter()
+        .chain(args.iter().map(String::as_str))
+        .map(CString::new)
+        .collect::<Result<Vec<_>, _>>()?;
+    let pointers: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+    let overview = CString::default();
+    LLVM_ARGS.call_once(|| unsafe {
+        llvm_sys::support::LLVMParseCommandLineOptions(
+            pointers.len() as c_int,
+            pointers.as_ptr(),
+            overview.as_ptr(),
+        );
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::module::ModuleCompiler;
+    use crate::codegen::{target_machine, CodeGen, DEFAULT_TARGET_TRIPLE};
+    use crate::syntax::ast::SourceUnit;
+    use crate::syntax::parser::parse;
+    use inkwell::context::Context;
+    use inkwell::targets::{InitializationConfig, Target};
+    use std::env;
+    use std::process::Command;
+
+    fn optimize(codegen: &CodeGen, opt_level: OptLevel) {
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+        let machine = target_machine(&DEFAULT_TARGET_TRIPLE, opt_level).unwrap();
+        codegen.optimize(opt_level, &machine).unwrap();
+    }
+
+    #[test]
+    fn test_o2_promotes_variables_to_registers() {
+        let source = "function sum(n: s64) -> s64 {
+            let total: s64 = 0;
+            for (x in 0..n) { total += x; }
+            return total;
+        }";
+        let program = parse(source).unwrap();
+        let context = Context::create();
+        let codegen = CodeGen::named(&context, "sum");
+        for unit in program.body().iter() {
+            let SourceUnit::Module(module) = unit.value;
+            let mut compiler = ModuleCompiler::new(&codegen, "sum", &module).unwrap();
+            compiler.compile(&module).unwrap();
+        }
+        let unoptimized = codegen.write_to_string();
+        assert!(unoptimized.contains("alloca"));
+
+        optimize(&codegen, OptLevel::Default);
+        let optimized = codegen.write_to_string();
+        assert_ne!(optimized, unoptimized);
+        assert!(!optimized.contains("alloca"));
+        codegen.module.verify().unwrap();
+    }
+
+    /// Optimizes a module whose `s32` function returns nothing, verifying
+    /// each pass if `CATLANG_VERIFY_EACH` is set. LLVM aborts the process
+    /// when verification fails, so `test_verify_each_catches_invalid_ir`
+    /// runs this in a process of its own.
+    #[test]
+    #[ignore]
+    fn optimize_invalid_ir() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context);
+        codegen.passes.verify_each = env::var_os("CATLANG_VERIFY_EACH").is_some();
+        let fn_type = context.i32_type().fn_type(&[], false);
+        let function = codegen.module.add_function("broken", fn_type, None);
+        let entry = context.append_basic_block(function, "entry");
+        codegen.builder.position_at_end(entry);
+        codegen.builder.build_return(None);
+        optimize(&codegen, OptLevel::None);
+    }
+
+    #[test]
+    fn test_verify_each_catches_invalid_ir() {
+        let run = |verify_each: bool| {
+            let mut command = Command::new(env::current_exe().unwrap());
+            command.args(&[
+                "--exact",
+                "codegen::optimize::tests::optimize_invalid_ir",
+                "--ignored",
+            ]);
+            if verify_each {
+                command.env("CATLANG_VERIFY_EACH", "1");
+            }
+            let output = command.output().unwrap();
+            let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+            (output.status.success(), stderr)
+        };
+        let (succeeded, stderr) = run(true);
+        assert!(!succeeded);
+        assert!(stderr.contains("Broken module found"));
+        let (succeeded, stderr) = run(false);
+        assert!(succeeded, "{}", stderr);
+    }
+}
@@ -0,0 +1,411 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{unsupported, ExternalVariable, ModuleCompiler};
+use crate::codegen::{Checks, CodeGen};
+use crate::semantic::types::Type;
+use crate::syntax::ast::*;
+use crate::syntax::parser::{parse, parse_expression};
+use anyhow::anyhow;
+use inkwell::context::Context;
+use inkwell::execution_engine::ExecutionEngine;
+use inkwell::targets::{InitializationConfig, Target};
+use inkwell::OptimizationLevel;
+use std::collections::HashMap;
+use toolshed::Arena;
+
+const HELP: &str = "\
+:type <expr>  show the type of an expression without evaluating it
+:ast <expr>   show the syntax tree of an expression
+:ir           show the LLVM IR compiled so far
+:quit         leave the REPL";
+
+/// A declaration kept from an earlier input. Functions and types are
+/// compiled again into every later input, so they are kept as source.
+#[derive(Clone, Debug)]
+struct Definition {
+    /// `None` for declarations that are never shadowed, such as `impl`s.
+    name: Option<String>,
+    source: String,
+}
+
+/// The type of an input's trailing expression, and whether the compiled
+/// code prints its value.
+type Echo = Option<(Type, bool)>;
+
+/// An input compiled into its own LLVM module.
+struct Compiled<'ctx> {
+    module: inkwell::module::Module<'ctx>,
+    entry: String,
+    echo: Echo,
+    definitions: Vec<Definition>,
+    globals: Vec<(String, ExternalVariable)>,
+    /// Names the input declares, which shadow earlier declarations.
+    declared: Vec<String>,
+}
+
+/// A read-eval-print loop. Each input is compiled into a module of its own
+/// and added to a JIT that lives as long as the session, so variables keep
+/// their values between inputs. Declaring a name again shadows the earlier
+/// declaration.
+pub struct Repl<'ctx> {
+    context: &'ctx Context,
+    engine: Option<ExecutionEngine<'ctx>>,
+    modules: Vec<inkwell::module::Module<'ctx>>,
+    definitions: Vec<Definition>,
+    globals: HashMap<String, ExternalVariable>,
+}
+
+impl<'ctx> Repl<'ctx> {
+    pub fn new(context: &'ctx Context) -> Self {
+        Repl {
+            context,
+            engine: None,
+            modules: vec![],
+            definitions: vec![],
+            globals: HashMap::new(),
+        }
+    }
+
+    /// Evaluates a line of input: a meta-command, or declarations, statements
+    /// and a trailing expression. Returns what should be shown besides the
+    /// output of the code itself.
+    pub fn eval(&mut self, input: &str) -> anyhow::Result<Option<String>> {
+        let input = input.trim();
+        if let Some(command) = input.strip_prefix(':') {
+            return self.meta_command(command);
+        }
+
+        let compiled = self.compile(input, true)?;
+        match &self.engine {
+            Some(engine) => engine
+                .add_module(&compiled.module)
+                .map_err(|_| anyhow!("could not add the input to the JIT"))?,
+            None => {
+                Target::initialize_native(&InitializationConfig::default())
+                    .map_err(|e| anyhow!(e))?;
+                let engine = compiled
+                    .module
+                    .create_jit_execution_engine(OptimizationLevel::None)
+                    .map_err(CodeGenError::from)?;
+                self.engine = Some(engine);
+            }
+        }
+        let engine = self.engine.as_ref().ok_or(CodeGenError::Unknown)?;
+        unsafe {
+            let entry = engine.get_function::<unsafe extern "C" fn()>(&compiled.entry)?;
+            entry.call();
+        }
+
+        self.definitions
+            .retain(|definition| match &definition.name {
+                Some(name) => !compiled.declared.contains(name),
+                None => true,
+            });
+        self.definitions.extend(compiled.definitions);
+        for name in &compiled.declared {
+            self.globals.remove(name);
+        }
+        self.globals.extend(compiled.globals);
+        self.modules.push(compiled.module);
+
+        Ok(match compiled.echo {
+            Some((ty, false)) if ty != Type::Primitive(PrimitiveType::CVoid) => {
+                Some(format!(": {}", ty))
+            }
+            _ => None,
+        })
+    }
+
+    fn meta_command(&self, command: &str) -> anyhow::Result<Option<String>> {
+        let (name, argument) = match command.find(char::is_whitespace) {
+            Some(at) => (&command[..at], command[at..].trim()),
+            None => (command, ""),
+        };
+        match name {
+            "type" | "t" => match self.compile(argument, false)?.echo {
+                Some((ty, _)) => Ok(Some(ty.to_string())),
+                None => Err(anyhow!("{:?} is not an expression", argument)),
+            },
+            "ast" => {
+                let arena = Arena::new();
+                let expression = parse_expression(argument, &arena).map_err(join_errors)?;
+                Ok(Some(format!("{:#?}", expression)))
+            }
+            "ir" => Ok(Some(
+                self.modules
+                    .iter()
+                    .map(|module| module.print_to_string().to_string())
+                    .collect(),
+            )),
+            "help" | "h" => Ok(Some(HELP.to_string())),
+            _ => Err(anyhow!("unknown command :{}; try :help", name)),
+        }
+    }
+
+    /// Compiles an input together with the declarations kept from earlier
+    /// inputs. Nothing is run, and the session is not changed.
+    fn compile(&self, input: &str, echo: bool) -> anyhow::Result<Compiled<'ctx>> {
+        // A trailing expression does not need a semicolon.
+        let source = match parse(input) {
+            Ok(_) => input.to_string(),
+            Err(errors) => {
+                let terminated = format!("{};", input);
+                parse(&terminated).map_err(|_| join_errors(errors))?;
+                terminated
+            }
+        };
+
+        let program = parse(&source).map_err(join_errors)?;
+        let mut definitions = vec![];
+        let mut variables = vec![];
+        let mut declared = vec![];
+        let mut elements = 0;
+        for module in modules(&program) {
+            let nodes: Vec<_> = module.elements.iter().collect();
+            elements += nodes.len();
+            for (i, element) in nodes.iter().enumerate() {
+                let declaration = match element_declaration(&element.value) {
+                    Some(declaration) => declaration,
+                    None => continue,
+                };
+                let name = declared_name(&declaration.value.declarator).map(str::to_string);
+                declared.extend(name.clone());
+                match declaration.value.declarator {
+                    Declarator::Variable(VariableDeclarator { identifier, .. }) => {
+                        variables.push((identifier.value.to_string(), true))
+                    }
+                    Declarator::Constant(ConstantDeclarator { identifier, .. }) => {
+                        variables.push((identifier.value.to_string(), false))
+                    }
+                    _ => {
+                        let end = nodes
+                            .get(i + 1)
+                            .map_or(source.len(), |next| next.start as usize);
+                        let source = source[element.start as usize..end].trim().to_string();
+                        definitions.push(Definition { name, source });
+                    }
+                }
+            }
+        }
+
+        // The input comes first so that spans in errors point into it.
+        let mut combined = source;
+        for definition in &self.definitions {
+            let shadowed = matches!(&definition.name, Some(name) if declared.contains(name));
+            if !shadowed {
+                combined.push('\n');
+                combined.push_str(&definition.source);
+            }
+        }
+        let program = parse(&combined).map_err(join_errors)?;
+
+        let name = format!("repl{}", self.modules.len());
+        let entry = format!("{}::main", name);
+        let mut codegen = CodeGen::named(self.context, &name);
+        codegen.checks = Checks::all();
+        let mut result = None;
+        let mut globals = vec![];
+        for module in modules(&program) {
+            let mut compiler = ModuleCompiler::new(&codegen, &name, &module)?;
+            for (name, global) in &self.globals {
+                compiler.declare_external(name, global)?;
+            }
+            result = compiler.compile_input(&module, elements, &entry, echo)?;
+            for (variable, mutable) in &variables {
+                let ty = match compiler.globals.get(variable.as_str()) {
+                    Some(declared) => declared.ty.clone(),
+                    None => continue,
+                };
+                let symbol = compiler.global_symbol(variable);
+                globals.push((
+                    variable.clone(),
+                    ExternalVariable {
+                        symbol,
+                        ty,
+                        mutable: *mutable,
+                    },
+                ));
+            }
+        }
+        codegen.module.verify().map_err(CodeGenError::from)?;
+        Ok(Compiled {
+            module: codegen.module,
+            entry,
+            echo: result,
+            definitions,
+            globals,
+            declared,
+        })
+    }
+}
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Compiles the first `input` elements of `module` into `entry`, which
+    /// takes no arguments and returns nothing; the rest are declarations kept
+    /// from earlier inputs. If the input ends in an expression, its type is
+    /// returned, and when `echo` is set its value is printed.
+    fn compile_input(
+        &mut self,
+        module: &Module<'ast>,
+        input: usize,
+        entry: &str,
+        echo: bool,
+    ) -> Result<Echo, CodeGenError> {
+        let bodies = self.declare_functions(module)?;
+        let fn_type = self.codegen.context.void_type().fn_type(&[], false);
+        let function = self.codegen.module.add_function(entry, fn_type, None);
+        self.begin_function(function, None);
+
+        let mut result = None;
+        for (i, element) in module.elements.iter().take(input).enumerate() {
+            match element.value {
+                ModuleElement::Import(import) => {
+                    return Err(unsupported("imports in the REPL", &import))
+                }
+                ModuleElement::Statement(statement) => match statement.value {
+                    Statement::Expression(expression) if i + 1 == input => {
+                        let value = self.compile_expression(&expression, None)?;
+                        let printed = echo
+                            && self
+                                .print_value(&value, &format!(" : {}", value.ty))?
+                                .is_some();
+                        result = Some((value.ty, printed));
+                    }
+                    _ => self.compile_statement(&statement)?,
+                },
+                _ => {
+                    if let Some(declaration) = element_declaration(&element.value) {
+                        self.compile_global(&declaration)?;
+                    }
+                }
+            }
+        }
+        if self.reachable {
+            self.flush_output();
+        }
+        self.end_function(entry, 0..0)?;
+        self.compile_functions(bodies)?;
+        Ok(result)
+    }
+}
+
+/// How many brackets are opened but not closed, outside of literals. An
+/// input continues over several lines while this is positive.
+pub fn open_brackets(input: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '(') | (None, '[') | (None, '{') => depth += 1,
+            (None, ')') | (None, ']') | (None, '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+fn modules<'ast>(program: &Program<'ast>) -> Vec<Module<'ast>> {
+    program
+        .body()
+        .iter()
+        .map(|unit| match unit.value {
+            SourceUnit::Module(module) => module,
+        })
+        .collect()
+}
+
+fn element_declaration<'ast>(element: &ModuleElement<'ast>) -> Option<DeclarationNode<'ast>> {
+    match element {
+        ModuleElement::Declaration(declaration) => Some(*declaration),
+        ModuleElement::Export(export) => match export.value {
+            Export::Declaration(declaration) => Some(declaration),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The name a declaration binds, if a later declaration can shadow it.
+fn declared_name<'ast>(declarator: &Declarator<'ast>) -> Option<&'ast str> {
+    match declarator {
+        Declarator::Constant(c) => Some(c.identifier.value),
+        Declarator::Variable(v) => Some(v.identifier.value),
+        Declarator::Type(t) => Some(t.identifier.value),
+        Declarator::Function(f) if f.scope.is_none() => Some(f.function_name.value),
+        Declarator::Struct(s) => Some(s.identifier.value),
+        Declarator::Enum(e) => Some(e.identifier.value),
+        Declarator::Interface(i) => Some(i.identifier.value),
+        Declarator::Function(_) | Declarator::Impl(_) => None,
+    }
+}
+
+fn join_errors<E: ToString>(errors: Vec<E>) -> anyhow::Error {
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    anyhow!(messages.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// The value of an `s32` variable declared by an earlier input.
+    fn value(repl: &Repl, name: &str) -> i32 {
+        let symbol = &repl.globals[name].symbol;
+        let engine = repl.engine.as_ref().unwrap();
+        let address = engine.get_function_address(symbol).unwrap();
+        unsafe { *(address as *const i32) }
+    }
+
+    #[test]
+    fn test_inputs_continue_while_brackets_are_open() {
+        let context = Context::create();
+        let mut repl = Repl::new(&context);
+        let mut input = String::new();
+        for line in &["function twice(n: s32) -> s32 {", "    return n * 2;"] {
+            input.push_str(line);
+            input.push('\n');
+            assert!(open_brackets(&input) > 0, "{:?} looks complete", input);
+        }
+        input.push_str("}\n");
+        assert_eq!(open_brackets(&input), 0);
+        assert_eq!(open_brackets("let brace = \"{\";"), 0);
+
+        assert_eq!(repl.eval(&input).unwrap(), None);
+        repl.eval("let doubled: s32 = twice(21);").unwrap();
+        assert_eq!(value(&repl, "doubled"), 42);
+    }
+
+    #[test]
+    fn test_redefinitions_shadow() {
+        let context = Context::create();
+        let mut repl = Repl::new(&context);
+        repl.eval("function answer() -> s32 { return 1; }").unwrap();
+        repl.eval("let first: s32 = answer();").unwrap();
+        repl.eval("function answer() -> s32 { return 2; }").unwrap();
+        repl.eval("let second: s32 = answer();").unwrap();
+        assert_eq!(value(&repl, "first"), 1);
+        assert_eq!(value(&repl, "second"), 2);
+
+        repl.eval("let first: bool = true;").unwrap();
+        assert_eq!(repl.eval(":type first").unwrap(), Some("bool".to_string()));
+    }
+
+    #[test]
+    fn test_variables_are_kept_between_inputs() {
+        let context = Context::create();
+        let mut repl = Repl::new(&context);
+        repl.eval("let count: s32 = 1;").unwrap();
+        repl.eval("count = count + 1;").unwrap();
+        repl.eval("for (i in 0..3) { count += i; }").unwrap();
+        assert_eq!(value(&repl, "count"), 5);
+        assert_eq!(repl.globals["count"].symbol, "repl0::count");
+        assert_eq!(repl.modules.len(), 3);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{span, unsupported, ModuleCompiler, Value};
+use crate::codegen::CodeGen;
+use crate::semantic::error::Error;
+use crate::semantic::results::{check_try, Propagation};
+use crate::semantic::types::Type;
+use crate::syntax::ast::*;
+use inkwell::basic_block::BasicBlock;
+use inkwell::values::{BasicValueEnum, IntValue, StructValue};
+use inkwell::IntPredicate;
+use std::ops::Range;
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Whether the union `value` holds one of the members tagged `tags`.
+    pub fn build_tag_check(&self, value: StructValue<'ctx>, tags: &[u32]) -> IntValue<'ctx> {
+        let tag = self
+            .builder
+            .build_extract_value(value, 0, "tag")
+            .unwrap()
+            .into_int_value();
+        let tag_type = self.context.i32_type();
+        tags.iter()
+            .map(|expected| {
+                self.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    tag,
+                    tag_type.const_int(*expected as u64, false),
+                    "is_error",
+                )
+            })
+            .reduce(|a, b| self.builder.build_or(a, b, "is_error"))
+            .unwrap_or_else(|| self.context.bool_type().const_zero())
+    }
+
+    /// Lowers `try value`: branches to `on_error` if the result union holds
+    /// an error, and otherwise continues in a new block, where the builder is
+    /// left positioned.
+    pub fn build_try(
+        &self,
+        value: StructValue<'ctx>,
+        propagation: &Propagation,
+        on_error: BasicBlock<'ctx>,
+    ) -> Option<BasicBlock<'ctx>> {
+        let function = self.builder.get_insert_block()?.get_parent()?;
+        let is_error = self.build_tag_check(value, &propagation.error_tags);
+        let on_value = self.context.append_basic_block(function, "try.value");
+        self.builder
+            .build_conditional_branch(is_error, on_error, on_value);
+        self.builder.position_at_end(on_value);
+        Some(on_value)
+    }
+
+    /// The field of a union of `members` that holds the member tagged `tag`.
+    /// Members without a value, like `null`, have none.
+    pub fn union_field(&self, members: &[Type], tag: u32) -> Result<Option<u32>, CodeGenError> {
+        let mut field = 1;
+        for (index, member) in members.iter().enumerate() {
+            let has_value = self.llvm_type(member)?.is_some();
+            if index == tag as usize {
+                return Ok(if has_value { Some(field) } else { None });
+            }
+            if has_value {
+                field += 1;
+            }
+        }
+        Err(CodeGenError::Unknown)
+    }
+}
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Wraps a value in a union, as the member of its type or else the first
+    /// member it converts to. Pointers convert to the interface pointers
+    /// their pointee implements.
+    pub(super) fn build_union(
+        &self,
+        value: Value<'ctx>,
+        union: &Type,
+        span: Range<usize>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let members = union.unqualified().members();
+        let exact = members
+            .iter()
+            .position(|member| member.unqualified() == value.ty.unqualified());
+        let tag = exact
+            .or_else(|| {
+                members.iter().position(|member| {
+                    value.ty.widens_to(member)
+                        || (member.interface_pointer().is_some()
+                            && self
+                                .interfaces
+                                .check_cast(&self.env, &value.ty, member)
+                                .is_ok())
+                })
+            })
+            .ok_or_else(|| Error::TypeMismatch {
+                expected: union.to_string(),
+                found: value.ty.to_string(),
+                span: span.clone(),
+            })?;
+        let member = &members[tag];
+        let value = if member.interface_pointer().is_some() && exact.is_none() {
+            self.compile_interface_cast(value, member, span.clone())?
+        } else {
+            self.convert(value, member, span)?
+        };
+        let llvm_type = self
+            .codegen
+            .llvm_type(union)?
+            .ok_or(CodeGenError::Unknown)?;
+        let builder = &self.codegen.builder;
+        let tag_value = self.codegen.context.i32_type().const_int(tag as u64, false);
+        let mut wrapped = builder
+            .build_insert_value(
+                llvm_type.const_zero().into_struct_value(),
+                tag_value,
+                0,
+                "union",
+            )
+            .ok_or(CodeGenError::Unknown)?
+            .into_struct_value();
+        if let (Some(field), Some(value)) =
+            (self.codegen.union_field(members, tag as u32)?, value.value)
+        {
+            wrapped = builder
+                .build_insert_value(wrapped, value, field, "union")
+                .ok_or(CodeGenError::Unknown)?
+                .into_struct_value();
+        }
+        Ok(Value {
+            value: Some(wrapped.into()),
+            ty: union.clone(),
+        })
+    }
+
+    /// Reads the member tagged `tag` out of a union of `members`, which must
+    /// hold it.
+    pub(super) fn build_union_member(
+        &self,
+        union: StructValue<'ctx>,
+        members: &[Type],
+        tag: u32,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let value: Option<BasicValueEnum<'ctx>> = match self.codegen.union_field(members, tag)? {
+            Some(field) => Some(
+                self.codegen
+                    .builder
+                    .build_extract_value(union, field, "member")
+                    .ok_or(CodeGenError::Unknown)?,
+            ),
+            None => None,
+        };
+        Ok(Value {
+            value,
+            ty: members[tag as usize].clone(),
+        })
+    }
+
+    /// Reads a union of `members` as a value of `ty`, which contains the
+    /// members tagged `tags`. The union must hold one of them.
+    pub(super) fn build_union_narrowing(
+        &self,
+        union: StructValue<'ctx>,
+        members: &[Type],
+        tags: &[u32],
+        ty: &Type,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let narrowed = ty.unqualified().members();
+        if let (&[tag], &[_]) = (tags, narrowed) {
+            let member = self.build_union_member(union, members, tag)?;
+            return Ok(Value {
+                value: member.value,
+                ty: ty.clone(),
+            });
+        }
+        let builder = &self.codegen.builder;
+        let tag_type = self.codegen.context.i32_type();
+        let tag = builder
+            .build_extract_value(union, 0, "tag")
+            .ok_or(CodeGenError::Unknown)?
+            .into_int_value();
+        let llvm_type = self.codegen.llvm_type(ty)?.ok_or(CodeGenError::Unknown)?;
+        let mut wrapped = llvm_type.const_zero().into_struct_value();
+        let mut narrowed_tag = None;
+        for &from in tags {
+            let member = &members[from as usize];
+            let to = narrowed
+                .iter()
+                .position(|narrowed| narrowed == member)
+                .ok_or(CodeGenError::Unknown)? as u32;
+            let to_value = tag_type.const_int(to as u64, false);
+            narrowed_tag = Some(match narrowed_tag {
+                None => to_value,
+                Some(previous) => {
+                    let from_value = tag_type.const_int(from as u64, false);
+                    let held = builder.build_int_compare(IntPredicate::EQ, tag, from_value, "held");
+                    builder
+                        .build_select(held, to_value, previous, "tag")
+                        .into_int_value()
+                }
+            });
+            let fields = (
+                self.codegen.union_field(members, from)?,
+                self.codegen.union_field(narrowed, to)?,
+            );
+            if let (Some(from_field), Some(to_field)) = fields {
+                let value = builder
+                    .build_extract_value(union, from_field, "member")
+                    .ok_or(CodeGenError::Unknown)?;
+                wrapped = builder
+                    .build_insert_value(wrapped, value, to_field, "union")
+                    .ok_or(CodeGenError::Unknown)?
+                    .into_struct_value();
+            }
+        }
+        let narrowed_tag = narrowed_tag.ok_or(CodeGenError::Unknown)?;
+        wrapped = builder
+            .build_insert_value(wrapped, narrowed_tag, 0, "union")
+            .ok_or(CodeGenError::Unknown)?
+            .into_struct_value();
+        Ok(Value {
+            value: Some(wrapped.into()),
+            ty: ty.clone(),
+        })
+    }
+
+    /// Compiles `try operand`, which returns the error a result union holds
+    /// from the function, and otherwise evaluates to the value it holds.
+    pub(super) fn compile_try(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+        try_expression: &TryExpression<'ast>,
+    ) -> Result<Value<'ctx>, CodeGenError> {
+        let operand = self.compile_expression(&try_expression.operand, None)?;
+        let propagation = check_try(
+            &self.interfaces,
+            &operand.ty,
+            self.return_type.as_ref(),
+            span(expression),
+        )?;
+        if let Type::Union(_) = propagation.value {
+            return Err(unsupported(
+                "try on results with several values",
+                expression,
+            ));
+        }
+        let members = operand.ty.unqualified().members().to_vec();
+        let union = operand
+            .value
+            .ok_or(CodeGenError::Unknown)?
+            .into_struct_value();
+        let function = self
+            .current_block()?
+            .get_parent()
+            .ok_or(CodeGenError::Unknown)?;
+        let context = self.codegen.context;
+        let on_error = context.append_basic_block(function, "try.error");
+        let on_value = self
+            .codegen
+            .build_try(union, &propagation, on_error)
+            .ok_or(CodeGenError::Unknown)?;
+
+        // Each error is returned as the member of the return type it
+        // converts to.
+        let return_type = self.return_type.clone().ok_or(CodeGenError::Unknown)?;
+        self.codegen.builder.position_at_end(on_error);
+        for tag in &propagation.error_tags {
+            let returned = context.append_basic_block(function, "try.return");
+            let next = context.append_basic_block(function, "try.error");
+            let is_tag = self.codegen.build_tag_check(union, &[*tag]);
+            self.codegen
+                .builder
+                .build_conditional_branch(is_tag, returned, next);
+            self.codegen.builder.position_at_end(returned);
+            let error = self.build_union_member(union, &members, *tag)?;
+            let error = self.convert(error, &return_type, span(expression))?;
+            let error = error.value.ok_or(CodeGenError::Unknown)?;
+            self.codegen.builder.build_return(Some(&error));
+            self.codegen.builder.position_at_end(next);
+        }
+        self.codegen.builder.build_unreachable();
+
+        self.codegen.builder.position_at_end(on_value);
+        let tag = members
+            .iter()
+            .position(|member| *member == propagation.value)
+            .ok_or(CodeGenError::Unknown)?;
+        self.build_union_member(union, &members, tag as u32)
+    }
+}
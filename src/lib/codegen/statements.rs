@@ -0,0 +1,450 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::loops::LoopTargets;
+use crate::codegen::module::{span, unsupported, EnclosingLoop, ModuleCompiler, Value};
+use crate::semantic::error::Error;
+use crate::semantic::iteration::{range_type, resolve_iteration, Iteration};
+use crate::semantic::results::check_discarded_result;
+use crate::semantic::types::Type;
+use crate::syntax::ast::*;
+use inkwell::values::IntValue;
+use std::collections::HashMap;
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    pub(super) fn compile_block(&mut self, block: &BlockNode<'ast>) -> Result<(), CodeGenError> {
+        self.scopes.push(HashMap::new());
+        let res = block
+            .value
+            .elements
+            .iter()
+            .try_for_each(|element| match element.value {
+                BlockElement::Statement(statement) => self.compile_statement(&statement),
+                BlockElement::Declaration(declaration) => self.compile_local(&declaration),
+                BlockElement::Import(import) => Err(unsupported("imports in blocks", &import)),
+            });
+        self.scopes.pop();
+        res
+    }
+
+    pub(super) fn compile_statement(
+        &mut self,
+        statement: &StatementNode<'ast>,
+    ) -> Result<(), CodeGenError> {
+        match statement.value {
+            Statement::InnerBlock(block) => self.compile_block(&block),
+            Statement::If(if_statement) => self.compile_if(&if_statement),
+            Statement::Loop(LoopStatement::WhileLoop(while_loop)) => {
+                self.compile_while(&while_loop)
+            }
+            Statement::Loop(LoopStatement::InfiniteLoop(infinite)) => {
+                self.compile_infinite_loop(&infinite)
+            }
+            Statement::Loop(LoopStatement::ForLoop(for_loop)) => self.compile_for(&for_loop),
+            Statement::Jump(jump) => self.compile_jump(statement, jump),
+            Statement::Expression(expression) => {
+                let value = self.compile_expression(&expression, None)?;
+                self.warnings.extend(check_discarded_result(
+                    &self.interfaces,
+                    &expression,
+                    &value.ty,
+                ));
+                Ok(())
+            }
+            Statement::Match(matching) => self.compile_match(statement, &matching),
+            Statement::Delete(_) => Err(unsupported("delete statements", statement)),
+        }
+    }
+
+    /// Declares a local variable or constant.
+    fn compile_local(&mut self, declaration: &DeclarationNode<'ast>) -> Result<(), CodeGenError> {
+        match declaration.value.declarator {
+            Declarator::Variable(_) | Declarator::Constant(_) => {}
+            Declarator::Type(_) => return Ok(()),
+            _ => {
+                return Err(unsupported(
+                    "local type and function declarations",
+                    declaration,
+                ))
+            }
+        }
+        if let Some((identifier, value, mutable)) = self.compile_initializer(declaration)? {
+            let variable = self.declare_local(identifier.value, value.ty, mutable)?;
+            if let Some(value) = value.value {
+                self.codegen.builder.build_store(variable.pointer, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluates the initial value of a `let` or `const` declaration. The
+    /// variable has its declared type, or the type of its initializer.
+    /// Variables declared without a value start out zeroed.
+    pub(super) fn compile_initializer(
+        &mut self,
+        declaration: &DeclarationNode<'ast>,
+    ) -> Result<Option<(IdentifierNode<'ast>, Value<'ctx>, bool)>, CodeGenError> {
+        let (identifier, type_expression, expression, mutable) = match declaration.value.declarator
+        {
+            Declarator::Variable(variable) => (
+                variable.identifier,
+                variable.type_expression,
+                variable.expression,
+                true,
+            ),
+            Declarator::Constant(constant) => (
+                constant.identifier,
+                constant.type_expression,
+                Some(constant.expression),
+                false,
+            ),
+            _ => return Ok(None),
+        };
+        let declared = match type_expression {
+            Some(ty) => Some(self.env.resolve(&ty, &[])?),
+            None => None,
+        };
+        let value = match (expression, declared) {
+            (Some(expression), Some(declared)) => {
+                let value = self.compile_expression(&expression, Some(&declared))?;
+                self.convert(value, &declared, span(&expression))?
+            }
+            (Some(expression), None) => {
+                let value = self.compile_expression(&expression, None)?;
+                if value.value.is_none() {
+                    return Err(unsupported("variables without a value", &expression));
+                }
+                value
+            }
+            (None, Some(declared)) => {
+                let zero = self
+                    .codegen
+                    .llvm_type(&declared)?
+                    .ok_or_else(|| unsupported("variables without a value", declaration))?
+                    .const_zero();
+                Value {
+                    value: Some(zero),
+                    ty: declared,
+                }
+            }
+            (None, None) => return Err(unsupported("variables without a type", declaration)),
+        };
+        Ok(Some((identifier, value, mutable)))
+    }
+
+    fn compile_if(&mut self, if_statement: &IfStatement<'ast>) -> Result<(), CodeGenError> {
+        let reachable = self.reachable;
+        let condition = self.compile_condition(&if_statement.condition)?;
+        let function = self
+            .current_block()?
+            .get_parent()
+            .ok_or(CodeGenError::Unknown)?;
+        let context = self.codegen.context;
+        let then_block = context.append_basic_block(function, "if.then");
+        let else_block = context.append_basic_block(function, "if.else");
+        let merge = context.append_basic_block(function, "if.end");
+        self.codegen
+            .builder
+            .build_conditional_branch(condition, then_block, else_block);
+
+        self.codegen.builder.position_at_end(then_block);
+        self.compile_branch(&if_statement.true_block)?;
+        let then_reachable = self.fall_through(merge);
+
+        self.codegen.builder.position_at_end(else_block);
+        self.reachable = reachable;
+        if let Some(else_statement) = if_statement.else_block {
+            self.compile_branch(&else_statement)?;
+        }
+        let else_reachable = self.fall_through(merge);
+
+        self.codegen.builder.position_at_end(merge);
+        self.reachable = then_reachable || else_reachable;
+        Ok(())
+    }
+
+    fn compile_while(&mut self, while_loop: &WhileLoop<'ast>) -> Result<(), CodeGenError> {
+        let reachable = self.reachable;
+        let function = self
+            .current_block()?
+            .get_parent()
+            .ok_or(CodeGenError::Unknown)?;
+        let context = self.codegen.context;
+        let header = context.append_basic_block(function, "while.cond");
+        let body = context.append_basic_block(function, "while.body");
+        let exit = context.append_basic_block(function, "while.end");
+        let first = if while_loop.is_do_while { body } else { header };
+        self.codegen.builder.build_unconditional_branch(first);
+
+        self.codegen.builder.position_at_end(header);
+        let condition = self.compile_condition(&while_loop.condition)?;
+        self.codegen
+            .builder
+            .build_conditional_branch(condition, body, exit);
+
+        self.codegen.builder.position_at_end(body);
+        let targets = LoopTargets {
+            continue_to: header,
+            break_to: exit,
+        };
+        self.compile_loop_body(targets, &while_loop.statement)?;
+        self.fall_through(header);
+
+        self.codegen.builder.position_at_end(exit);
+        self.reachable = reachable;
+        Ok(())
+    }
+
+    fn compile_infinite_loop(&mut self, infinite: &InfiniteLoop<'ast>) -> Result<(), CodeGenError> {
+        let reachable = self.reachable;
+        let function = self
+            .current_block()?
+            .get_parent()
+            .ok_or(CodeGenError::Unknown)?;
+        let context = self.codegen.context;
+        let body = context.append_basic_block(function, "loop.body");
+        let exit = context.append_basic_block(function, "loop.end");
+        self.codegen.builder.build_unconditional_branch(body);
+
+        self.codegen.builder.position_at_end(body);
+        let targets = LoopTargets {
+            continue_to: body,
+            break_to: exit,
+        };
+        let broken = self.compile_loop_body(targets, &infinite.statement)?;
+        self.fall_through(body);
+
+        // Only a `break` leaves the loop.
+        self.codegen.builder.position_at_end(exit);
+        self.reachable = reachable && broken;
+        Ok(())
+    }
+
+    /// Lowers `for (x in expr)` as `resolve_iteration` decides: ranges and
+    /// arrays count through their values, and anything else is stepped
+    /// through by calling its `next` method until it returns `null`.
+    fn compile_for(&mut self, for_loop: &ForLoop<'ast>) -> Result<(), CodeGenError> {
+        let reachable = self.reachable;
+        let codegen = self.codegen;
+        let range = for_loop.range;
+        // Ranges written in the loop count between their bounds without
+        // creating a `Range` value.
+        let bounds = match range.value {
+            Expression::BinaryExpression(binary)
+                if matches!(
+                    binary.operator,
+                    BinaryOperator::RangeExclusive | BinaryOperator::RangeInclusive
+                ) =>
+            {
+                let (start, end) = self.compile_operands(&binary.left, &binary.right, None)?;
+                Some(self.unify(start, end, span(&range))?)
+            }
+            _ => None,
+        };
+        let iterated = match &bounds {
+            Some((start, _)) => Value {
+                value: None,
+                ty: range_type(&start.ty),
+            },
+            None => self.compile_expression(&range, None)?,
+        };
+        let iteration = resolve_iteration(
+            &self.methods,
+            &self.interfaces,
+            &self.env,
+            for_loop,
+            &iterated.ty,
+        )?;
+        let element = iteration.element().clone();
+        let mut res = Ok(false);
+        match iteration {
+            Iteration::Range { inclusive, .. } => {
+                let signed = match element.unqualified() {
+                    Type::Primitive(pt) => pt.integer_layout().map(|(signed, _)| signed),
+                    _ => None,
+                }
+                .ok_or(CodeGenError::Unknown)?;
+                let body = |targets, i: IntValue<'ctx>| {
+                    res = self.compile_for_body(for_loop, targets, element, i.into());
+                };
+                match bounds {
+                    Some((start, end)) => {
+                        let inclusive = codegen
+                            .context
+                            .bool_type()
+                            .const_int((inclusive == Some(true)) as u64, false);
+                        let (start, end) = (int_value(&start)?, int_value(&end)?);
+                        codegen.build_counted_loop(start, end, inclusive, signed, body);
+                    }
+                    None => {
+                        let range = iterated
+                            .value
+                            .ok_or(CodeGenError::Unknown)?
+                            .into_struct_value();
+                        codegen.build_range_loop(range, signed, body);
+                    }
+                }
+            }
+            Iteration::Array { .. } => {
+                let array = iterated
+                    .value
+                    .ok_or(CodeGenError::Unknown)?
+                    .into_struct_value();
+                codegen.build_array_loop(array, |targets, value| {
+                    res = self.compile_for_body(for_loop, targets, element, value);
+                });
+            }
+            // The iterator is copied, so that `next` advances the copy.
+            Iteration::Iterator { next, .. } => {
+                let iterator = self.build_temporary(iterated)?;
+                let mut called = Ok(());
+                codegen.build_iterator_loop(
+                    self,
+                    |compiler| match compiler.build_resolved_call(
+                        &next,
+                        &iterator,
+                        &[],
+                        span(&range),
+                    ) {
+                        Ok(optional) => optional.value,
+                        Err(e) => {
+                            called = Err(e);
+                            None
+                        }
+                    },
+                    |compiler, targets, value| {
+                        res = compiler.compile_for_body(for_loop, targets, element, value);
+                    },
+                );
+                called?;
+            }
+        }
+        self.end_for(reachable, res)
+    }
+
+    fn compile_for_body(
+        &mut self,
+        for_loop: &ForLoop<'ast>,
+        targets: LoopTargets<'ctx>,
+        element: Type,
+        value: inkwell::values::BasicValueEnum<'ctx>,
+    ) -> Result<bool, CodeGenError> {
+        self.scopes.push(HashMap::new());
+        let variable = self.declare_local(for_loop.identifier.value, element, false);
+        let res = variable.and_then(|variable| {
+            self.codegen.builder.build_store(variable.pointer, value);
+            self.compile_loop_body(targets, &for_loop.statement)
+        });
+        self.scopes.pop();
+        // The loop helpers only add the branch to the next iteration when the
+        // body falls through.
+        if !self.reachable {
+            self.codegen.builder.build_unreachable();
+        }
+        res
+    }
+
+    /// The loop helpers leave the builder at their exit block, which the
+    /// loop condition reaches whenever the loop itself is reached.
+    fn end_for(
+        &mut self,
+        reachable: bool,
+        res: Result<bool, CodeGenError>,
+    ) -> Result<(), CodeGenError> {
+        res?;
+        self.reachable = reachable;
+        Ok(())
+    }
+
+    /// Compiles the body of a loop, returning whether it contains a `break`.
+    /// The builder is left where the body falls through.
+    fn compile_loop_body(
+        &mut self,
+        targets: LoopTargets<'ctx>,
+        statement: &StatementNode<'ast>,
+    ) -> Result<bool, CodeGenError> {
+        self.loops.push(EnclosingLoop {
+            targets,
+            broken: false,
+        });
+        let res = self.compile_branch(statement);
+        let enclosing = self.loops.pop().ok_or(CodeGenError::Unknown)?;
+        res?;
+        Ok(enclosing.broken)
+    }
+
+    /// Compiles the body of an `if` or loop in its own scope.
+    pub(super) fn compile_branch(
+        &mut self,
+        statement: &StatementNode<'ast>,
+    ) -> Result<(), CodeGenError> {
+        self.scopes.push(HashMap::new());
+        let res = self.compile_statement(statement);
+        self.scopes.pop();
+        res
+    }
+
+    /// Branches to `to` if control reaches the end of the current block.
+    /// Returns whether it did.
+    pub(super) fn fall_through(&mut self, to: inkwell::basic_block::BasicBlock<'ctx>) -> bool {
+        if self.reachable {
+            self.codegen.builder.build_unconditional_branch(to);
+        } else {
+            self.codegen.builder.build_unreachable();
+        }
+        self.reachable
+    }
+
+    fn compile_jump(
+        &mut self,
+        statement: &StatementNode<'ast>,
+        jump: JumpStatement<'ast>,
+    ) -> Result<(), CodeGenError> {
+        match jump {
+            JumpStatement::Break | JumpStatement::Continue => {
+                let enclosing = self.loops.last_mut().ok_or_else(|| {
+                    CodeGenError::from(Error::JumpOutsideLoop {
+                        span: span(statement),
+                    })
+                })?;
+                let target = if jump == JumpStatement::Break {
+                    enclosing.broken = true;
+                    enclosing.targets.break_to
+                } else {
+                    enclosing.targets.continue_to
+                };
+                self.codegen.builder.build_unconditional_branch(target);
+            }
+            JumpStatement::Return(expression) => {
+                let return_type = self.return_type.clone().ok_or_else(|| {
+                    CodeGenError::from(Error::TypeMismatch {
+                        expected: "no return value".to_string(),
+                        found: "a return value".to_string(),
+                        span: span(&expression),
+                    })
+                })?;
+                let value = self.compile_expression(&expression, Some(&return_type))?;
+                let value = self.convert(value, &return_type, span(&expression))?;
+                let value = value.value.ok_or(CodeGenError::Unknown)?;
+                self.codegen.builder.build_return(Some(&value));
+            }
+        }
+        self.continue_unreachable()
+    }
+
+    /// Compiles a condition, which must be a `bool`.
+    pub(super) fn compile_condition(
+        &mut self,
+        condition: &ExpressionNode<'ast>,
+    ) -> Result<IntValue<'ctx>, CodeGenError> {
+        let boolean = Type::Primitive(PrimitiveType::Bool);
+        let value = self.compile_expression(condition, Some(&boolean))?;
+        let value = self.convert(value, &boolean, span(condition))?;
+        int_value(&value)
+    }
+}
+
+pub(super) fn int_value<'ctx>(value: &Value<'ctx>) -> Result<IntValue<'ctx>, CodeGenError> {
+    value
+        .value
+        .map(|value| value.into_int_value())
+        .ok_or(CodeGenError::Unknown)
+}
@@ -0,0 +1,196 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{span, unsupported, ModuleCompiler, Variable};
+use crate::codegen::operators::is_array;
+use crate::semantic::constants::representation_type;
+use crate::semantic::error::Error;
+use crate::semantic::types::{NamedTypeRef, Type, TypeDefinition};
+use crate::syntax::ast::*;
+use inkwell::types::BasicTypeEnum;
+use inkwell::values::BasicValueEnum;
+use std::collections::HashMap;
+use std::ops::Range;
+
+impl<'a, 'ctx, 'ast> ModuleCompiler<'a, 'ctx, 'ast> {
+    /// Gives the non-generic structs of the module their fields, in the
+    /// order they are declared, and records how its enums are stored.
+    pub(super) fn define_types(&self) -> Result<(), CodeGenError> {
+        for (name, definition) in self.env.definitions() {
+            match definition {
+                TypeDefinition::Enum(declarator) => {
+                    let representation = declarator
+                        .representation
+                        .map_or(PrimitiveType::S32, representation_type);
+                    self.codegen.define_enum(name, representation);
+                }
+                TypeDefinition::Struct(declarator) if declarator.generic_parameters.is_empty() => {
+                    let struct_type = self
+                        .codegen
+                        .llvm_type(&Type::Struct(NamedTypeRef::new(name)))?
+                        .ok_or(CodeGenError::Unknown)?
+                        .into_struct_type();
+                    if !struct_type.is_opaque() {
+                        continue;
+                    }
+                    let mut fields: Vec<BasicTypeEnum<'ctx>> = vec![];
+                    for member in declarator.members.iter() {
+                        let ty = self.env.resolve(&member.value.type_expression, &[])?;
+                        let field = self
+                            .codegen
+                            .llvm_type(&ty)?
+                            .ok_or_else(|| unsupported("members without a value", member))?;
+                        fields.push(field);
+                    }
+                    struct_type.set_body(&fields, false);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The position and type of the field `member` of a struct.
+    fn struct_field(
+        &self,
+        named: &NamedTypeRef,
+        member: &IdentifierNode<'ast>,
+    ) -> Result<(u32, Type), CodeGenError> {
+        let unknown_member = || {
+            CodeGenError::from(Error::UnknownMember {
+                ty: named.to_string(),
+                member: member.value.to_string(),
+                span: span(member),
+            })
+        };
+        let declarator = match self.env.get(&named.name) {
+            Some(TypeDefinition::Struct(declarator)) => *declarator,
+            _ => return Err(unknown_member()),
+        };
+        let generics: Vec<&str> = declarator
+            .generic_parameters
+            .iter()
+            .map(|g| g.value)
+            .collect();
+        let bindings: HashMap<String, Type> = generics
+            .iter()
+            .map(|g| g.to_string())
+            .zip(named.generic_arguments.iter().cloned())
+            .collect();
+        let (index, field) = declarator
+            .members
+            .iter()
+            .enumerate()
+            .find(|(_, field)| field.value.identifier.value == member.value)
+            .ok_or_else(unknown_member)?;
+        let ty = self.env.resolve(&field.value.type_expression, &generics)?;
+        Ok((index as u32, ty.substitute(&bindings)))
+    }
+
+    /// The storage an expression refers to: a variable, an array element, a
+    /// field, or what a pointer points to. Other values, including what an
+    /// overload of `[]` returns, are stored in a temporary, which cannot be
+    /// assigned to.
+    pub(super) fn compile_address(
+        &mut self,
+        expression: &ExpressionNode<'ast>,
+    ) -> Result<Variable<'ctx>, CodeGenError> {
+        match expression.value {
+            Expression::IdentifierExpression(identifier)
+                if self.foreign_constant(expression).is_none() =>
+            {
+                self.lookup(&identifier)
+            }
+            Expression::IndexExpression(index) => {
+                let object = self.compile_address(&index.array)?;
+                if is_array(&object.ty) {
+                    return self.compile_element_of(object, &index);
+                }
+                let value = self.compile_index_overload(object, &index)?;
+                self.build_temporary(value)
+            }
+            Expression::MemberAccessExpression(access)
+                if self.foreign_constant(expression).is_none() =>
+            {
+                if let Some(variable) = self.namespace_variable(expression) {
+                    return variable;
+                }
+                let object = self.compile_address(&access.object)?;
+                self.compile_field(object, &access.member)
+            }
+            Expression::PrefixExpression(PrefixExpression {
+                operator: PrefixOperator::Dereference,
+                operand,
+            }) => {
+                let pointer = self.compile_expression(&operand, None)?;
+                self.pointee(pointer.value, &pointer.ty, span(&operand))
+            }
+            _ => {
+                let value = self.compile_expression(expression, None)?;
+                self.build_temporary(value)
+            }
+        }
+    }
+
+    /// The storage of the field `member` of `object`, a struct or a pointer
+    /// to one. Fields are as mutable as what holds them.
+    pub(super) fn compile_field(
+        &mut self,
+        object: Variable<'ctx>,
+        member: &IdentifierNode<'ast>,
+    ) -> Result<Variable<'ctx>, CodeGenError> {
+        let object = match object.ty.unqualified() {
+            Type::Pointer(_) => {
+                let pointer = self.codegen.builder.build_load(object.pointer, "object");
+                self.pointee(Some(pointer), &object.ty, span(member))?
+            }
+            _ => object,
+        };
+        let named = match object.ty.unqualified() {
+            Type::Struct(named) => named.clone(),
+            ty => {
+                return Err(Error::UnknownMember {
+                    ty: ty.to_string(),
+                    member: member.value.to_string(),
+                    span: span(member),
+                }
+                .into())
+            }
+        };
+        let (index, ty) = self.struct_field(&named, member)?;
+        let pointer = self
+            .codegen
+            .builder
+            .build_struct_gep(object.pointer, index, member.value)
+            .map_err(|_| unsupported("members of generic structs", member))?;
+        Ok(Variable {
+            pointer,
+            ty,
+            mutable: object.mutable && !matches!(object.ty, Type::Const(_)),
+        })
+    }
+
+    /// What a pointer of type `ty` points to. Pointers to constants cannot be
+    /// assigned through.
+    fn pointee(
+        &self,
+        pointer: Option<BasicValueEnum<'ctx>>,
+        ty: &Type,
+        span: Range<usize>,
+    ) -> Result<Variable<'ctx>, CodeGenError> {
+        let pointee = match ty.unqualified() {
+            Type::Pointer(pointee) if ty.interface_pointer().is_none() => (**pointee).clone(),
+            _ => {
+                return Err(Error::NoOperatorOverload {
+                    operator: format!("{:?}", PrefixOperator::Dereference),
+                    operands: ty.to_string(),
+                    span,
+                }
+                .into())
+            }
+        };
+        Ok(Variable {
+            pointer: pointer.ok_or(CodeGenError::Unknown)?.into_pointer_value(),
+            mutable: !matches!(pointee, Type::Const(_)),
+            ty: pointee,
+        })
+    }
+}
@@ -0,0 +1,288 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::{CodeGen, DEFAULT_TARGET_TRIPLE};
+use crate::semantic::prelude::RANGE_STRUCT;
+use crate::semantic::types::{FunctionSignature, NamedTypeRef, Type};
+use crate::syntax::ast::PrimitiveType;
+use inkwell::types::{
+    BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FloatType, FunctionType, StructType,
+};
+use inkwell::AddressSpace;
+use std::convert::TryFrom;
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Lowers a resolved type to its LLVM representation. Returns `None` for
+    /// types without a runtime representation (`noreturn`, `c_void`).
+    ///
+    /// Pointers to interfaces are fat pointers: `{ i8*, <Interface>.vtable* }`.
+    pub fn llvm_type(&self, ty: &Type) -> Result<Option<BasicTypeEnum<'ctx>>, CodeGenError> {
+        let res = match ty {
+            Type::Primitive(pt) => return Ok(self.primitive_type(*pt)),
+            Type::Const(inner) | Type::Volatile(inner) => return self.llvm_type(inner),
+            Type::Pointer(inner) => match inner.unqualified() {
+                Type::Interface(interface) => self.interface_pointer_type(interface).into(),
+                inner => self.pointer_to(inner)?.into(),
+            },
+            // Sized arrays are slices: a pointer and a length.
+            Type::SizedArray(inner) => self
+                .context
+                .struct_type(
+                    &[
+                        self.pointer_to(inner)?.into(),
+                        self.context.i64_type().into(),
+                    ],
+                    false,
+                )
+                .into(),
+            Type::Array(inner, size) => {
+                let size = u32::try_from(*size).map_err(|_| CodeGenError::Unknown)?;
+                self.llvm_type(inner)?
+                    .ok_or(CodeGenError::Unknown)?
+                    .array_type(size)
+                    .into()
+            }
+            // Growable arrays additionally carry their capacity.
+            Type::UnsizedArray(inner) => self
+                .context
+                .struct_type(
+                    &[
+                        self.pointer_to(inner)?.into(),
+                        self.context.i64_type().into(),
+                        self.context.i64_type().into(),
+                    ],
+                    false,
+                )
+                .into(),
+            // Optional pointers use null for `null`; everything else carries a flag.
+            Type::Optional(inner) => match inner.unqualified() {
+                Type::Pointer(_) => return self.llvm_type(inner),
+                inner => {
+                    let mut fields = vec![self.context.bool_type().into()];
+                    fields.extend(self.llvm_type(inner)?);
+                    self.context.struct_type(&fields, false).into()
+                }
+            },
+            // A tag followed by one field per member.
+            Type::Union(members) => {
+                let mut fields = vec![self.context.i32_type().into()];
+                for member in members {
+                    fields.extend(self.llvm_type(member)?);
+                }
+                self.context.struct_type(&fields, false).into()
+            }
+            // The prelude's `Range<T>` is the one generic struct that is
+            // lowered, since range expressions create it.
+            Type::Struct(named)
+                if named.name == RANGE_STRUCT && named.generic_arguments.len() == 1 =>
+            {
+                let struct_type = self.named_struct_type(&named.to_string());
+                if struct_type.is_opaque() {
+                    let bound = self
+                        .llvm_type(&named.generic_arguments[0])?
+                        .ok_or(CodeGenError::Unknown)?;
+                    let inclusive = self.context.bool_type().into();
+                    struct_type.set_body(&[bound, bound, inclusive], false);
+                }
+                struct_type.into()
+            }
+            Type::Struct(named) => self.named_struct_type(&named.to_string()).into(),
+            // Enums are stored as their representation type, `s32` unless
+            // they declare one.
+            Type::Enum(name) => {
+                let representation = self
+                    .enums
+                    .borrow()
+                    .get(name)
+                    .copied()
+                    .unwrap_or(PrimitiveType::S32);
+                return Ok(self.primitive_type(representation));
+            }
+            // Interfaces only exist behind pointers; a bare interface value is
+            // the data it points to.
+            Type::Interface(_) | Type::Any => self.opaque_pointer_type().into(),
+            Type::Generic(name) => return Err(CodeGenError::UnsubstitutedGeneric(name.clone())),
+        };
+        Ok(Some(res))
+    }
+
+    /// Records the integer type the variants of an enum are stored as.
+    pub fn define_enum(&self, name: &str, representation: PrimitiveType) {
+        self.enums
+            .borrow_mut()
+            .insert(name.to_string(), representation);
+    }
+
+    fn primitive_type(&self, pt: PrimitiveType) -> Option<BasicTypeEnum<'ctx>> {
+        let res = match pt.canonical() {
+            PrimitiveType::Bool => self.context.bool_type().into(),
+            PrimitiveType::S8 | PrimitiveType::U8 | PrimitiveType::Char => {
+                self.context.i8_type().into()
+            }
+            PrimitiveType::S16
+            | PrimitiveType::U16
+            | PrimitiveType::CShort
+            | PrimitiveType::CUShort => self.context.i16_type().into(),
+            PrimitiveType::S32
+            | PrimitiveType::U32
+            | PrimitiveType::CInt
+            | PrimitiveType::CUInt => self.context.i32_type().into(),
+            PrimitiveType::S64
+            | PrimitiveType::U64
+            | PrimitiveType::CLong
+            | PrimitiveType::CULong
+            | PrimitiveType::CLongLong
+            | PrimitiveType::CULongLong => self.context.i64_type().into(),
+            PrimitiveType::F32 => self.context.f32_type().into(),
+            PrimitiveType::F64 => self.context.f64_type().into(),
+            PrimitiveType::CLongDouble => self.long_double_type().into(),
+            PrimitiveType::Null => self.opaque_pointer_type().into(),
+            PrimitiveType::NoReturn | PrimitiveType::CVoid => return None,
+            PrimitiveType::Short
+            | PrimitiveType::Int
+            | PrimitiveType::Long
+            | PrimitiveType::Float
+            | PrimitiveType::Double => unreachable!(),
+        };
+        Some(res)
+    }
+
+    /// C's `long double` on the module's target, or the host if it has none:
+    /// x87's 80-bit format on x86 outside of MSVC, IEEE quad precision on
+    /// 64-bit Arm Linux and RISC-V, IBM's double-double on PowerPC, and
+    /// `double` everywhere else.
+    fn long_double_type(&self) -> FloatType<'ctx> {
+        let triple = self.module.get_triple();
+        let triple = match triple.as_str().to_string_lossy() {
+            triple if triple.is_empty() => DEFAULT_TARGET_TRIPLE.clone(),
+            triple => triple.into_owned(),
+        };
+        let arch = triple.split('-').next().unwrap_or_default();
+        let apple_or_windows = triple.contains("apple") || triple.contains("windows");
+        match arch {
+            "x86_64" | "i386" | "i586" | "i686" if !triple.contains("msvc") => {
+                self.context.x86_f80_type()
+            }
+            "aarch64" | "arm64" if !apple_or_windows => self.context.f128_type(),
+            "riscv64" | "s390x" | "mips64" | "mips64el" => self.context.f128_type(),
+            "powerpc" | "powerpc64" | "powerpc64le" => self.context.ppc_f128_type(),
+            _ => self.context.f64_type(),
+        }
+    }
+
+    /// The LLVM function type of a function or method. Receivers are passed
+    /// as an untyped data pointer so the same type fits every implementor.
+    pub fn function_type(
+        &self,
+        signature: &FunctionSignature,
+    ) -> Result<FunctionType<'ctx>, CodeGenError> {
+        let mut parameters: Vec<BasicMetadataTypeEnum<'ctx>> = vec![];
+        if signature.receiver {
+            parameters.push(self.opaque_pointer_type().into());
+        }
+        for parameter in &signature.parameters {
+            parameters.extend(self.llvm_type(parameter)?.map(BasicMetadataTypeEnum::from));
+        }
+        let return_type = match &signature.return_type {
+            Some(return_type) => self.llvm_type(return_type)?,
+            None => None,
+        };
+        Ok(match return_type {
+            Some(return_type) => return_type.fn_type(&parameters, false),
+            None => self.context.void_type().fn_type(&parameters, false),
+        })
+    }
+
+    /// The fat pointer type of `*Interface`.
+    pub fn interface_pointer_type(&self, interface: &NamedTypeRef) -> StructType<'ctx> {
+        let vtable = self.vtable_type(interface);
+        self.context.struct_type(
+            &[
+                self.opaque_pointer_type().into(),
+                vtable.ptr_type(AddressSpace::Generic).into(),
+            ],
+            false,
+        )
+    }
+
+    /// The vtable type of an interface. Its body is filled in by
+    /// `define_vtable_type` once the method signatures are known.
+    pub fn vtable_type(&self, interface: &NamedTypeRef) -> StructType<'ctx> {
+        self.named_struct_type(&format!("{}.vtable", interface))
+    }
+
+    pub fn opaque_pointer_type(&self) -> inkwell::types::PointerType<'ctx> {
+        self.context.i8_type().ptr_type(AddressSpace::Generic)
+    }
+
+    pub(super) fn pointer_to(
+        &self,
+        pointee: &Type,
+    ) -> Result<inkwell::types::PointerType<'ctx>, CodeGenError> {
+        Ok(match self.llvm_type(pointee)? {
+            Some(pointee) => pointee.ptr_type(AddressSpace::Generic),
+            None => self.opaque_pointer_type(),
+        })
+    }
+
+    fn named_struct_type(&self, name: &str) -> StructType<'ctx> {
+        self.module
+            .get_struct_type(name)
+            .unwrap_or_else(|| self.context.opaque_struct_type(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::module::ModuleCompiler;
+    use crate::syntax::ast::SourceUnit;
+    use crate::syntax::parser::parse;
+    use inkwell::context::Context;
+    use inkwell::targets::TargetTriple;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_enums_are_stored_as_their_representation() {
+        let program = parse("enum Small: u8 { A, B } enum Plain { C }").unwrap();
+        let SourceUnit::Module(module) = program.body().iter().next().unwrap().value;
+        let context = Context::create();
+        let codegen = CodeGen::new(&context);
+        ModuleCompiler::new(&codegen, "enums", &module).unwrap();
+
+        let small = codegen.llvm_type(&Type::Enum("Small".to_string()));
+        assert_eq!(small.unwrap(), Some(context.i8_type().into()));
+        let plain = codegen.llvm_type(&Type::Enum("Plain".to_string()));
+        assert_eq!(plain.unwrap(), Some(context.i32_type().into()));
+    }
+
+    #[test]
+    fn test_long_double_follows_the_target() {
+        let context = Context::create();
+        let codegen = CodeGen::new(&context);
+        let long_double = Type::Primitive(PrimitiveType::CLongDouble);
+        let targets = [
+            ("x86_64-unknown-linux-gnu", context.x86_f80_type()),
+            ("i686-pc-windows-gnu", context.x86_f80_type()),
+            ("x86_64-pc-windows-msvc", context.f64_type()),
+            ("aarch64-unknown-linux-gnu", context.f128_type()),
+            ("aarch64-apple-darwin", context.f64_type()),
+            ("powerpc64le-unknown-linux-gnu", context.ppc_f128_type()),
+            ("armv7-unknown-linux-gnueabihf", context.f64_type()),
+        ];
+        for (triple, expected) in targets.iter() {
+            codegen.module.set_triple(&TargetTriple::create(triple));
+            let lowered = codegen.llvm_type(&long_double).unwrap();
+            assert_eq!(lowered, Some((*expected).into()), "{}", triple);
+        }
+    }
+
+    #[test]
+    fn test_generic_parameters_are_not_lowered() {
+        let context = Context::create();
+        let codegen = CodeGen::new(&context);
+        let pointer = Type::Pointer(Box::new(Type::Generic("T".to_string())));
+        match codegen.llvm_type(&pointer) {
+            Err(CodeGenError::UnsubstitutedGeneric(name)) => assert_eq!(name, "T"),
+            res => panic!("expected an unsubstituted generic, got {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::codegen::error::CodeGenError;
+use crate::codegen::CodeGen;
+use crate::semantic::interfaces::{Implementation, InterfaceTable};
+use crate::semantic::types::{FunctionSignature, NamedTypeRef, TypeEnvironment};
+use inkwell::module::Linkage;
+use inkwell::types::{BasicTypeEnum, StructType};
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValueEnum, CallableValue, FunctionValue, GlobalValue,
+    PointerValue, StructValue,
+};
+use inkwell::AddressSpace;
+use std::convert::TryFrom;
+
+impl<'ctx> CodeGen<'ctx> {
+    /// Fills in the vtable type of an interface: one function pointer per
+    /// method, in slot order.
+    pub fn define_vtable_type(
+        &self,
+        interface: &NamedTypeRef,
+        signatures: &[(&str, FunctionSignature)],
+    ) -> Result<StructType<'ctx>, CodeGenError> {
+        let vtable = self.vtable_type(interface);
+        let mut slots: Vec<BasicTypeEnum<'ctx>> = vec![];
+        for (_, signature) in signatures {
+            let slot = self
+                .function_type(signature)?
+                .ptr_type(AddressSpace::Generic);
+            slots.push(slot.into());
+        }
+        vtable.set_body(&slots, false);
+        Ok(vtable)
+    }
+
+    /// Declares (or looks up) the function a method of an implementation is
+    /// compiled to.
+    pub fn declare_method(
+        &self,
+        implementation: &Implementation,
+        method: &str,
+        signature: &FunctionSignature,
+    ) -> Result<FunctionValue<'ctx>, CodeGenError> {
+        let symbol = implementation.method_symbol(method);
+        if let Some(function) = self.module.get_function(&symbol) {
+            return Ok(function);
+        }
+        let fn_type = self.function_type(signature)?;
+        Ok(self.module.add_function(&symbol, fn_type, None))
+    }
+
+    /// Emits the vtable of an `(impl, interface)` pair as a constant global.
+    pub fn emit_vtable<'ast>(
+        &self,
+        interfaces: &InterfaceTable<'ast>,
+        env: &TypeEnvironment<'ast>,
+        implementation: &Implementation<'ast>,
+    ) -> Result<GlobalValue<'ctx>, CodeGenError> {
+        let symbol = implementation.vtable_symbol();
+        if let Some(existing) = self.module.get_global(&symbol) {
+            return Ok(existing);
+        }
+        let signatures = interfaces.method_signatures(env, &implementation.interface)?;
+        let vtable_type = self.define_vtable_type(&implementation.interface, &signatures)?;
+        let mut slots: Vec<BasicValueEnum<'ctx>> = vec![];
+        for (method, signature) in &signatures {
+            let function = self.declare_method(implementation, method, signature)?;
+            slots.push(function.as_global_value().as_pointer_value().into());
+        }
+        let vtable = self.module.add_global(vtable_type, None, &symbol);
+        vtable.set_initializer(&vtable_type.const_named_struct(&slots));
+        vtable.set_constant(true);
+        vtable.set_unnamed_addr(true);
+        vtable.set_linkage(Linkage::LinkOnceODR);
+        Ok(vtable)
+    }
+
+    /// Builds the fat pointer for casting `data`, a pointer to the implementor
+    /// of `implementation`, to a pointer to its interface.
+    pub fn build_interface_cast<'ast>(
+        &self,
+        interfaces: &InterfaceTable<'ast>,
+        env: &TypeEnvironment<'ast>,
+        implementation: &Implementation<'ast>,
+        data: PointerValue<'ctx>,
+    ) -> Result<StructValue<'ctx>, CodeGenError> {
+        let vtable = self.emit_vtable(interfaces, env, implementation)?;
+        let fat_type = self.interface_pointer_type(&implementation.interface);
+        let data = self
+            .builder
+            .build_pointer_cast(data, self.opaque_pointer_type(), "data");
+        let fat = self
+            .builder
+            .build_insert_value(fat_type.get_undef(), data, 0, "fat")
+            .unwrap()
+            .into_struct_value();
+        let fat = self
+            .builder
+            .build_insert_value(fat, vtable.as_pointer_value(), 1, "fat")
+            .unwrap()
+            .into_struct_value();
+        Ok(fat)
+    }
+
+    /// Calls the method in `slot` through the vtable of an interface fat
+    /// pointer, passing the data pointer as the receiver.
+    pub fn build_dynamic_call(
+        &self,
+        interface: &NamedTypeRef,
+        fat: StructValue<'ctx>,
+        slot: u32,
+        arguments: &[BasicMetadataValueEnum<'ctx>],
+    ) -> Result<Option<BasicValueEnum<'ctx>>, CodeGenError> {
+        let data = self.builder.build_extract_value(fat, 0, "data").unwrap();
+        let vtable = self
+            .builder
+            .build_extract_value(fat, 1, "vtable")
+            .unwrap()
+            .into_pointer_value();
+        let slot_ptr = self
+            .builder
+            .build_struct_gep(vtable, slot, "slot")
+            .map_err(|_| CodeGenError::InvalidVTableSlot {
+                interface: interface.to_string(),
+                slot,
+            })?;
+        let method = self
+            .builder
+            .build_load(slot_ptr, "method")
+            .into_pointer_value();
+        let callable =
+            CallableValue::try_from(method).map_err(|_| CodeGenError::InvalidVTableSlot {
+                interface: interface.to_string(),
+                slot,
+            })?;
+        let mut call_arguments = vec![data.into()];
+        call_arguments.extend_from_slice(arguments);
+        Ok(self
+            .builder
+            .build_call(callable, &call_arguments, "call")
+            .try_as_basic_value()
+            .left())
+    }
+}
@@ -0,0 +1,558 @@
+//! Generates C headers declaring what catlang modules export, so C (and
+//! anything else that can read C headers) can call into them.
+
+use crate::interop::exports::*;
+use crate::semantic::constants::{representation_type, Constant, ConstantEvaluator};
+use crate::semantic::error::*;
+use crate::semantic::functions::Overload;
+use crate::semantic::types::{Type, TypeDefinition, TypeEnvironment};
+use crate::syntax::ast::*;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::Path;
+
+/// Generates a C header for the module at path `name`.
+///
+/// The header declares the module's exported functions, and defines its
+/// exported structs and enums along with every type they use. Generic
+/// functions and structs are left out, since they are only compiled for the
+/// types they are used with. Exporting anything else C cannot represent,
+/// like unions and interfaces, is an error.
+pub fn generate_header(name: &str, module: &Module) -> std::result::Result<String, Vec<Error>> {
+    let ModuleExports {
+        headers,
+        env,
+        functions,
+        constants,
+    } = ModuleExports::resolve(name, module)?;
+    let mut writer = HeaderWriter {
+        module: name,
+        env: &env,
+        constants,
+        forward: String::new(),
+        definitions: String::new(),
+        written: HashSet::new(),
+        in_progress: HashSet::new(),
+    };
+    let mut errors = vec![];
+    let mut prototypes = String::new();
+    for declaration in exported_declarations(module) {
+        let res = match declaration.value.declarator {
+            Declarator::Struct(s) if s.generic_parameters.is_empty() => {
+                writer.define_struct(s.identifier.value)
+            }
+            Declarator::Enum(e) => writer.define_enum(e.identifier.value),
+            Declarator::Type(t) => writer.define_alias(t),
+            _ => Ok(()),
+        };
+        if let Err(e) = res {
+            errors.push(e);
+        }
+    }
+    let parameter_names = parameter_names(module);
+    for overload in exported_overloads(module, &functions) {
+        let names = parameter_names
+            .get(&overload.span)
+            .cloned()
+            .unwrap_or_default();
+        let overloaded = functions.overloads(overload.name).len() > 1;
+        match writer.prototype(overload, overloaded, &names) {
+            Ok(prototype) => prototypes.push_str(&prototype),
+            Err(e) => errors.push(e),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let guard = include_guard(name);
+    let mut header = String::new();
+    let _ = writeln!(
+        header,
+        "/* Generated by catlang from {}. Do not edit. */",
+        name
+    );
+    let _ = writeln!(header, "#ifndef {}\n#define {}\n", guard, guard);
+    header.push_str("#include <stdbool.h>\n#include <stdint.h>\n");
+    for imported in &headers {
+        let _ = writeln!(header, "#include \"{}\"", imported.path);
+    }
+    header.push_str("\n#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n");
+    // Every definition is followed by a blank line already.
+    for section in [&writer.forward, &writer.definitions, &prototypes].iter() {
+        header.push_str(section);
+        if !section.is_empty() && !section.ends_with("\n\n") {
+            header.push('\n');
+        }
+    }
+    header.push_str("#ifdef __cplusplus\n}\n#endif\n\n");
+    let _ = writeln!(header, "#endif /* {} */", guard);
+    Ok(header)
+}
+
+/// The macro guarding a header against being included twice, such as
+/// `SHAPES_H` for `src/shapes.cat`.
+fn include_guard(name: &str) -> String {
+    let stem = Path::new(name).file_stem().map_or_else(
+        || name.to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    );
+    format!("{}_H", identifier(&stem).to_uppercase())
+}
+
+/// Writes the C definitions of types, each after the types it depends on.
+struct HeaderWriter<'a, 'ast> {
+    module: &'a str,
+    env: &'a TypeEnvironment<'ast>,
+    constants: ConstantEvaluator<'ast>,
+    /// `typedef struct X X;` for every struct, so they can be pointed to
+    /// before they are defined.
+    forward: String,
+    definitions: String,
+    /// The names of the types already written.
+    written: HashSet<String>,
+    /// The structs being written, which can only be used through pointers.
+    in_progress: HashSet<String>,
+}
+
+impl<'a, 'ast> HeaderWriter<'a, 'ast> {
+    fn define_struct(&mut self, name: &str) -> Result<()> {
+        let declarator = match self.env.get(name) {
+            Some(TypeDefinition::Struct(s)) => *s,
+            _ => return Ok(()),
+        };
+        self.declare_struct(name);
+        if self.written.contains(name) || self.in_progress.contains(name) {
+            return Ok(());
+        }
+        self.in_progress.insert(name.to_string());
+        let mut fields = String::new();
+        for member in declarator.members.iter() {
+            let span = member.start as usize..member.end as usize;
+            let ty = self.env.resolve(&member.value.type_expression, &[])?;
+            let c_type = self.c_type(&ty, true, name, &span)?;
+            let _ = writeln!(
+                fields,
+                "    {};",
+                declare(&c_type, member.value.identifier.value)
+            );
+        }
+        self.in_progress.remove(name);
+        self.written.insert(name.to_string());
+        let _ = writeln!(self.definitions, "struct {} {{\n{}}};\n", name, fields);
+        Ok(())
+    }
+
+    fn declare_struct(&mut self, name: &str) {
+        let declaration = format!("typedef struct {} {};\n", name, name);
+        if !self.forward.contains(&declaration) {
+            self.forward.push_str(&declaration);
+        }
+    }
+
+    /// Defines an enum as its representation type, with a macro for each
+    /// variant: C's own enums are always `int`s.
+    fn define_enum(&mut self, name: &str) -> Result<()> {
+        let declarator = match self.env.get(name) {
+            Some(TypeDefinition::Enum(e)) => *e,
+            _ => return Ok(()),
+        };
+        if !self.written.insert(name.to_string()) {
+            return Ok(());
+        }
+        let representation = declarator
+            .representation
+            .map_or(PrimitiveType::S32, representation_type);
+        let mut definition = String::new();
+        let _ = writeln!(
+            definition,
+            "typedef {} {};",
+            primitive_type(representation).unwrap_or("int32_t"),
+            name
+        );
+        for (variant, value) in self.constants.enum_values(self.module, name)? {
+            if let Constant::Integer { value, .. } = value {
+                let literal = match value > i128::from(i64::MAX) {
+                    true => format!("{}u", value),
+                    false => value.to_string(),
+                };
+                let _ = writeln!(
+                    definition,
+                    "#define {}_{} (({}){})",
+                    name, variant, name, literal
+                );
+            }
+        }
+        let _ = writeln!(self.definitions, "{}", definition);
+        Ok(())
+    }
+
+    fn define_alias(&mut self, declarator: TypeDeclarator<'ast>) -> Result<()> {
+        let name = declarator.identifier.value;
+        let span = declarator.identifier.start as usize..declarator.identifier.end as usize;
+        let ty = self.env.resolve(&declarator.type_expression, &[])?;
+        let c_type = self.c_type(&ty, true, name, &span)?;
+        if self.written.insert(name.to_string()) {
+            let _ = writeln!(self.definitions, "typedef {};\n", declare(&c_type, name));
+        }
+        Ok(())
+    }
+
+    /// The prototype of an exported function. Functions declared
+    /// `#[extern("C")]` are declared under their own name; others keep
+    /// their catlang symbol through an `__asm__` label, and are named after
+    /// their parameter types in C if they are `overloaded`.
+    fn prototype(
+        &mut self,
+        overload: &Overload,
+        overloaded: bool,
+        parameter_names: &[&str],
+    ) -> Result<String> {
+        let span = &overload.span;
+        let name = overload.name;
+        let return_type = match &overload.signature.return_type {
+            None => "void".to_string(),
+            Some(Type::Primitive(PrimitiveType::NoReturn)) => "_Noreturn void".to_string(),
+            Some(ty) => self.c_type(ty, true, name, span)?,
+        };
+        let mut parameters = vec![];
+        for (index, ty) in overload.signature.parameters.iter().enumerate() {
+            let c_type = self.c_type(ty, true, name, span)?;
+            let parameter = match parameter_names.get(index) {
+                Some(parameter) => declare(&c_type, parameter),
+                None => c_type,
+            };
+            parameters.push(parameter);
+        }
+        let parameters = match parameters.len() {
+            0 => "void".to_string(),
+            _ => parameters.join(", "),
+        };
+        if overload.extern_c {
+            return Ok(format!(
+                "{};\n",
+                declare(&return_type, &format!("{}({})", name, parameters))
+            ));
+        }
+        let c_name = match overloaded {
+            true => identifier(&overload.to_string()),
+            false => name.to_string(),
+        };
+        let declarator = format!("{}({})", c_name, parameters);
+        Ok(format!(
+            "{} __asm__(\"{}\");\n",
+            declare(&return_type, &declarator),
+            overload.symbol
+        ))
+    }
+
+    /// Spells `ty` in C, writing the definitions it needs first. Types used
+    /// `by_value` need complete definitions; pointed-to structs only need to
+    /// be declared.
+    fn c_type(
+        &mut self,
+        ty: &Type,
+        by_value: bool,
+        item: &str,
+        span: &Range<usize>,
+    ) -> Result<String> {
+        let not_representable = || Error::NotRepresentableInC {
+            name: item.to_string(),
+            ty: ty.to_string(),
+            span: span.clone(),
+        };
+        let res = match ty {
+            Type::Primitive(pt) => primitive_type(*pt)
+                .ok_or_else(not_representable)?
+                .to_string(),
+            Type::Pointer(inner) => match inner.unqualified() {
+                Type::Interface(_) | Type::Any => return Err(not_representable()),
+                _ => format!("{} *", self.c_type(inner, false, item, span)?),
+            },
+            Type::Const(inner) => qualify("const", &self.c_type(inner, by_value, item, span)?),
+            Type::Volatile(inner) => {
+                qualify("volatile", &self.c_type(inner, by_value, item, span)?)
+            }
+            // Sized arrays are slices: a pointer and a length.
+            Type::SizedArray(inner) => {
+                let element = self.c_type(inner, false, item, span)?;
+                self.typedef(
+                    &format!("catlang_slice_{}", identifier(&element)),
+                    &[
+                        (format!("{} *", element), "data"),
+                        ("int64_t".to_string(), "length"),
+                    ],
+                )
+            }
+            // C arrays cannot be passed or returned by value, so fixed-size
+            // arrays are wrapped in a struct of the same layout.
+            Type::Array(inner, size) => {
+                let element = self.c_type(inner, true, item, span)?;
+                self.typedef(
+                    &format!("catlang_array{}_{}", size, identifier(&element)),
+                    &[(element, &format!("items[{}]", size))],
+                )
+            }
+            // Growable arrays additionally carry their capacity.
+            Type::UnsizedArray(inner) => {
+                let element = self.c_type(inner, false, item, span)?;
+                self.typedef(
+                    &format!("catlang_array_{}", identifier(&element)),
+                    &[
+                        (format!("{} *", element), "data"),
+                        ("int64_t".to_string(), "length"),
+                        ("int64_t".to_string(), "capacity"),
+                    ],
+                )
+            }
+            // Optional pointers use null for `null`; everything else carries a flag.
+            Type::Optional(inner) => match inner.unqualified() {
+                Type::Pointer(_) => self.c_type(inner, by_value, item, span)?,
+                _ => {
+                    let value = self.c_type(inner, true, item, span)?;
+                    self.typedef(
+                        &format!("catlang_optional_{}", identifier(&value)),
+                        &[("bool".to_string(), "has_value"), (value, "value")],
+                    )
+                }
+            },
+            Type::Struct(named) if named.generic_arguments.is_empty() => {
+                match self.env.get(&named.name) {
+                    Some(TypeDefinition::Struct(s)) if s.generic_parameters.is_empty() => {
+                        match by_value {
+                            true => self.define_struct(&named.name)?,
+                            false => self.declare_struct(&named.name),
+                        }
+                    }
+                    // Structs imported from C headers come with the header.
+                    None => {}
+                    _ => return Err(not_representable()),
+                }
+                named.name.clone()
+            }
+            Type::Enum(name) => {
+                self.define_enum(name)?;
+                name.clone()
+            }
+            Type::Struct(_)
+            | Type::Union(_)
+            | Type::Interface(_)
+            | Type::Generic(_)
+            | Type::Any => return Err(not_representable()),
+        };
+        Ok(res)
+    }
+
+    /// Defines a struct for a catlang type with no C equivalent, such as a
+    /// slice. Other generated headers may define the same struct, so the
+    /// definition is guarded.
+    fn typedef(&mut self, name: &str, fields: &[(String, &str)]) -> String {
+        if self.written.insert(name.to_string()) {
+            let guard = format!("{}_DEFINED", name.to_uppercase());
+            let mut definition =
+                format!("#ifndef {}\n#define {}\ntypedef struct {{\n", guard, guard);
+            for (ty, field) in fields {
+                let _ = writeln!(definition, "    {};", declare(ty, field));
+            }
+            let _ = writeln!(definition, "}} {};\n#endif\n", name);
+            self.definitions.push_str(&definition);
+        }
+        name.to_string()
+    }
+}
+
+/// The C spelling of a primitive type, if it has one. `noreturn` is only
+/// spelled in return types.
+fn primitive_type(pt: PrimitiveType) -> Option<&'static str> {
+    let res = match pt.canonical() {
+        PrimitiveType::S8 => "int8_t",
+        PrimitiveType::U8 => "uint8_t",
+        PrimitiveType::S16 => "int16_t",
+        PrimitiveType::U16 => "uint16_t",
+        PrimitiveType::S32 => "int32_t",
+        PrimitiveType::U32 => "uint32_t",
+        PrimitiveType::S64 => "int64_t",
+        PrimitiveType::U64 => "uint64_t",
+        PrimitiveType::Char => "char",
+        PrimitiveType::Bool => "bool",
+        PrimitiveType::F32 => "float",
+        PrimitiveType::F64 => "double",
+        PrimitiveType::CShort => "short",
+        PrimitiveType::CUShort => "unsigned short",
+        PrimitiveType::CInt => "int",
+        PrimitiveType::CUInt => "unsigned int",
+        PrimitiveType::CLong => "long",
+        PrimitiveType::CULong => "unsigned long",
+        PrimitiveType::CLongLong => "long long",
+        PrimitiveType::CULongLong => "unsigned long long",
+        PrimitiveType::CLongDouble => "long double",
+        PrimitiveType::CVoid => "void",
+        _ => return None,
+    };
+    Some(res)
+}
+
+/// Qualifies a C type: `const char` but `char *const`, since the qualifier
+/// applies to the pointer itself.
+fn qualify(qualifier: &str, c_type: &str) -> String {
+    match c_type.ends_with('*') {
+        true => format!("{}{}", c_type, qualifier),
+        false => format!("{} {}", qualifier, c_type),
+    }
+}
+
+/// Declares `name` as a `c_type`: `int32_t x`, but `char *x`.
+fn declare(c_type: &str, name: &str) -> String {
+    match c_type.ends_with('*') {
+        true => format!("{}{}", c_type, name),
+        false => format!("{} {}", c_type, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::module::ModuleCompiler;
+    use crate::codegen::optimize::OptLevel;
+    use crate::codegen::{target_machine, CodeGen};
+    use crate::syntax::parser::parse;
+    use inkwell::context::Context;
+    use inkwell::targets::{InitializationConfig, Target};
+    use pretty_assertions::assert_eq;
+
+    fn header(source: &str) -> std::result::Result<String, Vec<Error>> {
+        let program = parse(source).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        generate_header("shapes.cat", &module)
+    }
+
+    #[test]
+    fn test_exported_declarations() {
+        let source = "
+            enum Kind: u8 { Square, Circle = 4 }
+            struct Label { text: []char; weight: ?f32; }
+            export struct Shape { kind: Kind; label: Label; next: *Shape; }
+            #[extern(\"C\")]
+            export function area(shape: *const Shape) -> f64 {}
+            export function scale(shape: *Shape, by: s32) {}
+            export function scale(shape: *Shape, by: f32) {}
+            function hidden() {}";
+        let expected = r#"/* Generated by catlang from shapes.cat. Do not edit. */
+#ifndef SHAPES_H
+#define SHAPES_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Shape Shape;
+typedef struct Label Label;
+
+typedef uint8_t Kind;
+#define Kind_Square ((Kind)0)
+#define Kind_Circle ((Kind)4)
+
+#ifndef CATLANG_SLICE_CHAR_DEFINED
+#define CATLANG_SLICE_CHAR_DEFINED
+typedef struct {
+    char *data;
+    int64_t length;
+} catlang_slice_char;
+#endif
+
+#ifndef CATLANG_OPTIONAL_FLOAT_DEFINED
+#define CATLANG_OPTIONAL_FLOAT_DEFINED
+typedef struct {
+    bool has_value;
+    float value;
+} catlang_optional_float;
+#endif
+
+struct Label {
+    catlang_slice_char text;
+    catlang_optional_float weight;
+};
+
+struct Shape {
+    Kind kind;
+    Label label;
+    Shape *next;
+};
+
+double area(const Shape *shape);
+void scale_ptr_Shape_s32(Shape *shape, int32_t by) __asm__("shapes.cat::scale(*Shape, s32)");
+void scale_ptr_Shape_f32(Shape *shape, float by) __asm__("shapes.cat::scale(*Shape, f32)");
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SHAPES_H */
+"#;
+        assert_eq!(header(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_enum_typedefs_match_llvm() {
+        let source = "
+            export enum Plain { A, B }
+            export enum Small: u8 { A }
+            export enum Signed: s16 { A = -1 }
+            export enum Wide: u64 { A = 1 << 40 }";
+        let header = header(source).unwrap();
+        let program = parse(source).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let context = Context::create();
+        let codegen = CodeGen::named(&context, "shapes");
+        ModuleCompiler::new(&codegen, "shapes.cat", &module).unwrap();
+        Target::initialize_all(&InitializationConfig::default());
+        let machine = target_machine("x86_64-unknown-linux-gnu", OptLevel::None).unwrap();
+        let data = machine.get_target_data();
+        let integers = [
+            PrimitiveType::S8,
+            PrimitiveType::U8,
+            PrimitiveType::S16,
+            PrimitiveType::U16,
+            PrimitiveType::S32,
+            PrimitiveType::U32,
+            PrimitiveType::S64,
+            PrimitiveType::U64,
+        ];
+        for name in &["Plain", "Small", "Signed", "Wide"] {
+            // The catlang type the header declares the enum as.
+            let declared = integers
+                .iter()
+                .find(|pt| {
+                    let c_type = primitive_type(**pt).unwrap();
+                    header.contains(&format!("typedef {} {};", c_type, name))
+                })
+                .unwrap();
+            let size = |ty: &Type| data.get_abi_size(&codegen.llvm_type(ty).unwrap().unwrap());
+            assert_eq!(
+                size(&Type::Enum(name.to_string())),
+                size(&Type::Primitive(*declared)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_unrepresentable_exports() {
+        let source = "export function first(values: s32 | f32) {}";
+        assert_eq!(
+            header(source),
+            Err(vec![Error::NotRepresentableInC {
+                name: "first".to_string(),
+                ty: "(s32 | f32)".to_string(),
+                span: 16..21,
+            }])
+        );
+    }
+}
@@ -0,0 +1,1859 @@
+use crate::semantic::constants::Constant;
+use crate::semantic::patterns::{char_value, integer_bounds};
+use crate::semantic::types::{FunctionSignature, NamedTypeRef, Type};
+use crate::syntax::ast::PrimitiveType;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::Path;
+
+/// The declarations of a C header, mapped to catlang types.
+///
+/// Headers are read without following `#include`s, so types from other
+/// headers are unknown unless they are one of the fixed-width and size types
+/// of `<stdint.h>` and `<stddef.h>`, which assume a 64-bit target.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CHeader {
+    pub functions: Vec<CFunction>,
+    pub structs: Vec<CStruct>,
+    pub enums: Vec<CEnum>,
+    pub typedefs: Vec<(String, Type)>,
+    /// Integer, floating point and string `#define`s, and enumerators.
+    pub constants: Vec<(String, Constant)>,
+    /// Declarations that could not be imported, and why.
+    pub unsupported: Vec<Unsupported>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CFunction {
+    pub name: String,
+    pub signature: FunctionSignature,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CStruct {
+    pub name: String,
+    /// The fields in declaration order, or `None` if the struct is only
+    /// declared, as in `struct FILE;`.
+    pub fields: Option<Vec<(String, Type)>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CEnum {
+    pub name: String,
+    pub variants: Vec<(String, i128)>,
+}
+
+/// A declaration that could not be imported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unsupported {
+    /// The name the declaration would have been imported as, if it has one.
+    pub name: Option<String>,
+    pub reason: String,
+    pub line: usize,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(
+                f,
+                "cannot import {:?} declared on line {}: {}",
+                name, self.line, self.reason
+            ),
+            None => write!(f, "cannot import line {}: {}", self.line, self.reason),
+        }
+    }
+}
+
+/// A declaration of a header, found by name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symbol<'h> {
+    Function(&'h CFunction),
+    Struct(&'h CStruct),
+    Enum(&'h CEnum),
+    Typedef(&'h Type),
+    Constant(&'h Constant),
+}
+
+impl CHeader {
+    /// Finds the declaration named `name`.
+    pub fn lookup(&self, name: &str) -> Option<Symbol<'_>> {
+        let function = self.functions.iter().find(|f| f.name == name);
+        let structure = self.structs.iter().find(|s| s.name == name);
+        let enumeration = self.enums.iter().find(|e| e.name == name);
+        let typedef = self.typedefs.iter().find(|(n, _)| n == name);
+        let constant = self.constants.iter().find(|(n, _)| n == name);
+        function
+            .map(Symbol::Function)
+            .or_else(|| structure.map(Symbol::Struct))
+            .or_else(|| enumeration.map(Symbol::Enum))
+            .or_else(|| typedef.map(|(_, ty)| Symbol::Typedef(ty)))
+            .or_else(|| constant.map(|(_, value)| Symbol::Constant(value)))
+    }
+
+    /// Why the declaration named `name` could not be imported, if it could
+    /// not.
+    pub fn unsupported(&self, name: &str) -> Option<&Unsupported> {
+        self.unsupported
+            .iter()
+            .find(|u| u.name.as_deref() == Some(name))
+    }
+}
+
+/// Reads the C header at `path`.
+pub fn load_header(path: &Path) -> std::io::Result<CHeader> {
+    Ok(parse_header(&std::fs::read_to_string(path)?))
+}
+
+/// Reads the declarations of a C header.
+pub fn parse_header(source: &str) -> CHeader {
+    let mut preprocessor = Preprocessor::default();
+    for (line, text) in logical_lines(source) {
+        preprocessor.line(line, &text);
+    }
+    let mut parser = DeclarationParser {
+        tokens: std::mem::take(&mut preprocessor.tokens),
+        ..DeclarationParser::default()
+    };
+    parser.parse();
+    let mut header = parser.header;
+    let mut constants = preprocessor.constants(&parser.enumerators);
+    constants.append(&mut header.constants);
+    header.constants = constants;
+    header.unsupported.append(&mut preprocessor.unsupported);
+    header
+        .unsupported
+        .sort_by_key(|unsupported| unsupported.line);
+    header
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Identifier(String),
+    Number(String),
+    String(String),
+    Char(String),
+    Punctuation(&'static str),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Lexeme {
+    token: Token,
+    line: usize,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Identifier(s) | Token::Number(s) | Token::String(s) | Token::Char(s) => {
+                f.write_str(s)
+            }
+            Token::Punctuation(p) => f.write_str(p),
+        }
+    }
+}
+
+const PUNCTUATION: [&str; 33] = [
+    "...", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "->", "##", "{", "}", "(", ")", "[",
+    "]", ";", ",", "*", "=", "&", "|", "^", "~", "!", "<", ">", "+", "-", "/", "%", "?",
+];
+const OTHER_PUNCTUATION: [&str; 3] = [":", ".", "#"];
+
+/// Splits a header into lines without comments, joining lines continued
+/// with a backslash. Each line keeps the number it starts on.
+fn logical_lines(source: &str) -> Vec<(usize, String)> {
+    let mut stripped = String::new();
+    let mut chars = source.chars().peekable();
+    let mut quote = None;
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                stripped.push(c);
+                stripped.extend(chars.next());
+                continue;
+            }
+            (Some(q), c) if c == q || c == '\n' => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '/') if chars.peek() == Some(&'/') => {
+                while matches!(chars.peek(), Some(&c) if c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            (None, '/') if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push('\n');
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                stripped.push(' ');
+                continue;
+            }
+            _ => {}
+        }
+        stripped.push(c);
+    }
+
+    let mut lines: Vec<(usize, String)> = vec![];
+    let mut continued = false;
+    for (index, text) in stripped.lines().enumerate() {
+        let (text, continues) = match text.strip_suffix('\\') {
+            Some(text) => (text, true),
+            None => (text, false),
+        };
+        match lines.last_mut() {
+            Some((_, last)) if continued => last.push_str(text),
+            _ => lines.push((index + 1, text.to_string())),
+        }
+        continued = continues;
+    }
+    lines
+}
+
+fn tokenize(line: usize, text: &str) -> Vec<Lexeme> {
+    let mut tokens = vec![];
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let token = if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+            {
+                i += 1;
+            }
+            Token::Identifier(chars[start..i].iter().collect())
+        } else if c.is_ascii_digit()
+            || (c == '.' && matches!(chars.get(i + 1), Some(c) if c.is_ascii_digit()))
+        {
+            while i < chars.len() {
+                let c = chars[i];
+                let exponent_sign = (c == '+' || c == '-')
+                    && matches!(chars[i - 1], 'e' | 'E' | 'p' | 'P')
+                    && !chars[start..i].iter().collect::<String>().starts_with("0x");
+                if c.is_ascii_alphanumeric() || c == '.' || c == '_' || exponent_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            Token::Number(chars[start..i].iter().collect())
+        } else if c == '"' || c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+            let literal: String = chars[start..i.min(chars.len())].iter().collect();
+            if c == '"' {
+                Token::String(literal)
+            } else {
+                Token::Char(literal)
+            }
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 3)].iter().collect();
+            let punctuation = PUNCTUATION
+                .iter()
+                .chain(OTHER_PUNCTUATION.iter())
+                .find(|p| rest.starts_with(*p));
+            match punctuation {
+                Some(p) => {
+                    i += p.len();
+                    Token::Punctuation(p)
+                }
+                None => {
+                    i += 1;
+                    continue;
+                }
+            }
+        };
+        tokens.push(Lexeme { token, line });
+    }
+    tokens
+}
+
+#[derive(Clone, Debug)]
+enum Macro {
+    Object(Vec<Lexeme>),
+    Function {
+        parameters: Vec<String>,
+        body: Vec<Lexeme>,
+    },
+}
+
+/// A `#if` group: whether its lines are read, and whether any of its
+/// branches has been taken.
+#[derive(Clone, Copy, Debug)]
+struct Condition {
+    active: bool,
+    taken: bool,
+}
+
+/// Handles directives and expands macros, collecting the tokens of the
+/// declarations that remain.
+#[derive(Clone, Debug, Default)]
+struct Preprocessor {
+    macros: HashMap<String, Macro>,
+    /// Defined macros in the order they were defined, with their lines.
+    defined: Vec<(String, usize)>,
+    conditions: Vec<Condition>,
+    tokens: Vec<Lexeme>,
+    unsupported: Vec<Unsupported>,
+}
+
+impl Preprocessor {
+    fn active(&self) -> bool {
+        self.conditions.iter().all(|c| c.active)
+    }
+
+    fn line(&mut self, line: usize, text: &str) {
+        let trimmed = text.trim_start();
+        let directive = match trimmed.strip_prefix('#') {
+            Some(directive) => directive.trim_start(),
+            None => {
+                if self.active() {
+                    let tokens = tokenize(line, text);
+                    let mut expanded = self.expand(tokens, &mut HashSet::new());
+                    self.tokens.append(&mut expanded);
+                }
+                return;
+            }
+        };
+        let name: String = directive
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        let rest = &directive[name.len()..];
+        match name.as_str() {
+            "ifdef" | "ifndef" => {
+                let defined = self.macros.contains_key(rest.trim());
+                let active = defined == (name == "ifdef");
+                self.conditions.push(Condition {
+                    active,
+                    taken: active,
+                });
+            }
+            "if" => {
+                let active = self.active() && self.condition(line, rest);
+                self.conditions.push(Condition {
+                    active,
+                    taken: active,
+                });
+            }
+            "elif" => {
+                let taken = !matches!(self.conditions.last(), Some(c) if !c.taken);
+                let active = !taken && self.condition(line, rest);
+                if let Some(condition) = self.conditions.last_mut() {
+                    condition.active = active;
+                    condition.taken |= active;
+                }
+            }
+            "else" => {
+                if let Some(condition) = self.conditions.last_mut() {
+                    condition.active = !condition.taken;
+                    condition.taken = true;
+                }
+            }
+            "endif" => {
+                self.conditions.pop();
+            }
+            "define" if self.active() => self.define(line, rest),
+            "undef" if self.active() => {
+                let name = rest.trim();
+                self.macros.remove(name);
+                self.defined.retain(|(defined, _)| defined != name);
+            }
+            // Included headers are imported separately, and the remaining
+            // directives do not declare anything.
+            _ => {}
+        }
+    }
+
+    fn define(&mut self, line: usize, text: &str) {
+        let text = text.trim_start();
+        let name: String = text
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+        if name.is_empty() {
+            return;
+        }
+        let rest = &text[name.len()..];
+        let definition = match rest.strip_prefix('(') {
+            Some(rest) => {
+                let close = rest.find(')').unwrap_or(rest.len());
+                let parameters = rest[..close]
+                    .split(',')
+                    .map(|p| p.trim().to_string())
+                    .filter(|p| !p.is_empty())
+                    .collect();
+                let body = tokenize(line, rest.get(close + 1..).unwrap_or(""));
+                Macro::Function { parameters, body }
+            }
+            None => Macro::Object(tokenize(line, rest)),
+        };
+        self.defined.retain(|(defined, _)| *defined != name);
+        self.defined.push((name.clone(), line));
+        self.macros.insert(name, definition);
+    }
+
+    /// Evaluates the condition of a `#if` or `#elif`. Identifiers that are
+    /// not macros are 0, as in C.
+    fn condition(&self, line: usize, text: &str) -> bool {
+        let mut tokens = vec![];
+        let mut raw = tokenize(line, text).into_iter().peekable();
+        while let Some(lexeme) = raw.next() {
+            if lexeme.token != Token::Identifier("defined".to_string()) {
+                tokens.push(lexeme);
+                continue;
+            }
+            let parenthesized = raw.peek().map(|l| &l.token) == Some(&Token::Punctuation("("));
+            if parenthesized {
+                raw.next();
+            }
+            let defined = match raw.next().map(|l| l.token) {
+                Some(Token::Identifier(name)) => self.macros.contains_key(&name),
+                _ => false,
+            };
+            if parenthesized {
+                raw.next();
+            }
+            tokens.push(Lexeme {
+                token: Token::Number((defined as u8).to_string()),
+                line,
+            });
+        }
+        let tokens = self.expand(tokens, &mut HashSet::new());
+        let mut evaluator = Evaluator {
+            tokens: &tokens,
+            position: 0,
+            names: &HashMap::new(),
+            undefined_is_zero: true,
+            unsigned: false,
+            long: false,
+        };
+        matches!(evaluator.evaluate(), Ok(value) if value != 0)
+    }
+
+    /// Replaces macros in `tokens` with their definitions. Macros being
+    /// expanded are left alone, as in C.
+    fn expand(&self, tokens: Vec<Lexeme>, expanding: &mut HashSet<String>) -> Vec<Lexeme> {
+        let mut res = vec![];
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(lexeme) = tokens.next() {
+            let name = match &lexeme.token {
+                Token::Identifier(name) if !expanding.contains(name) => name.clone(),
+                _ => {
+                    res.push(lexeme);
+                    continue;
+                }
+            };
+            let replacement = match self.macros.get(&name) {
+                Some(Macro::Object(body)) => body.clone(),
+                Some(Macro::Function { parameters, body })
+                    if tokens.peek().map(|l| &l.token) == Some(&Token::Punctuation("(")) =>
+                {
+                    tokens.next();
+                    let arguments = macro_arguments(&mut tokens);
+                    substitute(body, parameters, &arguments)
+                }
+                _ => {
+                    res.push(lexeme);
+                    continue;
+                }
+            };
+            let replacement = replacement
+                .into_iter()
+                .map(|l| Lexeme {
+                    token: l.token,
+                    line: lexeme.line,
+                })
+                .collect();
+            expanding.insert(name.clone());
+            res.append(&mut self.expand(replacement, expanding));
+            expanding.remove(&name);
+        }
+        res
+    }
+
+    /// The values of the object-like macros that expand to a constant.
+    /// Function-like macros and macros expanding to anything else cannot be
+    /// imported; empty macros are only flags for `#ifdef`s.
+    fn constants(&mut self, enumerators: &HashMap<String, i128>) -> Vec<(String, Constant)> {
+        let mut constants = vec![];
+        for (name, line) in self.defined.clone() {
+            let body = match &self.macros[&name] {
+                Macro::Object(body) if body.is_empty() => continue,
+                Macro::Object(body) => body.clone(),
+                Macro::Function { .. } => {
+                    self.unsupported.push(Unsupported {
+                        name: Some(name),
+                        reason: "function-like macros are not supported".to_string(),
+                        line,
+                    });
+                    continue;
+                }
+            };
+            let mut expanding = HashSet::new();
+            expanding.insert(name.clone());
+            let tokens = self.expand(body, &mut expanding);
+            match constant(&tokens, enumerators) {
+                Some(value) => constants.push((name, value)),
+                None => self.unsupported.push(Unsupported {
+                    name: Some(name),
+                    reason: "only macros expanding to a number or string constant are supported"
+                        .to_string(),
+                    line,
+                }),
+            }
+        }
+        constants
+    }
+}
+
+/// Reads the arguments of a function-like macro call up to its closing
+/// parenthesis.
+fn macro_arguments(tokens: &mut impl Iterator<Item = Lexeme>) -> Vec<Vec<Lexeme>> {
+    let mut arguments = vec![vec![]];
+    let mut depth = 0;
+    for lexeme in tokens {
+        match lexeme.token {
+            Token::Punctuation("(") => depth += 1,
+            Token::Punctuation(")") if depth == 0 => break,
+            Token::Punctuation(")") => depth -= 1,
+            Token::Punctuation(",") if depth == 0 => {
+                arguments.push(vec![]);
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(argument) = arguments.last_mut() {
+            argument.push(lexeme);
+        }
+    }
+    arguments
+}
+
+fn substitute(body: &[Lexeme], parameters: &[String], arguments: &[Vec<Lexeme>]) -> Vec<Lexeme> {
+    let mut res = vec![];
+    for lexeme in body {
+        let argument = match &lexeme.token {
+            Token::Identifier(name) => parameters
+                .iter()
+                .position(|p| p == name)
+                .and_then(|i| arguments.get(i)),
+            _ => None,
+        };
+        match argument {
+            Some(argument) => res.extend(argument.iter().cloned()),
+            None => res.push(lexeme.clone()),
+        }
+    }
+    res
+}
+
+/// The value of a macro body, if it is a constant.
+fn constant(tokens: &[Lexeme], enumerators: &HashMap<String, i128>) -> Option<Constant> {
+    match tokens {
+        [Lexeme {
+            token: Token::String(literal),
+            ..
+        }] => {
+            let bytes = crate::semantic::constants::unescape(&literal[1..literal.len() - 1]);
+            return Some(Constant::String(
+                String::from_utf8_lossy(&bytes).into_owned(),
+            ));
+        }
+        [Lexeme {
+            token: Token::Number(number),
+            ..
+        }] if is_float(number) => {
+            let digits = number.trim_end_matches(['f', 'F', 'l', 'L']);
+            let ty = match number.ends_with(['f', 'F']) {
+                true => PrimitiveType::F32,
+                false => PrimitiveType::F64,
+            };
+            let value = digits.parse().ok()?;
+            return Some(Constant::Float { value, ty });
+        }
+        _ => {}
+    }
+    let mut evaluator = Evaluator {
+        tokens,
+        position: 0,
+        names: enumerators,
+        undefined_is_zero: false,
+        unsigned: false,
+        long: false,
+    };
+    let value = evaluator.evaluate().ok()?;
+    let ty = integer_type(value, evaluator.unsigned, evaluator.long)?;
+    Some(Constant::Integer { value, ty })
+}
+
+/// The C type of an integer constant: the first of `int`, `unsigned int`,
+/// `long` and `unsigned long` that holds it, skipping the signed types if
+/// the constant is unsigned and the `int`s if it is long.
+fn integer_type(value: i128, unsigned: bool, long: bool) -> Option<PrimitiveType> {
+    let candidates = [
+        (PrimitiveType::CInt, false),
+        (PrimitiveType::CUInt, true),
+        (PrimitiveType::CLong, false),
+        (PrimitiveType::CULong, true),
+    ];
+    candidates.iter().find_map(|&(ty, is_unsigned)| {
+        let (_, bits) = ty.integer_layout()?;
+        let (min, max) = integer_bounds(&Type::Primitive(ty))?;
+        let allowed = (is_unsigned || !unsigned) && (bits > 32 || !long);
+        if allowed && min <= value && value <= max {
+            Some(ty)
+        } else {
+            None
+        }
+    })
+}
+
+fn is_float(number: &str) -> bool {
+    let hex = number.starts_with("0x") || number.starts_with("0X");
+    number.contains('.') || (!hex && number.contains(['e', 'E', 'f', 'F']))
+}
+
+/// The value of an integer literal, and whether it is unsigned and long.
+fn integer_literal(number: &str) -> Option<(i128, bool, bool)> {
+    let digits = number.trim_end_matches(['u', 'U', 'l', 'L']);
+    let suffix = &number[digits.len()..];
+    let unsigned = suffix.contains(['u', 'U']);
+    let long = suffix.contains(['l', 'L']);
+    let (digits, radix) = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        (hex, 16)
+    } else if let Some(binary) = digits
+        .strip_prefix("0b")
+        .or_else(|| digits.strip_prefix("0B"))
+    {
+        (binary, 2)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (&digits[1..], 8)
+    } else {
+        (digits, 10)
+    };
+    let value = i128::from_str_radix(digits, radix).ok()?;
+    Some((value, unsigned, long))
+}
+
+/// Evaluates the integer constant expressions of `#if`s, `#define`s, enum
+/// values and array sizes.
+struct Evaluator<'t> {
+    tokens: &'t [Lexeme],
+    position: usize,
+    /// Enumerators declared so far.
+    names: &'t HashMap<String, i128>,
+    /// Whether unknown identifiers are 0, as in `#if`s.
+    undefined_is_zero: bool,
+    /// Whether any literal was unsigned.
+    unsigned: bool,
+    /// Whether any literal was long.
+    long: bool,
+}
+
+type Evaluation = std::result::Result<i128, String>;
+
+impl<'t> Evaluator<'t> {
+    fn evaluate(&mut self) -> Evaluation {
+        let value = self.conditional()?;
+        match self.tokens.get(self.position) {
+            None => Ok(value),
+            Some(lexeme) => Err(format!("unexpected `{}`", lexeme.token)),
+        }
+    }
+
+    fn peek(&self) -> Option<&'t Token> {
+        self.tokens.get(self.position).map(|l| &l.token)
+    }
+
+    fn eat(&mut self, punctuation: &str) -> bool {
+        if self.peek() == Some(&Token::Punctuation(punctuation_str(punctuation))) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn conditional(&mut self) -> Evaluation {
+        let condition = self.binary(0)?;
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+        let when_true = self.conditional()?;
+        if !self.eat(":") {
+            return Err("expected `:`".to_string());
+        }
+        let when_false = self.conditional()?;
+        Ok(if condition != 0 {
+            when_true
+        } else {
+            when_false
+        })
+    }
+
+    fn binary(&mut self, min_precedence: u8) -> Evaluation {
+        let mut left = self.unary()?;
+        while let Some(Token::Punctuation(operator)) = self.peek() {
+            let operator = *operator;
+            let precedence = match operator {
+                "||" => 1,
+                "&&" => 2,
+                "|" => 3,
+                "^" => 4,
+                "&" => 5,
+                "==" | "!=" => 6,
+                "<" | "<=" | ">" | ">=" => 7,
+                "<<" | ">>" => 8,
+                "+" | "-" => 9,
+                "*" | "/" | "%" => 10,
+                _ => break,
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            self.position += 1;
+            let right = self.binary(precedence + 1)?;
+            let overflow = || format!("`{}` overflows", operator);
+            left = match operator {
+                "||" => (left != 0 || right != 0) as i128,
+                "&&" => (left != 0 && right != 0) as i128,
+                "|" => left | right,
+                "^" => left ^ right,
+                "&" => left & right,
+                "==" => (left == right) as i128,
+                "!=" => (left != right) as i128,
+                "<" => (left < right) as i128,
+                "<=" => (left <= right) as i128,
+                ">" => (left > right) as i128,
+                ">=" => (left >= right) as i128,
+                "<<" => u32::try_from(right)
+                    .ok()
+                    .and_then(|right| left.checked_shl(right))
+                    .ok_or_else(overflow)?,
+                ">>" => u32::try_from(right)
+                    .ok()
+                    .and_then(|right| left.checked_shr(right))
+                    .ok_or_else(overflow)?,
+                "+" => left.checked_add(right).ok_or_else(overflow)?,
+                "-" => left.checked_sub(right).ok_or_else(overflow)?,
+                "*" => left.checked_mul(right).ok_or_else(overflow)?,
+                "/" => left.checked_div(right).ok_or("division by zero")?,
+                _ => left.checked_rem(right).ok_or("division by zero")?,
+            };
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Evaluation {
+        if self.eat("-") {
+            return Ok(-self.unary()?);
+        }
+        if self.eat("+") {
+            return self.unary();
+        }
+        if self.eat("~") {
+            return Ok(!self.unary()?);
+        }
+        if self.eat("!") {
+            return Ok((self.unary()? == 0) as i128);
+        }
+        if self.eat("(") {
+            // Casts to integer types do not change constants that fit them.
+            if let Some(Token::Identifier(name)) = self.peek() {
+                if is_type_word(name) {
+                    while !self.eat(")") {
+                        if self.peek().is_none() {
+                            return Err("expected `)`".to_string());
+                        }
+                        self.position += 1;
+                    }
+                    return self.unary();
+                }
+            }
+            let value = self.conditional()?;
+            if !self.eat(")") {
+                return Err("expected `)`".to_string());
+            }
+            return Ok(value);
+        }
+        let token = self.peek().ok_or("expected an expression")?;
+        self.position += 1;
+        match token {
+            Token::Number(number) if !is_float(number) => {
+                let (value, unsigned, long) = integer_literal(number)
+                    .ok_or_else(|| format!("invalid number `{}`", number))?;
+                self.unsigned |= unsigned;
+                self.long |= long;
+                Ok(value)
+            }
+            Token::Char(literal) => {
+                char_value(literal).ok_or_else(|| format!("invalid character {}", literal))
+            }
+            Token::Identifier(name) => match self.names.get(name) {
+                Some(value) => Ok(*value),
+                None if self.undefined_is_zero => Ok(0),
+                None => Err(format!("`{}` is not a constant", name)),
+            },
+            token => Err(format!("unexpected `{}`", token)),
+        }
+    }
+}
+
+/// The `'static` spelling of a punctuation token.
+fn punctuation_str(punctuation: &str) -> &'static str {
+    PUNCTUATION
+        .iter()
+        .chain(OTHER_PUNCTUATION.iter())
+        .find(|p| **p == punctuation)
+        .copied()
+        .unwrap_or("")
+}
+
+/// Words that make up the names of C's built-in types.
+const TYPE_WORDS: [&str; 11] = [
+    "void", "char", "short", "int", "long", "float", "double", "signed", "unsigned", "_Bool",
+    "bool",
+];
+
+fn is_type_word(word: &str) -> bool {
+    TYPE_WORDS.contains(&word) || fixed_width_type(word).is_some()
+}
+
+/// The types of `<stdint.h>` and `<stddef.h>`, for a 64-bit target.
+fn fixed_width_type(name: &str) -> Option<PrimitiveType> {
+    let ty = match name {
+        "int8_t" => PrimitiveType::S8,
+        "uint8_t" => PrimitiveType::U8,
+        "int16_t" => PrimitiveType::S16,
+        "uint16_t" => PrimitiveType::U16,
+        "int32_t" => PrimitiveType::S32,
+        "uint32_t" => PrimitiveType::U32,
+        "int64_t" | "intptr_t" | "ptrdiff_t" | "ssize_t" => PrimitiveType::S64,
+        "uint64_t" | "uintptr_t" | "size_t" => PrimitiveType::U64,
+        _ => return None,
+    };
+    Some(ty)
+}
+
+/// Maps a combination of C's type words, such as `unsigned long`, to a
+/// primitive type.
+fn builtin_type(words: &[&str]) -> std::result::Result<PrimitiveType, String> {
+    let count = |word| words.iter().filter(|w| **w == word).count();
+    let unsigned = count("unsigned") > 0;
+    let ty = match () {
+        _ if count("void") > 0 => PrimitiveType::CVoid,
+        _ if count("_Bool") > 0 || count("bool") > 0 => PrimitiveType::Bool,
+        _ if count("char") > 0 && unsigned => PrimitiveType::U8,
+        _ if count("char") > 0 && count("signed") > 0 => PrimitiveType::S8,
+        _ if count("char") > 0 => PrimitiveType::Char,
+        _ if count("float") > 0 => PrimitiveType::F32,
+        _ if count("double") > 0 && count("long") > 0 => PrimitiveType::CLongDouble,
+        _ if count("double") > 0 => PrimitiveType::F64,
+        _ if count("short") > 0 && unsigned => PrimitiveType::CUShort,
+        _ if count("short") > 0 => PrimitiveType::CShort,
+        _ if count("long") > 1 && unsigned => PrimitiveType::CULongLong,
+        _ if count("long") > 1 => PrimitiveType::CLongLong,
+        _ if count("long") > 0 && unsigned => PrimitiveType::CULong,
+        _ if count("long") > 0 => PrimitiveType::CLong,
+        _ if unsigned => PrimitiveType::CUInt,
+        _ => PrimitiveType::CInt,
+    };
+    if words.is_empty() {
+        return Err("expected a type".to_string());
+    }
+    Ok(ty)
+}
+
+/// Storage class and other specifiers that do not change a declaration's
+/// type.
+const IGNORED_SPECIFIERS: [&str; 10] = [
+    "extern",
+    "register",
+    "auto",
+    "_Noreturn",
+    "__extension__",
+    "restrict",
+    "__restrict",
+    "__restrict__",
+    "_Nullable",
+    "_Nonnull",
+];
+
+/// Attribute syntax, which is skipped along with its parenthesized arguments.
+const ATTRIBUTES: [&str; 5] = [
+    "__attribute__",
+    "__attribute",
+    "__declspec",
+    "__asm__",
+    "__asm",
+];
+
+/// The type a declaration's specifiers name, or why it cannot be imported.
+type Specified = std::result::Result<Type, String>;
+
+/// What a declarator declares.
+#[derive(Clone, Debug)]
+enum Declared {
+    Value(Specified),
+    Function {
+        return_type: Specified,
+        parameters: std::result::Result<Vec<Type>, String>,
+        variadic: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Specifiers {
+    typedef: bool,
+    is_static: bool,
+    inline: bool,
+}
+
+/// Parses the declarations of a preprocessed header.
+#[derive(Clone, Debug, Default)]
+struct DeclarationParser {
+    tokens: Vec<Lexeme>,
+    position: usize,
+    header: CHeader,
+    /// Typedefs declared so far, or why they could not be imported.
+    typedefs: HashMap<String, Specified>,
+    enumerators: HashMap<String, i128>,
+    /// The fields of the last anonymous struct or the enumerators of the
+    /// last anonymous enum, which a typedef may name.
+    anonymous: Option<Anonymous>,
+}
+
+#[derive(Clone, Debug)]
+enum Anonymous {
+    Struct(std::result::Result<Vec<(String, Type)>, String>),
+    Enum(Vec<(String, i128)>),
+}
+
+impl DeclarationParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|l| &l.token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.position + offset).map(|l| &l.token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position.min(self.tokens.len().saturating_sub(1)))
+            .map_or(0, |l| l.line)
+    }
+
+    fn is(&self, punctuation: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punctuation(p)) if *p == punctuation)
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(w)) if w == word)
+    }
+
+    fn eat(&mut self, punctuation: &str) -> bool {
+        if self.is(punctuation) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punctuation: &str) -> std::result::Result<(), String> {
+        if self.eat(punctuation) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(token) => Err(format!("expected `{}` but found `{}`", punctuation, token)),
+            None => Err(format!(
+                "expected `{}` at the end of the header",
+                punctuation
+            )),
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                self.position += 1;
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Skips a parenthesized, bracketed or braced group starting at the
+    /// current token, returning its tokens.
+    fn skip_group(&mut self) -> Vec<Lexeme> {
+        let start = self.position;
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punctuation("(") | Token::Punctuation("[") | Token::Punctuation("{") => {
+                    depth += 1
+                }
+                Token::Punctuation(")") | Token::Punctuation("]") | Token::Punctuation("}") => {
+                    depth -= 1
+                }
+                _ => {}
+            }
+            self.position += 1;
+            if depth <= 0 {
+                break;
+            }
+        }
+        self.tokens[start..self.position].to_vec()
+    }
+
+    /// Skips the rest of a declaration that could not be parsed.
+    fn recover(&mut self) {
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punctuation(";") => {
+                    self.position += 1;
+                    return;
+                }
+                Token::Punctuation("(") | Token::Punctuation("[") | Token::Punctuation("{") => {
+                    self.skip_group();
+                }
+                _ => self.position += 1,
+            }
+        }
+    }
+
+    fn skip_attributes(&mut self) {
+        while let Some(Token::Identifier(word)) = self.peek() {
+            if ATTRIBUTES.contains(&word.as_str()) {
+                self.position += 1;
+                if self.is("(") {
+                    self.skip_group();
+                }
+            } else if IGNORED_SPECIFIERS.contains(&word.as_str()) || word == "volatile" {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn unsupported(&mut self, name: Option<String>, reason: impl Into<String>, line: usize) {
+        self.header.unsupported.push(Unsupported {
+            name,
+            reason: reason.into(),
+            line,
+        });
+    }
+
+    fn parse(&mut self) {
+        while self.peek().is_some() {
+            let line = self.line();
+            // `extern "C" {` and its closing brace wrap declarations for C++.
+            if self.is_word("extern") && matches!(self.peek_at(1), Some(Token::String(_))) {
+                self.position += 2;
+                self.eat("{");
+                continue;
+            }
+            if self.eat(";") || self.eat("}") {
+                continue;
+            }
+            if self.is_word("_Static_assert") || self.is_word("static_assert") {
+                self.recover();
+                continue;
+            }
+            if let Err(reason) = self.declaration() {
+                self.unsupported(
+                    None,
+                    format!("could not read declaration: {}", reason),
+                    line,
+                );
+                self.recover();
+            }
+        }
+    }
+
+    fn declaration(&mut self) -> std::result::Result<(), String> {
+        let line = self.line();
+        let (specifiers, base) = self.specifiers()?;
+        if self.eat(";") {
+            self.anonymous = None;
+            return Ok(());
+        }
+        loop {
+            let (name, declared) = self.declarator(base.clone())?;
+            let name = name.ok_or("expected a name")?;
+            if specifiers.typedef {
+                self.typedef(name, declared, line);
+            } else {
+                let body = self.is("{");
+                if body {
+                    self.skip_group();
+                }
+                self.declare(name, declared, specifiers, body, line);
+                if body {
+                    break;
+                }
+            }
+            if self.eat(";") {
+                break;
+            }
+            self.expect(",")?;
+        }
+        self.anonymous = None;
+        Ok(())
+    }
+
+    fn typedef(&mut self, name: String, declared: Declared, line: usize) {
+        let ty = match declared {
+            Declared::Value(ty) => ty,
+            Declared::Function { .. } => Err("function types are not supported".to_string()),
+        };
+        match self.anonymous.take() {
+            Some(Anonymous::Struct(fields)) => {
+                self.define_struct(&name, fields, line);
+                let ty = Ok(Type::Struct(NamedTypeRef::new(&name)));
+                self.typedefs.insert(name, ty);
+                return;
+            }
+            Some(Anonymous::Enum(variants)) => {
+                self.header.enums.push(CEnum {
+                    name: name.clone(),
+                    variants,
+                });
+                self.typedefs
+                    .insert(name, Ok(Type::Primitive(PrimitiveType::CInt)));
+                return;
+            }
+            None => {}
+        }
+        match &ty {
+            // `typedef struct point point;` names the struct it declares.
+            Ok(Type::Struct(named)) if named.name == name => {}
+            Ok(ty) => self.header.typedefs.push((name.clone(), ty.clone())),
+            Err(reason) => self.unsupported(Some(name.clone()), reason.clone(), line),
+        }
+        self.typedefs.insert(name, ty);
+    }
+
+    fn declare(
+        &mut self,
+        name: String,
+        declared: Declared,
+        specifiers: Specifiers,
+        body: bool,
+        line: usize,
+    ) {
+        let (return_type, parameters, variadic) = match declared {
+            Declared::Value(_) => {
+                return self.unsupported(Some(name), "variables are not supported", line);
+            }
+            Declared::Function {
+                return_type,
+                parameters,
+                variadic,
+            } => (return_type, parameters, variadic),
+        };
+        let reason = if body || specifiers.inline {
+            Some("function definitions are not supported".to_string())
+        } else if specifiers.is_static {
+            Some("static functions cannot be linked against".to_string())
+        } else if variadic {
+            Some("variadic functions are not supported".to_string())
+        } else {
+            return_type
+                .as_ref()
+                .err()
+                .or(parameters.as_ref().err())
+                .cloned()
+        };
+        if let Some(reason) = reason {
+            return self.unsupported(Some(name), reason, line);
+        }
+        let return_type = return_type
+            .ok()
+            .filter(|ty| *ty != Type::Primitive(PrimitiveType::CVoid));
+        self.header.functions.push(CFunction {
+            name,
+            signature: FunctionSignature {
+                receiver: false,
+                parameters: parameters.unwrap_or_default(),
+                return_type,
+            },
+        });
+    }
+
+    fn define_struct(
+        &mut self,
+        name: &str,
+        fields: std::result::Result<Vec<(String, Type)>, String>,
+        line: usize,
+    ) {
+        match fields {
+            Ok(fields) => {
+                self.header.structs.retain(|s| s.name != name);
+                self.header.structs.push(CStruct {
+                    name: name.to_string(),
+                    fields: Some(fields),
+                });
+            }
+            Err(reason) => self.unsupported(Some(name.to_string()), reason, line),
+        }
+    }
+
+    /// Reads the specifiers of a declaration and the type they name.
+    fn specifiers(&mut self) -> std::result::Result<(Specifiers, Specified), String> {
+        let mut specifiers = Specifiers::default();
+        let mut words = vec![];
+        let mut base = None;
+        let mut is_const = false;
+        loop {
+            self.skip_attributes();
+            let word = match self.peek() {
+                Some(Token::Identifier(word)) => word.clone(),
+                _ => break,
+            };
+            match word.as_str() {
+                "typedef" => specifiers.typedef = true,
+                "static" => specifiers.is_static = true,
+                "inline" | "__inline" | "__inline__" => specifiers.inline = true,
+                "const" => is_const = true,
+                "struct" | "union" | "enum" if base.is_none() && words.is_empty() => {
+                    self.position += 1;
+                    base = Some(self.tagged(&word)?);
+                    continue;
+                }
+                word if TYPE_WORDS.contains(&word) && base.is_none() => {
+                    words.push(
+                        TYPE_WORDS
+                            .iter()
+                            .find(|w| **w == word)
+                            .copied()
+                            .unwrap_or(""),
+                    );
+                }
+                _ if base.is_some() || !words.is_empty() => break,
+                word => {
+                    base = Some(match (self.typedefs.get(word), fixed_width_type(word)) {
+                        (Some(Ok(ty)), _) => Ok(ty.clone()),
+                        (Some(Err(reason)), _) => Err(format!(
+                            "uses `{}`, which cannot be imported: {}",
+                            word, reason
+                        )),
+                        (None, Some(pt)) => Ok(Type::Primitive(pt)),
+                        (None, None) => {
+                            // An unknown name followed by a declarator is a
+                            // type from a header that was not read.
+                            let declarator_follows = matches!(
+                                self.peek_at(1),
+                                Some(Token::Identifier(_)) | Some(Token::Punctuation("*"))
+                            );
+                            if !declarator_follows {
+                                break;
+                            }
+                            Err(format!("unknown type `{}`", word))
+                        }
+                    });
+                }
+            }
+            self.position += 1;
+        }
+        let base = match base {
+            Some(base) => base,
+            None => builtin_type(&words).map(Type::Primitive),
+        };
+        let base = match is_const {
+            true => base.map(|ty| Type::Const(Box::new(ty))),
+            false => base,
+        };
+        Ok((specifiers, base))
+    }
+
+    /// Reads a `struct`, `union` or `enum` specifier after its keyword.
+    fn tagged(&mut self, keyword: &str) -> std::result::Result<Specified, String> {
+        self.skip_attributes();
+        let line = self.line();
+        let tag = self.identifier();
+        let has_body = self.is("{");
+        match keyword {
+            "struct" => {
+                if has_body {
+                    let fields = self.fields();
+                    match &tag {
+                        Some(tag) => self.define_struct(tag, fields.clone(), line),
+                        None => self.anonymous = Some(Anonymous::Struct(fields.clone())),
+                    }
+                    if let (Err(reason), Some(_)) = (fields, &tag) {
+                        return Ok(Err(reason));
+                    }
+                }
+                match tag {
+                    Some(tag) => {
+                        if !self.header.structs.iter().any(|s| s.name == tag) {
+                            self.header.structs.push(CStruct {
+                                name: tag.clone(),
+                                fields: None,
+                            });
+                        }
+                        Ok(Ok(Type::Struct(NamedTypeRef::new(&tag))))
+                    }
+                    None => Ok(Ok(Type::Struct(NamedTypeRef::new("")))),
+                }
+            }
+            "union" => {
+                if has_body {
+                    self.skip_group();
+                    if let Some(tag) = &tag {
+                        self.unsupported(Some(tag.clone()), "unions are not supported", line);
+                    }
+                }
+                Ok(Err("unions are not supported".to_string()))
+            }
+            _ => {
+                if has_body {
+                    let variants = self.enumerators()?;
+                    match &tag {
+                        Some(tag) => self.header.enums.push(CEnum {
+                            name: tag.clone(),
+                            variants,
+                        }),
+                        None => self.anonymous = Some(Anonymous::Enum(variants)),
+                    }
+                }
+                // C enums are `int`s, and their enumerators are constants.
+                Ok(Ok(Type::Primitive(PrimitiveType::CInt)))
+            }
+        }
+    }
+
+    fn fields(&mut self) -> std::result::Result<Vec<(String, Type)>, String> {
+        let tokens = self.skip_group();
+        let mut fields = DeclarationParser {
+            tokens: tokens[1..tokens.len() - 1].to_vec(),
+            typedefs: self.typedefs.clone(),
+            enumerators: self.enumerators.clone(),
+            ..DeclarationParser::default()
+        };
+        let mut res = vec![];
+        let mut unsupported = None;
+        while fields.peek().is_some() {
+            let (_, base) = fields.specifiers()?;
+            if fields.anonymous.is_some() && fields.eat(";") {
+                unsupported.get_or_insert("anonymous members are not supported".to_string());
+                continue;
+            }
+            loop {
+                let (name, declared) = fields.declarator(base.clone())?;
+                let name = name.ok_or("expected a field name")?;
+                if fields.eat(":") {
+                    fields.recover();
+                    unsupported.get_or_insert("bit-fields are not supported".to_string());
+                    break;
+                }
+                match declared {
+                    Declared::Value(Ok(ty)) => res.push((name, ty)),
+                    Declared::Value(Err(reason)) => {
+                        unsupported.get_or_insert(format!("field `{}`: {}", name, reason));
+                    }
+                    Declared::Function { .. } => {
+                        unsupported.get_or_insert("function members are not supported".to_string());
+                    }
+                }
+                if fields.eat(";") {
+                    break;
+                }
+                fields.expect(",")?;
+            }
+        }
+        // Structs declared inside others are visible outside them in C.
+        self.header.structs.append(&mut fields.header.structs);
+        self.header.enums.append(&mut fields.header.enums);
+        self.header.constants.append(&mut fields.header.constants);
+        self.enumerators.extend(fields.enumerators);
+        match unsupported {
+            Some(reason) => Err(reason),
+            None => Ok(res),
+        }
+    }
+
+    fn enumerators(&mut self) -> std::result::Result<Vec<(String, i128)>, String> {
+        self.expect("{")?;
+        let mut variants = vec![];
+        let mut next = 0;
+        while !self.eat("}") {
+            let name = self.identifier().ok_or("expected an enumerator")?;
+            self.skip_attributes();
+            let value = if self.eat("=") {
+                let start = self.position;
+                while !self.is(",") && !self.is("}") {
+                    match self.peek() {
+                        Some(Token::Punctuation("(")) => {
+                            self.skip_group();
+                        }
+                        Some(_) => self.position += 1,
+                        None => return Err("expected `}`".to_string()),
+                    }
+                }
+                let mut evaluator = Evaluator {
+                    tokens: &self.tokens[start..self.position],
+                    position: 0,
+                    names: &self.enumerators,
+                    undefined_is_zero: false,
+                    unsigned: false,
+                    long: false,
+                };
+                evaluator
+                    .evaluate()
+                    .map_err(|e| format!("value of `{}`: {}", name, e))?
+            } else {
+                next
+            };
+            next = value + 1;
+            self.enumerators.insert(name.clone(), value);
+            let ty = integer_type(value, false, false).unwrap_or(PrimitiveType::CLong);
+            self.header
+                .constants
+                .push((name.clone(), Constant::Integer { value, ty }));
+            variants.push((name, value));
+            if !self.eat(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        Ok(variants)
+    }
+
+    /// Reads a declarator, such as `*name`, `name[4]` or `name(int x)`,
+    /// applying it to the type its specifiers name.
+    fn declarator(
+        &mut self,
+        base: Specified,
+    ) -> std::result::Result<(Option<String>, Declared), String> {
+        let mut ty = base;
+        loop {
+            self.skip_attributes();
+            if self.eat("*") {
+                ty = ty.map(|ty| Type::Pointer(Box::new(ty)));
+            } else if self.is_word("const") {
+                self.position += 1;
+                ty = ty.map(|ty| Type::Const(Box::new(ty)));
+            } else {
+                break;
+            }
+        }
+        // `(*name)(...)` declares a function pointer.
+        if self.is("(")
+            && matches!(
+                self.peek_at(1),
+                Some(Token::Punctuation("*")) | Some(Token::Punctuation("^"))
+            )
+        {
+            let inner = self.skip_group();
+            let name = inner.iter().find_map(|l| match &l.token {
+                Token::Identifier(name) if name != "const" => Some(name.clone()),
+                _ => None,
+            });
+            while self.is("(") || self.is("[") {
+                self.skip_group();
+            }
+            self.skip_attributes();
+            let reason = "function pointers are not supported".to_string();
+            return Ok((name, Declared::Value(Err(reason))));
+        }
+        let name = self.identifier();
+        self.skip_attributes();
+        if self.is("(") {
+            let (parameters, variadic) = self.parameters()?;
+            self.skip_attributes();
+            let declared = Declared::Function {
+                return_type: ty,
+                parameters,
+                variadic,
+            };
+            return Ok((name, declared));
+        }
+        let mut array = false;
+        while self.is("[") {
+            self.skip_group();
+            array = true;
+        }
+        self.skip_attributes();
+        if array {
+            ty = Err("fixed-size arrays are not supported".to_string());
+        }
+        Ok((name, Declared::Value(ty)))
+    }
+
+    /// Reads a parameter list. Array parameters are pointers, and `(void)`
+    /// and `()` take no parameters.
+    #[allow(clippy::type_complexity)]
+    fn parameters(
+        &mut self,
+    ) -> std::result::Result<(std::result::Result<Vec<Type>, String>, bool), String> {
+        self.expect("(")?;
+        let mut parameters = Ok(vec![]);
+        let mut variadic = false;
+        if self.is_word("void") && matches!(self.peek_at(1), Some(Token::Punctuation(")"))) {
+            self.position += 1;
+        }
+        let mut index = 0;
+        while !self.eat(")") {
+            index += 1;
+            if self.eat("...") {
+                variadic = true;
+                self.expect(")")?;
+                break;
+            }
+            let (_, base) = self.specifiers()?;
+            let mut ty = base;
+            loop {
+                if self.eat("*") {
+                    ty = ty.map(|ty| Type::Pointer(Box::new(ty)));
+                } else if self.is_word("const")
+                    || self.is_word("restrict")
+                    || self.is_word("__restrict")
+                {
+                    self.position += 1;
+                } else {
+                    break;
+                }
+            }
+            let declared = if self.is("(") {
+                self.skip_group();
+                while self.is("(") {
+                    self.skip_group();
+                }
+                Err("function pointers are not supported".to_string())
+            } else {
+                self.identifier();
+                let mut ty = ty;
+                while self.is("[") {
+                    self.skip_group();
+                    ty = ty.map(|ty| Type::Pointer(Box::new(ty)));
+                }
+                ty
+            };
+            self.skip_attributes();
+            if let (Ok(list), declared) = (&mut parameters, declared) {
+                match declared {
+                    Ok(ty) => list.push(ty),
+                    Err(reason) => parameters = Err(format!("parameter {}: {}", index, reason)),
+                }
+            }
+            if !self.eat(",") {
+                self.expect(")")?;
+                break;
+            }
+        }
+        Ok((parameters, variadic))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn primitive(pt: PrimitiveType) -> Type {
+        Type::Primitive(pt)
+    }
+
+    fn pointer(ty: Type) -> Type {
+        Type::Pointer(Box::new(ty))
+    }
+
+    fn integer(value: i128, ty: PrimitiveType) -> Constant {
+        Constant::Integer { value, ty }
+    }
+
+    fn reasons(header: &CHeader) -> Vec<(Option<&str>, &str)> {
+        header
+            .unsupported
+            .iter()
+            .map(|u| (u.name.as_deref(), u.reason.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_functions() {
+        let header = parse_header(
+            "#include <stdio.h>
+            void my_c_function();
+            extern unsigned long hash(const char *data, size_t length);
+            int count(void), total(int values[], int n);",
+        );
+        let signatures: Vec<(&str, &FunctionSignature)> = header
+            .functions
+            .iter()
+            .map(|f| (f.name.as_str(), &f.signature))
+            .collect();
+        let char_pointer = pointer(Type::Const(Box::new(primitive(PrimitiveType::Char))));
+        assert_eq!(
+            signatures,
+            vec![
+                (
+                    "my_c_function",
+                    &FunctionSignature {
+                        receiver: false,
+                        parameters: vec![],
+                        return_type: None,
+                    }
+                ),
+                (
+                    "hash",
+                    &FunctionSignature {
+                        receiver: false,
+                        parameters: vec![char_pointer, primitive(PrimitiveType::U64)],
+                        return_type: Some(primitive(PrimitiveType::CULong)),
+                    }
+                ),
+                (
+                    "count",
+                    &FunctionSignature {
+                        receiver: false,
+                        parameters: vec![],
+                        return_type: Some(primitive(PrimitiveType::CInt)),
+                    }
+                ),
+                (
+                    "total",
+                    &FunctionSignature {
+                        receiver: false,
+                        parameters: vec![
+                            pointer(primitive(PrimitiveType::CInt)),
+                            primitive(PrimitiveType::CInt)
+                        ],
+                        return_type: Some(primitive(PrimitiveType::CInt)),
+                    }
+                ),
+            ]
+        );
+        assert_eq!(reasons(&header), vec![]);
+    }
+
+    #[test]
+    fn test_structs_enums_and_typedefs() {
+        let header = parse_header(
+            "struct FILE;
+            typedef struct { double x, y; } point;
+            struct node { struct node *next; point value; };
+            typedef unsigned char byte;
+            typedef enum { RED, GREEN = 4, BLUE } color;
+            point midpoint(point a, point b);",
+        );
+        let point = Type::Struct(NamedTypeRef::new("point"));
+        assert_eq!(
+            header.structs,
+            vec![
+                CStruct {
+                    name: "FILE".to_string(),
+                    fields: None,
+                },
+                CStruct {
+                    name: "point".to_string(),
+                    fields: Some(vec![
+                        ("x".to_string(), primitive(PrimitiveType::F64)),
+                        ("y".to_string(), primitive(PrimitiveType::F64)),
+                    ]),
+                },
+                CStruct {
+                    name: "node".to_string(),
+                    fields: Some(vec![
+                        (
+                            "next".to_string(),
+                            pointer(Type::Struct(NamedTypeRef::new("node")))
+                        ),
+                        ("value".to_string(), point.clone()),
+                    ]),
+                },
+            ]
+        );
+        assert_eq!(
+            header.typedefs,
+            vec![("byte".to_string(), primitive(PrimitiveType::U8))]
+        );
+        assert_eq!(
+            header.enums,
+            vec![CEnum {
+                name: "color".to_string(),
+                variants: vec![
+                    ("RED".to_string(), 0),
+                    ("GREEN".to_string(), 4),
+                    ("BLUE".to_string(), 5),
+                ],
+            }]
+        );
+        assert_eq!(
+            header.lookup("BLUE"),
+            Some(Symbol::Constant(&integer(5, PrimitiveType::CInt)))
+        );
+        assert_eq!(
+            header.functions[0].signature.parameters,
+            vec![point.clone(), point]
+        );
+    }
+
+    #[test]
+    fn test_defines() {
+        let header = parse_header(
+            "#ifndef IO_H
+            #define IO_H
+            #define BUFFER_SIZE (1 << 12) /* bytes */
+            #define MASK 0xFFu
+            #define BIG 0x100000000
+            #define TWICE_BUFFER (BUFFER_SIZE * 2)
+            #define NEWLINE '\\n'
+            #define NAME \"io\"
+            #define RATIO 0.5f
+            #define LONG_SUM \\
+                (1L + 2)
+            #ifdef _WIN32
+            #define SEPARATOR '\\\\'
+            #else
+            #define SEPARATOR '/'
+            #endif
+            #if defined(IO_H) && BUFFER_SIZE > 1024
+            #define LARGE 1
+            #elif 1
+            #define LARGE 0
+            #endif
+            #endif",
+        );
+        assert_eq!(
+            header.constants,
+            vec![
+                (
+                    "BUFFER_SIZE".to_string(),
+                    integer(4096, PrimitiveType::CInt)
+                ),
+                ("MASK".to_string(), integer(255, PrimitiveType::CUInt)),
+                ("BIG".to_string(), integer(1 << 32, PrimitiveType::CLong)),
+                (
+                    "TWICE_BUFFER".to_string(),
+                    integer(8192, PrimitiveType::CInt)
+                ),
+                ("NEWLINE".to_string(), integer(10, PrimitiveType::CInt)),
+                ("NAME".to_string(), Constant::String("io".to_string())),
+                (
+                    "RATIO".to_string(),
+                    Constant::Float {
+                        value: 0.5,
+                        ty: PrimitiveType::F32
+                    }
+                ),
+                ("LONG_SUM".to_string(), integer(3, PrimitiveType::CLong)),
+                ("SEPARATOR".to_string(), integer(47, PrimitiveType::CInt)),
+                ("LARGE".to_string(), integer(1, PrimitiveType::CInt)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_macros_are_expanded_in_declarations() {
+        let header = parse_header(
+            "#define API extern
+            #define HANDLE(name) struct name##_handle *
+            #define INT_TYPE long
+            API INT_TYPE size(void);",
+        );
+        assert_eq!(header.functions[0].name, "size");
+        assert_eq!(
+            header.functions[0].signature.return_type,
+            Some(primitive(PrimitiveType::CLong))
+        );
+        assert_eq!(
+            reasons(&header),
+            vec![
+                (
+                    Some("API"),
+                    "only macros expanding to a number or string constant are supported"
+                ),
+                (Some("HANDLE"), "function-like macros are not supported"),
+                (
+                    Some("INT_TYPE"),
+                    "only macros expanding to a number or string constant are supported"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_declarations_are_reported() {
+        let header = parse_header(
+            "#ifdef __cplusplus
+            extern \"C\" {
+            #endif
+            int printf(const char *format, ...);
+            typedef void (*callback)(int);
+            void on_event(callback handler);
+            void on_signal(void (*handler)(int));
+            union value { int i; float f; };
+            struct flags { unsigned ready : 1; };
+            struct buffer { char data[16]; };
+            extern int errno_value;
+            static inline int twice(int x) { return 2 * x; }
+            struct stat file_stat(const char *path);
+            long double precise(void);
+            #ifdef __cplusplus
+            }
+            #endif",
+        );
+        assert_eq!(
+            reasons(&header),
+            vec![
+                (Some("printf"), "variadic functions are not supported"),
+                (Some("callback"), "function pointers are not supported"),
+                (
+                    Some("on_event"),
+                    "parameter 1: uses `callback`, which cannot be imported: \
+                     function pointers are not supported"
+                ),
+                (
+                    Some("on_signal"),
+                    "parameter 1: function pointers are not supported"
+                ),
+                (Some("value"), "unions are not supported"),
+                (Some("flags"), "bit-fields are not supported"),
+                (
+                    Some("buffer"),
+                    "field `data`: fixed-size arrays are not supported"
+                ),
+                (Some("errno_value"), "variables are not supported"),
+                (Some("twice"), "function definitions are not supported"),
+            ]
+        );
+        let names: Vec<&str> = header.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["file_stat", "precise"]);
+        assert_eq!(
+            header.unsupported("printf").map(ToString::to_string),
+            Some(
+                "cannot import \"printf\" declared on line 4: variadic functions are not supported"
+                    .to_string()
+            )
+        );
+    }
+}
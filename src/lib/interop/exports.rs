@@ -0,0 +1,126 @@
+//! What catlang modules export, for generating the declarations other
+//! languages use to call into them.
+
+use crate::interop::{import_functions, import_types, load_headers, ImportedHeader};
+use crate::semantic::constants::ConstantEvaluator;
+use crate::semantic::error::*;
+use crate::semantic::functions::{FunctionTable, Overload};
+use crate::semantic::module_declarations;
+use crate::semantic::types::TypeEnvironment;
+use crate::syntax::ast::*;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+/// The types, functions and constants of a module, including those it
+/// imports from C headers.
+pub struct ModuleExports<'ast> {
+    pub headers: Vec<ImportedHeader<'ast>>,
+    pub env: TypeEnvironment<'ast>,
+    pub functions: FunctionTable<'ast>,
+    pub constants: ConstantEvaluator<'ast>,
+}
+
+impl<'ast> ModuleExports<'ast> {
+    /// Resolves the module at path `name`.
+    pub fn resolve(name: &str, module: &Module<'ast>) -> std::result::Result<Self, Vec<Error>> {
+        let directory = Path::new(name).parent().unwrap_or_else(|| Path::new(""));
+        let headers = load_headers(module, directory)?;
+        let mut env = TypeEnvironment::from_module(module)?;
+        import_types(&headers, &mut env)?;
+        let mut functions = FunctionTable::build(name, module, &env)?;
+        import_functions(&headers, &mut functions)?;
+        let mut constants = ConstantEvaluator::new();
+        constants.add_module(name, module)?;
+        for imported in &headers {
+            constants.add_header(imported.path, &imported.header);
+        }
+        Ok(ModuleExports {
+            headers,
+            env,
+            functions,
+            constants,
+        })
+    }
+}
+
+/// The declarations `module` exports with `export` in front of them.
+pub fn exported_declarations<'ast>(module: &Module<'ast>) -> Vec<DeclarationNode<'ast>> {
+    module
+        .elements
+        .iter()
+        .filter_map(|element| match element.value {
+            ModuleElement::Export(export) => match export.value {
+                Export::Declaration(declaration) => Some(declaration),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// The non-generic overloads `module` exports, either directly or with
+/// `export name;`, in the order they are exported.
+pub fn exported_overloads<'f, 'ast>(
+    module: &Module<'ast>,
+    functions: &'f FunctionTable<'ast>,
+) -> Vec<&'f Overload<'ast>> {
+    let mut overloads: Vec<&Overload> = vec![];
+    for element in module.elements.iter() {
+        let exported: Vec<&Overload> = match element.value {
+            ModuleElement::Export(export) => match export.value {
+                Export::Declaration(declaration) => match declaration.value.declarator {
+                    Declarator::Function(f) if f.scope.is_none() => {
+                        let span = f.function_name.start as usize..f.function_name.end as usize;
+                        functions
+                            .overloads(f.function_name.value)
+                            .iter()
+                            .filter(|overload| overload.span == span)
+                            .collect()
+                    }
+                    _ => vec![],
+                },
+                Export::Statement(statement) => {
+                    let exported_as = statement.renamed_to.unwrap_or(statement.identifier);
+                    functions.exported_overloads(exported_as.value)
+                }
+                Export::ReExport(_) => vec![],
+            },
+            _ => vec![],
+        };
+        for overload in exported {
+            let seen = overloads.iter().any(|o| o.span == overload.span);
+            if overload.generic_parameters.is_empty() && !seen {
+                overloads.push(overload);
+            }
+        }
+    }
+    overloads
+}
+
+/// The parameter names of every free function of `module`, by the span of
+/// its name.
+pub fn parameter_names<'ast>(module: &Module<'ast>) -> HashMap<Range<usize>, Vec<&'ast str>> {
+    module_declarations(module)
+        .into_iter()
+        .filter_map(|declaration| match declaration.value.declarator {
+            Declarator::Function(f) if f.scope.is_none() => {
+                let span = f.function_name.start as usize..f.function_name.end as usize;
+                let names = f.parameters.iter().map(|p| p.value.identifier.value);
+                Some((span, names.collect()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Makes `text` usable as (part of) a C identifier.
+pub fn identifier(text: &str) -> String {
+    let replaced = text.replace('*', " ptr ");
+    let words: Vec<String> = replaced
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .map(String::from)
+        .collect();
+    words.join("_")
+}
@@ -0,0 +1,176 @@
+use crate::semantic::error::*;
+use crate::semantic::functions::FunctionTable;
+use crate::semantic::types::{NamedTypeRef, Type, TypeEnvironment};
+use crate::syntax::ast::*;
+use c_header::{CHeader, Symbol};
+use std::path::Path;
+use tracing::warn;
+
+pub mod c_export;
+pub mod c_header;
+mod exports;
+pub mod rust_bindings;
+
+/// Whether an import path names a C header rather than a catlang module.
+pub fn is_c_header(path: &str) -> bool {
+    path.ends_with(".h")
+}
+
+/// Whether a declaration is marked `#[extern("C")]`. C is the only ABI
+/// `extern` accepts.
+pub fn is_extern_c(attributes: AttributeList) -> Result<bool> {
+    let mut extern_c = false;
+    for attribute in attributes.iter() {
+        if attribute.value.identifier.value != "extern" {
+            continue;
+        }
+        let span = attribute.start as usize..attribute.end as usize;
+        let parameters: Vec<_> = attribute.value.parameters.iter().collect();
+        match parameters.as_slice() {
+            [abi] => match abi.value {
+                Expression::PrimitiveExpression(Primitive::String(s))
+                    if s.trim_matches('"') == "C" =>
+                {
+                    extern_c = true
+                }
+                _ => {
+                    return Err(Error::InvalidAttribute {
+                        name: "extern".to_string(),
+                        reason: "the only supported ABI is \"C\"".to_string(),
+                        span,
+                    })
+                }
+            },
+            _ => {
+                return Err(Error::InvalidAttribute {
+                    name: "extern".to_string(),
+                    reason: "expected an ABI, as in `#[extern(\"C\")]`".to_string(),
+                    span,
+                })
+            }
+        }
+    }
+    Ok(extern_c)
+}
+
+/// A C header imported by a module.
+#[derive(Clone, Debug)]
+pub struct ImportedHeader<'ast> {
+    /// The path the header is imported from, as written.
+    pub path: &'ast str,
+    pub import_list: ImportList<'ast>,
+    pub header: CHeader,
+}
+
+/// Reads the C headers `module` imports. Header paths are relative to
+/// `directory`, the directory of the importing module, or else to the
+/// current directory.
+pub fn load_headers<'ast>(
+    module: &Module<'ast>,
+    directory: &Path,
+) -> std::result::Result<Vec<ImportedHeader<'ast>>, Vec<Error>> {
+    let mut headers = vec![];
+    let mut errors = vec![];
+    for element in module.elements.iter() {
+        let import = match element.value {
+            ModuleElement::Import(import) => import,
+            _ => continue,
+        };
+        let path = import.value.path.value.trim_matches('"');
+        if !is_c_header(path) {
+            continue;
+        }
+        let relative = directory.join(path);
+        let file = match relative.exists() {
+            true => relative.as_path(),
+            false => Path::new(path),
+        };
+        match c_header::load_header(file) {
+            Ok(header) => headers.push(ImportedHeader {
+                path,
+                import_list: import.value.import_list,
+                header,
+            }),
+            Err(e) => errors.push(Error::CouldNotReadHeader {
+                path: path.to_string(),
+                reason: e.to_string(),
+                span: import.value.path.start as usize..import.value.path.end as usize,
+            }),
+        }
+    }
+    match errors.len() {
+        0 => Ok(headers),
+        _ => Err(errors),
+    }
+}
+
+/// Imports the structs, enums and typedefs C headers declare under the
+/// (possibly renamed) names a module imports them as. Types of headers
+/// imported with `import * as` are not nameable.
+pub fn import_types<'ast>(
+    headers: &[ImportedHeader<'ast>],
+    env: &mut TypeEnvironment<'ast>,
+) -> std::result::Result<(), Vec<Error>> {
+    let mut errors = vec![];
+    for (imported, import) in named_imports(headers) {
+        let ty = match imported.header.lookup(import.identifier.value) {
+            Some(Symbol::Struct(s)) => Type::Struct(NamedTypeRef::new(&s.name)),
+            // C enums are `int`s; their enumerators are imported as
+            // constants.
+            Some(Symbol::Enum(_)) => Type::Primitive(PrimitiveType::CInt),
+            Some(Symbol::Typedef(ty)) => ty.clone(),
+            _ => continue,
+        };
+        let local_name = import.renamed_to.unwrap_or(import.identifier);
+        if let Err(e) = env.define_foreign(local_name, ty) {
+            errors.push(e);
+        }
+    }
+    match errors.len() {
+        0 => Ok(()),
+        _ => Err(errors),
+    }
+}
+
+/// Imports the functions of C headers into a module's function table.
+///
+/// Importing a name the header does not declare, or declares in a way
+/// catlang cannot represent, is an error. Headers imported with
+/// `import * as` make all their functions callable through the namespace,
+/// and warn about the declarations that were left out.
+pub fn import_functions<'ast>(
+    headers: &[ImportedHeader<'ast>],
+    functions: &mut FunctionTable<'ast>,
+) -> std::result::Result<(), Vec<Error>> {
+    let mut errors = vec![];
+    for imported in headers {
+        if let ImportList::GlobImportList(glob) = imported.import_list {
+            for unsupported in &imported.header.unsupported {
+                warn!("{}: {}", imported.path, unsupported);
+            }
+            functions.import_foreign_namespace(glob.identifier.value, imported.header.clone());
+        }
+    }
+    for (imported, import) in named_imports(headers) {
+        if let Err(e) = functions.import_foreign(&imported.header, imported.path, &import) {
+            errors.push(e);
+        }
+    }
+    match errors.len() {
+        0 => Ok(()),
+        _ => Err(errors),
+    }
+}
+
+/// The names imported from headers with named imports.
+pub fn named_imports<'h, 'ast>(
+    headers: &'h [ImportedHeader<'ast>],
+) -> impl Iterator<Item = (&'h ImportedHeader<'ast>, ImportIdentifier<'ast>)> {
+    headers.iter().flat_map(|imported| {
+        let imports: Vec<ImportIdentifier<'ast>> = match imported.import_list {
+            ImportList::NamedImportList(list) => list.imports.iter().map(|i| i.value).collect(),
+            ImportList::GlobImportList(_) => vec![],
+        };
+        imports.into_iter().map(move |import| (imported, import))
+    })
+}
@@ -0,0 +1,724 @@
+//! Generates Rust bindings for what catlang modules export, so Rust crates
+//! linking catlang objects can call into them.
+
+use crate::interop::exports::*;
+use crate::semantic::constants::{representation_type, Constant, ConstantEvaluator};
+use crate::semantic::error::*;
+use crate::semantic::functions::Overload;
+use crate::semantic::types::{Type, TypeDefinition, TypeEnvironment};
+use crate::syntax::ast::*;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::ops::Range;
+
+/// `[]T`, `[..]T` and `?T` have no Rust equivalent, so the bindings define
+/// `#[repr(C)]` structs laid out like catlang's.
+const SLICE: &str = "/// A catlang `[]T`: a pointer to the elements and their count.
+#[repr(C)]
+pub struct Slice<T> {
+    pub data: *mut T,
+    pub length: i64,
+}
+
+impl<T> Clone for Slice<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Slice<T> {}
+
+impl<'a, T> From<&'a [T]> for Slice<T> {
+    fn from(slice: &'a [T]) -> Self {
+        Slice {
+            data: slice.as_ptr() as *mut T,
+            length: slice.len() as i64,
+        }
+    }
+}
+
+impl<'a, T> From<&'a mut [T]> for Slice<T> {
+    fn from(slice: &'a mut [T]) -> Self {
+        Slice {
+            data: slice.as_mut_ptr(),
+            length: slice.len() as i64,
+        }
+    }
+}
+";
+
+const ARRAY: &str = "/// A catlang `[..]T`: a growable array.
+#[repr(C)]
+pub struct Array<T> {
+    pub data: *mut T,
+    pub length: i64,
+    pub capacity: i64,
+}
+
+impl<T> Clone for Array<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Array<T> {}
+";
+
+const OPTIONAL: &str = "/// A catlang `?T`. `value` is only initialized if `has_value` is set.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Optional<T> {
+    pub has_value: bool,
+    pub value: T,
+}
+";
+
+/// Generates Rust bindings for the module at path `name`.
+///
+/// The bindings mirror the module's exported structs and enums, along with
+/// every type they use, as `#[repr(C)]` structs and `#[repr(uN)]` enums.
+/// Each struct comes with assertions on its size and alignment, which fail
+/// to compile if Rust lays it out differently than catlang does on a 64-bit
+/// target. Exported functions are declared in an `extern "C"` block in the
+/// `ffi` module, and wrapped in safe functions taking references and slices
+/// instead of pointers where they can be.
+pub fn generate_bindings(name: &str, module: &Module) -> std::result::Result<String, Vec<Error>> {
+    let ModuleExports {
+        env,
+        functions,
+        constants,
+        ..
+    } = ModuleExports::resolve(name, module)?;
+    let mut writer = BindingsWriter {
+        module: name,
+        env: &env,
+        constants,
+        definitions: String::new(),
+        written: HashSet::new(),
+        helpers: HashSet::new(),
+    };
+    let mut errors = vec![];
+    for declaration in exported_declarations(module) {
+        let res = match declaration.value.declarator {
+            Declarator::Struct(s) if s.generic_parameters.is_empty() => {
+                writer.define_struct(s.identifier.value)
+            }
+            Declarator::Enum(e) => writer.define_enum(e.identifier.value),
+            Declarator::Type(t) => writer.define_alias(t),
+            _ => Ok(()),
+        };
+        if let Err(e) = res {
+            errors.push(e);
+        }
+    }
+    let parameter_names = parameter_names(module);
+    let mut declarations = String::new();
+    let mut wrappers = String::new();
+    for overload in exported_overloads(module, &functions) {
+        let names = parameter_names
+            .get(&overload.span)
+            .cloned()
+            .unwrap_or_default();
+        let overloaded = functions.overloads(overload.name).len() > 1;
+        match writer.function(overload, overloaded, &names) {
+            Ok((declaration, wrapper)) => {
+                declarations.push_str(&declaration);
+                if let Some(wrapper) = wrapper {
+                    wrappers.push_str(&wrapper);
+                }
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut bindings = format!("// Generated by catlang from {}. Do not edit.\n\n", name);
+    for (helper, definition) in [("Slice", SLICE), ("Array", ARRAY), ("Optional", OPTIONAL)].iter()
+    {
+        if writer.helpers.contains(helper) {
+            let _ = writeln!(bindings, "{}", definition);
+        }
+    }
+    bindings.push_str(&writer.definitions);
+    if !declarations.is_empty() {
+        let _ = writeln!(
+            bindings,
+            "#[allow(non_snake_case)]\npub mod ffi {{\n    use super::*;\n\n    extern \"C\" {{\n{}    }}\n}}\n",
+            declarations
+        );
+    }
+    bindings.push_str(&wrappers);
+    Ok(bindings.trim_end().to_string() + "\n")
+}
+
+/// Writes the Rust definitions of the types the bindings use.
+struct BindingsWriter<'a, 'ast> {
+    module: &'a str,
+    env: &'a TypeEnvironment<'ast>,
+    constants: ConstantEvaluator<'ast>,
+    definitions: String,
+    /// The names of the types already written.
+    written: HashSet<String>,
+    /// The generic structs standing in for catlang types used so far.
+    helpers: HashSet<&'static str>,
+}
+
+impl<'a, 'ast> BindingsWriter<'a, 'ast> {
+    fn define_struct(&mut self, name: &str) -> Result<()> {
+        let declarator = match self.env.get(name) {
+            Some(TypeDefinition::Struct(s)) => *s,
+            _ => return Ok(()),
+        };
+        if !self.written.insert(name.to_string()) {
+            return Ok(());
+        }
+        let mut fields = String::new();
+        let mut snake_case = true;
+        let mut layouts = vec![];
+        for member in declarator.members.iter() {
+            let span = member.start as usize..member.end as usize;
+            let ty = self.env.resolve(&member.value.type_expression, &[])?;
+            let rust_type = self.rust_type(&ty, name, &span)?;
+            let field = rust_identifier(member.value.identifier.value);
+            snake_case &= !field.chars().any(|c| c.is_ascii_uppercase());
+            let _ = writeln!(fields, "    pub {}: {},", field, rust_type);
+            layouts.extend(self.layout(&ty));
+        }
+        let (size, align) = struct_layout(&layouts);
+        let mut definition = String::from("#[repr(C)]\n#[derive(Clone, Copy)]\n");
+        if !snake_case {
+            definition.push_str("#[allow(non_snake_case)]\n");
+        }
+        let _ = writeln!(definition, "pub struct {} {{\n{}}}\n", name, fields);
+        let _ = writeln!(
+            definition,
+            "const _: () = assert!(std::mem::size_of::<{}>() == {});",
+            name, size
+        );
+        let _ = writeln!(
+            definition,
+            "const _: () = assert!(std::mem::align_of::<{}>() == {});\n",
+            name, align
+        );
+        self.definitions.push_str(&definition);
+        Ok(())
+    }
+
+    fn define_enum(&mut self, name: &str) -> Result<()> {
+        let declarator = match self.env.get(name) {
+            Some(TypeDefinition::Enum(e)) => *e,
+            _ => return Ok(()),
+        };
+        if !self.written.insert(name.to_string()) {
+            return Ok(());
+        }
+        let representation = declarator
+            .representation
+            .map_or(PrimitiveType::S32, representation_type);
+        let repr = primitive_type(representation).unwrap_or("i32");
+        let variants = self.constants.enum_values(self.module, name)?;
+        // Rust does not allow enums without variants to have a representation.
+        if variants.is_empty() {
+            let _ = writeln!(self.definitions, "pub type {} = {};\n", name, repr);
+            return Ok(());
+        }
+        let mut definition = format!(
+            "#[repr({})]\n#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]\npub enum {} {{\n",
+            repr, name
+        );
+        for (variant, value) in variants {
+            if let Constant::Integer { value, .. } = value {
+                let _ = writeln!(definition, "    {} = {},", rust_identifier(variant), value);
+            }
+        }
+        let _ = writeln!(definition, "}}\n");
+        self.definitions.push_str(&definition);
+        Ok(())
+    }
+
+    fn define_alias(&mut self, declarator: TypeDeclarator<'ast>) -> Result<()> {
+        let name = declarator.identifier.value;
+        let span = declarator.identifier.start as usize..declarator.identifier.end as usize;
+        let ty = self.env.resolve(&declarator.type_expression, &[])?;
+        let rust_type = self.rust_type(&ty, name, &span)?;
+        if self.written.insert(name.to_string()) {
+            let _ = writeln!(self.definitions, "pub type {} = {};\n", name, rust_type);
+        }
+        Ok(())
+    }
+
+    /// The declaration of an exported function in the `extern "C"` block,
+    /// and its safe wrapper, unless it takes pointers that may be null.
+    /// Functions not declared `#[extern("C")]` are linked by their catlang
+    /// symbol, and are named after their parameter types if they are
+    /// `overloaded`.
+    fn function(
+        &mut self,
+        overload: &Overload,
+        overloaded: bool,
+        parameter_names: &[&str],
+    ) -> Result<(String, Option<String>)> {
+        let span = &overload.span;
+        let name = match !overload.extern_c && overloaded {
+            true => identifier(&overload.to_string()),
+            false => rust_identifier(overload.name),
+        };
+        let return_type = match &overload.signature.return_type {
+            None => String::new(),
+            Some(Type::Primitive(PrimitiveType::NoReturn)) => " -> !".to_string(),
+            Some(ty) => format!(" -> {}", self.rust_type(ty, overload.name, span)?),
+        };
+        let mut parameters = vec![];
+        let mut safe_parameters = Some(vec![]);
+        let mut arguments = vec![];
+        for (index, ty) in overload.signature.parameters.iter().enumerate() {
+            let parameter = match parameter_names.get(index) {
+                Some(parameter) => rust_identifier(parameter),
+                None => format!("arg{}", index),
+            };
+            let rust_type = self.rust_type(ty, overload.name, span)?;
+            parameters.push(format!("{}: {}", parameter, rust_type));
+            match safe_type(ty, &rust_type) {
+                Some((safe_type, argument)) => {
+                    if let Some(safe) = &mut safe_parameters {
+                        safe.push(format!("{}: {}", parameter, safe_type));
+                    }
+                    arguments.push(argument.replace("{}", &parameter));
+                }
+                None => safe_parameters = None,
+            }
+        }
+        let mut declaration = String::new();
+        if !overload.extern_c {
+            let _ = writeln!(declaration, "        #[link_name = {:?}]", overload.symbol);
+        }
+        let _ = writeln!(
+            declaration,
+            "        pub fn {}({}){};",
+            name,
+            parameters.join(", "),
+            return_type
+        );
+        let wrapper = safe_parameters.map(|safe_parameters| {
+            let allow = match name.chars().any(|c| c.is_ascii_uppercase()) {
+                true => "#[allow(non_snake_case)]\n",
+                false => "",
+            };
+            format!(
+                "{}pub fn {}({}){} {{\n    unsafe {{ ffi::{}({}) }}\n}}\n\n",
+                allow,
+                name,
+                safe_parameters.join(", "),
+                return_type,
+                name,
+                arguments.join(", ")
+            )
+        });
+        Ok((declaration, wrapper))
+    }
+
+    /// Spells `ty` in Rust, writing the definitions it needs.
+    fn rust_type(&mut self, ty: &Type, item: &str, span: &Range<usize>) -> Result<String> {
+        let not_representable = || Error::NotRepresentableInRust {
+            name: item.to_string(),
+            ty: ty.to_string(),
+            span: span.clone(),
+        };
+        let res = match ty {
+            Type::Primitive(pt) => primitive_type(*pt)
+                .ok_or_else(not_representable)?
+                .to_string(),
+            Type::Pointer(inner) => match inner.unqualified() {
+                Type::Interface(_) | Type::Any => return Err(not_representable()),
+                pointee => {
+                    let mutability = match inner.as_ref() {
+                        Type::Const(_) => "const",
+                        _ => "mut",
+                    };
+                    format!("*{} {}", mutability, self.rust_type(pointee, item, span)?)
+                }
+            },
+            // Rust has no const or volatile types; constness is a property
+            // of pointers and references.
+            Type::Const(inner) | Type::Volatile(inner) => self.rust_type(inner, item, span)?,
+            Type::SizedArray(inner) => {
+                self.helpers.insert("Slice");
+                format!("Slice<{}>", self.rust_type(inner, item, span)?)
+            }
+            Type::Array(inner, size) => {
+                format!("[{}; {}]", self.rust_type(inner, item, span)?, size)
+            }
+            Type::UnsizedArray(inner) => {
+                self.helpers.insert("Array");
+                format!("Array<{}>", self.rust_type(inner, item, span)?)
+            }
+            // Optional pointers use null for `null`.
+            Type::Optional(inner) => match inner.unqualified() {
+                Type::Pointer(_) => self.rust_type(inner, item, span)?,
+                _ => {
+                    self.helpers.insert("Optional");
+                    format!("Optional<{}>", self.rust_type(inner, item, span)?)
+                }
+            },
+            Type::Struct(named) if named.generic_arguments.is_empty() => {
+                match self.env.get(&named.name) {
+                    Some(TypeDefinition::Struct(s)) if s.generic_parameters.is_empty() => {
+                        self.define_struct(&named.name)?
+                    }
+                    _ => return Err(not_representable()),
+                }
+                named.name.clone()
+            }
+            Type::Enum(name) => {
+                self.define_enum(name)?;
+                name.clone()
+            }
+            Type::Struct(_)
+            | Type::Union(_)
+            | Type::Interface(_)
+            | Type::Generic(_)
+            | Type::Any => return Err(not_representable()),
+        };
+        Ok(res)
+    }
+
+    /// The size and alignment catlang gives `ty` on a 64-bit target.
+    fn layout(&self, ty: &Type) -> Option<(u64, u64)> {
+        let res = match ty {
+            Type::Primitive(PrimitiveType::Bool) => (1, 1),
+            Type::Primitive(pt) => match (pt.integer_layout(), pt.float_width()) {
+                (Some((_, bits)), _) | (None, Some(bits)) => {
+                    (u64::from(bits) / 8, u64::from(bits) / 8)
+                }
+                (None, None) => return None,
+            },
+            Type::Const(inner) | Type::Volatile(inner) => return self.layout(inner),
+            Type::Pointer(_) => (8, 8),
+            Type::SizedArray(_) => (16, 8),
+            Type::UnsizedArray(_) => (24, 8),
+            Type::Array(inner, size) => {
+                let (element_size, align) = self.layout(inner)?;
+                (element_size * size, align)
+            }
+            Type::Optional(inner) => match inner.unqualified() {
+                Type::Pointer(_) => return self.layout(inner),
+                _ => struct_layout(&[(1, 1), self.layout(inner)?]),
+            },
+            Type::Struct(named) => match self.env.get(&named.name) {
+                Some(TypeDefinition::Struct(s)) => {
+                    let mut fields = vec![];
+                    for member in s.members.iter() {
+                        let ty = self.env.resolve(&member.value.type_expression, &[]).ok()?;
+                        fields.push(self.layout(&ty)?);
+                    }
+                    struct_layout(&fields)
+                }
+                _ => return None,
+            },
+            Type::Enum(name) => match self.env.get(name) {
+                Some(TypeDefinition::Enum(e)) => {
+                    let representation = e
+                        .representation
+                        .map_or(PrimitiveType::S32, representation_type);
+                    return self.layout(&Type::Primitive(representation));
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(res)
+    }
+}
+
+/// The size and alignment of a struct with fields of the given sizes and
+/// alignments, laid out in order like C does.
+fn struct_layout(fields: &[(u64, u64)]) -> (u64, u64) {
+    let mut size = 0;
+    let mut align = 1;
+    for (field_size, field_align) in fields {
+        size = round_up(size, *field_align) + field_size;
+        align = align.max(*field_align);
+    }
+    (round_up(size, align), align)
+}
+
+fn round_up(offset: u64, align: u64) -> u64 {
+    match offset % align {
+        0 => offset,
+        remainder => offset + align - remainder,
+    }
+}
+
+/// The type a safe wrapper takes instead of a parameter of type `ty`
+/// (spelled `rust_type`), and how it is passed on, with `{}` standing for
+/// the parameter. Pointers that may be null, and void pointers, have no
+/// safe equivalent.
+fn safe_type(ty: &Type, rust_type: &str) -> Option<(String, &'static str)> {
+    let res = match ty.unqualified() {
+        Type::Optional(inner) if matches!(inner.unqualified(), Type::Pointer(_)) => return None,
+        Type::Pointer(inner)
+            if matches!(inner.unqualified(), Type::Primitive(PrimitiveType::CVoid)) =>
+        {
+            return None
+        }
+        Type::Pointer(_) => {
+            let (mutability, pointee) = match rust_type.strip_prefix("*const ") {
+                Some(pointee) => ("", pointee),
+                None => ("mut ", rust_type.trim_start_matches("*mut ")),
+            };
+            (format!("&{}{}", mutability, pointee), "{}")
+        }
+        Type::SizedArray(inner) => {
+            let element = &rust_type["Slice<".len()..rust_type.len() - 1];
+            let mutability = match inner.as_ref() {
+                Type::Const(_) => "",
+                _ => "mut ",
+            };
+            (format!("&{}[{}]", mutability, element), "Slice::from({})")
+        }
+        _ => (rust_type.to_string(), "{}"),
+    };
+    Some(res)
+}
+
+/// The Rust spelling of a primitive type, if it has one. `noreturn` is only
+/// spelled in return types.
+fn primitive_type(pt: PrimitiveType) -> Option<&'static str> {
+    let res = match pt.canonical() {
+        PrimitiveType::S8 => "i8",
+        PrimitiveType::U8 | PrimitiveType::Char => "u8",
+        PrimitiveType::S16 => "i16",
+        PrimitiveType::U16 => "u16",
+        PrimitiveType::S32 => "i32",
+        PrimitiveType::U32 => "u32",
+        PrimitiveType::S64 => "i64",
+        PrimitiveType::U64 => "u64",
+        PrimitiveType::Bool => "bool",
+        PrimitiveType::F32 => "f32",
+        PrimitiveType::F64 => "f64",
+        PrimitiveType::CShort => "std::os::raw::c_short",
+        PrimitiveType::CUShort => "std::os::raw::c_ushort",
+        PrimitiveType::CInt => "std::os::raw::c_int",
+        PrimitiveType::CUInt => "std::os::raw::c_uint",
+        PrimitiveType::CLong => "std::os::raw::c_long",
+        PrimitiveType::CULong => "std::os::raw::c_ulong",
+        PrimitiveType::CLongLong => "std::os::raw::c_longlong",
+        PrimitiveType::CULongLong => "std::os::raw::c_ulonglong",
+        PrimitiveType::CVoid => "std::os::raw::c_void",
+        _ => return None,
+    };
+    Some(res)
+}
+
+/// Escapes names that are Rust keywords, as in `r#type`.
+fn rust_identifier(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type",
+        "unsafe", "use", "where", "while", "yield",
+    ];
+    match KEYWORDS.contains(&name) {
+        true => format!("r#{}", name),
+        false => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::module::ModuleCompiler;
+    use crate::codegen::optimize::OptLevel;
+    use crate::codegen::{target_machine, CodeGen};
+    use crate::semantic::types::NamedTypeRef;
+    use crate::syntax::parser::parse;
+    use inkwell::context::Context;
+    use inkwell::targets::{InitializationConfig, Target};
+    use pretty_assertions::assert_eq;
+
+    fn bindings(source: &str) -> std::result::Result<String, Vec<Error>> {
+        let program = parse(source).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        generate_bindings("shapes.cat", &module)
+    }
+
+    #[test]
+    fn test_exported_declarations() {
+        let source = "
+            enum Kind: u8 { Square, Circle = 4 }
+            struct Label { text: []char; weight: ?f32; }
+            export struct Shape { kind: Kind; label: Label; next: *Shape; }
+            #[extern(\"C\")]
+            export function area(shape: *const Shape) -> f64 {}
+            export function rename(shape: *Shape, name: []const char) {}
+            export function scale(shape: *Shape, by: s32) {}
+            export function scale(shape: *Shape, by: f32) {}
+            export function release(data: *c_void) {}
+            function hidden() {}";
+        let expected = r#"// Generated by catlang from shapes.cat. Do not edit.
+
+/// A catlang `[]T`: a pointer to the elements and their count.
+#[repr(C)]
+pub struct Slice<T> {
+    pub data: *mut T,
+    pub length: i64,
+}
+
+impl<T> Clone for Slice<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Slice<T> {}
+
+impl<'a, T> From<&'a [T]> for Slice<T> {
+    fn from(slice: &'a [T]) -> Self {
+        Slice {
+            data: slice.as_ptr() as *mut T,
+            length: slice.len() as i64,
+        }
+    }
+}
+
+impl<'a, T> From<&'a mut [T]> for Slice<T> {
+    fn from(slice: &'a mut [T]) -> Self {
+        Slice {
+            data: slice.as_mut_ptr(),
+            length: slice.len() as i64,
+        }
+    }
+}
+
+/// A catlang `?T`. `value` is only initialized if `has_value` is set.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Optional<T> {
+    pub has_value: bool,
+    pub value: T,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Square = 0,
+    Circle = 4,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Label {
+    pub text: Slice<u8>,
+    pub weight: Optional<f32>,
+}
+
+const _: () = assert!(std::mem::size_of::<Label>() == 24);
+const _: () = assert!(std::mem::align_of::<Label>() == 8);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Shape {
+    pub kind: Kind,
+    pub label: Label,
+    pub next: *mut Shape,
+}
+
+const _: () = assert!(std::mem::size_of::<Shape>() == 40);
+const _: () = assert!(std::mem::align_of::<Shape>() == 8);
+
+#[allow(non_snake_case)]
+pub mod ffi {
+    use super::*;
+
+    extern "C" {
+        pub fn area(shape: *const Shape) -> f64;
+        #[link_name = "shapes.cat::rename(*Shape, []const char)"]
+        pub fn rename(shape: *mut Shape, name: Slice<u8>);
+        #[link_name = "shapes.cat::scale(*Shape, s32)"]
+        pub fn scale_ptr_Shape_s32(shape: *mut Shape, by: i32);
+        #[link_name = "shapes.cat::scale(*Shape, f32)"]
+        pub fn scale_ptr_Shape_f32(shape: *mut Shape, by: f32);
+        #[link_name = "shapes.cat::release(*c_void)"]
+        pub fn release(data: *mut std::os::raw::c_void);
+    }
+}
+
+pub fn area(shape: &Shape) -> f64 {
+    unsafe { ffi::area(shape) }
+}
+
+pub fn rename(shape: &mut Shape, name: &[u8]) {
+    unsafe { ffi::rename(shape, Slice::from(name)) }
+}
+
+#[allow(non_snake_case)]
+pub fn scale_ptr_Shape_s32(shape: &mut Shape, by: i32) {
+    unsafe { ffi::scale_ptr_Shape_s32(shape, by) }
+}
+
+#[allow(non_snake_case)]
+pub fn scale_ptr_Shape_f32(shape: &mut Shape, by: f32) {
+    unsafe { ffi::scale_ptr_Shape_f32(shape, by) }
+}
+"#;
+        assert_eq!(bindings(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_layouts_match_llvm() {
+        let source = "
+            enum Kind: u8 { Square, Circle = 4 }
+            struct Label { text: []char; weight: ?f32; }
+            struct Shape { kind: Kind; label: Label; next: *Shape; flags: [3]u16; ratio: f64; }
+            struct Growable { items: [..]s64; small: ?s8; }";
+        let program = parse(source).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let ModuleExports { env, constants, .. } =
+            ModuleExports::resolve("shapes.cat", &module).unwrap();
+        let writer = BindingsWriter {
+            module: "shapes.cat",
+            env: &env,
+            constants,
+            definitions: String::new(),
+            written: HashSet::new(),
+            helpers: HashSet::new(),
+        };
+
+        let context = Context::create();
+        let codegen = CodeGen::named(&context, "shapes");
+        ModuleCompiler::new(&codegen, "shapes.cat", &module).unwrap();
+        Target::initialize_all(&InitializationConfig::default());
+        let machine = target_machine("x86_64-unknown-linux-gnu", OptLevel::None).unwrap();
+        let data = machine.get_target_data();
+        for ty in &[
+            Type::Enum("Kind".to_string()),
+            Type::Struct(NamedTypeRef::new("Label")),
+            Type::Struct(NamedTypeRef::new("Shape")),
+            Type::Struct(NamedTypeRef::new("Growable")),
+        ] {
+            let llvm_type = codegen.llvm_type(ty).unwrap().unwrap();
+            let expected = (
+                data.get_abi_size(&llvm_type),
+                u64::from(data.get_abi_alignment(&llvm_type)),
+            );
+            assert_eq!(writer.layout(ty), Some(expected), "{}", ty);
+        }
+    }
+
+    #[test]
+    fn test_unrepresentable_exports() {
+        let source = "export struct Wrapper { value: s32 | f32; }";
+        assert_eq!(
+            bindings(source),
+            Err(vec![Error::NotRepresentableInRust {
+                name: "Wrapper".to_string(),
+                ty: "(s32 | f32)".to_string(),
+                span: 24..40,
+            }])
+        );
+    }
+}
@@ -1,7 +1,16 @@
 #![deny(clippy::all)]
 #![allow(unused_variables)]
 #![allow(dead_code)]
+pub mod build;
 pub mod codegen;
+pub mod interop;
 pub mod language_server;
+pub mod package;
 pub mod semantic;
+mod session;
 pub mod syntax;
+
+pub use inkwell::context::Context;
+pub use session::{
+    Compilation, Diagnostic, Object, ReturnValue, Session, SessionError, Signature, Value,
+};
@@ -0,0 +1,1030 @@
+use crate::build::{archive, find_modules_with, normalize, Modules};
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{exported_items, ExportedItems, ModuleCompiler};
+use crate::codegen::optimize::PassDebugging;
+use crate::codegen::{Checks, CodeGen, DEFAULT_TARGET_TRIPLE};
+use crate::package::cache::{
+    fingerprint, hash, interface, Cache, CachedStart, Entry, CACHE_DIRECTORY,
+};
+use crate::package::manifest::{ManifestError, MANIFEST_FILE};
+use crate::package::profile::Profile;
+use crate::package::project::Project;
+use crate::package::resolve::{resolve_locked, Registry, Resolution, ResolveError};
+use crate::package::workspace::{Workspace, WorkspaceError};
+use crate::semantic::cfg::{strip, Config};
+use crate::session::Diagnostic;
+use crate::syntax::ast::*;
+use crate::syntax::parser::parse;
+use inkwell::context::Context;
+use inkwell::targets::{InitializationConfig, Target, TargetTriple};
+use inkwell::values::FunctionValue;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use thiserror::Error;
+use toolshed::Arena;
+use tracing::warn;
+
+#[derive(Error, Debug)]
+pub enum BuildError {
+    #[error(transparent)]
+    Manifest(#[from] ManifestError),
+    #[error("{} does not name a main file to build", .0.display())]
+    NoMain(PathBuf),
+    #[error("could not read {}: {}", path.display(), source)]
+    CouldNotRead { path: PathBuf, source: io::Error },
+    #[error("could not write {}: {}", path.display(), source)]
+    CouldNotWrite { path: PathBuf, source: io::Error },
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+    Compile(Vec<Diagnostic>),
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+    #[error(transparent)]
+    Resolve(#[from] ResolveError),
+    #[error(transparent)]
+    Modules(#[from] crate::build::BuildError),
+    #[error("{} failed: {}", program, message)]
+    Tool { program: String, message: String },
+    #[error("no package being built has a feature named {}", .0)]
+    UnknownFeature(String),
+}
+
+pub type Result<T> = std::result::Result<T, BuildError>;
+
+/// How to build a package.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildOptions {
+    /// The settings to build with, named after the directory under
+    /// `target/` outputs are written to.
+    pub profile: Profile,
+    /// The LLVM target triple.
+    pub target: String,
+    /// The features to turn on in the packages built, on top of their
+    /// default ones.
+    pub features: Vec<String>,
+    /// Whether to turn on the `default` feature of the packages built.
+    pub default_features: bool,
+    /// How many modules to compile at once.
+    pub jobs: usize,
+    /// What to report about the optimization passes as they run.
+    pub passes: PassDebugging,
+}
+
+impl BuildOptions {
+    /// Builds for the host with `profile` and the default features, on as
+    /// many threads as the host runs at once.
+    pub fn new(profile: Profile) -> Self {
+        BuildOptions {
+            profile,
+            target: DEFAULT_TARGET_TRIPLE.clone(),
+            features: vec![],
+            default_features: true,
+            jobs: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            passes: PassDebugging::default(),
+        }
+    }
+
+    /// The `#[cfg]` configuration of the build, before features: its
+    /// target, `profile = "<name>"`, and `debug` for the `debug` profile
+    /// and the ones inheriting from it.
+    fn config(&self) -> Config {
+        let mut config = Config::for_target(&self.target);
+        config.value("profile", &self.profile.name);
+        if self.profile.debug {
+            config.flag("debug");
+        }
+        config
+    }
+}
+
+/// What building a package produced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Artifact {
+    /// A program, for packages whose main file has an entry point.
+    Executable(PathBuf),
+    /// A static library, for packages whose main file only declares.
+    Library(PathBuf),
+}
+
+impl Artifact {
+    pub fn path(&self) -> &Path {
+        match self {
+            Artifact::Executable(path) | Artifact::Library(path) => path,
+        }
+    }
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.path().display().fmt(f)
+    }
+}
+
+/// Builds the members of `workspace` into `target/<profile>/` under its
+/// root, each after the members it depends on, or only the member named
+/// `package`. Their dependencies are resolved together, and pinned by the
+/// workspace's lockfile. Each requested feature is turned on in the members
+/// that declare it, and at least one must.
+pub fn build(
+    workspace: &Workspace,
+    package: Option<&str>,
+    options: &BuildOptions,
+) -> Result<Vec<Artifact>> {
+    let members = match package {
+        Some(name) => vec![workspace
+            .member(name)
+            .ok_or_else(|| WorkspaceError::UnknownMember(name.to_string()))?],
+        None => workspace.build_order()?,
+    };
+    if let Some(unknown) = options.features.iter().find(|feature| {
+        !members
+            .iter()
+            .any(|member| member.manifest.declares_feature(feature))
+    }) {
+        return Err(BuildError::UnknownFeature(unknown.clone()));
+    }
+    let resolution = resolve_locked(workspace, &Registry::from_env())?;
+    let directory = workspace.target_directory(&options.profile.name);
+    members
+        .into_iter()
+        .map(|member| {
+            let requested: Vec<String> = options
+                .features
+                .iter()
+                .filter(|feature| member.manifest.declares_feature(feature))
+                .cloned()
+                .collect();
+            let features = member
+                .manifest
+                .enabled_features(&requested, options.default_features)
+                .map_err(BuildError::UnknownFeature)?;
+            build_member(member, &resolution, options, &features, &directory)
+        })
+        .collect()
+}
+
+/// Builds a package into `directory`.
+///
+/// The main file and the modules it imports are each compiled into an object
+/// kept in the build's incremental cache, the package's with `features`
+/// turned on and its dependencies' with their default ones, with the scripts
+/// matched by the manifest's `scripts` patterns included ahead of the main
+/// file so their declarations are in its scope. Modules whose source, build
+/// settings and imported interfaces did not change reuse their cached
+/// object. The C sources in `link.c` are compiled with `CC`, or else `cc`,
+/// and linked in with the objects in `link.obj`: into an executable named
+/// after the package if the main file has an entry point, and otherwise
+/// into the static library `lib<name>.a`.
+fn build_member(
+    project: &Project,
+    resolution: &Resolution,
+    options: &BuildOptions,
+    features: &[String],
+    directory: &Path,
+) -> Result<Artifact> {
+    let main = project
+        .main()
+        .map(|main| normalize(&main))
+        .ok_or_else(|| BuildError::NoMain(project.root.join(MANIFEST_FILE)))?;
+    let scripts = project
+        .scripts()
+        .map_err(|source| BuildError::CouldNotRead {
+            path: project.root.join(MANIFEST_FILE),
+            source,
+        })?;
+    let mut source = Source::default();
+    for path in scripts.iter().chain(Some(&main)) {
+        source.push(project, path)?;
+    }
+    source.check_scripts()?;
+
+    fs::create_dir_all(directory).map_err(|source| BuildError::CouldNotWrite {
+        path: directory.to_path_buf(),
+        source,
+    })?;
+    let name = project.manifest.name.unscoped();
+    let (mut objects, executable) = compile(
+        project, resolution, &main, &source, options, features, directory,
+    )?;
+    for c in &project.manifest.link.c {
+        objects.push(compile_c(project, c, options, directory)?);
+    }
+    objects.extend(
+        project
+            .manifest
+            .link
+            .obj
+            .iter()
+            .map(|obj| project.root.join(obj)),
+    );
+    if executable {
+        let mut path = directory.join(name);
+        if options.target.contains("windows") {
+            path.set_extension("exe");
+        }
+        link(&objects, &path, options)?;
+        Ok(Artifact::Executable(path))
+    } else {
+        let path = directory.join(format!("lib{}.a", name));
+        archive(&options.target, &path, &objects)?;
+        Ok(Artifact::Library(path))
+    }
+}
+
+/// The source of a module, which for the package's main module is its
+/// scripts followed by the main file.
+#[derive(Clone, Debug, Default)]
+struct Source {
+    text: String,
+    /// The files `text` is made of, by their path relative to the package
+    /// root, and where each of them is in it.
+    files: Vec<(String, Range<usize>)>,
+}
+
+impl Source {
+    fn push(&mut self, project: &Project, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path).map_err(|source| BuildError::CouldNotRead {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        self.push_text(project, path, &text);
+        Ok(())
+    }
+
+    /// Adds the file `path`, which holds `text`.
+    fn push_text(&mut self, project: &Project, path: &Path, text: &str) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        if !text.ends_with('\n') {
+            self.text.push('\n');
+        }
+        let path = path.strip_prefix(&project.root).unwrap_or(path);
+        self.files
+            .push((path.to_string_lossy().into_owned(), start..self.text.len()));
+    }
+
+    /// Parses the text, reporting syntax errors in the files they are in.
+    fn parse(&self) -> Result<Program<'_>> {
+        parse(&self.text).map_err(|errors| {
+            let diagnostics = errors
+                .iter()
+                .map(|error| self.diagnostic(error.span(), error))
+                .collect();
+            BuildError::Compile(diagnostics)
+        })
+    }
+
+    /// Checks that every file but the last is a script on its own, so that
+    /// one cannot break the parse of the next, and none of them turns the
+    /// main file into a module by importing or exporting.
+    fn check_scripts(&self) -> Result<()> {
+        let mut diagnostics = vec![];
+        let scripts = &self.files[..self.files.len().saturating_sub(1)];
+        for (path, range) in scripts {
+            let text = &self.text[range.clone()];
+            match parse(text) {
+                Ok(program) => {
+                    for unit in program.body().iter() {
+                        let SourceUnit::Module(module) = unit.value;
+                        if !module.is_script {
+                            let message = "scripts cannot import or export";
+                            diagnostics.push(Diagnostic::new(path, text, None, message));
+                        }
+                    }
+                }
+                Err(errors) => diagnostics.extend(errors.iter().map(|error| {
+                    Diagnostic::new(path, text, error.span(), strip_span(error, error.span()))
+                })),
+            }
+        }
+        match diagnostics.is_empty() {
+            true => Ok(()),
+            false => Err(BuildError::Compile(diagnostics)),
+        }
+    }
+
+    /// Reports an error at `span` of the text in the file it falls in.
+    fn diagnostic<E: ToString>(&self, span: Option<Range<usize>>, error: E) -> Diagnostic {
+        let message = strip_span(error, span.clone());
+        let file = span
+            .as_ref()
+            .and_then(|span| {
+                self.files
+                    .iter()
+                    .find(|(_, range)| range.contains(&span.start))
+            })
+            .or_else(|| self.files.last());
+        match file {
+            Some((path, range)) => {
+                let span = span.map(|span| {
+                    let start = span.start.max(range.start) - range.start;
+                    start..span.end.min(range.end).max(span.start) - range.start
+                });
+                Diagnostic::new(path, &self.text[range.clone()], span, message)
+            }
+            None => Diagnostic::new("", "", None, message),
+        }
+    }
+
+    fn codegen_diagnostics(&self, error: CodeGenError) -> Vec<Diagnostic> {
+        match error {
+            CodeGenError::SemanticErrors(errors) => errors
+                .iter()
+                .map(|error| self.diagnostic(error.span(), error))
+                .collect(),
+            error => vec![self.diagnostic(error.span(), error)],
+        }
+    }
+}
+
+/// The message of an error without the ` at <start>:<end>` its byte range
+/// is appended with, which is replaced by a line and column.
+fn strip_span<E: ToString>(error: E, span: Option<Range<usize>>) -> String {
+    let message = error.to_string();
+    match span {
+        Some(span) => {
+            let suffix = format!(" at {}:{}", span.start, span.end);
+            message
+                .strip_suffix(&suffix)
+                .unwrap_or(&message)
+                .to_string()
+        }
+        None => message,
+    }
+}
+
+/// A module to compile, with what decides whether its cached object can be
+/// reused.
+struct Unit<'m> {
+    path: &'m Path,
+    source: Source,
+    /// The `#[cfg]` configuration of the package the module is in.
+    config: Config,
+    source_hash: String,
+    /// The hash of the module's own interface. See [`interface`].
+    interface: String,
+    previous: Option<Entry>,
+}
+
+/// Compiles the main module and the modules it imports, from the package or
+/// its dependencies, each into an object of its own, and then the entry
+/// point or constructor calling them into `object`. Returns the objects,
+/// starting with `object`, and whether the package has an entry point.
+///
+/// Modules are kept in the [`Cache`] under `directory`, and compiled again
+/// only if their source, the build settings or the interfaces of the
+/// modules they import, directly or not, changed since. The declarations
+/// `#[cfg]` disables are left out. Modules are parsed and compiled on up to
+/// `options.jobs` threads, each into an LLVM module of its own that declares
+/// the functions it imports from the others.
+fn compile(
+    project: &Project,
+    resolution: &Resolution,
+    main: &Path,
+    source: &Source,
+    options: &BuildOptions,
+    features: &[String],
+    directory: &Path,
+) -> Result<(Vec<PathBuf>, bool)> {
+    let modules = find_modules_with(&[main.to_path_buf()], |module, import| {
+        resolution.resolve_import(module, import)
+    })?;
+    // Objects are written into the cache before their entries are stored.
+    let cache_directory = directory
+        .join(CACHE_DIRECTORY)
+        .join(project.manifest.name.unscoped());
+    fs::create_dir_all(&cache_directory).map_err(|source| BuildError::CouldNotWrite {
+        path: cache_directory.clone(),
+        source,
+    })?;
+    let cache = Cache::new(&cache_directory);
+    let checks = Checks {
+        assertions: options.profile.assertions,
+        overflow: options.profile.overflow_checks,
+    };
+    Target::initialize_all(&InitializationConfig::default());
+
+    // Modules whose source changed are parsed again for their interface.
+    let units = run_jobs(options.jobs, &modules.order, |path| {
+        let module_source = match path.as_path() == main {
+            true => source.clone(),
+            false => {
+                let mut module_source = Source::default();
+                let text = modules.source(path).unwrap_or_default();
+                module_source.push_text(project, path, text);
+                module_source
+            }
+        };
+        let features = match resolution.package_of(path) {
+            Some(package) if package.name != project.manifest.name => &package.features,
+            _ => features,
+        };
+        let mut config = options.config();
+        for feature in features {
+            config.feature(feature);
+        }
+        let source_hash = hash(&module_source.text);
+        let previous = cache.entry(path);
+        let interface = match &previous {
+            Some(previous) if previous.source == source_hash => previous.interface.clone(),
+            _ => interface(&module_source.text, &module_source.parse()?),
+        };
+        Ok(Unit {
+            path,
+            source: module_source,
+            config,
+            source_hash,
+            interface,
+            previous,
+        })
+    })
+    .into_iter()
+    .collect::<Result<Vec<_>>>()?;
+
+    // Modules come after the modules they import, whose interfaces are
+    // known by the time they are reached.
+    let mut interfaces: HashMap<&Path, String> = HashMap::new();
+    let mut stale = vec![];
+    for (index, unit) in units.iter().enumerate() {
+        let mut imported: Vec<&PathBuf> = modules
+            .imports
+            .get(unit.path)
+            .into_iter()
+            .flatten()
+            .map(|(_, module)| module)
+            .collect();
+        imported.sort();
+        imported.dedup();
+        let mut inputs = vec![
+            env!("CARGO_PKG_VERSION").to_string(),
+            unit.path.to_string_lossy().into_owned(),
+            unit.source_hash.clone(),
+            options.target.clone(),
+            options.profile.opt_level.to_string(),
+            format!("{:?}", checks),
+            format!("{:?}", options.passes),
+            format!("{:?}", unit.config),
+        ];
+        let mut effective_interface = vec![unit.interface.clone()];
+        for import in imported {
+            let imported_interface = interfaces
+                .get(import.as_path())
+                .cloned()
+                .unwrap_or_default();
+            inputs.push(imported_interface.clone());
+            effective_interface.push(imported_interface);
+        }
+        let module_fingerprint = fingerprint(&inputs);
+        interfaces.insert(unit.path, fingerprint(&effective_interface));
+        let fresh = match &unit.previous {
+            Some(previous) => {
+                previous.fingerprint == module_fingerprint && cache.object(unit.path).is_file()
+            }
+            None => false,
+        };
+        if !fresh {
+            stale.push((index, module_fingerprint));
+        }
+    }
+
+    // What the modules stale ones import export is found once, here, rather
+    // than by each importer. Modules re-export what they import, so the
+    // modules those import are needed as well.
+    let imports_of = |unit: &Unit| {
+        modules
+            .imports
+            .get(unit.path)
+            .into_iter()
+            .flatten()
+            .map(|(_, module)| module.as_path())
+    };
+    let mut needed: HashSet<&Path> = stale
+        .iter()
+        .flat_map(|(index, _)| imports_of(&units[*index]))
+        .collect();
+    for unit in units.iter().rev() {
+        if needed.contains(unit.path) {
+            needed.extend(imports_of(unit));
+        }
+    }
+    let programs = units
+        .iter()
+        .filter(|unit| needed.contains(unit.path))
+        .map(|unit| Ok((unit, unit.source.parse()?)))
+        .collect::<Result<Vec<_>>>()?;
+    let arena = Arena::new();
+    let mut exports: HashMap<&Path, Vec<ExportedItems>> = HashMap::new();
+    for (unit, program) in &programs {
+        let items = exported_items_of(
+            unit,
+            program,
+            &module_imports(&modules, unit, &exports),
+            &arena,
+        )?;
+        exports.insert(unit.path, items);
+    }
+
+    let compiled = run_jobs(
+        options.jobs,
+        &stale,
+        |(index, module_fingerprint)| -> Result<_> {
+            let unit = &units[*index];
+            let imports = module_imports(&modules, unit, &exports);
+            let object = cache.object(unit.path);
+            let (initializer, start) = compile_module(unit, &imports, checks, options, &object)?;
+            let entry = Entry {
+                source: unit.source_hash.clone(),
+                interface: unit.interface.clone(),
+                fingerprint: module_fingerprint.clone(),
+                initializer,
+                start,
+            };
+            cache
+                .store(unit.path, &entry)
+                .map_err(|source| BuildError::CouldNotWrite {
+                    path: object.clone(),
+                    source,
+                })?;
+            Ok((*index, entry))
+        },
+    );
+    let mut entries: Vec<Option<Entry>> = units.iter().map(|unit| unit.previous.clone()).collect();
+    for result in compiled {
+        let (index, entry) = result?;
+        entries[index] = Some(entry);
+    }
+    let entries: Vec<(&Path, Entry)> = units
+        .iter()
+        .zip(entries)
+        .filter_map(|(unit, entry)| Some((unit.path, entry?)))
+        .collect();
+    let mut objects: Vec<PathBuf> = units.iter().map(|unit| cache.object(unit.path)).collect();
+
+    let error = |message: String| BuildError::Compile(vec![Diagnostic::new("", "", None, message)]);
+    let context = Context::create();
+    let mut codegen = CodeGen::named(&context, project.manifest.name.as_str());
+    codegen.passes = options.passes.clone();
+    let initializers: Vec<_> = entries
+        .iter()
+        .filter_map(|(_, entry)| entry.initializer.as_deref())
+        .map(|symbol| codegen.declare_initializer(symbol))
+        .collect();
+    let start = entries
+        .iter()
+        .find(|(path, _)| *path == main)
+        .and_then(|(_, entry)| entry.start.as_ref());
+    match start {
+        Some(start) => {
+            let start = codegen
+                .declare_start(&start.symbol, start.takes_arguments, start.exit_code)
+                .ok_or_else(|| error(CodeGenError::Unknown.to_string()))?;
+            codegen
+                .build_entry_point(&start, &initializers)
+                .ok_or_else(|| error(CodeGenError::Unknown.to_string()))?;
+        }
+        None if !initializers.is_empty() => {
+            codegen.build_constructor(&initializers);
+        }
+        None => {}
+    }
+    let object = directory.join(format!("{}.o", project.manifest.name.unscoped()));
+    codegen.module.verify().map_err(|e| error(e.to_string()))?;
+    codegen
+        .write_output_file(options.profile.opt_level, &options.target, &object)
+        .map_err(|e| error(e.to_string()))?;
+    objects.insert(0, object);
+    Ok((objects, start.is_some()))
+}
+
+/// What the modules `unit` imports export, from `exports`, along with the
+/// imports naming them.
+fn module_imports<'a, 'ast>(
+    modules: &'a Modules,
+    unit: &Unit,
+    exports: &'a HashMap<&Path, Vec<ExportedItems<'ast>>>,
+) -> Vec<(&'a str, &'a ExportedItems<'ast>)> {
+    modules
+        .imports
+        .get(unit.path)
+        .into_iter()
+        .flatten()
+        .flat_map(|(import, module)| {
+            let items = exports.get(module.as_path()).into_iter().flatten();
+            items.map(move |items| (import.as_str(), items))
+        })
+        .collect()
+}
+
+/// What the module `unit`, parsed into `program`, exports to the modules
+/// importing it, given what the modules it imports export.
+fn exported_items_of<'ast>(
+    unit: &Unit,
+    program: &'ast Program<'ast>,
+    imports: &[(&str, &ExportedItems<'ast>)],
+    arena: &'ast Arena,
+) -> Result<Vec<ExportedItems<'ast>>> {
+    let name = unit.path.to_string_lossy();
+    let mut exports = vec![];
+    for source_unit in program.body().iter() {
+        let SourceUnit::Module(module) = source_unit.value;
+        let module = strip(arena, &module, &unit.config)
+            .map_err(|e| BuildError::Compile(vec![unit.source.diagnostic(e.span(), e)]))?;
+        let items = exported_items(&name, &module, imports)
+            .map_err(|e| BuildError::Compile(unit.source.codegen_diagnostics(e)))?;
+        exports.push(items);
+    }
+    Ok(exports)
+}
+
+/// Compiles `unit` into `object`, returning the symbols of its initializer
+/// and start function. What it imports from `imports`, which are what the
+/// modules it imports export along with the imports naming them, is
+/// declared for its object to link against theirs.
+fn compile_module(
+    unit: &Unit,
+    imports: &[(&str, &ExportedItems)],
+    checks: Checks,
+    options: &BuildOptions,
+    object: &Path,
+) -> Result<(Option<String>, Option<CachedStart>)> {
+    let program = unit.source.parse()?;
+    let arena = Arena::new();
+
+    let module_name = unit.path.to_string_lossy();
+    let context = Context::create();
+    let mut codegen = CodeGen::named(&context, &module_name);
+    codegen.checks = checks;
+    codegen.passes = options.passes.clone();
+    // The target decides the layout of some C types, like `long double`.
+    codegen
+        .module
+        .set_triple(&TargetTriple::create(&options.target));
+    let mut initializer = None;
+    let mut start = None;
+    for source_unit in program.body().iter() {
+        let SourceUnit::Module(module) = source_unit.value;
+        let module = strip(&arena, &module, &unit.config)
+            .map_err(|e| BuildError::Compile(vec![unit.source.diagnostic(e.span(), e)]))?;
+        let compiled = ModuleCompiler::with_imports(&codegen, &module_name, &module, imports)
+            .and_then(|mut compiler| {
+                let compiled = compiler.compile(&module)?;
+                for warning in compiler.warnings() {
+                    warn!("{}", unit.source.diagnostic(Some(warning.span()), warning));
+                }
+                Ok(compiled)
+            })
+            .map_err(|e| BuildError::Compile(unit.source.codegen_diagnostics(e)))?;
+        let symbol = |function: FunctionValue| function.get_name().to_string_lossy().into_owned();
+        initializer = compiled.initializer.map(symbol);
+        start = compiled.start.map(|start| CachedStart {
+            symbol: symbol(start.function),
+            takes_arguments: start.takes_arguments,
+            exit_code: start.exit_code,
+        });
+    }
+    let error = |message: String| BuildError::Compile(vec![unit.source.diagnostic(None, message)]);
+    codegen.module.verify().map_err(|e| error(e.to_string()))?;
+    codegen
+        .write_output_file(options.profile.opt_level, &options.target, object)
+        .map_err(|e| error(e.to_string()))?;
+    Ok((initializer, start))
+}
+
+/// Runs `job` on each of `items` on up to `jobs` threads, and returns the
+/// results in the order of `items`.
+fn run_jobs<'i, T, R, F>(jobs: usize, items: &'i [T], job: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&'i T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1).min(items.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let item = match items.get(index) {
+                    Some(item) => item,
+                    None => break,
+                };
+                let result = job(item);
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// The C compiler, which also links: `CC`, or else `cc`.
+fn c_compiler() -> String {
+    env::var("CC").unwrap_or_else(|_| "cc".to_string())
+}
+
+/// Compiles the C source `path`, relative to the package root, into an
+/// object under `directory/c/<package>/`.
+fn compile_c(
+    project: &Project,
+    path: &Path,
+    options: &BuildOptions,
+    directory: &Path,
+) -> Result<PathBuf> {
+    let object = directory
+        .join("c")
+        .join(project.manifest.name.unscoped())
+        .join(path)
+        .with_extension("o");
+    if let Some(parent) = object.parent() {
+        fs::create_dir_all(parent).map_err(|source| BuildError::CouldNotWrite {
+            path: parent.to_path_buf(),
+            source,
+        })?;
+    }
+    let mut command = Command::new(c_compiler());
+    command
+        .arg("-c")
+        .arg(project.root.join(path))
+        .arg("-o")
+        .arg(&object)
+        .arg(format!("-O{}", options.profile.opt_level));
+    if options.profile.debug_info {
+        command.arg("-g");
+    }
+    run(command, options)?;
+    Ok(object)
+}
+
+/// Links `objects` into the executable `output`.
+fn link(objects: &[PathBuf], output: &Path, options: &BuildOptions) -> Result<()> {
+    let mut command = Command::new(c_compiler());
+    command.args(objects).arg("-o").arg(output);
+    if options.profile.debug_info {
+        command.arg("-g");
+    }
+    run(command, options)
+}
+
+/// Runs a C compiler `command`, for `options.target` if it is not the host.
+fn run(mut command: Command, options: &BuildOptions) -> Result<()> {
+    if options.target != *DEFAULT_TARGET_TRIPLE {
+        command.arg(format!("--target={}", options.target));
+    }
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command.output().map_err(|e| BuildError::Tool {
+        program: program.clone(),
+        message: e.to_string(),
+    })?;
+    if !output.status.success() {
+        return Err(BuildError::Tool {
+            program,
+            message: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn source(files: &[(&str, &str)]) -> Source {
+        let root = env::temp_dir().join("catlang-package-build-test-source");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join(MANIFEST_FILE),
+            "name: test\nversion: 1.0.0\nmain: main.cat\n",
+        )
+        .unwrap();
+        let project = Project::read(&root).unwrap();
+        let mut source = Source::default();
+        for (path, text) in files {
+            fs::write(root.join(path), text).unwrap();
+            source.push(&project, &root.join(path)).unwrap();
+        }
+        fs::remove_dir_all(root).unwrap();
+        source
+    }
+
+    /// Builds a package of `files` in a directory of its own named `name`,
+    /// with `main.cat` as its main file.
+    fn build_package(name: &str, files: &[(&str, &str)]) -> (PathBuf, Artifact) {
+        let root = write_package(name, files);
+        let artifact = build_root(&root, &BuildOptions::new(Profile::debug()));
+        (root, artifact)
+    }
+
+    fn write_package(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join(MANIFEST_FILE),
+            "name: test\nversion: 1.0.0\nmain: main.cat\n",
+        )
+        .unwrap();
+        for (path, text) in files {
+            fs::write(root.join(path), text).unwrap();
+        }
+        root
+    }
+
+    fn build_root(root: &Path, options: &BuildOptions) -> Artifact {
+        let workspace = Workspace::discover(root).unwrap().unwrap();
+        build(&workspace, None, options).unwrap().remove(0)
+    }
+
+    fn cache_directory(root: &Path) -> PathBuf {
+        root.join("target/debug").join(CACHE_DIRECTORY).join("test")
+    }
+
+    /// When each object in the cache of the package at `root` was written.
+    fn cached_objects(root: &Path) -> HashMap<PathBuf, std::time::SystemTime> {
+        fs::read_dir(cache_directory(root))
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.path().extension() == Some("o".as_ref()))
+            .map(|entry| (entry.path(), entry.metadata().unwrap().modified().unwrap()))
+            .collect()
+    }
+
+    fn exit_code(artifact: &Artifact) -> i32 {
+        Command::new(artifact.path())
+            .status()
+            .unwrap()
+            .code()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_modules_read_variables_other_modules_initialize() {
+        let (root, artifact) = build_package(
+            "catlang-package-build-test-variables",
+            &[
+                (
+                    "config.cat",
+                    "function seven() -> s32 { return 7; }\n\
+                     export let width: s32 = seven();\n\
+                     export const height = 3 * 2;\n",
+                ),
+                (
+                    "main.cat",
+                    "import { width, height as h } from \"./config\";\n\
+                     function main() -> s32 { return width * h; }\n",
+                ),
+            ],
+        );
+        assert_eq!(exit_code(&artifact), 42);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_modules_compile_in_parallel() {
+        let mut files = vec![];
+        let mut imports = String::new();
+        let mut sum = vec![];
+        for i in 1..=6 {
+            files.push((
+                format!("part{}.cat", i),
+                format!("export function part{0}() -> s32 {{ return {0}; }}\n", i),
+            ));
+            imports.push_str(&format!("import {{ part{0} }} from \"./part{0}\";\n", i));
+            sum.push(format!("part{}()", i));
+        }
+        files.push((
+            "main.cat".to_string(),
+            format!(
+                "{}function main() -> s32 {{ return {}; }}\n",
+                imports,
+                sum.join(" + ")
+            ),
+        ));
+        let files: Vec<(&str, &str)> = files
+            .iter()
+            .map(|(path, text)| (path.as_str(), text.as_str()))
+            .collect();
+        let root = write_package("catlang-package-build-test-parallel", &files);
+        let mut options = BuildOptions::new(Profile::debug());
+        options.jobs = 4;
+        let artifact = build_root(&root, &options);
+        assert_eq!(exit_code(&artifact), 21);
+        assert_eq!(cached_objects(&root).len(), 7);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_changed_modules_are_rebuilt() {
+        let (root, artifact) = build_package(
+            "catlang-package-build-test-rebuild",
+            &[
+                ("a.cat", "export function a() -> s32 { return 1; }\n"),
+                ("b.cat", "export function b() -> s32 { return 2; }\n"),
+                (
+                    "main.cat",
+                    "import { a } from \"./a\";\nimport { b } from \"./b\";\n\
+                     function main() -> s32 { return a() * 10 + b(); }\n",
+                ),
+            ],
+        );
+        assert_eq!(exit_code(&artifact), 12);
+        let before = cached_objects(&root);
+
+        fs::write(
+            root.join("b.cat"),
+            "export function b() -> s32 { return 5; }\n",
+        )
+        .unwrap();
+        let artifact = build_root(&root, &BuildOptions::new(Profile::debug()));
+        assert_eq!(exit_code(&artifact), 15);
+        let after = cached_objects(&root);
+        let rewritten: Vec<&PathBuf> = after
+            .iter()
+            .filter(|(path, modified)| before.get(*path) != Some(modified))
+            .map(|(path, _)| path)
+            .collect();
+        let cache = Cache::new(cache_directory(&root));
+        assert_eq!(rewritten, vec![&cache.object(&root.join("b.cat"))]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_modules_are_imported_through_namespaces() {
+        let (root, artifact) = build_package(
+            "catlang-package-build-test-namespaces",
+            &[
+                (
+                    "geometry.cat",
+                    "export let sides: s32 = 4;\n\
+                     export function area(side: s32) -> s32 { return side * side; }\n\
+                     function hidden() -> s32 { return 0; }\n",
+                ),
+                (
+                    "main.cat",
+                    "import * as geometry from \"./geometry\";\n\
+                     function main() -> s32 { return geometry.area(5) + geometry.sides; }\n",
+                ),
+            ],
+        );
+        assert_eq!(exit_code(&artifact), 29);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_errors_are_located_in_their_file() {
+        let source = source(&[
+            ("a.cat", "function a() -> s64 { return 1; }"),
+            ("main.cat", "print(\"hi\");\nlet x: s64 = ;\n"),
+        ]);
+        source.check_scripts().unwrap();
+        let errors = match parse(&source.text) {
+            Ok(_) => panic!("expected a syntax error"),
+            Err(errors) => errors,
+        };
+        let diagnostic = source.diagnostic(errors[0].span(), &errors[0]);
+        assert_eq!(diagnostic.path, "main.cat");
+        assert_eq!(diagnostic.location, Some((2, 14)));
+        assert!(!diagnostic.message.contains(" at "));
+    }
+
+    #[test]
+    fn test_jobs_keep_the_order_of_their_items() {
+        let items: Vec<u64> = (0..20).collect();
+        let squares = run_jobs(4, &items, |item| {
+            thread::sleep(std::time::Duration::from_millis(20 - item));
+            item * item
+        });
+        assert_eq!(
+            squares,
+            items.iter().map(|item| item * item).collect::<Vec<_>>()
+        );
+        assert_eq!(run_jobs(0, &items[..2], |item| item + 1), vec![1, 2]);
+        assert_eq!(run_jobs(4, &[] as &[u64], |item| *item), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_scripts_cannot_be_modules() {
+        let source = source(&[
+            ("a.cat", "export function a() -> s64 { return 1; }"),
+            ("main.cat", "print(\"hi\");"),
+        ]);
+        match source.check_scripts() {
+            Err(BuildError::Compile(diagnostics)) => {
+                assert_eq!(diagnostics.len(), 1);
+                assert_eq!(diagnostics[0].path, "a.cat");
+            }
+            result => panic!("expected a diagnostic, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::syntax::ast::*;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The directory under `target/<profile>/` compiled modules are kept in.
+pub const CACHE_DIRECTORY: &str = "incremental";
+
+/// Modules compiled by earlier builds, each into an object of its own, so
+/// that the modules whose inputs did not change are not compiled again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cache {
+    directory: PathBuf,
+}
+
+/// What a module was compiled from, and the symbols of its object the
+/// entry point calls.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Entry {
+    /// The hash of the module's source.
+    pub source: String,
+    /// The hash of what modules importing it can see of it. See
+    /// [`interface`].
+    pub interface: String,
+    /// The hash of everything the object depends on: the source, the
+    /// compiler's version, the build settings and the interfaces of the
+    /// modules it imports.
+    pub fingerprint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initializer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<CachedStart>,
+}
+
+/// The start function of a module a program can start in.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct CachedStart {
+    pub symbol: String,
+    pub takes_arguments: bool,
+    /// The signedness and width of the exit code it returns, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<(bool, u32)>,
+}
+
+impl Cache {
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        Cache {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// The name the files of the module `path` are stored under.
+    fn key(path: &Path) -> String {
+        hash(&path.to_string_lossy())[..16].to_string()
+    }
+
+    /// Where the object of the module `path` is written.
+    pub fn object(&self, path: &Path) -> PathBuf {
+        self.directory.join(format!("{}.o", Cache::key(path)))
+    }
+
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        self.directory.join(format!("{}.yaml", Cache::key(path)))
+    }
+
+    /// The entry of the module `path`, if it was compiled before. Entries
+    /// that cannot be read are treated as missing.
+    pub fn entry(&self, path: &Path) -> Option<Entry> {
+        let source = fs::read_to_string(self.entry_path(path)).ok()?;
+        serde_yaml::from_str(&source).ok()
+    }
+
+    /// Records `entry` for the module `path`, after its object is written.
+    pub fn store(&self, path: &Path, entry: &Entry) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        let yaml = serde_yaml::to_string(entry)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(self.entry_path(path), yaml)
+    }
+}
+
+/// The SHA-256 hash of `text`, in hexadecimal.
+pub fn hash(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+/// The hash of `parts`, which cannot run into each other.
+pub fn fingerprint<S: AsRef<str>>(parts: &[S]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_ref().as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// The hash of what modules importing the module can depend on: its
+/// top-level declarations, exports and imports, with the bodies of its
+/// functions left out. Changing a function's body keeps the interface, so
+/// the modules importing it are not compiled again. `text` is the source
+/// `program` was parsed from.
+pub fn interface(text: &str, program: &Program) -> String {
+    let mut parts = vec![];
+    for unit in program.body().iter() {
+        let SourceUnit::Module(module) = unit.value;
+        for element in module.elements.iter() {
+            let declaration = match element.value {
+                ModuleElement::Statement(_) => continue,
+                ModuleElement::Declaration(declaration) => Some(declaration),
+                ModuleElement::Export(export) => match export.value {
+                    Export::Declaration(declaration) => Some(declaration),
+                    _ => None,
+                },
+                ModuleElement::Import(_) => None,
+            };
+            let end = match declaration.map(|declaration| declaration.value.declarator) {
+                Some(Declarator::Function(function)) => function.block.start,
+                _ => element.end,
+            };
+            let range = element.start as usize..end as usize;
+            parts.push(text.get(range).unwrap_or_default());
+        }
+    }
+    fingerprint(&parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parser::parse;
+    use pretty_assertions::assert_eq;
+    use std::env;
+
+    fn interface_of(text: &str) -> String {
+        interface(text, &parse(text).unwrap())
+    }
+
+    #[test]
+    fn test_function_bodies_are_not_part_of_the_interface() {
+        let original = interface_of(
+            "export function area(side: s64) -> s64 { return side * side; }\nlet count: s64 = 0;",
+        );
+        assert_eq!(
+            interface_of(
+                "export function area(side: s64) -> s64 { count++; return side * side; }\nlet count: s64 = 0;"
+            ),
+            original
+        );
+        assert!(
+            interface_of(
+                "export function area(side: s32) -> s64 { return side * side; }\nlet count: s64 = 0;"
+            ) != original
+        );
+        assert!(
+            interface_of(
+                "export function area(side: s64) -> s64 { return side * side; }\nlet count: s64 = 1;"
+            ) != original
+        );
+    }
+
+    #[test]
+    fn test_entries_are_stored_by_module() {
+        let directory = env::temp_dir().join("catlang-cache-test");
+        let _ = fs::remove_dir_all(&directory);
+        let cache = Cache::new(&directory);
+        let path = Path::new("src/main.cat");
+        assert_eq!(cache.entry(path), None);
+
+        let entry = Entry {
+            source: hash("print(1);"),
+            interface: fingerprint::<&str>(&[]),
+            fingerprint: fingerprint(&["a", "b"]),
+            initializer: None,
+            start: Some(CachedStart {
+                symbol: "src/main.cat::main".to_string(),
+                takes_arguments: true,
+                exit_code: Some((true, 32)),
+            }),
+        };
+        cache.store(path, &entry).unwrap();
+        assert_eq!(cache.entry(path), Some(entry));
+        assert_eq!(cache.entry(Path::new("src/other.cat")), None);
+        assert!(cache.object(path).starts_with(&directory));
+        assert!(fingerprint(&["ab", "c"]) != fingerprint(&["a", "bc"]));
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
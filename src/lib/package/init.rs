@@ -0,0 +1,187 @@
+use crate::package::manifest::{PackageName, MANIFEST_FILE};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum InitError {
+    #[error("invalid package name {:?}: {}", name, reason)]
+    InvalidName { name: String, reason: &'static str },
+    #[error("{} has no name to give the package", .0.display())]
+    Unnamed(PathBuf),
+    #[error("{} already exists", .0.display())]
+    AlreadyInitialized(PathBuf),
+    #[error("could not write {}: {}", path.display(), source)]
+    Io { path: PathBuf, source: io::Error },
+}
+
+pub type Result<T> = std::result::Result<T, InitError>;
+
+/// The kind of project to create.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Template {
+    /// A program starting in `main`.
+    Binary,
+    /// A module exporting declarations for other packages.
+    Library,
+    /// A script of top-level statements.
+    Script,
+}
+
+impl Template {
+    /// The source file the project starts with, and its contents.
+    fn source(self) -> (&'static str, &'static str) {
+        match self {
+            Template::Binary => (
+                "src/main.cat",
+                "export function main(args: [][]char) -> int {\n  print(\"Hello, world!\");\n  return 0;\n}\n",
+            ),
+            Template::Library => (
+                "src/lib.cat",
+                "export function add(a: int, b: int) -> int {\n  return a + b;\n}\n",
+            ),
+            Template::Script => ("src/main.cat", "print(\"Hello, world!\");\n"),
+        }
+    }
+}
+
+const GITIGNORE: &str = "/target/\n*.o\n";
+
+/// Creates a project in `directory`, which is created if it does not exist,
+/// and returns the files written. The package is named after the directory
+/// unless `name` is given.
+///
+/// An existing `package.yaml` is only replaced if `force` is set. Other files
+/// that already exist, such as sources, are left alone either way.
+pub fn init(
+    directory: &Path,
+    name: Option<&str>,
+    template: Template,
+    force: bool,
+) -> Result<Vec<PathBuf>> {
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| InitError::Io { path, source }
+    };
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => directory_name(directory)?,
+    };
+    let name: PackageName = name.parse().map_err(|reason| InitError::InvalidName {
+        name: name.clone(),
+        reason,
+    })?;
+    let manifest_path = directory.join(MANIFEST_FILE);
+    if manifest_path.exists() && !force {
+        return Err(InitError::AlreadyInitialized(manifest_path));
+    }
+
+    let (main, source) = template.source();
+    let mut written = vec![];
+    fs::create_dir_all(directory.join("src")).map_err(io_error(directory))?;
+    fs::write(&manifest_path, manifest(&name, template)).map_err(io_error(&manifest_path))?;
+    written.push(manifest_path);
+    for (path, contents) in &[(main, source), (".gitignore", GITIGNORE)] {
+        let path = directory.join(path);
+        if !path.exists() {
+            fs::write(&path, contents).map_err(io_error(&path))?;
+            written.push(path);
+        }
+    }
+    Ok(written)
+}
+
+/// The name of `directory` itself, even if it is given as `.`.
+fn directory_name(directory: &Path) -> Result<String> {
+    let unnamed = || InitError::Unnamed(directory.to_path_buf());
+    let directory = match directory.file_name() {
+        Some(_) => directory.to_path_buf(),
+        None => directory.canonicalize().map_err(|_| unnamed())?,
+    };
+    let name = directory.file_name().ok_or_else(unnamed)?;
+    Ok(name.to_string_lossy().into_owned())
+}
+
+fn manifest(name: &PackageName, template: Template) -> String {
+    let (main, _) = template.source();
+    format!(
+        concat!(
+            "name: \"{}\"\n",
+            "version: 0.1.0\n",
+            "description: \"\"\n",
+            "authors: []\n",
+            "main: {}\n",
+            "dependencies: {{}}\n",
+            "devDependencies: {{}}\n",
+            "private: {}\n",
+        ),
+        name,
+        main,
+        template != Template::Library,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Manifest;
+    use pretty_assertions::assert_eq;
+    use std::env;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn test_init_library() {
+        let directory = temp_dir("catlang-init-test-library").join("shapes");
+        let written = init(&directory, None, Template::Library, false).unwrap();
+        assert_eq!(
+            written,
+            vec![
+                directory.join("package.yaml"),
+                directory.join("src/lib.cat"),
+                directory.join(".gitignore"),
+            ]
+        );
+        let manifest = Manifest::read(directory.join("package.yaml")).unwrap();
+        assert_eq!(manifest.name.as_str(), "shapes");
+        assert_eq!(manifest.main, Some(PathBuf::from("src/lib.cat")));
+        assert!(!manifest.private);
+        fs::remove_dir_all(directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_existing_projects_are_kept() {
+        let directory = temp_dir("catlang-init-test-existing");
+        init(&directory, Some("@me/first"), Template::Binary, false).unwrap();
+        fs::write(directory.join("src/main.cat"), "// mine").unwrap();
+
+        assert!(matches!(
+            init(&directory, Some("second"), Template::Binary, false),
+            Err(InitError::AlreadyInitialized(_))
+        ));
+        let written = init(&directory, Some("second"), Template::Script, true).unwrap();
+        assert_eq!(written, vec![directory.join("package.yaml")]);
+        let manifest = Manifest::read(directory.join("package.yaml")).unwrap();
+        assert_eq!(manifest.name.as_str(), "second");
+        assert_eq!(
+            fs::read_to_string(directory.join("src/main.cat")).unwrap(),
+            "// mine"
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let directory = temp_dir("catlang-init-test-invalid").join("_hidden");
+        assert!(matches!(
+            init(&directory, None, Template::Binary, false),
+            Err(InitError::InvalidName { .. })
+        ));
+        assert!(!directory.exists());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::package::resolve::{PackageSource, Resolution};
+use semver::Version;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The name of the file pinning the versions of a project's dependencies.
+pub const LOCKFILE: &str = "catlang.lock";
+
+const HEADER: &str = "# This file is generated by catlang. It is not meant to be edited by hand.\n";
+
+#[derive(Error, Debug)]
+pub enum LockfileError {
+    #[error("could not read {}: {}", path.display(), source)]
+    CouldNotRead { path: PathBuf, source: io::Error },
+    #[error("could not write {}: {}", path.display(), source)]
+    CouldNotWrite { path: PathBuf, source: io::Error },
+    #[error("{}: {}", path.display(), message)]
+    Invalid { path: PathBuf, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, LockfileError>;
+
+/// The exact versions a project's dependencies were resolved to, and the
+/// hashes of their contents.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: Version,
+    /// Where the package comes from; the project itself has none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<LockedSource>,
+    /// The hash of the package's files, as `sha256:<hex>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// The packages it depends on, as `<name> <version>`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockedSource {
+    Registry,
+    Path,
+}
+
+impl Lockfile {
+    /// Pins the packages of `resolution`, sorted by name.
+    pub fn new(resolution: &Resolution) -> Self {
+        let mut packages: Vec<LockedPackage> = resolution
+            .packages
+            .iter()
+            .map(|package| LockedPackage {
+                name: package.name.to_string(),
+                version: package.version.clone(),
+                source: match package.source {
+                    PackageSource::Root => None,
+                    PackageSource::Path => Some(LockedSource::Path),
+                    PackageSource::Registry => Some(LockedSource::Registry),
+                },
+                checksum: package.checksum.clone(),
+                dependencies: package
+                    .dependencies
+                    .iter()
+                    .filter_map(|name| resolution.package(name.as_str()))
+                    .map(|dependency| format!("{} {}", dependency.name, dependency.version))
+                    .collect(),
+            })
+            .collect();
+        packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        Lockfile { packages }
+    }
+
+    /// Reads the lockfile at `path`, if there is one.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => {
+                return Err(LockfileError::CouldNotRead {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        serde_yaml::from_str(&source)
+            .map(Some)
+            .map_err(|e| LockfileError::Invalid {
+                path: path.to_path_buf(),
+                message: e.to_string(),
+            })
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let invalid = |message: String| LockfileError::Invalid {
+            path: path.to_path_buf(),
+            message,
+        };
+        let yaml = serde_yaml::to_string(self).map_err(|e| invalid(e.to_string()))?;
+        fs::write(path, format!("{}{}", HEADER, yaml)).map_err(|source| {
+            LockfileError::CouldNotWrite {
+                path: path.to_path_buf(),
+                source,
+            }
+        })
+    }
+
+    /// The entry for the package `name`.
+    pub fn package(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+}
@@ -0,0 +1,751 @@
+use crate::codegen::optimize::OptLevel;
+use semver::{Version, VersionReq};
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_derive::Deserialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The name of the file describing a project.
+pub const MANIFEST_FILE: &str = "package.yaml";
+
+#[derive(Error, Debug)]
+pub enum ManifestError {
+    #[error("could not read {}: {}", path.display(), source)]
+    CouldNotRead { path: PathBuf, source: io::Error },
+    #[error("{}: {}", describe_location(path, *location), message)]
+    Invalid {
+        path: PathBuf,
+        message: String,
+        /// The byte range of the offending value.
+        span: Option<Range<usize>>,
+        /// The line and column the offending value starts at, both counted
+        /// from 1.
+        location: Option<(usize, usize)>,
+    },
+}
+
+fn describe_location(path: &Path, location: Option<(usize, usize)>) -> String {
+    match location {
+        Some((line, column)) => format!("{}:{}:{}", path.display(), line, column),
+        None => path.display().to_string(),
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ManifestError>;
+
+/// A project's `package.yaml`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Manifest {
+    pub name: PackageName,
+    #[serde(deserialize_with = "version")]
+    pub version: Version,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    pub homepage: Option<String>,
+    #[serde(default)]
+    pub bugs: Bugs,
+    pub license: Option<String>,
+    #[serde(default)]
+    pub authors: Vec<Author>,
+    /// Globs matching the project's scripts.
+    #[serde(default)]
+    pub scripts: Vec<String>,
+    /// The file the program starts in.
+    pub main: Option<PathBuf>,
+    /// Aliases for directories, which imports can start with.
+    #[serde(default, deserialize_with = "entries")]
+    pub paths: Vec<(String, PathBuf)>,
+    pub repository: Option<String>,
+    #[serde(default, deserialize_with = "dependencies")]
+    pub dependencies: Vec<Dependency>,
+    #[serde(default, deserialize_with = "dependencies")]
+    pub dev_dependencies: Vec<Dependency>,
+    #[serde(default)]
+    pub link: Link,
+    /// Whether the package must not be published.
+    #[serde(default)]
+    pub private: bool,
+    /// The packages of the workspace this package is the root of.
+    pub workspace: Option<WorkspaceConfig>,
+    /// Build settings by profile name, on top of the built-in `debug` and
+    /// `release` profiles.
+    #[serde(default, deserialize_with = "entries")]
+    pub profiles: Vec<(String, ProfileConfig)>,
+    /// The features that can be turned on, each with the features it turns
+    /// on in turn. `default` is turned on unless asked not to be.
+    #[serde(default, deserialize_with = "entries")]
+    pub features: Vec<(String, Vec<String>)>,
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|source| ManifestError::CouldNotRead {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Manifest::parse_file(path, &source)
+    }
+
+    /// Parses the source of a manifest.
+    pub fn parse(source: &str) -> Result<Self> {
+        Manifest::parse_file(Path::new(MANIFEST_FILE), source)
+    }
+
+    fn parse_file(path: &Path, source: &str) -> Result<Self> {
+        let manifest: Manifest = serde_yaml::from_str(source).map_err(|error| {
+            let location = error.location();
+            let mut message = error.to_string();
+            if let Some(location) = &location {
+                // The location is reported separately.
+                let suffix = format!(" at line {} column {}", location.line(), location.column());
+                if message.ends_with(&suffix) {
+                    message.truncate(message.len() - suffix.len());
+                }
+            }
+            ManifestError::Invalid {
+                path: path.to_path_buf(),
+                message,
+                span: location
+                    .as_ref()
+                    .map(|location| value_span(source, location.line(), location.column())),
+                location: location.map(|location| (location.line(), location.column())),
+            }
+        })?;
+        for (feature, enables) in &manifest.features {
+            if let Some(unknown) = enables.iter().find(|name| !manifest.declares_feature(name)) {
+                return Err(ManifestError::Invalid {
+                    path: path.to_path_buf(),
+                    message: format!(
+                        "features: {} turns on {}, which is not a feature",
+                        feature, unknown
+                    ),
+                    span: None,
+                    location: None,
+                });
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub fn declares_feature(&self, name: &str) -> bool {
+        self.features.iter().any(|(feature, _)| feature == name)
+    }
+
+    /// The features turned on by building with `requested` and, if
+    /// `defaults` is set, `default`, including the ones they turn on in
+    /// turn, in the order they are declared. A requested feature the package
+    /// does not declare is returned as the error.
+    pub fn enabled_features(
+        &self,
+        requested: &[String],
+        defaults: bool,
+    ) -> std::result::Result<Vec<String>, String> {
+        let mut pending: Vec<&str> = vec![];
+        for feature in requested {
+            if !self.declares_feature(feature) {
+                return Err(feature.clone());
+            }
+            pending.push(feature);
+        }
+        if defaults && self.declares_feature("default") {
+            pending.push("default");
+        }
+        let mut enabled: Vec<&str> = vec![];
+        while let Some(feature) = pending.pop() {
+            if enabled.contains(&feature) {
+                continue;
+            }
+            enabled.push(feature);
+            if let Some((_, enables)) = self.features.iter().find(|(name, _)| name == feature) {
+                pending.extend(enables.iter().map(String::as_str));
+            }
+        }
+        Ok(self
+            .features
+            .iter()
+            .map(|(name, _)| name.clone())
+            .filter(|name| enabled.contains(&name.as_str()))
+            .collect())
+    }
+}
+
+/// The extent of the value starting at `line` and `column`: the rest of
+/// its line, without trailing whitespace and comments. The byte index
+/// serde_yaml reports is not set for errors raised while deserializing.
+fn value_span(source: &str, line: usize, column: usize) -> Range<usize> {
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let rest = &source[line_start..];
+    let start = line_start
+        + rest
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map_or(rest.len(), |(i, _)| i);
+    let value = source[start..].split('\n').next().unwrap_or("");
+    let value = match value.find(" #") {
+        Some(comment) => &value[..comment],
+        None => value,
+    };
+    start..start + value.trim_end().len()
+}
+
+/// The name of a package, such as `my-package` or `@owner/my-package`.
+///
+/// Names consist of letters, digits, `@`, `-` and `_`, and do not start with
+/// `-` or `_`. A name can be prefixed with the scope of its owner, as in
+/// `@owner/`. Names are at most 256 characters long, including the scope.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PackageName(String);
+
+impl PackageName {
+    pub const MAX_LENGTH: usize = 256;
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The owner the name is scoped to, without the `@`.
+    pub fn scope(&self) -> Option<&str> {
+        let slash = self.0.find('/')?;
+        Some(&self.0[1..slash])
+    }
+
+    /// The name without its scope.
+    pub fn unscoped(&self) -> &str {
+        match self.0.find('/') {
+            Some(slash) => &self.0[slash + 1..],
+            None => &self.0,
+        }
+    }
+}
+
+impl FromStr for PackageName {
+    type Err = &'static str;
+
+    /// Checks that `name` is a valid name, or says why it is not.
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        if name.chars().count() > PackageName::MAX_LENGTH {
+            return Err("names must be at most 256 characters long");
+        }
+        let parts: Vec<&str> = name.splitn(2, '/').collect();
+        let parts = match parts.as_slice() {
+            [scope, name] if scope.starts_with('@') => vec![&scope[1..], *name],
+            [_, _] => return Err("only a scope like `@owner` can be followed by `/`"),
+            _ => parts,
+        };
+        for part in parts {
+            match part.chars().next() {
+                None => return Err("names cannot be empty"),
+                Some('-') | Some('_') => return Err("names cannot start with `-` or `_`"),
+                Some(_) => {}
+            }
+            let valid = |c: char| c.is_ascii_alphanumeric() || c == '@' || c == '-' || c == '_';
+            if !part.chars().all(valid) {
+                return Err("names may only contain letters, digits, `@`, `-` and `_`");
+            }
+        }
+        Ok(PackageName(name.to_string()))
+    }
+}
+
+impl fmt::Display for PackageName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for PackageName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_str(ParseVisitor::new("a package name", |name: &str| {
+            name.parse()
+                .map_err(|reason| format!("invalid package name {:?}: {}", name, reason))
+        }))
+    }
+}
+
+/// Where to report problems with a package.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bugs {
+    pub url: Option<String>,
+    pub email: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for Bugs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Clone, Copy, PartialEq, Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Key {
+            Url,
+            Email,
+        }
+        let mut bugs = Bugs::default();
+        for (key, value) in entries::<D, Key, String>(deserializer)? {
+            match key {
+                Key::Url => bugs.url = Some(value),
+                Key::Email => bugs.email = Some(value),
+            }
+        }
+        Ok(bugs)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Author {
+    pub name: String,
+    pub email: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// Globs matching the directories of the member packages, relative to
+    /// the workspace root.
+    pub members: Vec<String>,
+}
+
+/// The settings of a build profile. Unset ones are those of the profile it
+/// inherits from: `debug`, unless it names another.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct ProfileConfig {
+    pub inherits: Option<String>,
+    /// The optimization level: from 0 to 3, or `s` or `z` to optimize for
+    /// size.
+    pub opt_level: Option<OptLevel>,
+    /// Whether to compile C sources with debugging information.
+    pub debug_info: Option<bool>,
+    /// Whether `assert` checks its condition.
+    pub assertions: Option<bool>,
+    /// Whether integer arithmetic traps on overflow.
+    pub overflow_checks: Option<bool>,
+}
+
+/// Code in other languages linked into the program.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Link {
+    /// C sources, compiled and linked.
+    pub c: Vec<PathBuf>,
+    /// Object files, linked as they are.
+    pub obj: Vec<PathBuf>,
+}
+
+impl<'de> Deserialize<'de> for Link {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Clone, Copy, PartialEq, Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Key {
+            C,
+            Obj,
+        }
+        let mut link = Link::default();
+        for (key, OneOrMany(paths)) in entries::<D, Key, OneOrMany<PathBuf>>(deserializer)? {
+            match key {
+                Key::C => link.c.extend(paths),
+                Key::Obj => link.obj.extend(paths),
+            }
+        }
+        Ok(link)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dependency {
+    pub name: PackageName,
+    pub source: DependencySource,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DependencySource {
+    /// Versions of the package published to the registry, like `^2.3.1` or
+    /// `3.3.x`.
+    Registry(VersionReq),
+    /// A repository on a code host, like `github:owner/repository`.
+    Repository { host: String, path: String },
+    /// A package in a local directory, like `file:../other`, relative to the
+    /// depending package.
+    Path(PathBuf),
+}
+
+impl FromStr for DependencySource {
+    type Err = String;
+
+    fn from_str(source: &str) -> std::result::Result<Self, Self::Err> {
+        if let Some(path) = source.strip_prefix("file:") {
+            if path.is_empty() {
+                return Err(format!("invalid path {:?}: expected `file:path`", source));
+            }
+            return Ok(DependencySource::Path(PathBuf::from(path)));
+        }
+        if let Some(colon) = source.find(':') {
+            let (host, path) = (&source[..colon], &source[colon + 1..]);
+            if host.is_empty() || path.is_empty() {
+                return Err(format!(
+                    "invalid repository {:?}: expected `host:path`",
+                    source
+                ));
+            }
+            return Ok(DependencySource::Repository {
+                host: host.to_string(),
+                path: path.to_string(),
+            });
+        }
+        VersionReq::parse(source)
+            .map(DependencySource::Registry)
+            .map_err(|e| format!("invalid version requirement {:?}: {}", source, e))
+    }
+}
+
+impl fmt::Display for DependencySource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DependencySource::Registry(requirement) => requirement.fmt(f),
+            DependencySource::Repository { host, path } => write!(f, "{}:{}", host, path),
+            DependencySource::Path(path) => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DependencySource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_str(ParseVisitor::new(
+            "a version requirement or repository",
+            str::parse,
+        ))
+    }
+}
+
+fn version<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Version, D::Error> {
+    deserializer.deserialize_str(ParseVisitor::new("a version", |version: &str| {
+        Version::parse(version).map_err(|e| format!("invalid version {:?}: {}", version, e))
+    }))
+}
+
+fn dependencies<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<Dependency>, D::Error> {
+    let dependencies = entries::<D, PackageName, DependencySource>(deserializer)?;
+    Ok(dependencies
+        .into_iter()
+        .map(|(name, source)| Dependency { name, source })
+        .collect())
+}
+
+/// Parses a string with `parse`. Values are checked while they are visited
+/// rather than after, since serde_yaml only reports the location of errors
+/// raised while visiting the value.
+struct ParseVisitor<F> {
+    expecting: &'static str,
+    parse: F,
+}
+
+impl<F> ParseVisitor<F> {
+    fn new(expecting: &'static str, parse: F) -> Self {
+        ParseVisitor { expecting, parse }
+    }
+}
+
+impl<'de, T, E, F> Visitor<'de> for ParseVisitor<F>
+where
+    F: FnOnce(&str) -> std::result::Result<T, E>,
+    E: fmt::Display,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_str<Err: de::Error>(self, value: &str) -> std::result::Result<T, Err> {
+        (self.parse)(value).map_err(Err::custom)
+    }
+}
+
+/// A single value or a list of them.
+struct OneOrMany<T>(Vec<T>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for OneOrMany<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Either<T> {
+            Many(Vec<T>),
+            One(T),
+        }
+        match Either::deserialize(deserializer)? {
+            Either::Many(values) => Ok(OneOrMany(values)),
+            Either::One(value) => Ok(OneOrMany(vec![value])),
+        }
+    }
+}
+
+/// Reads key-value pairs written either as a map or, like the examples in
+/// the documentation, as a list of single-entry maps. Keys cannot repeat.
+fn entries<'de, D, K, V>(deserializer: D) -> std::result::Result<Vec<(K, V)>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: Deserialize<'de> + PartialEq,
+    V: Deserialize<'de>,
+{
+    struct Entries<K, V>(Vec<(K, V)>);
+
+    struct EntriesVisitor<K, V>(PhantomData<(K, V)>);
+
+    impl<'de, K, V> Visitor<'de> for EntriesVisitor<K, V>
+    where
+        K: Deserialize<'de> + PartialEq,
+        V: Deserialize<'de>,
+    {
+        type Value = Entries<K, V>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map, or a list of maps")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> std::result::Result<Self::Value, A::Error> {
+            let mut entries: Vec<(K, V)> = vec![];
+            while let Some((key, value)) = map.next_entry()? {
+                if entries.iter().any(|(k, _)| *k == key) {
+                    return Err(de::Error::custom("a key is listed more than once"));
+                }
+                entries.push((key, value));
+            }
+            Ok(Entries(entries))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(
+            self,
+            mut seq: A,
+        ) -> std::result::Result<Self::Value, A::Error> {
+            let mut entries: Vec<(K, V)> = vec![];
+            while let Some(Entries(more)) = seq.next_element::<Entries<K, V>>()? {
+                for (key, value) in more {
+                    if entries.iter().any(|(k, _)| *k == key) {
+                        return Err(de::Error::custom("a key is listed more than once"));
+                    }
+                    entries.push((key, value));
+                }
+            }
+            Ok(Entries(entries))
+        }
+    }
+
+    impl<'de, K, V> Deserialize<'de> for Entries<K, V>
+    where
+        K: Deserialize<'de> + PartialEq,
+        V: Deserialize<'de>,
+    {
+        fn deserialize<D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Self, D::Error> {
+            deserializer.deserialize_any(EntriesVisitor(PhantomData))
+        }
+    }
+
+    Ok(Entries::deserialize(deserializer)?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn invalid(source: &str) -> (String, Option<Range<usize>>, Option<(usize, usize)>) {
+        match Manifest::parse(source) {
+            Err(ManifestError::Invalid {
+                message,
+                span,
+                location,
+                ..
+            }) => (message, span, location),
+            result => panic!("expected an invalid manifest, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_documented_example() {
+        let manifest = Manifest::parse(
+            r#"
+name: "@aszecsei/my-library"
+version: 0.1.0
+description: A basic library to do some basic things.
+keywords:
+  - catlang
+  - example
+homepage: https://github.com/aszecsei/catlang#readme
+bugs:
+  - url: https://github.com/aszecsei/catlang/issues
+  - email: aszecsei@gmail.com
+license: MIT
+authors:
+  - name: Alic Szecsei
+    email: aszecsei@gmail.com
+    url: https://alic-szecsei.com
+scripts:
+  - src/scripts/**/*.cat
+main: src/main.cat
+paths:
+  - "@workers": src/utils/workers/
+repository: github:aszecsei/catlang
+dependencies:
+  - "@guyfieri/project": ^2.3.1
+  - other: github:username/other
+  - yaml: 3.3.x
+  - local: file:../local
+link:
+  - c: src/clib/hello.c
+  - obj: ["src/lib/world.o", "src/lib/other.o"]
+private: false
+profiles:
+  - profiling:
+      inherits: release
+      debugInfo: true
+  - small:
+      inherits: release
+      optLevel: z
+features:
+  - default: [json]
+  - json: []
+  - all: [json, yaml]
+  - yaml: []
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.name.scope(), Some("aszecsei"));
+        assert_eq!(manifest.name.unscoped(), "my-library");
+        assert_eq!(manifest.version, Version::new(0, 1, 0));
+        assert_eq!(
+            manifest.authors[0].email.as_deref(),
+            Some("aszecsei@gmail.com")
+        );
+        assert_eq!(
+            manifest.paths,
+            vec![("@workers".to_string(), PathBuf::from("src/utils/workers/"))]
+        );
+        assert_eq!(
+            manifest.dependencies[1].source,
+            DependencySource::Repository {
+                host: "github".to_string(),
+                path: "username/other".to_string(),
+            }
+        );
+        let yaml = &manifest.dependencies[2];
+        assert_eq!(yaml.name.as_str(), "yaml");
+        match &yaml.source {
+            DependencySource::Registry(requirement) => {
+                assert!(requirement.matches(&Version::new(3, 3, 9)));
+                assert!(!requirement.matches(&Version::new(3, 4, 0)));
+            }
+            source => panic!("expected a registry dependency, got {:?}", source),
+        }
+        assert_eq!(
+            manifest.dependencies[3].source,
+            DependencySource::Path(PathBuf::from("../local"))
+        );
+        assert_eq!(manifest.link.obj.len(), 2);
+        assert!(manifest.dev_dependencies.is_empty());
+        assert_eq!(
+            manifest.profiles,
+            vec![
+                (
+                    "profiling".to_string(),
+                    ProfileConfig {
+                        inherits: Some("release".to_string()),
+                        debug_info: Some(true),
+                        ..ProfileConfig::default()
+                    }
+                ),
+                (
+                    "small".to_string(),
+                    ProfileConfig {
+                        inherits: Some("release".to_string()),
+                        opt_level: Some(OptLevel::MinSize),
+                        ..ProfileConfig::default()
+                    }
+                )
+            ]
+        );
+        assert_eq!(
+            manifest.enabled_features(&["all".to_string()], false),
+            Ok(vec![
+                "json".to_string(),
+                "all".to_string(),
+                "yaml".to_string()
+            ])
+        );
+        assert_eq!(
+            manifest.enabled_features(&[], true),
+            Ok(vec!["default".to_string(), "json".to_string()])
+        );
+        assert_eq!(
+            manifest.enabled_features(&["xml".to_string()], true),
+            Err("xml".to_string())
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert!("my_package-2".parse::<PackageName>().is_ok());
+        assert!("@owner/package".parse::<PackageName>().is_ok());
+        assert!("_private".parse::<PackageName>().is_err());
+        assert!("-dash".parse::<PackageName>().is_err());
+        assert!("@owner/-dash".parse::<PackageName>().is_err());
+        assert!("some/package".parse::<PackageName>().is_err());
+        assert!("dots.are.not.allowed".parse::<PackageName>().is_err());
+        assert!("a".repeat(256).parse::<PackageName>().is_ok());
+        assert!("a".repeat(257).parse::<PackageName>().is_err());
+    }
+
+    #[test]
+    fn test_errors_have_spans() {
+        let (message, span, location) = invalid("name: _hidden\nversion: 1.0.0\n");
+        assert_eq!(
+            message,
+            "name: invalid package name \"_hidden\": names cannot start with `-` or `_`"
+        );
+        assert_eq!(span, Some(6..13));
+        assert_eq!(location, Some((1, 7)));
+
+        let (message, _, location) = invalid("name: ok\nversion: 1.0  # no patch\n");
+        assert_eq!(
+            message,
+            "version: invalid version \"1.0\": unexpected end of input while parsing minor version number"
+        );
+        assert_eq!(location, Some((2, 10)));
+
+        let (_, span, location) = invalid("name: ok\nversion: [1.0.0\n");
+        assert_eq!(location.map(|(line, _)| line), Some(2));
+        assert!(span.is_some());
+
+        let (message, _, _) = invalid("name: ok\nversion: 1.0.0\nfeatures:\n  default: [json]\n");
+        assert_eq!(
+            message,
+            "features: default turns on json, which is not a feature"
+        );
+
+        let (message, _, _) =
+            invalid("name: ok\nversion: 1.0.0\nprofiles:\n  fast:\n    optLevel: 4\n");
+        assert_eq!(
+            message,
+            "profiles.fast: 4 is not an optimization level; levels are 0 to 3, s and z"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+//! Projects described by a `package.yaml`.
+
+pub mod build;
+pub mod cache;
+pub mod init;
+pub mod lockfile;
+pub mod manifest;
+pub mod profile;
+pub mod project;
+pub mod resolve;
+pub mod workspace;
+
+pub use build::{build, Artifact, BuildOptions};
+pub use init::{init, Template};
+pub use lockfile::{Lockfile, LOCKFILE};
+pub use manifest::{Manifest, ManifestError, PackageName, MANIFEST_FILE};
+pub use profile::Profile;
+pub use project::Project;
+pub use resolve::{resolve, resolve_locked, Registry, Resolution};
+pub use workspace::Workspace;
@@ -0,0 +1,181 @@
+use crate::codegen::optimize::OptLevel;
+use crate::package::manifest::ProfileConfig;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ProfileError {
+    #[error("there is no profile named {}", .0)]
+    Unknown(String),
+    #[error("profiles inherit from each other in a cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+}
+
+pub type Result<T> = std::result::Result<T, ProfileError>;
+
+/// The settings a package is built with, which are named after the
+/// directory under `target/` the build is written to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    /// The optimization level, for both catlang and C sources.
+    pub opt_level: OptLevel,
+    /// Whether C sources are compiled, and executables linked, with `-g`.
+    pub debug_info: bool,
+    /// Whether `assert` checks its condition.
+    pub assertions: bool,
+    /// Whether integer arithmetic traps on overflow.
+    pub overflow_checks: bool,
+    /// Whether the profile is `debug` or inherits from it, which sets the
+    /// `debug` name for `#[cfg]`.
+    pub debug: bool,
+}
+
+impl Profile {
+    /// Unoptimized builds with every check, written to `target/debug/`.
+    pub fn debug() -> Self {
+        Profile {
+            name: "debug".to_string(),
+            opt_level: OptLevel::None,
+            debug_info: true,
+            assertions: true,
+            overflow_checks: true,
+            debug: true,
+        }
+    }
+
+    /// Optimized builds without checks, written to `target/release/`.
+    pub fn release() -> Self {
+        Profile {
+            name: "release".to_string(),
+            opt_level: OptLevel::Aggressive,
+            debug_info: false,
+            assertions: false,
+            overflow_checks: false,
+            debug: false,
+        }
+    }
+
+    /// The profile `name`, from the settings in `configs` applied over the
+    /// profile each inherits from, down to a built-in one.
+    pub fn resolve(name: &str, configs: &[(String, ProfileConfig)]) -> Result<Self> {
+        Profile::resolve_inheriting(name, configs, &mut vec![])
+    }
+
+    fn resolve_inheriting(
+        name: &str,
+        configs: &[(String, ProfileConfig)],
+        inheriting: &mut Vec<String>,
+    ) -> Result<Self> {
+        if inheriting.iter().any(|profile| profile == name) {
+            let mut cycle = inheriting.clone();
+            cycle.push(name.to_string());
+            return Err(ProfileError::Cycle(cycle));
+        }
+        let config = configs
+            .iter()
+            .find(|(profile, _)| profile == name)
+            .map(|(_, config)| config);
+        let inherits = config.and_then(|config| config.inherits.as_deref());
+        let mut profile = match (name, inherits) {
+            (_, Some(parent)) => {
+                inheriting.push(name.to_string());
+                let profile = Profile::resolve_inheriting(parent, configs, inheriting)?;
+                inheriting.pop();
+                profile
+            }
+            ("debug", None) => Profile::debug(),
+            ("release", None) => Profile::release(),
+            (_, None) if config.is_some() => Profile::debug(),
+            (_, None) => return Err(ProfileError::Unknown(name.to_string())),
+        };
+        profile.name = name.to_string();
+        if let Some(config) = config {
+            profile.opt_level = config.opt_level.unwrap_or(profile.opt_level);
+            profile.debug_info = config.debug_info.unwrap_or(profile.debug_info);
+            profile.assertions = config.assertions.unwrap_or(profile.assertions);
+            profile.overflow_checks = config.overflow_checks.unwrap_or(profile.overflow_checks);
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_profiles_inherit_settings() {
+        let configs = vec![
+            (
+                "release".to_string(),
+                ProfileConfig {
+                    overflow_checks: Some(true),
+                    ..ProfileConfig::default()
+                },
+            ),
+            (
+                "profiling".to_string(),
+                ProfileConfig {
+                    inherits: Some("release".to_string()),
+                    debug_info: Some(true),
+                    ..ProfileConfig::default()
+                },
+            ),
+            (
+                "fast-debug".to_string(),
+                ProfileConfig {
+                    opt_level: Some(OptLevel::Less),
+                    ..ProfileConfig::default()
+                },
+            ),
+        ];
+        assert_eq!(
+            Profile::resolve("profiling", &configs),
+            Ok(Profile {
+                name: "profiling".to_string(),
+                debug_info: true,
+                overflow_checks: true,
+                ..Profile::release()
+            })
+        );
+        assert_eq!(
+            Profile::resolve("fast-debug", &configs),
+            Ok(Profile {
+                name: "fast-debug".to_string(),
+                opt_level: OptLevel::Less,
+                ..Profile::debug()
+            })
+        );
+        assert_eq!(Profile::resolve("debug", &[]), Ok(Profile::debug()));
+        assert_eq!(
+            Profile::resolve("bench", &configs),
+            Err(ProfileError::Unknown("bench".to_string()))
+        );
+
+        let cyclic = vec![
+            (
+                "a".to_string(),
+                ProfileConfig {
+                    inherits: Some("b".to_string()),
+                    ..ProfileConfig::default()
+                },
+            ),
+            (
+                "b".to_string(),
+                ProfileConfig {
+                    inherits: Some("a".to_string()),
+                    ..ProfileConfig::default()
+                },
+            ),
+        ];
+        assert_eq!(
+            Profile::resolve("a", &cyclic),
+            Err(ProfileError::Cycle(vec![
+                "a".to_string(),
+                "b".to_string(),
+                "a".to_string()
+            ]))
+        );
+    }
+}
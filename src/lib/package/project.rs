@@ -0,0 +1,105 @@
+use crate::build::normalize;
+use crate::package::manifest::{Manifest, ManifestError, MANIFEST_FILE};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A package on disk: its manifest and the directory holding it, which the
+/// manifest's paths are relative to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Project {
+    pub root: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Project {
+    /// Reads the package whose `package.yaml` is in `root`.
+    pub fn read<P: AsRef<Path>>(root: P) -> Result<Self, ManifestError> {
+        let root = root.as_ref().to_path_buf();
+        let manifest = Manifest::read(root.join(MANIFEST_FILE))?;
+        Ok(Project { root, manifest })
+    }
+
+    /// Finds the package `directory` is in, looking for a `package.yaml` in
+    /// it and then in each of its parents.
+    pub fn discover<P: AsRef<Path>>(directory: P) -> Result<Option<Self>, ManifestError> {
+        let directory =
+            directory
+                .as_ref()
+                .canonicalize()
+                .map_err(|source| ManifestError::CouldNotRead {
+                    path: directory.as_ref().to_path_buf(),
+                    source,
+                })?;
+        for root in directory.ancestors() {
+            if root.join(MANIFEST_FILE).is_file() {
+                return Project::read(root).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The main file, if the manifest names one.
+    pub fn main(&self) -> Option<PathBuf> {
+        self.manifest.main.as_ref().map(|main| self.root.join(main))
+    }
+
+    /// The files matched by the manifest's `scripts` patterns, sorted and
+    /// without the main file. Patterns are relative to the package root.
+    pub fn scripts(&self) -> io::Result<Vec<PathBuf>> {
+        let main = self.main().map(|main| normalize(&main));
+        let mut scripts = vec![];
+        for pattern in &self.manifest.scripts {
+            let pattern = self.root.join(pattern);
+            let paths = glob::glob(&pattern.to_string_lossy())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            for path in paths {
+                let path = path.map_err(io::Error::from)?;
+                if path.is_file() && Some(&path) != main.as_ref() {
+                    scripts.push(path);
+                }
+            }
+        }
+        scripts.sort();
+        scripts.dedup();
+        Ok(scripts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::{env, fs};
+
+    #[test]
+    fn test_discover_and_scripts() {
+        let root = env::temp_dir().join("catlang-project-test-scripts");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/scripts/shapes")).unwrap();
+        let write = |path: &str, source: &str| fs::write(root.join(path), source).unwrap();
+        write(
+            MANIFEST_FILE,
+            "name: game\nversion: 1.0.0\nmain: src/main.cat\nscripts:\n  - src/scripts/**/*.cat\n  - src/*.cat\n",
+        );
+        write("src/main.cat", "");
+        write("src/util.cat", "");
+        write("src/scripts/b.cat", "");
+        write("src/scripts/shapes/a.cat", "");
+        write("src/scripts/notes.txt", "");
+
+        let project = Project::discover(root.join("src/scripts"))
+            .unwrap()
+            .unwrap();
+        let root = root.canonicalize().unwrap();
+        assert_eq!(project.root, root);
+        assert_eq!(
+            project.scripts().unwrap(),
+            vec![
+                root.join("src/scripts/b.cat"),
+                root.join("src/scripts/shapes/a.cat"),
+                root.join("src/util.cat"),
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,676 @@
+use crate::build::normalize;
+use crate::package::lockfile::{LockedSource, Lockfile, LockfileError, LOCKFILE};
+use crate::package::manifest::{
+    Dependency, DependencySource, Manifest, ManifestError, PackageName, MANIFEST_FILE,
+};
+use crate::package::workspace::Workspace;
+use semver::{Version, VersionReq};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ResolveError {
+    #[error(transparent)]
+    Manifest(#[from] ManifestError),
+    #[error(transparent)]
+    Lockfile(#[from] LockfileError),
+    #[error("could not read {}: {}", path.display(), source)]
+    CouldNotRead { path: PathBuf, source: io::Error },
+    #[error("no version of {} in the registry matches {}", name, requirement)]
+    NotFound {
+        name: PackageName,
+        requirement: Requirement,
+    },
+    #[error("conflicting requirements for {}: {}, and {}", name, first, second)]
+    Conflict {
+        name: PackageName,
+        first: Requirement,
+        second: Requirement,
+    },
+    #[error("{} holds {} rather than {}", path.display(), found, expected)]
+    NameMismatch {
+        path: PathBuf,
+        expected: PackageName,
+        found: PackageName,
+    },
+    #[error("{} cannot be fetched from a repository yet: {}", name, requirement)]
+    Unsupported {
+        name: PackageName,
+        requirement: Requirement,
+    },
+    #[error(
+        "the contents of {} {} do not match the checksum in {}; it was changed after it was locked",
+        name,
+        version,
+        LOCKFILE
+    )]
+    ChecksumMismatch { name: PackageName, version: Version },
+}
+
+pub type Result<T> = std::result::Result<T, ResolveError>;
+
+/// A dependency on a package, as written in a manifest, and the chain of
+/// packages that led to it from a workspace member.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Requirement {
+    pub source: String,
+    /// Each package of the chain, as `<name> <version>`, starting with a
+    /// workspace member. Members themselves have none.
+    pub chain: Vec<String>,
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.chain.is_empty() {
+            true => f.write_str(&self.source),
+            false => write!(f, "{} required by {}", self.source, self.chain.join(" -> ")),
+        }
+    }
+}
+
+/// A directory of published packages, holding each version of a package in
+/// `<name>/<version>/`, as in `@scope/name/1.2.3/package.yaml`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Registry {
+    root: PathBuf,
+}
+
+impl Registry {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Registry {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// The registry named by `CATLANG_REGISTRY`, or else
+    /// `~/.catlang/registry`.
+    pub fn from_env() -> Self {
+        match env::var_os("CATLANG_REGISTRY") {
+            Some(root) => Registry::new(root),
+            None => {
+                let home = env::var_os("HOME")
+                    .or_else(|| env::var_os("USERPROFILE"))
+                    .unwrap_or_default();
+                Registry::new(PathBuf::from(home).join(".catlang").join("registry"))
+            }
+        }
+    }
+
+    /// The directory holding `version` of `name`.
+    pub fn package(&self, name: &PackageName, version: &Version) -> PathBuf {
+        self.root.join(name.as_str()).join(version.to_string())
+    }
+
+    /// The versions of `name` in the registry, in no particular order.
+    pub fn versions(&self, name: &PackageName) -> io::Result<Vec<Version>> {
+        let entries = match fs::read_dir(self.root.join(name.as_str())) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        let mut versions = vec![];
+        for entry in entries {
+            let entry = entry?;
+            let version = entry.file_name().to_str().map(Version::parse);
+            if let Some(Ok(version)) = version {
+                if entry.path().join(MANIFEST_FILE).is_file() {
+                    versions.push(version);
+                }
+            }
+        }
+        Ok(versions)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageSource {
+    /// A member of the workspace being resolved.
+    Root,
+    /// A local directory.
+    Path,
+    Registry,
+}
+
+/// A package of a resolved dependency graph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Package {
+    pub name: PackageName,
+    pub version: Version,
+    pub source: PackageSource,
+    /// The directory holding the package's manifest.
+    pub root: PathBuf,
+    /// The module importing the package by its name alone refers to.
+    pub main: Option<PathBuf>,
+    /// The hash of the package's files, as `sha256:<hex>`. Workspace
+    /// members have none.
+    pub checksum: Option<String>,
+    /// The packages it can import.
+    pub dependencies: Vec<PackageName>,
+    /// The features it is built with when another package depends on it:
+    /// its default ones.
+    pub features: Vec<String>,
+    /// The packages that led to it from a workspace member, for errors.
+    chain: Vec<String>,
+}
+
+impl Package {
+    fn describe(&self) -> String {
+        format!("{} {}", self.name, self.version)
+    }
+}
+
+/// The packages the members of a workspace depend on, directly or not, with
+/// a single version of each.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resolution {
+    /// The workspace members, followed by their dependencies in the order
+    /// they were reached.
+    pub packages: Vec<Package>,
+}
+
+impl Resolution {
+    pub fn package(&self, name: &str) -> Option<&Package> {
+        self.packages
+            .iter()
+            .find(|package| package.name.as_str() == name)
+    }
+
+    /// The package the file `module` is in: the one with the closest
+    /// enclosing root.
+    pub fn package_of(&self, module: &Path) -> Option<&Package> {
+        self.packages
+            .iter()
+            .filter(|package| module.starts_with(&package.root))
+            .max_by_key(|package| package.root.components().count())
+    }
+
+    /// Resolves an import of `module` that names a package it depends on,
+    /// such as `"@scope/package/module"`, to the file it refers to. Importing
+    /// a package by its name alone refers to its main file.
+    pub fn resolve_import(&self, module: &Path, import: &str) -> Option<PathBuf> {
+        let importer = self.package_of(module)?;
+        let segments = if import.starts_with('@') { 2 } else { 1 };
+        let mut parts = import.splitn(segments + 1, '/');
+        let name: Vec<&str> = parts.by_ref().take(segments).collect();
+        let name = name.join("/");
+        if !importer.dependencies.iter().any(|d| d.as_str() == name) {
+            return None;
+        }
+        let dependency = self.package(&name)?;
+        match parts.next() {
+            None | Some("") => dependency.main.clone(),
+            Some(path) => {
+                let mut path = normalize(&dependency.root.join(path));
+                if path.extension().is_none() {
+                    path.set_extension("cat");
+                }
+                Some(path)
+            }
+        }
+    }
+}
+
+/// Resolves the dependencies of `workspace`, preferring the versions pinned
+/// by its `catlang.lock`, and writes the lockfile if they changed.
+pub fn resolve_locked(workspace: &Workspace, registry: &Registry) -> Result<Resolution> {
+    let path = workspace.lockfile();
+    let lockfile = Lockfile::read(&path)?;
+    let resolution = resolve(workspace, registry, lockfile.as_ref())?;
+    let updated = Lockfile::new(&resolution);
+    if lockfile.as_ref() != Some(&updated) {
+        updated.write(&path)?;
+    }
+    Ok(resolution)
+}
+
+/// Resolves the dependencies of the members of `workspace`, and their
+/// development dependencies, to a single version of each package. A
+/// dependency on a member resolves to the member. Registry dependencies resolve to the
+/// newest version meeting every requirement on them, unless `lockfile` pins
+/// one that does, in which case its contents must still match the pinned
+/// checksum.
+pub fn resolve(
+    workspace: &Workspace,
+    registry: &Registry,
+    lockfile: Option<&Lockfile>,
+) -> Result<Resolution> {
+    let mut resolver = Resolver {
+        registry,
+        lockfile,
+        constraints: HashMap::new(),
+    };
+    let resolution = loop {
+        match resolver.attempt(workspace)? {
+            Attempt::Resolved(resolution) => break resolution,
+            Attempt::Retry(name, requirement) => {
+                resolver
+                    .constraints
+                    .entry(name)
+                    .or_default()
+                    .push(requirement);
+            }
+        }
+    };
+
+    for package in &resolution.packages {
+        let locked = lockfile.and_then(|lockfile| lockfile.package(package.name.as_str()));
+        if let Some(locked) = locked {
+            let pinned = locked.source == Some(LockedSource::Registry)
+                && package.source == PackageSource::Registry
+                && locked.version == package.version;
+            if pinned && locked.checksum.is_some() && locked.checksum != package.checksum {
+                return Err(ResolveError::ChecksumMismatch {
+                    name: package.name.clone(),
+                    version: package.version.clone(),
+                });
+            }
+        }
+    }
+    Ok(resolution)
+}
+
+struct Resolver<'a> {
+    registry: &'a Registry,
+    lockfile: Option<&'a Lockfile>,
+    /// Requirements every version of a package is chosen to meet, learned
+    /// from attempts that chose one meeting too few.
+    constraints: HashMap<PackageName, Vec<VersionReq>>,
+}
+
+enum Attempt {
+    Resolved(Resolution),
+    /// A version was chosen that a later requirement rules out, although
+    /// another meets both; the resolution starts over, constrained by it.
+    Retry(PackageName, VersionReq),
+}
+
+impl<'a> Resolver<'a> {
+    fn attempt(&self, workspace: &Workspace) -> Result<Attempt> {
+        let mut packages: Vec<Package> = workspace
+            .members
+            .iter()
+            .map(|member| Package {
+                name: member.manifest.name.clone(),
+                version: member.manifest.version.clone(),
+                source: PackageSource::Root,
+                root: member.root.clone(),
+                main: member.main(),
+                checksum: None,
+                dependencies: vec![],
+                features: member
+                    .manifest
+                    .enabled_features(&[], true)
+                    .unwrap_or_default(),
+                chain: vec![],
+            })
+            .collect();
+        let mut manifests: Vec<Manifest> = workspace
+            .members
+            .iter()
+            .map(|member| member.manifest.clone())
+            .collect();
+        let mut requirements: HashMap<PackageName, Vec<(Dependency, Requirement)>> = HashMap::new();
+        let mut next = 0;
+        while next < packages.len() {
+            let mut dependencies = manifests[next].dependencies.clone();
+            if packages[next].source == PackageSource::Root {
+                dependencies.extend(manifests[next].dev_dependencies.iter().cloned());
+            }
+            let mut chain = packages[next].chain.clone();
+            chain.push(packages[next].describe());
+            for dependency in &dependencies {
+                let requirement = Requirement {
+                    source: dependency.source.to_string(),
+                    chain: chain.clone(),
+                };
+                let existing = packages
+                    .iter()
+                    .find(|package| package.name == dependency.name);
+                match existing {
+                    Some(existing) => {
+                        let earlier = requirements
+                            .get(&dependency.name)
+                            .map(Vec::as_slice)
+                            .unwrap_or_default();
+                        let base = &packages[next].root;
+                        if let Some(retry) =
+                            self.check(existing, dependency, base, earlier, &requirement)?
+                        {
+                            return Ok(Attempt::Retry(dependency.name.clone(), retry));
+                        }
+                    }
+                    None => {
+                        let (package, manifest) =
+                            self.fetch(dependency, &packages[next].root, &requirement)?;
+                        packages.push(package);
+                        manifests.push(manifest);
+                    }
+                }
+                requirements
+                    .entry(dependency.name.clone())
+                    .or_default()
+                    .push((dependency.clone(), requirement));
+                packages[next].dependencies.push(dependency.name.clone());
+            }
+            next += 1;
+        }
+        Ok(Attempt::Resolved(Resolution { packages }))
+    }
+
+    /// Checks that a package already in the graph meets another requirement
+    /// on it, returning the requirement if the resolution should start over
+    /// to choose a version meeting it as well.
+    fn check(
+        &self,
+        existing: &Package,
+        dependency: &Dependency,
+        base: &Path,
+        earlier: &[(Dependency, Requirement)],
+        requirement: &Requirement,
+    ) -> Result<Option<VersionReq>> {
+        let conflict = || ResolveError::Conflict {
+            name: dependency.name.clone(),
+            first: match earlier.first() {
+                Some((_, first)) => first.clone(),
+                None => Requirement {
+                    source: format!("{} in the workspace", existing.describe()),
+                    chain: vec![],
+                },
+            },
+            second: requirement.clone(),
+        };
+        match (&dependency.source, existing.source) {
+            (DependencySource::Registry(req), _) if req.matches(&existing.version) => Ok(None),
+            (DependencySource::Registry(req), PackageSource::Registry) => {
+                let mut all: Vec<&VersionReq> = earlier
+                    .iter()
+                    .filter_map(|(dependency, _)| match &dependency.source {
+                        DependencySource::Registry(req) => Some(req),
+                        _ => None,
+                    })
+                    .collect();
+                all.push(req);
+                match self.best_version(&dependency.name, &all)? {
+                    Some(_) => Ok(Some(req.clone())),
+                    None => Err(conflict()),
+                }
+            }
+            (DependencySource::Path(path), PackageSource::Path | PackageSource::Root) => {
+                let root = canonicalize(&base.join(path))?;
+                match root == existing.root {
+                    true => Ok(None),
+                    false => Err(conflict()),
+                }
+            }
+            _ => Err(conflict()),
+        }
+    }
+
+    /// Finds the package `dependency` names, and reads its manifest.
+    fn fetch(
+        &self,
+        dependency: &Dependency,
+        base: &Path,
+        requirement: &Requirement,
+    ) -> Result<(Package, Manifest)> {
+        let name = &dependency.name;
+        let (root, source) = match &dependency.source {
+            DependencySource::Path(path) => (canonicalize(&base.join(path))?, PackageSource::Path),
+            DependencySource::Registry(req) => {
+                let version =
+                    self.best_version(name, &[req])?
+                        .ok_or_else(|| ResolveError::NotFound {
+                            name: name.clone(),
+                            requirement: requirement.clone(),
+                        })?;
+                (
+                    self.registry.package(name, &version),
+                    PackageSource::Registry,
+                )
+            }
+            DependencySource::Repository { .. } => {
+                return Err(ResolveError::Unsupported {
+                    name: name.clone(),
+                    requirement: requirement.clone(),
+                })
+            }
+        };
+        let manifest = Manifest::read(root.join(MANIFEST_FILE))?;
+        if manifest.name != *name {
+            return Err(ResolveError::NameMismatch {
+                path: root,
+                expected: name.clone(),
+                found: manifest.name,
+            });
+        }
+        let checksum = checksum(&root).map_err(|source| ResolveError::CouldNotRead {
+            path: root.clone(),
+            source,
+        })?;
+        let package = Package {
+            name: name.clone(),
+            version: manifest.version.clone(),
+            source,
+            main: manifest.main.as_ref().map(|main| root.join(main)),
+            root,
+            checksum: Some(checksum),
+            dependencies: vec![],
+            features: manifest.enabled_features(&[], true).unwrap_or_default(),
+            chain: requirement.chain.clone(),
+        };
+        Ok((package, manifest))
+    }
+
+    /// The version of `name` in the registry to use, which meets
+    /// `requirements` and every constraint learned on it: the one pinned by
+    /// the lockfile if it does, and otherwise the newest.
+    fn best_version(
+        &self,
+        name: &PackageName,
+        requirements: &[&VersionReq],
+    ) -> Result<Option<Version>> {
+        let versions =
+            self.registry
+                .versions(name)
+                .map_err(|source| ResolveError::CouldNotRead {
+                    path: self.registry.root.join(name.as_str()),
+                    source,
+                })?;
+        let constraints = self.constraints.get(name).map(Vec::as_slice);
+        let matching: Vec<Version> = versions
+            .into_iter()
+            .filter(|version| {
+                requirements.iter().all(|req| req.matches(version))
+                    && constraints
+                        .unwrap_or_default()
+                        .iter()
+                        .all(|req| req.matches(version))
+            })
+            .collect();
+        let locked = self
+            .lockfile
+            .and_then(|lockfile| lockfile.package(name.as_str()))
+            .filter(|locked| locked.source == Some(LockedSource::Registry));
+        if let Some(locked) = locked {
+            if matching.contains(&locked.version) {
+                return Ok(Some(locked.version.clone()));
+            }
+        }
+        Ok(matching.into_iter().max())
+    }
+}
+
+fn canonicalize(path: &Path) -> Result<PathBuf> {
+    path.canonicalize()
+        .map_err(|source| ResolveError::CouldNotRead {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Hashes the files of the package in `root` along with their paths
+/// relative to it. Hidden files and the `target` directory are left out.
+fn checksum(root: &Path) -> io::Result<String> {
+    let mut files = vec![];
+    find_files(root, root, &mut files)?;
+    files.sort();
+    let mut hasher = Sha256::new();
+    for file in &files {
+        let path = file.strip_prefix(root).unwrap_or(file);
+        let contents = fs::read(file)?;
+        hasher.update(path.to_string_lossy().replace('\\', "/").as_bytes());
+        hasher.update([0]);
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+    }
+    Ok(format!("sha256:{:x}", hasher.finalize()))
+}
+
+fn find_files(root: &Path, directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || (directory == root && name == "target") {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            find_files(root, &entry.path(), files)?;
+        } else {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::project::Project;
+    use pretty_assertions::assert_eq;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory.canonicalize().unwrap()
+    }
+
+    fn package(root: &Path, name: &str, version: &str, dependencies: &str) {
+        fs::create_dir_all(root.join("src")).unwrap();
+        let manifest = format!(
+            "name: \"{}\"\nversion: {}\nmain: src/lib.cat\ndependencies:\n{}",
+            name, version, dependencies
+        );
+        fs::write(root.join(MANIFEST_FILE), manifest).unwrap();
+        fs::write(
+            root.join("src/lib.cat"),
+            format!("// {} {}\n", name, version),
+        )
+        .unwrap();
+    }
+
+    fn versions(resolution: &Resolution) -> Vec<String> {
+        resolution.packages.iter().map(Package::describe).collect()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let directory = temp_dir("catlang-resolve-test");
+        let registry = Registry::new(directory.join("registry"));
+        for version in &["1.2.0", "1.5.0", "2.0.0"] {
+            let root = registry.package(&"yaml".parse().unwrap(), &version.parse().unwrap());
+            package(&root, "yaml", version, "  {}");
+        }
+        let colors = directory.join("registry/@me/colors/1.0.0");
+        package(
+            &colors,
+            "@me/colors",
+            "1.0.0",
+            "  yaml: \">=1.0.0, <1.4.0\"",
+        );
+        package(&directory.join("local"), "local", "0.2.0", "  yaml: ^1.0.0");
+        package(
+            &directory.join("app"),
+            "app",
+            "0.1.0",
+            "  - local: file:../local\n  - \"@me/colors\": ^1.0.0\n",
+        );
+        let workspace = Workspace::single(Project::read(directory.join("app")).unwrap());
+
+        // `local` reaches yaml first, but 1.5.0 is too new for colors.
+        let resolution = resolve(&workspace, &registry, None).unwrap();
+        assert_eq!(
+            versions(&resolution),
+            vec!["app 0.1.0", "local 0.2.0", "@me/colors 1.0.0", "yaml 1.2.0"]
+        );
+        let main = directory.join("app/src/lib.cat");
+        assert_eq!(
+            resolution.resolve_import(&main, "@me/colors/src/palette"),
+            Some(colors.join("src/palette.cat"))
+        );
+        assert_eq!(resolution.resolve_import(&main, "yaml"), None);
+        assert_eq!(
+            resolution.resolve_import(&directory.join("local/src/lib.cat"), "yaml"),
+            Some(directory.join("registry/yaml/1.2.0/src/lib.cat"))
+        );
+
+        // Newer versions are only picked up once the lockfile is removed.
+        resolve_locked(&workspace, &registry).unwrap();
+        let root = registry.package(&"yaml".parse().unwrap(), &"1.3.0".parse().unwrap());
+        package(&root, "yaml", "1.3.0", "  {}");
+        let lockfile = Lockfile::read(directory.join("app").join(LOCKFILE)).unwrap();
+        let yaml = lockfile.as_ref().unwrap().package("yaml").unwrap();
+        assert_eq!(yaml.version, Version::new(1, 2, 0));
+        assert!(yaml.checksum.as_ref().unwrap().starts_with("sha256:"));
+        let resolution = resolve(&workspace, &registry, lockfile.as_ref()).unwrap();
+        assert_eq!(
+            resolution.package("yaml").unwrap().version,
+            Version::new(1, 2, 0)
+        );
+        let resolution = resolve(&workspace, &registry, None).unwrap();
+        assert_eq!(
+            resolution.package("yaml").unwrap().version,
+            Version::new(1, 3, 0)
+        );
+
+        // Changing a locked package is caught.
+        fs::write(directory.join("registry/yaml/1.2.0/src/lib.cat"), "").unwrap();
+        assert!(matches!(
+            resolve(&workspace, &registry, lockfile.as_ref()),
+            Err(ResolveError::ChecksumMismatch { .. })
+        ));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_conflicts_name_both_chains() {
+        let directory = temp_dir("catlang-resolve-test-conflict");
+        let registry = Registry::new(directory.join("registry"));
+        for version in &["1.0.0", "2.0.0"] {
+            let root = registry.package(&"yaml".parse().unwrap(), &version.parse().unwrap());
+            package(&root, "yaml", version, "  {}");
+        }
+        package(&directory.join("a"), "a", "1.0.0", "  yaml: ^1.0.0");
+        package(&directory.join("b"), "b", "1.0.0", "  yaml: ^2.0.0");
+        package(
+            &directory.join("app"),
+            "app",
+            "0.1.0",
+            "  - a: file:../a\n  - b: file:../b\n",
+        );
+        let workspace = Workspace::single(Project::read(directory.join("app")).unwrap());
+        let error = resolve(&workspace, &registry, None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "conflicting requirements for yaml: ^1.0.0 required by app 0.1.0 -> a 1.0.0, and ^2.0.0 required by app 0.1.0 -> b 1.0.0"
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+use crate::package::lockfile::LOCKFILE;
+use crate::package::manifest::{ManifestError, ProfileConfig, MANIFEST_FILE};
+use crate::package::project::Project;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum WorkspaceError {
+    #[error(transparent)]
+    Manifest(#[from] ManifestError),
+    #[error("invalid workspace member pattern {:?}: {}", pattern, message)]
+    InvalidPattern { pattern: String, message: String },
+    #[error("could not read {}: {}", path.display(), source)]
+    CouldNotRead { path: PathBuf, source: io::Error },
+    #[error("{} and {} are both named {}", first.display(), second.display(), name)]
+    DuplicateMember {
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+    #[error("the workspace has no package named {}", .0)]
+    UnknownMember(String),
+    #[error("workspace members depend on each other in a cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+}
+
+pub type Result<T> = std::result::Result<T, WorkspaceError>;
+
+/// Packages built together, sharing one lockfile and one target directory.
+/// A project that is not in a workspace is a workspace of its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Workspace {
+    /// The directory holding the workspace's `package.yaml`.
+    pub root: PathBuf,
+    /// The member packages, sorted by directory. The root package is a
+    /// member if it has a main file.
+    pub members: Vec<Project>,
+    /// The profiles declared by the root's manifest. Those declared by
+    /// other members are ignored.
+    pub profiles: Vec<(String, ProfileConfig)>,
+}
+
+impl Workspace {
+    /// The workspace of `project` alone.
+    pub fn single(project: Project) -> Self {
+        Workspace {
+            root: project.root.clone(),
+            profiles: project.manifest.profiles.clone(),
+            members: vec![project],
+        }
+    }
+
+    /// Reads the workspace rooted at `root`, whose manifest's `workspace`
+    /// section lists the members. A manifest without one is a single
+    /// package.
+    pub fn read<P: AsRef<Path>>(root: P) -> Result<Self> {
+        Workspace::from_root(Project::read(root)?)
+    }
+
+    fn from_root(project: Project) -> Result<Self> {
+        let config = match &project.manifest.workspace {
+            Some(config) => config.clone(),
+            None => return Ok(Workspace::single(project)),
+        };
+        let mut members: Vec<Project> = vec![];
+        for pattern in &config.members {
+            let directories =
+                glob::glob(&project.root.join(pattern).to_string_lossy()).map_err(|e| {
+                    WorkspaceError::InvalidPattern {
+                        pattern: pattern.clone(),
+                        message: e.to_string(),
+                    }
+                })?;
+            for directory in directories {
+                let directory = directory.map_err(|e| WorkspaceError::CouldNotRead {
+                    path: e.path().to_path_buf(),
+                    source: e.into(),
+                })?;
+                if !directory.join(MANIFEST_FILE).is_file() {
+                    continue;
+                }
+                let directory =
+                    directory
+                        .canonicalize()
+                        .map_err(|source| WorkspaceError::CouldNotRead {
+                            path: directory.clone(),
+                            source,
+                        })?;
+                if directory != project.root && members.iter().all(|m| m.root != directory) {
+                    members.push(Project::read(directory)?);
+                }
+            }
+        }
+        if project.manifest.main.is_some() {
+            members.push(project.clone());
+        }
+        members.sort_by(|a, b| a.root.cmp(&b.root));
+        for (i, member) in members.iter().enumerate() {
+            if let Some(other) = members[..i]
+                .iter()
+                .find(|other| other.manifest.name == member.manifest.name)
+            {
+                return Err(WorkspaceError::DuplicateMember {
+                    name: member.manifest.name.to_string(),
+                    first: other.root.clone(),
+                    second: member.root.clone(),
+                });
+            }
+        }
+        Ok(Workspace {
+            root: project.root,
+            members,
+            profiles: project.manifest.profiles,
+        })
+    }
+
+    /// Finds the workspace of the package `directory` is in: the closest
+    /// enclosing workspace listing the package as a member, or else the
+    /// package alone.
+    pub fn discover<P: AsRef<Path>>(directory: P) -> Result<Option<Self>> {
+        let project = match Project::discover(directory)? {
+            Some(project) => project,
+            None => return Ok(None),
+        };
+        for root in project.root.ancestors() {
+            if !root.join(MANIFEST_FILE).is_file() {
+                continue;
+            }
+            let candidate = match root == project.root {
+                true => project.clone(),
+                false => Project::read(root)?,
+            };
+            if candidate.manifest.workspace.is_none() {
+                continue;
+            }
+            let workspace = Workspace::from_root(candidate)?;
+            if root == project.root || workspace.members.iter().any(|m| m.root == project.root) {
+                return Ok(Some(workspace));
+            }
+        }
+        Ok(Some(Workspace::single(project)))
+    }
+
+    pub fn member(&self, name: &str) -> Option<&Project> {
+        self.members
+            .iter()
+            .find(|member| member.manifest.name.as_str() == name)
+    }
+
+    /// Where builds with `profile`, such as `debug` or `release`, are written.
+    pub fn target_directory(&self, profile: &str) -> PathBuf {
+        self.root.join("target").join(profile)
+    }
+
+    pub fn lockfile(&self) -> PathBuf {
+        self.root.join(LOCKFILE)
+    }
+
+    /// The members, each after the members it depends on.
+    pub fn build_order(&self) -> Result<Vec<&Project>> {
+        let mut order = vec![];
+        let mut visiting = vec![];
+        for member in &self.members {
+            self.visit(member, &mut visiting, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    fn visit<'a>(
+        &'a self,
+        member: &'a Project,
+        visiting: &mut Vec<&'a Project>,
+        order: &mut Vec<&'a Project>,
+    ) -> Result<()> {
+        if order.contains(&member) {
+            return Ok(());
+        }
+        if let Some(start) = visiting.iter().position(|m| *m == member) {
+            let mut cycle: Vec<String> = visiting[start..]
+                .iter()
+                .map(|m| m.manifest.name.to_string())
+                .collect();
+            cycle.push(member.manifest.name.to_string());
+            return Err(WorkspaceError::Cycle(cycle));
+        }
+        visiting.push(member);
+        let manifest = &member.manifest;
+        for dependency in manifest
+            .dependencies
+            .iter()
+            .chain(&manifest.dev_dependencies)
+        {
+            if let Some(dependency) = self.member(dependency.name.as_str()) {
+                self.visit(dependency, visiting, order)?;
+            }
+        }
+        visiting.pop();
+        order.push(member);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::resolve::{resolve, Registry};
+    use pretty_assertions::assert_eq;
+    use std::{env, fs};
+
+    #[test]
+    fn test_members_are_built_in_dependency_order() {
+        let root = env::temp_dir().join("catlang-workspace-test");
+        let _ = fs::remove_dir_all(&root);
+        let write = |path: &str, source: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        };
+        write(
+            MANIFEST_FILE,
+            "name: monorepo\nversion: 0.0.0\nprivate: true\nworkspace:\n  members: [packages/*]\n",
+        );
+        write(
+            "packages/app/package.yaml",
+            "name: app\nversion: 0.1.0\nmain: main.cat\ndependencies:\n  - \"@me/util\": ^1.0.0\n  - shapes: file:../shapes\n",
+        );
+        write(
+            "packages/shapes/package.yaml",
+            "name: shapes\nversion: 0.1.0\nmain: lib.cat\ndependencies:\n  \"@me/util\": file:../util\n",
+        );
+        write(
+            "packages/util/package.yaml",
+            "name: \"@me/util\"\nversion: 1.0.0\nmain: lib.cat\n",
+        );
+        write("packages/notes.txt", "");
+
+        let workspace = Workspace::discover(root.join("packages/shapes"))
+            .unwrap()
+            .unwrap();
+        let root = root.canonicalize().unwrap();
+        assert_eq!(workspace.root, root);
+        assert_eq!(workspace.lockfile(), root.join("catlang.lock"));
+        let names = |members: Vec<&Project>| -> Vec<String> {
+            members
+                .iter()
+                .map(|member| member.manifest.name.to_string())
+                .collect()
+        };
+        assert_eq!(
+            names(workspace.members.iter().collect()),
+            vec!["app", "shapes", "@me/util"]
+        );
+        assert_eq!(
+            names(workspace.build_order().unwrap()),
+            vec!["@me/util", "shapes", "app"]
+        );
+        assert!(workspace.member("shapes").is_some());
+
+        // Members depend on each other rather than on the registry.
+        let registry = Registry::new(root.join("registry"));
+        let resolution = resolve(&workspace, &registry, None).unwrap();
+        assert_eq!(resolution.packages.len(), 3);
+        assert_eq!(
+            resolution.package("app").unwrap().dependencies,
+            vec!["@me/util".parse().unwrap(), "shapes".parse().unwrap()]
+        );
+
+        write(
+            "packages/util/package.yaml",
+            "name: \"@me/util\"\nversion: 1.0.0\ndependencies:\n  app: ^0.1.0\n",
+        );
+        let workspace = Workspace::read(&root).unwrap();
+        assert!(matches!(
+            workspace.build_order(),
+            Err(WorkspaceError::Cycle(cycle)) if cycle == vec!["app", "@me/util", "app"]
+        ));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+//! Conditional compilation with `#[cfg(...)]`.
+//!
+//! A top-level declaration marked `#[cfg(predicate)]` is only compiled when
+//! the predicate holds for the build. Predicates are:
+//!
+//! - a name, like `debug` or `unix`, which holds when the build sets it;
+//! - `name = "value"`, like `target_os = "linux"` or `feature = "json"`,
+//!   which holds when the build gives `name` that value;
+//! - `!p`, `p && q` and `p || q`, where values are compared with `==`, as
+//!   in `unix && target_arch == "x86_64"`, since `=` binds looser than
+//!   `&&`.
+
+use crate::semantic::error::{Error, Result};
+use crate::syntax::ast::*;
+use toolshed::list::GrowableList;
+use toolshed::Arena;
+
+/// The names and values `#[cfg]` predicates are checked against.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    flags: Vec<String>,
+    values: Vec<(String, String)>,
+}
+
+impl Config {
+    /// The configuration of builds for the LLVM target `triple`, like
+    /// `x86_64-unknown-linux-gnu`. It sets `target_arch`, `target_vendor`,
+    /// `target_os`, `target_env` and `target_family`, and `unix` or
+    /// `windows` for targets of those families.
+    pub fn for_target(triple: &str) -> Self {
+        let mut config = Config::default();
+        let parts: Vec<&str> = triple.split('-').collect();
+        let (arch, vendor, os, env) = match parts.as_slice() {
+            [arch, vendor, os, env, ..] => (*arch, *vendor, *os, *env),
+            [arch, vendor, os] => (*arch, *vendor, *os, ""),
+            [arch, os] => (*arch, "unknown", *os, ""),
+            _ => (triple, "unknown", "none", ""),
+        };
+        let os = match os {
+            "darwin" | "macosx" => "macos",
+            "unknown" => "none",
+            os if os.starts_with("macos") => "macos",
+            os if os.starts_with("ios") => "ios",
+            os => os,
+        };
+        let family = match os {
+            "windows" => Some("windows"),
+            "linux" | "macos" | "ios" | "android" | "freebsd" | "netbsd" | "openbsd"
+            | "dragonfly" | "solaris" | "illumos" => Some("unix"),
+            _ if arch.starts_with("wasm") => Some("wasm"),
+            _ => None,
+        };
+        config.value("target_arch", arch);
+        config.value("target_vendor", vendor);
+        config.value("target_os", os);
+        config.value("target_env", env);
+        if let Some(family) = family {
+            config.value("target_family", family);
+            if family != "wasm" {
+                config.flag(family);
+            }
+        }
+        config
+    }
+
+    /// Sets the name `flag`.
+    pub fn flag(&mut self, flag: &str) -> &mut Self {
+        if !self.flags.iter().any(|f| f == flag) {
+            self.flags.push(flag.to_string());
+        }
+        self
+    }
+
+    /// Gives `name` the value `value`, on top of any it already has.
+    pub fn value(&mut self, name: &str, value: &str) -> &mut Self {
+        let entry = (name.to_string(), value.to_string());
+        if !self.values.contains(&entry) {
+            self.values.push(entry);
+        }
+        self
+    }
+
+    /// Turns on the feature `feature`.
+    pub fn feature(&mut self, feature: &str) -> &mut Self {
+        self.value("feature", feature)
+    }
+
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    fn has_value(&self, name: &str, value: &str) -> bool {
+        self.values.iter().any(|(n, v)| n == name && v == value)
+    }
+
+    /// Whether the predicate `expression` holds.
+    fn holds(&self, expression: &ExpressionNode) -> Result<bool> {
+        let invalid = |reason: &str| Error::InvalidAttribute {
+            name: "cfg".to_string(),
+            reason: reason.to_string(),
+            span: expression.start as usize..expression.end as usize,
+        };
+        match expression.value {
+            Expression::IdentifierExpression(identifier) => Ok(self.has_flag(identifier.value)),
+            Expression::AssignmentExpression(AssignmentExpression {
+                left,
+                operator: AssignmentOperator::Plain,
+                right,
+            })
+            | Expression::BinaryExpression(BinaryExpression {
+                left,
+                operator: BinaryOperator::Equals,
+                right,
+            }) => match (left.value, right.value) {
+                (
+                    Expression::IdentifierExpression(name),
+                    Expression::PrimitiveExpression(Primitive::String(value)),
+                ) => Ok(self.has_value(name.value, value.trim_matches('"'))),
+                _ => Err(invalid("expected `name = \"value\"`")),
+            },
+            Expression::BinaryExpression(BinaryExpression {
+                left,
+                operator: BinaryOperator::LogicalAnd,
+                right,
+            }) => Ok(self.holds(&left)? && self.holds(&right)?),
+            Expression::BinaryExpression(BinaryExpression {
+                left,
+                operator: BinaryOperator::LogicalOr,
+                right,
+            }) => Ok(self.holds(&left)? || self.holds(&right)?),
+            Expression::PrefixExpression(PrefixExpression {
+                operator: PrefixOperator::LogicalNot,
+                operand,
+            }) => Ok(!self.holds(&operand)?),
+            _ => Err(invalid(
+                "expected a name, `name = \"value\"`, `!`, `&&` or `||`",
+            )),
+        }
+    }
+
+    /// Whether a declaration with `attributes` is compiled: every `#[cfg]`
+    /// among them must take a single predicate that holds.
+    pub fn enables(&self, attributes: AttributeList) -> Result<bool> {
+        let mut enabled = true;
+        for attribute in attributes.iter() {
+            if attribute.value.identifier.value != "cfg" {
+                continue;
+            }
+            let parameters: Vec<_> = attribute.value.parameters.iter().collect();
+            match parameters.as_slice() {
+                [predicate] => enabled &= self.holds(predicate)?,
+                _ => {
+                    return Err(Error::InvalidAttribute {
+                        name: "cfg".to_string(),
+                        reason: "expected a single predicate, as in `#[cfg(debug)]`".to_string(),
+                        span: attribute.start as usize..attribute.end as usize,
+                    })
+                }
+            }
+        }
+        Ok(enabled)
+    }
+}
+
+/// The module without the top-level declarations `config` disables, which
+/// are dropped before they are analyzed. The elements kept are shared with
+/// `module`.
+pub fn strip<'ast>(
+    arena: &'ast Arena,
+    module: &Module<'ast>,
+    config: &Config,
+) -> Result<Module<'ast>> {
+    let elements = GrowableList::new();
+    for element in module.elements.iter() {
+        let declaration = match element.value {
+            ModuleElement::Declaration(declaration) => Some(declaration),
+            ModuleElement::Export(export) => match export.value {
+                Export::Declaration(declaration) => Some(declaration),
+                _ => None,
+            },
+            ModuleElement::Import(_) | ModuleElement::Statement(_) => None,
+        };
+        let enabled = match declaration {
+            Some(declaration) => config.enables(declaration.value.attributes)?,
+            None => true,
+        };
+        if enabled {
+            elements.push(arena, *element);
+        }
+    }
+    Ok(Module {
+        elements: elements.as_list(),
+        is_script: module.is_script,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::module_declarations;
+    use crate::syntax::parser::parse;
+    use pretty_assertions::assert_eq;
+
+    fn declared(source: &str, config: &Config) -> Result<Vec<String>> {
+        let program = parse(source).unwrap();
+        let arena = Arena::new();
+        let SourceUnit::Module(module) = program.body().iter().next().unwrap().value;
+        let module = strip(&arena, &module, config)?;
+        Ok(module_declarations(&module)
+            .iter()
+            .map(|declaration| match declaration.value.declarator {
+                Declarator::Constant(constant) => constant.identifier.value.to_string(),
+                _ => unreachable!(),
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_predicates() {
+        let mut config = Config::for_target("x86_64-unknown-linux-gnu");
+        config.flag("debug").feature("json");
+        let source = r#"
+            const always = 1;
+            #[cfg(debug)] const debug = 1;
+            #[cfg(!debug)] const release = 1;
+            #[cfg(target_os = "linux")] export const linux = 1;
+            #[cfg(unix && target_arch == "x86_64")] const unix = 1;
+            #[cfg(windows || (target_os = "macos"))] const other = 1;
+            #[cfg(feature = "json")] #[cfg(feature = "yaml")] const both = 1;
+        "#;
+        assert_eq!(
+            declared(source, &config).unwrap(),
+            vec!["always", "debug", "linux", "unix"]
+        );
+
+        let config = Config::for_target("x86_64-pc-windows-msvc");
+        assert_eq!(
+            declared(source, &config).unwrap(),
+            vec!["always", "release", "other"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_predicates() {
+        let config = Config::default();
+        let reason = |source: &str| match declared(source, &config) {
+            Err(Error::InvalidAttribute { reason, .. }) => reason,
+            result => panic!("expected an invalid attribute, got {:?}", result),
+        };
+        assert_eq!(
+            reason("#[cfg(a + b)] const x = 1;"),
+            "expected a name, `name = \"value\"`, `!`, `&&` or `||`"
+        );
+        assert_eq!(
+            reason("#[cfg(feature = 1)] const x = 1;"),
+            "expected `name = \"value\"`"
+        );
+        assert_eq!(
+            reason("#[cfg] const x = 1;"),
+            "expected a single predicate, as in `#[cfg(debug)]`"
+        );
+    }
+}
@@ -0,0 +1,1264 @@
+use crate::interop::c_header::CHeader;
+use crate::semantic::entry::is_constant_initializer;
+use crate::semantic::error::*;
+use crate::semantic::module_declarations;
+use crate::semantic::patterns::{char_value, integer_bounds};
+use crate::semantic::types::*;
+use crate::syntax::ast::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+
+/// A value computed at compile time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    /// An integer or `char`, which always fits `ty`.
+    Integer {
+        value: i128,
+        ty: PrimitiveType,
+    },
+    /// A floating point number, rounded to the precision of `ty`.
+    Float {
+        value: f64,
+        ty: PrimitiveType,
+    },
+    Bool(bool),
+    String(String),
+}
+
+impl Constant {
+    pub fn ty(&self) -> Type {
+        match self {
+            Constant::Integer { ty, .. } | Constant::Float { ty, .. } => Type::Primitive(*ty),
+            Constant::Bool(_) => Type::Primitive(PrimitiveType::Bool),
+            Constant::String(_) => Type::SizedArray(Box::new(Type::Primitive(PrimitiveType::Char))),
+        }
+    }
+
+    fn boolean(&self) -> Option<bool> {
+        match self {
+            Constant::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Integer { value, .. } => write!(f, "{}", value),
+            Constant::Float { value, .. } => write!(f, "{}", value),
+            Constant::Bool(b) => write!(f, "{}", b),
+            Constant::String(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// What a name refers to in the constant scope of a module.
+#[derive(Clone, Copy, Debug)]
+enum Binding<'ast> {
+    Constant(ConstantDeclarator<'ast>),
+    /// `name` as exported by the module imported from `module`.
+    Import {
+        module: &'ast str,
+        name: &'ast str,
+    },
+}
+
+/// The names a module's constant expressions can refer to.
+#[derive(Clone, Debug, Default)]
+struct ModuleScope<'ast> {
+    env: TypeEnvironment<'ast>,
+    bindings: HashMap<&'ast str, Binding<'ast>>,
+    /// The local names of exported bindings, by exported name.
+    exports: HashMap<&'ast str, &'ast str>,
+    /// Modules imported with `import * as name`, by name.
+    globs: HashMap<&'ast str, &'ast str>,
+    constants: Vec<ConstantDeclarator<'ast>>,
+    enums: Vec<EnumDeclarator<'ast>>,
+}
+
+/// A constant or enum of a module, as tracked while it is being evaluated.
+type Key<'ast> = (String, &'ast str);
+
+/// Evaluates `const` initializers, enum values and array sizes at compile
+/// time.
+///
+/// Integer arithmetic is exact: every intermediate value must fit its type,
+/// so `const x: u8 = 200 + 100;` is an error rather than 44. Constants may
+/// refer to other constants, including ones imported from other modules, in
+/// any order; each is evaluated once, on first use.
+#[derive(Clone, Debug, Default)]
+pub struct ConstantEvaluator<'ast> {
+    modules: HashMap<String, ModuleScope<'ast>>,
+    values: HashMap<Key<'ast>, Constant>,
+    variants: HashMap<Key<'ast>, Vec<(&'ast str, Constant)>>,
+    /// The constants and enums being evaluated, outermost first.
+    evaluating: Vec<Key<'ast>>,
+    /// The constants of imported C headers and of catlang modules compiled
+    /// on their own, by import path.
+    headers: HashMap<String, HashMap<String, Constant>>,
+}
+
+impl<'ast> ConstantEvaluator<'ast> {
+    pub fn new() -> Self {
+        ConstantEvaluator::default()
+    }
+
+    /// Collects the constants, enums and imports of `module`. Other modules
+    /// refer to it by `name`, the path they import it from.
+    pub fn add_module(
+        &mut self,
+        name: &str,
+        module: &Module<'ast>,
+    ) -> std::result::Result<(), Vec<Error>> {
+        let mut scope = ModuleScope {
+            env: TypeEnvironment::from_module(module)?,
+            ..ModuleScope::default()
+        };
+        let mut errors = vec![];
+        let mut define =
+            |scope: &mut ModuleScope<'ast>, identifier: IdentifierNode<'ast>, binding| {
+                if scope.bindings.insert(identifier.value, binding).is_some() {
+                    errors.push(Error::DuplicateDefinition {
+                        name: identifier.value.to_string(),
+                        span: identifier.start as usize..identifier.end as usize,
+                    });
+                }
+            };
+        for element in module.elements.iter() {
+            match element.value {
+                ModuleElement::Import(import) => {
+                    let path = import.value.path.value.trim_matches('"');
+                    match import.value.import_list {
+                        ImportList::NamedImportList(list) => {
+                            for import in list.imports.iter() {
+                                let identifier = import.value.identifier;
+                                let local = import.value.renamed_to.unwrap_or(identifier);
+                                let binding = Binding::Import {
+                                    module: path,
+                                    name: identifier.value,
+                                };
+                                define(&mut scope, local, binding);
+                            }
+                        }
+                        ImportList::GlobImportList(glob) => {
+                            scope.globs.insert(glob.identifier.value, path);
+                        }
+                    }
+                }
+                ModuleElement::Export(export) => match export.value {
+                    Export::Declaration(declaration) => match declaration.value.declarator {
+                        Declarator::Constant(c) => {
+                            scope.exports.insert(c.identifier.value, c.identifier.value);
+                        }
+                        Declarator::Enum(e) => {
+                            scope.exports.insert(e.identifier.value, e.identifier.value);
+                        }
+                        _ => {}
+                    },
+                    Export::Statement(statement) => {
+                        let exported_as = statement.renamed_to.unwrap_or(statement.identifier);
+                        scope
+                            .exports
+                            .insert(exported_as.value, statement.identifier.value);
+                    }
+                    Export::ReExport(_) => {}
+                },
+                _ => {}
+            }
+        }
+        for declaration in module_declarations(module) {
+            match declaration.value.declarator {
+                Declarator::Constant(c) => {
+                    define(&mut scope, c.identifier, Binding::Constant(c));
+                    scope.constants.push(c);
+                }
+                Declarator::Enum(e) => scope.enums.push(e),
+                _ => {}
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        self.modules.insert(name.to_string(), scope);
+        Ok(())
+    }
+
+    /// Makes the `#define`s and enumerators of a C header importable from
+    /// `path`.
+    pub fn add_header(&mut self, path: &str, header: &CHeader) {
+        let constants = header.constants.iter().cloned().collect();
+        self.headers.insert(path.to_string(), constants);
+    }
+
+    /// Makes the constants a catlang module compiled on its own exports,
+    /// by the names it exports them as, importable from `path`.
+    pub fn add_exports(&mut self, path: &str, constants: &HashMap<&str, Constant>) {
+        let constants = constants
+            .iter()
+            .map(|(name, constant)| (name.to_string(), constant.clone()))
+            .collect();
+        self.headers.insert(path.to_string(), constants);
+    }
+
+    /// Evaluates every constant and enum of `module`, returning the values
+    /// of its constants in declaration order.
+    pub fn evaluate_module(
+        &mut self,
+        module: &str,
+    ) -> std::result::Result<Vec<(&'ast str, Constant)>, Vec<Error>> {
+        let scope = self.scope(module, 0..0).map_err(|e| vec![e])?;
+        let constants = scope.constants.clone();
+        let enums = scope.enums.clone();
+        let mut values = vec![];
+        let mut errors = vec![];
+        // A cycle is reported once, not once for every constant in it.
+        let mut in_reported_cycle = HashSet::new();
+        for constant in constants {
+            let identifier = constant.identifier;
+            if in_reported_cycle.contains(identifier.value) {
+                continue;
+            }
+            match self.evaluate_constant(module, constant, span_of(&identifier)) {
+                Ok(value) => values.push((identifier.value, value)),
+                Err(e) => {
+                    if let Error::ConstantCycle { path, .. } = &e {
+                        in_reported_cycle.extend(path.clone());
+                    }
+                    errors.push(e);
+                }
+            }
+        }
+        for enumeration in enums {
+            let span = span_of(&enumeration.identifier);
+            if let Err(e) = self.evaluate_variants(module, enumeration, span) {
+                errors.push(e);
+            }
+        }
+        match errors.len() {
+            0 => Ok(values),
+            _ => Err(errors),
+        }
+    }
+
+    /// The value of the constant `name` declared in or imported into
+    /// `module`.
+    pub fn constant(&mut self, module: &str, name: &str) -> Result<Constant> {
+        self.lookup(module, name, 0..0)
+    }
+
+    /// The values of the variants of the enum `enumeration` declared in
+    /// `module`, in order. Variants without a value follow the previous one,
+    /// starting at 0, and all must fit the enum's representation.
+    pub fn enum_values(
+        &mut self,
+        module: &str,
+        enumeration: &str,
+    ) -> Result<Vec<(&'ast str, Constant)>> {
+        let declarator = self.enumeration(module, enumeration, 0..0)?;
+        let span = span_of(&declarator.identifier);
+        self.evaluate_variants(module, declarator, span)
+    }
+
+    /// Evaluates the size of a statically-sized array type.
+    pub fn array_size(&mut self, module: &str, expression: &ExpressionNode<'ast>) -> Result<u64> {
+        let span = span_of(expression);
+        match self.evaluate(module, expression, None)? {
+            Constant::Integer { value, .. } if value >= 0 && value <= u64::MAX as i128 => {
+                Ok(value as u64)
+            }
+            size => Err(Error::InvalidArraySize {
+                size: size.to_string(),
+                span,
+            }),
+        }
+    }
+
+    /// Evaluates an expression of `module`. Literals take the `expected`
+    /// type if it is numeric; otherwise integer literals are `int`, or the
+    /// smallest of `s64` and `u64` they fit.
+    pub fn evaluate(
+        &mut self,
+        module: &str,
+        expression: &ExpressionNode<'ast>,
+        expected: Option<&Type>,
+    ) -> Result<Constant> {
+        let span = span_of(expression);
+        match expression.value {
+            Expression::PrimitiveExpression(primitive) => literal(primitive, expected, false, span),
+            Expression::IdentifierExpression(identifier) => {
+                self.lookup(module, identifier.value, span)
+            }
+            Expression::PrefixExpression(prefix) => {
+                self.evaluate_prefix(module, &prefix, expected, span)
+            }
+            Expression::BinaryExpression(binary) => {
+                self.evaluate_binary(module, &binary, expected, span)
+            }
+            Expression::TernaryExpression(ternary) => {
+                let condition = self.evaluate_bool(module, &ternary.condition)?;
+                let branch = if condition {
+                    ternary.when_true
+                } else {
+                    ternary.when_false
+                };
+                self.evaluate(module, &branch, expected)
+            }
+            Expression::CastExpression(cast) => {
+                let value = self.evaluate(module, &cast.left, None)?;
+                let ty = self
+                    .scope(module, span.clone())?
+                    .env
+                    .resolve(&cast.cast_to, &[])?;
+                cast_constant(value, &ty, cast.forced, span)
+            }
+            Expression::ScopedExpression(scoped) => {
+                let scope = self.scope(module, span.clone())?;
+                match scope.globs.get(scoped.scope.value) {
+                    Some(&imported) => self.lookup_export(imported, scoped.member.value, span),
+                    None => self.variant(module, scoped.scope, scoped.member),
+                }
+            }
+            Expression::MemberAccessExpression(access) => {
+                if let Expression::IdentifierExpression(identifier) = access.object.value {
+                    let scope = self.scope(module, span.clone())?;
+                    if let Some(&imported) = scope.globs.get(identifier.value) {
+                        return self.lookup_export(imported, access.member.value, span);
+                    }
+                    if !scope.bindings.contains_key(identifier.value)
+                        && scope
+                            .enums
+                            .iter()
+                            .any(|e| e.identifier.value == identifier.value)
+                    {
+                        return self.variant(module, identifier, access.member);
+                    }
+                }
+                match self.evaluate(module, &access.object, None)? {
+                    Constant::String(s) if access.member.value == "length" => {
+                        Ok(Constant::Integer {
+                            value: s.len() as i128,
+                            ty: PrimitiveType::S64,
+                        })
+                    }
+                    _ => Err(Error::NotConstant { span }),
+                }
+            }
+            _ => Err(Error::NotConstant { span }),
+        }
+    }
+
+    fn scope(&self, module: &str, span: Range<usize>) -> Result<&ModuleScope<'ast>> {
+        self.modules.get(module).ok_or(Error::NotConstant { span })
+    }
+
+    /// Evaluates what `name` refers to in `module`.
+    fn lookup(&mut self, module: &str, name: &str, span: Range<usize>) -> Result<Constant> {
+        let binding = self
+            .scope(module, span.clone())?
+            .bindings
+            .get(name)
+            .copied();
+        match binding {
+            Some(Binding::Constant(constant)) => self.evaluate_constant(module, constant, span),
+            Some(Binding::Import { module, name }) => self.lookup_export(module, name, span),
+            None => Err(Error::NotConstant { span }),
+        }
+    }
+
+    /// Evaluates the constant `module` exports as `name`.
+    fn lookup_export(&mut self, module: &str, name: &str, span: Range<usize>) -> Result<Constant> {
+        if let Some(constants) = self.headers.get(module) {
+            return constants
+                .get(name)
+                .cloned()
+                .ok_or(Error::NotConstant { span });
+        }
+        let scope = self.scope(module, span.clone())?;
+        match scope.exports.get(name) {
+            Some(&local) => self.lookup(module, local, span),
+            None if scope.bindings.contains_key(name) => Err(Error::NotExported {
+                name: name.to_string(),
+                module: module.to_string(),
+                span,
+            }),
+            None => Err(Error::NotConstant { span }),
+        }
+    }
+
+    fn evaluate_constant(
+        &mut self,
+        module: &str,
+        constant: ConstantDeclarator<'ast>,
+        span: Range<usize>,
+    ) -> Result<Constant> {
+        let key = (module.to_string(), constant.identifier.value);
+        if let Some(value) = self.values.get(&key) {
+            return Ok(value.clone());
+        }
+        self.enter(key.clone(), span)?;
+        let value = self.evaluate_declared(module, constant);
+        self.evaluating.pop();
+        let value = value?;
+        self.values.insert(key, value.clone());
+        Ok(value)
+    }
+
+    fn evaluate_declared(
+        &mut self,
+        module: &str,
+        constant: ConstantDeclarator<'ast>,
+    ) -> Result<Constant> {
+        let expected = match constant.type_expression {
+            Some(ty) => Some(self.scope(module, 0..0)?.env.resolve(&ty, &[])?),
+            None => None,
+        };
+        let value = self.evaluate(module, &constant.expression, expected.as_ref())?;
+        match expected {
+            Some(ty) => convert(value, &ty, span_of(&constant.expression)),
+            None => Ok(value),
+        }
+    }
+
+    /// Starts evaluating `key`, unless it is already being evaluated.
+    fn enter(&mut self, key: Key<'ast>, span: Range<usize>) -> Result<()> {
+        if let Some(start) = self.evaluating.iter().position(|k| *k == key) {
+            let cycle = &self.evaluating[start..];
+            let across_modules = cycle.iter().any(|(module, _)| *module != key.0);
+            let path = cycle
+                .iter()
+                .chain(std::iter::once(&key))
+                .map(|(module, name)| match across_modules {
+                    true => format!("{}::{}", module, name),
+                    false => name.to_string(),
+                })
+                .collect();
+            return Err(Error::ConstantCycle { path, span });
+        }
+        self.evaluating.push(key);
+        Ok(())
+    }
+
+    fn enumeration(
+        &self,
+        module: &str,
+        name: &str,
+        span: Range<usize>,
+    ) -> Result<EnumDeclarator<'ast>> {
+        self.scope(module, span.clone())?
+            .enums
+            .iter()
+            .find(|e| e.identifier.value == name)
+            .copied()
+            .ok_or(Error::NotConstant { span })
+    }
+
+    /// Evaluates `enumeration.variant`.
+    fn variant(
+        &mut self,
+        module: &str,
+        enumeration: IdentifierNode<'ast>,
+        variant: IdentifierNode<'ast>,
+    ) -> Result<Constant> {
+        let span = enumeration.start as usize..variant.end as usize;
+        let declarator = self.enumeration(module, enumeration.value, span.clone())?;
+        self.evaluate_variants(module, declarator, span.clone())?
+            .into_iter()
+            .find(|(name, _)| *name == variant.value)
+            .map(|(_, value)| value)
+            .ok_or_else(|| Error::UnknownEnumVariant {
+                enumeration: enumeration.value.to_string(),
+                variant: variant.value.to_string(),
+                span,
+            })
+    }
+
+    fn evaluate_variants(
+        &mut self,
+        module: &str,
+        declarator: EnumDeclarator<'ast>,
+        span: Range<usize>,
+    ) -> Result<Vec<(&'ast str, Constant)>> {
+        let key = (module.to_string(), declarator.identifier.value);
+        if let Some(variants) = self.variants.get(&key) {
+            return Ok(variants.clone());
+        }
+        self.enter(key.clone(), span)?;
+        let variants = self.evaluate_variant_values(module, declarator);
+        self.evaluating.pop();
+        let variants = variants?;
+        self.variants.insert(key, variants.clone());
+        Ok(variants)
+    }
+
+    fn evaluate_variant_values(
+        &mut self,
+        module: &str,
+        declarator: EnumDeclarator<'ast>,
+    ) -> Result<Vec<(&'ast str, Constant)>> {
+        let representation = declarator
+            .representation
+            .map_or(PrimitiveType::S32, representation_type);
+        let ty = Type::Primitive(representation);
+        let mut next = Some(0);
+        let mut variants = vec![];
+        for variant in declarator.values.iter() {
+            let identifier = variant.value.identifier;
+            let value = match variant.value.value {
+                Some(expression) => {
+                    let span = span_of(&expression);
+                    match self.evaluate(module, &expression, Some(&ty))? {
+                        // Any integer that fits the representation will do.
+                        Constant::Integer { value, .. } => fit(Some(value), representation, span)?,
+                        value => {
+                            return Err(Error::TypeMismatch {
+                                expected: ty.to_string(),
+                                found: value.ty().to_string(),
+                                span,
+                            })
+                        }
+                    }
+                }
+                None => fit(next, representation, span_of(&identifier))?,
+            };
+            next = match value {
+                Constant::Integer { value, .. } => value.checked_add(1),
+                _ => None,
+            };
+            variants.push((identifier.value, value));
+        }
+        Ok(variants)
+    }
+
+    fn evaluate_bool(&mut self, module: &str, expression: &ExpressionNode<'ast>) -> Result<bool> {
+        let value = self.evaluate(module, expression, None)?;
+        value.boolean().ok_or_else(|| Error::TypeMismatch {
+            expected: PrimitiveType::Bool.keyword().to_string(),
+            found: value.ty().to_string(),
+            span: span_of(expression),
+        })
+    }
+
+    fn evaluate_prefix(
+        &mut self,
+        module: &str,
+        prefix: &PrefixExpression<'ast>,
+        expected: Option<&Type>,
+        span: Range<usize>,
+    ) -> Result<Constant> {
+        if prefix.operator == PrefixOperator::Minus {
+            // Negative literals are folded before they are typed, so that
+            // `-128` is a valid `s8`.
+            if let Expression::PrimitiveExpression(primitive) = prefix.operand.value {
+                return literal(primitive, expected, true, span);
+            }
+        }
+        let operand = self.evaluate(module, &prefix.operand, expected)?;
+        let no_overload = || Error::NoOperatorOverload {
+            operator: format!("{:?}", prefix.operator),
+            operands: operand.ty().to_string(),
+            span: span.clone(),
+        };
+        match (prefix.operator, &operand) {
+            (PrefixOperator::Plus, Constant::Integer { .. })
+            | (PrefixOperator::Plus, Constant::Float { .. }) => Ok(operand.clone()),
+            (PrefixOperator::Minus, Constant::Integer { value, ty }) => {
+                fit(value.checked_neg(), *ty, span)
+            }
+            (PrefixOperator::Minus, Constant::Float { value, ty }) => float(-value, *ty, span),
+            (PrefixOperator::BitNot, Constant::Integer { value, ty }) => {
+                let (signed, _) = ty.integer_layout().ok_or_else(no_overload)?;
+                let (_, max) = integer_bounds(&Type::Primitive(*ty)).ok_or_else(no_overload)?;
+                let value = if signed { !value } else { max ^ value };
+                fit(Some(value), *ty, span)
+            }
+            (PrefixOperator::LogicalNot, Constant::Bool(b)) => Ok(Constant::Bool(!b)),
+            (PrefixOperator::Increment, _)
+            | (PrefixOperator::Decrement, _)
+            | (PrefixOperator::AddressOf, _)
+            | (PrefixOperator::Dereference, _) => Err(Error::NotConstant { span }),
+            _ => Err(no_overload()),
+        }
+    }
+
+    fn evaluate_binary(
+        &mut self,
+        module: &str,
+        binary: &BinaryExpression<'ast>,
+        expected: Option<&Type>,
+        span: Range<usize>,
+    ) -> Result<Constant> {
+        let operator = binary.operator;
+        match operator {
+            BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => {
+                let left = self.evaluate_bool(module, &binary.left)?;
+                if left == (operator == BinaryOperator::LogicalOr) {
+                    return Ok(Constant::Bool(left));
+                }
+                Ok(Constant::Bool(self.evaluate_bool(module, &binary.right)?))
+            }
+            BinaryOperator::Equals
+            | BinaryOperator::NotEquals
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterThanEquals
+            | BinaryOperator::LessThan
+            | BinaryOperator::LessThanEquals => {
+                let (left, right) = self.evaluate_operands(module, binary, None)?;
+                let (left, right) = unify(left, right, span.clone())?;
+                compare(operator, &left, &right, span)
+            }
+            BinaryOperator::BitShiftLeft | BinaryOperator::BitShiftRight => {
+                let left = self.evaluate(module, &binary.left, expected)?;
+                let right = self.evaluate(module, &binary.right, None)?;
+                shift(operator, left, right, span)
+            }
+            BinaryOperator::Addition
+            | BinaryOperator::Subtraction
+            | BinaryOperator::Multiplication
+            | BinaryOperator::Division
+            | BinaryOperator::Remainder
+            | BinaryOperator::BitAnd
+            | BinaryOperator::BitOr
+            | BinaryOperator::BitXor => {
+                let (left, right) = self.evaluate_operands(module, binary, expected)?;
+                let (left, right) = unify(left, right, span.clone())?;
+                arithmetic(operator, left, right, span)
+            }
+            _ => Err(Error::NotConstant { span }),
+        }
+    }
+
+    /// Evaluates both operands of a binary operator. A literal operand takes
+    /// on the type of the other one.
+    fn evaluate_operands(
+        &mut self,
+        module: &str,
+        binary: &BinaryExpression<'ast>,
+        hint: Option<&Type>,
+    ) -> Result<(Constant, Constant)> {
+        if is_constant_initializer(&binary.left) && !is_constant_initializer(&binary.right) {
+            let right = self.evaluate(module, &binary.right, hint)?;
+            let left = self.evaluate(module, &binary.left, Some(&right.ty()))?;
+            Ok((left, right))
+        } else {
+            let left = self.evaluate(module, &binary.left, hint)?;
+            let right = self.evaluate(module, &binary.right, Some(&left.ty()))?;
+            Ok((left, right))
+        }
+    }
+}
+
+fn span_of<T>(node: &Node<'_, T>) -> Range<usize> {
+    node.start as usize..node.end as usize
+}
+
+/// The integer type an enum's variants are stored as.
+pub fn representation_type(representation: EnumRepresentationType) -> PrimitiveType {
+    match representation {
+        EnumRepresentationType::S8 => PrimitiveType::S8,
+        EnumRepresentationType::U8 => PrimitiveType::U8,
+        EnumRepresentationType::S16 => PrimitiveType::S16,
+        EnumRepresentationType::U16 => PrimitiveType::U16,
+        EnumRepresentationType::S32 => PrimitiveType::S32,
+        EnumRepresentationType::U32 => PrimitiveType::U32,
+        EnumRepresentationType::S64 => PrimitiveType::S64,
+        EnumRepresentationType::U64 => PrimitiveType::U64,
+    }
+}
+
+/// An integer of type `ty`, if `value` was computed without overflowing and
+/// fits it.
+fn fit(value: Option<i128>, ty: PrimitiveType, span: Range<usize>) -> Result<Constant> {
+    let ty = ty.canonical();
+    let overflow = || Error::ConstantOverflow {
+        ty: ty.keyword().to_string(),
+        span: span.clone(),
+    };
+    let (min, max) = integer_bounds(&Type::Primitive(ty)).ok_or_else(overflow)?;
+    match value {
+        Some(value) if min <= value && value <= max => Ok(Constant::Integer { value, ty }),
+        _ => Err(overflow()),
+    }
+}
+
+/// A float of type `ty`, if `value` is finite at its precision.
+fn float(value: f64, ty: PrimitiveType, span: Range<usize>) -> Result<Constant> {
+    let ty = ty.canonical();
+    let value = match ty.float_width() {
+        Some(32) => value as f32 as f64,
+        _ => value,
+    };
+    if !value.is_finite() {
+        return Err(Error::ConstantOverflow {
+            ty: ty.keyword().to_string(),
+            span,
+        });
+    }
+    Ok(Constant::Float { value, ty })
+}
+
+/// Truncates `value` to the width of the integer type `ty`, as a forced cast
+/// does.
+fn wrap(value: i128, ty: PrimitiveType) -> i128 {
+    let (signed, bits) = match ty.integer_layout() {
+        Some(layout) => layout,
+        None => return value,
+    };
+    let truncated = value & ((1 << bits) - 1);
+    if signed && truncated >= 1 << (bits - 1) {
+        truncated - (1 << bits)
+    } else {
+        truncated
+    }
+}
+
+fn literal(
+    primitive: Primitive<'_>,
+    expected: Option<&Type>,
+    negative: bool,
+    span: Range<usize>,
+) -> Result<Constant> {
+    let expected = match expected.map(Type::unqualified) {
+        Some(Type::Primitive(pt)) => Some(pt.canonical()),
+        _ => None,
+    };
+    let sign = if negative { -1 } else { 1 };
+    let integer = |digits: &str, radix| {
+        let value = i128::from_str_radix(&digits.replace('_', ""), radix)
+            .ok()
+            .map(|value| sign * value);
+        match expected {
+            Some(pt) if pt.integer_layout().is_some() => fit(value, pt, span.clone()),
+            Some(pt) if pt.float_width().is_some() => match value {
+                Some(value) => float(value as f64, pt, span.clone()),
+                None => fit(None, pt, span.clone()),
+            },
+            _ => [PrimitiveType::S32, PrimitiveType::S64, PrimitiveType::U64]
+                .iter()
+                .find_map(|&ty| fit(value, ty, span.clone()).ok())
+                .ok_or(Error::ConstantOverflow {
+                    ty: PrimitiveType::U64.keyword().to_string(),
+                    span: span.clone(),
+                }),
+        }
+    };
+    match primitive {
+        Primitive::DecimalNumber(digits) => integer(digits, 10),
+        Primitive::HexadecimalNumber(digits) => integer(&digits[2..], 16),
+        Primitive::OctalNumber(digits) => integer(&digits[2..], 8),
+        Primitive::BinaryNumber(digits) => integer(&digits[2..], 2),
+        Primitive::DecimalFloat(digits) => {
+            let value: f64 = digits
+                .replace('_', "")
+                .parse()
+                .map_err(|_| Error::NotConstant { span: span.clone() })?;
+            let ty = match expected {
+                Some(pt) if pt.float_width().is_some() => pt,
+                _ => PrimitiveType::F64,
+            };
+            float(sign as f64 * value, ty, span)
+        }
+        _ if negative => Err(Error::NoOperatorOverload {
+            operator: format!("{:?}", PrefixOperator::Minus),
+            operands: literal(primitive, None, false, span.clone())?
+                .ty()
+                .to_string(),
+            span,
+        }),
+        Primitive::Bool(b) => Ok(Constant::Bool(b)),
+        Primitive::Char(quoted) => fit(char_value(quoted), PrimitiveType::Char, span),
+        Primitive::String(quoted) => {
+            let bytes = unescape(&quoted[1..quoted.len() - 1]);
+            Ok(Constant::String(
+                String::from_utf8_lossy(&bytes).into_owned(),
+            ))
+        }
+        Primitive::Null => Err(Error::NotConstant { span }),
+    }
+}
+
+/// Converts `value` to `to` if its type widens to it.
+fn convert(value: Constant, to: &Type, span: Range<usize>) -> Result<Constant> {
+    let ty = value.ty();
+    if ty.unqualified() == to.unqualified() {
+        return Ok(value);
+    }
+    let mismatch = || Error::TypeMismatch {
+        expected: to.to_string(),
+        found: ty.to_string(),
+        span: span.clone(),
+    };
+    if !ty.widens_to(to) {
+        return Err(mismatch());
+    }
+    let pt = match to.unqualified() {
+        Type::Primitive(pt) => *pt,
+        _ => return Err(mismatch()),
+    };
+    match value {
+        Constant::Integer { value, .. } if pt.integer_layout().is_some() => {
+            fit(Some(value), pt, span)
+        }
+        Constant::Integer { value, .. } => float(value as f64, pt, span),
+        Constant::Float { value, .. } => float(value, pt, span),
+        _ => Err(mismatch()),
+    }
+}
+
+/// Converts the operands of a binary operator to a common type.
+fn unify(left: Constant, right: Constant, span: Range<usize>) -> Result<(Constant, Constant)> {
+    if right.ty().widens_to(&left.ty()) {
+        let ty = left.ty();
+        Ok((left, convert(right, &ty, span)?))
+    } else {
+        let ty = right.ty();
+        Ok((convert(left, &ty, span)?, right))
+    }
+}
+
+fn no_overload(
+    operator: BinaryOperator,
+    left: &Constant,
+    right: &Constant,
+    span: Range<usize>,
+) -> Error {
+    Error::NoOperatorOverload {
+        operator: format!("{:?}", operator),
+        operands: format!("{}, {}", left.ty(), right.ty()),
+        span,
+    }
+}
+
+fn compare(
+    operator: BinaryOperator,
+    left: &Constant,
+    right: &Constant,
+    span: Range<usize>,
+) -> Result<Constant> {
+    let ordering = match (left, right) {
+        (Constant::Integer { value: l, .. }, Constant::Integer { value: r, .. }) => {
+            l.partial_cmp(r)
+        }
+        (Constant::Float { value: l, .. }, Constant::Float { value: r, .. }) => l.partial_cmp(r),
+        (Constant::Bool(l), Constant::Bool(r)) => l.partial_cmp(r),
+        (Constant::String(l), Constant::String(r)) => l.partial_cmp(r),
+        _ => None,
+    };
+    let ordering = ordering.ok_or_else(|| no_overload(operator, left, right, span.clone()))?;
+    let ordered = matches!(left, Constant::Integer { .. } | Constant::Float { .. });
+    let res = match operator {
+        BinaryOperator::Equals => ordering.is_eq(),
+        BinaryOperator::NotEquals => ordering.is_ne(),
+        _ if !ordered => return Err(no_overload(operator, left, right, span)),
+        BinaryOperator::GreaterThan => ordering.is_gt(),
+        BinaryOperator::GreaterThanEquals => ordering.is_ge(),
+        BinaryOperator::LessThan => ordering.is_lt(),
+        _ => ordering.is_le(),
+    };
+    Ok(Constant::Bool(res))
+}
+
+fn shift(
+    operator: BinaryOperator,
+    left: Constant,
+    right: Constant,
+    span: Range<usize>,
+) -> Result<Constant> {
+    let (value, ty, amount) = match (&left, &right) {
+        (Constant::Integer { value, ty }, Constant::Integer { value: amount, .. }) => {
+            (*value, *ty, *amount)
+        }
+        _ => return Err(no_overload(operator, &left, &right, span)),
+    };
+    let (_, bits) = ty.integer_layout().unwrap_or_default();
+    if amount < 0 || amount >= bits as i128 {
+        return fit(None, ty, span);
+    }
+    let value = match operator {
+        BinaryOperator::BitShiftLeft => value.checked_mul(1 << amount),
+        _ => Some(value >> amount),
+    };
+    fit(value, ty, span)
+}
+
+fn arithmetic(
+    operator: BinaryOperator,
+    left: Constant,
+    right: Constant,
+    span: Range<usize>,
+) -> Result<Constant> {
+    let division = matches!(
+        operator,
+        BinaryOperator::Division | BinaryOperator::Remainder
+    );
+    match (&left, &right) {
+        (Constant::Integer { value: l, ty }, Constant::Integer { value: r, .. }) => {
+            let value = match operator {
+                _ if division && *r == 0 => return Err(Error::DivisionByZero { span }),
+                BinaryOperator::Addition => l.checked_add(*r),
+                BinaryOperator::Subtraction => l.checked_sub(*r),
+                BinaryOperator::Multiplication => l.checked_mul(*r),
+                BinaryOperator::Division => l.checked_div(*r),
+                BinaryOperator::Remainder => l.checked_rem(*r),
+                BinaryOperator::BitAnd => Some(l & r),
+                BinaryOperator::BitOr => Some(l | r),
+                _ => Some(l ^ r),
+            };
+            fit(value, *ty, span)
+        }
+        (Constant::Float { value: l, ty }, Constant::Float { value: r, .. }) => {
+            let value = match operator {
+                _ if division && *r == 0.0 => return Err(Error::DivisionByZero { span }),
+                BinaryOperator::Addition => l + r,
+                BinaryOperator::Subtraction => l - r,
+                BinaryOperator::Multiplication => l * r,
+                BinaryOperator::Division => l / r,
+                BinaryOperator::Remainder => l % r,
+                _ => return Err(no_overload(operator, &left, &right, span)),
+            };
+            float(value, *ty, span)
+        }
+        (Constant::Bool(l), Constant::Bool(r)) => match operator {
+            BinaryOperator::BitAnd => Ok(Constant::Bool(l & r)),
+            BinaryOperator::BitOr => Ok(Constant::Bool(l | r)),
+            BinaryOperator::BitXor => Ok(Constant::Bool(l ^ r)),
+            _ => Err(no_overload(operator, &left, &right, span)),
+        },
+        _ => Err(no_overload(operator, &left, &right, span)),
+    }
+}
+
+/// Applies `value as to`, or `value as! to` if `forced`. Casts between
+/// numbers must preserve the value unless they are forced, in which case
+/// integers are truncated like at runtime.
+fn cast_constant(value: Constant, to: &Type, forced: bool, span: Range<usize>) -> Result<Constant> {
+    let from = value.ty().to_string();
+    let invalid = |reason| Error::InvalidCast {
+        from: from.clone(),
+        to: to.to_string(),
+        reason,
+    };
+    if value.ty().unqualified() == to.unqualified() {
+        return Ok(value);
+    }
+    let pt = match to.unqualified() {
+        Type::Primitive(pt) => pt.canonical(),
+        _ => return Err(invalid("only numbers are cast at compile time")),
+    };
+    let integer = pt.integer_layout().is_some();
+    let floating = pt.float_width().is_some();
+    match value {
+        Constant::Integer { value, .. } if integer && forced => {
+            fit(Some(wrap(value, pt)), pt, span)
+        }
+        Constant::Integer { value, .. } if integer => fit(Some(value), pt, span),
+        Constant::Integer { value, .. } if floating => float(value as f64, pt, span),
+        Constant::Float { value, .. } if integer => {
+            let value = value.trunc() as i128;
+            let value = if forced { wrap(value, pt) } else { value };
+            fit(Some(value), pt, span)
+        }
+        Constant::Float { value, .. } if floating => float(value, pt, span),
+        Constant::Bool(b) if integer => fit(Some(b as i128), pt, span),
+        Constant::Bool(_) => Err(invalid("booleans only convert to integers")),
+        _ => Err(invalid("only numbers are cast at compile time")),
+    }
+}
+
+/// Resolves the escape sequences of a string or character literal.
+pub fn unescape(literal: &str) -> Vec<u8> {
+    let mut res = vec![];
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                Some(other) => other,
+                None => '\\',
+            },
+            c => c,
+        };
+        let mut buffer = [0; 4];
+        res.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parser::{parse, Parser};
+    use pretty_assertions::assert_eq;
+    use toolshed::Arena;
+
+    fn integer(value: i128, ty: PrimitiveType) -> Constant {
+        Constant::Integer { value, ty }
+    }
+
+    /// Evaluates the constants of the modules `sources`, named by their
+    /// index, and returns those of the last one.
+    fn evaluate(sources: &[&str]) -> std::result::Result<Vec<(String, Constant)>, Vec<Error>> {
+        let programs: Vec<_> = sources.iter().map(|s| parse(s).unwrap()).collect();
+        let mut evaluator = ConstantEvaluator::new();
+        for (index, program) in programs.iter().enumerate() {
+            let body = program.body();
+            let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+            evaluator.add_module(&index.to_string(), &module)?;
+        }
+        let last = (sources.len() - 1).to_string();
+        Ok(evaluator
+            .evaluate_module(&last)?
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect())
+    }
+
+    fn value(source: &str) -> Constant {
+        let values = evaluate(&[source]).unwrap();
+        values.last().unwrap().1.clone()
+    }
+
+    fn error(sources: &[&str]) -> Error {
+        evaluate(sources).unwrap_err().remove(0)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(
+            value("const x = 1 + 2 * 3;"),
+            integer(7, PrimitiveType::S32)
+        );
+        assert_eq!(
+            value("const x = (240 | 3) ^ 1;"),
+            integer(242, PrimitiveType::S32)
+        );
+        assert_eq!(value("const x = -7 / 2;"), integer(-3, PrimitiveType::S32));
+        assert_eq!(value("const x = -7 % 2;"), integer(-1, PrimitiveType::S32));
+        assert_eq!(
+            value("const x: u8 = ~15;"),
+            integer(0xF0, PrimitiveType::U8)
+        );
+        assert_eq!(
+            value("const x: s64 = 1 << 40;"),
+            integer(1 << 40, PrimitiveType::S64)
+        );
+        assert_eq!(
+            value("const x: s64 = 65536 * 65536;"),
+            integer(1 << 32, PrimitiveType::S64)
+        );
+        assert_eq!(
+            value("const x = (3 as f64) / (2 as f64);"),
+            Constant::Float {
+                value: 1.5,
+                ty: PrimitiveType::F64
+            }
+        );
+    }
+
+    #[test]
+    fn test_comparisons_and_logic() {
+        assert_eq!(value("const x = 2 < 3 && !(1 == 2);"), Constant::Bool(true));
+        assert_eq!(
+            value("const x = \"a\" != \"a\" || false;"),
+            Constant::Bool(false)
+        );
+        assert_eq!(
+            value("const x = 1 > 2 ? 10 : 20;"),
+            integer(20, PrimitiveType::S32)
+        );
+    }
+
+    #[test]
+    fn test_overflow_is_exact() {
+        assert_eq!(
+            value("const x: s8 = -128;"),
+            integer(-128, PrimitiveType::S8)
+        );
+        assert_eq!(
+            value("const m: u64 = 65536 * 65536; const x = (m - 1) * m + (m - 1);"),
+            integer(u64::MAX as i128, PrimitiveType::U64)
+        );
+        let overflow = |source| match error(&[source]) {
+            Error::ConstantOverflow { ty, .. } => ty,
+            e => panic!("expected an overflow, got {:?}", e),
+        };
+        assert_eq!(overflow("const x: u8 = 200 + 100;"), "u8");
+        assert_eq!(overflow("const x: s8 = -127 - 2;"), "s8");
+        assert_eq!(overflow("const x: u32 = 0 - 1;"), "u32");
+        assert_eq!(overflow("const x = 2147483647 + 1;"), "s32");
+        assert_eq!(overflow("const x: s16 = 1 << 15;"), "s16");
+        assert_eq!(overflow("const x = 1 << 32;"), "s32");
+        assert!(matches!(
+            error(&["const x = 1 / (2 - 2);"]),
+            Error::DivisionByZero { .. }
+        ));
+    }
+
+    #[test]
+    fn test_casts() {
+        assert_eq!(
+            value("const x = 200 as u8;"),
+            integer(200, PrimitiveType::U8)
+        );
+        assert_eq!(
+            value("const x = 300 as! u8;"),
+            integer(44, PrimitiveType::U8)
+        );
+        assert_eq!(
+            value("const x = (-1) as! u16;"),
+            integer(0xFFFF, PrimitiveType::U16)
+        );
+        assert_eq!(
+            value("const x = true as int;"),
+            integer(1, PrimitiveType::S32)
+        );
+        assert_eq!(
+            value("const x = 'a' as u32;"),
+            integer(97, PrimitiveType::U32)
+        );
+        assert!(matches!(
+            error(&["const x = 300 as u8;"]),
+            Error::ConstantOverflow { .. }
+        ));
+        assert!(matches!(
+            error(&["const x = \"a\" as int;"]),
+            Error::InvalidCast { .. }
+        ));
+    }
+
+    #[test]
+    fn test_constants_refer_to_each_other() {
+        let values = evaluate(&["const b = a * 2; const a: u16 = 21;"]).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                ("b".to_string(), integer(42, PrimitiveType::U16)),
+                ("a".to_string(), integer(21, PrimitiveType::U16)),
+            ]
+        );
+        assert!(matches!(
+            error(&["let y = 1; const x = y;"]),
+            Error::NotConstant { .. }
+        ));
+    }
+
+    #[test]
+    fn test_cycles_report_their_path() {
+        let errors = evaluate(&["const a = b; const b = c + 1; const c = a;"]).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![Error::ConstantCycle {
+                path: vec!["a", "b", "c", "a"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                span: 40..41,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_constants_across_modules() {
+        let math = "export const answer = 6 * 7; const hidden = 1; export limit as max; const limit: u8 = 255;";
+        let values = evaluate(&[
+            math,
+            "import { answer as a, max } from \"0\"; const x = a + max;",
+        ]);
+        assert_eq!(values.unwrap()[0].1, integer(297, PrimitiveType::S32));
+        let values = evaluate(&[math, "import * as math from \"0\"; const x = math::answer;"]);
+        assert_eq!(values.unwrap()[0].1, integer(42, PrimitiveType::S32));
+        assert!(matches!(
+            error(&[math, "import { hidden } from \"0\"; const x = hidden;"]),
+            Error::NotExported { .. }
+        ));
+
+        let first = "import { b } from \"1\"; export const a = b;";
+        let second = "import { a } from \"0\"; export const b = a;";
+        match error(&[first, second]) {
+            Error::ConstantCycle { path, .. } => assert_eq!(path, vec!["1::b", "0::a", "1::b"]),
+            e => panic!("expected a cycle, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_constants_from_c_headers() {
+        let program = parse(
+            "import { BUFFER_SIZE } from \"io.h\"; import * as io from \"io.h\"; \
+             const x = BUFFER_SIZE * 2; const y = io.VERSION; const z = io::NAME;",
+        )
+        .unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let header = crate::interop::c_header::parse_header(
+            "#define BUFFER_SIZE (1 << 12)\n#define VERSION 3UL\n#define NAME \"io\"\n",
+        );
+        let mut evaluator = ConstantEvaluator::new();
+        evaluator.add_header("io.h", &header);
+        evaluator.add_module("main", &module).unwrap();
+        let values: Vec<Constant> = evaluator
+            .evaluate_module("main")
+            .unwrap()
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                integer(8192, PrimitiveType::CInt),
+                integer(3, PrimitiveType::CULong),
+                Constant::String("io".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enum_values() {
+        let source = "enum E: u8 { A, B = 10, C, D = 2 * 11 } const x = E::C + E.D;";
+        let programs = parse(source).unwrap();
+        let body = programs.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let mut evaluator = ConstantEvaluator::new();
+        evaluator.add_module("main", &module).unwrap();
+        assert_eq!(
+            evaluator.enum_values("main", "E").unwrap(),
+            vec![
+                ("A", integer(0, PrimitiveType::U8)),
+                ("B", integer(10, PrimitiveType::U8)),
+                ("C", integer(11, PrimitiveType::U8)),
+                ("D", integer(22, PrimitiveType::U8)),
+            ]
+        );
+        assert_eq!(
+            evaluator.constant("main", "x").unwrap(),
+            integer(33, PrimitiveType::U8)
+        );
+        assert!(matches!(
+            error(&["enum E: u8 { A = 255, B }"]),
+            Error::ConstantOverflow { .. }
+        ));
+        assert!(matches!(
+            error(&["const x = E::Z; enum E { A }"]),
+            Error::UnknownEnumVariant { .. }
+        ));
+    }
+
+    #[test]
+    fn test_array_sizes() {
+        let arena = Arena::new();
+        let programs = parse("const n = 4;").unwrap();
+        let body = programs.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let mut evaluator = ConstantEvaluator::new();
+        evaluator.add_module("main", &module).unwrap();
+        let mut size = |source| {
+            let mut p = Parser::new(source, &arena);
+            evaluator.array_size("main", &p.expression_node().unwrap())
+        };
+        assert_eq!(size("n * 8"), Ok(32));
+        assert!(matches!(size("n - 5"), Err(Error::InvalidArraySize { .. })));
+    }
+}
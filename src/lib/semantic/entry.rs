@@ -0,0 +1,258 @@
+use crate::semantic::error::*;
+use crate::semantic::functions::{FunctionTable, Overload};
+use crate::semantic::module_declarations;
+use crate::semantic::types::*;
+use crate::syntax::ast::*;
+use std::collections::{HashMap, HashSet};
+
+/// The name of the function a program starts in.
+pub const MAIN: &str = "main";
+
+/// Where a program starts.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntryPoint<'ast> {
+    /// The top-level statements of a script. They see the program's
+    /// arguments as `args`, and a top-level `return` sets the exit code.
+    Script,
+    /// A `function main`, which may take the program's arguments and may
+    /// return an integer exit code.
+    Main {
+        overload: Overload<'ast>,
+        takes_arguments: bool,
+    },
+}
+
+/// The type of the program's arguments, `[][]char`.
+pub fn arguments_type() -> Type {
+    let string = Type::SizedArray(Box::new(Type::Primitive(PrimitiveType::Char)));
+    Type::SizedArray(Box::new(string))
+}
+
+/// Finds where a program starting in `module` begins: its `function main`,
+/// or else its top-level statements if it is a script. Modules without
+/// either have no entry point.
+pub fn find_entry_point<'ast>(
+    module: &Module<'ast>,
+    functions: &FunctionTable<'ast>,
+) -> Result<Option<EntryPoint<'ast>>> {
+    let mains: Vec<IdentifierNode<'ast>> = module_declarations(module)
+        .into_iter()
+        .filter_map(|declaration| match declaration.value.declarator {
+            Declarator::Function(f) if f.scope.is_none() && f.function_name.value == MAIN => {
+                Some(f.function_name)
+            }
+            _ => None,
+        })
+        .collect();
+    let main = match mains.as_slice() {
+        [] if module.is_script => return Ok(Some(EntryPoint::Script)),
+        [] => return Ok(None),
+        [main] => main,
+        [_, overload, ..] => {
+            return Err(Error::InvalidEntryPoint {
+                reason: "`main` cannot be overloaded",
+                span: overload.start as usize..overload.end as usize,
+            })
+        }
+    };
+    let span = main.start as usize..main.end as usize;
+    let invalid = |reason| Error::InvalidEntryPoint {
+        reason,
+        span: span.clone(),
+    };
+
+    let has_statements = module
+        .elements
+        .iter()
+        .any(|element| matches!(element.value, ModuleElement::Statement(_)));
+    if has_statements {
+        return Err(invalid(
+            "a script with top-level statements cannot also declare `main`",
+        ));
+    }
+    let overload = functions
+        .overloads(MAIN)
+        .iter()
+        .find(|overload| overload.span == span)
+        .cloned()
+        .ok_or_else(|| Error::UnknownFunction {
+            name: MAIN.to_string(),
+            span: span.clone(),
+        })?;
+    if !overload.generic_parameters.is_empty() {
+        return Err(invalid("`main` cannot be generic"));
+    }
+    let takes_arguments = match overload.signature.parameters.as_slice() {
+        [] => false,
+        [arguments] if arguments.unqualified() == &arguments_type() => true,
+        _ => {
+            return Err(invalid(
+                "`main` takes either no parameters or the arguments as a `[][]char`",
+            ))
+        }
+    };
+    match &overload.signature.return_type {
+        None => {}
+        Some(Type::Primitive(pt)) if pt.integer_layout().is_some() => {}
+        Some(_) => return Err(invalid("`main` returns either nothing or an integer")),
+    }
+    Ok(Some(EntryPoint::Main {
+        overload,
+        takes_arguments,
+    }))
+}
+
+/// Orders the modules a program starting in `entry` consists of so that
+/// every module is initialized after the modules it imports. `imports`
+/// lists the modules each module imports, in order.
+///
+/// Modules are visited depth-first in import order. An import cycle is
+/// broken where it closes: a module importing one that is still being
+/// visited is initialized first, so it may see that module's variables
+/// before they are initialized.
+pub fn initialization_order<'m>(
+    entry: &'m str,
+    imports: &HashMap<&'m str, Vec<&'m str>>,
+) -> Vec<&'m str> {
+    fn visit<'m>(
+        module: &'m str,
+        imports: &HashMap<&'m str, Vec<&'m str>>,
+        visited: &mut HashSet<&'m str>,
+        order: &mut Vec<&'m str>,
+    ) {
+        if !visited.insert(module) {
+            return;
+        }
+        for &imported in imports.get(module).into_iter().flatten() {
+            visit(imported, imports, visited, order);
+        }
+        order.push(module);
+    }
+
+    let mut order = vec![];
+    visit(entry, imports, &mut HashSet::new(), &mut order);
+    order
+}
+
+/// Whether a variable's initializer is a constant that can be stored in the
+/// program image. Other initializers run when the module is initialized.
+pub fn is_constant_initializer(expression: &ExpressionNode<'_>) -> bool {
+    match expression.value {
+        Expression::PrimitiveExpression(Primitive::Null) => false,
+        Expression::PrimitiveExpression(_) => true,
+        Expression::PrefixExpression(prefix) => {
+            matches!(
+                prefix.operator,
+                PrefixOperator::Minus | PrefixOperator::Plus
+            ) && is_constant_initializer(&prefix.operand)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parser::{parse, Parser};
+    use pretty_assertions::assert_eq;
+    use toolshed::Arena;
+
+    fn entry_point(source: &str) -> Result<Option<(bool, Option<bool>)>> {
+        let program = parse(source).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let env = TypeEnvironment::from_module(&module).unwrap();
+        let functions = FunctionTable::build("main", &module, &env).unwrap();
+        // Whether it is a script, and whether `main` takes the arguments.
+        Ok(
+            find_entry_point(&module, &functions)?.map(|entry| match entry {
+                EntryPoint::Script => (true, None),
+                EntryPoint::Main {
+                    takes_arguments, ..
+                } => (false, Some(takes_arguments)),
+            }),
+        )
+    }
+
+    #[test]
+    fn test_script_statements_are_the_entry_point() {
+        assert_eq!(entry_point("let x = 1; print(x);"), Ok(Some((true, None))));
+    }
+
+    #[test]
+    fn test_main_function() {
+        let source = "import { x } from \"x\"; function main() {}";
+        assert_eq!(entry_point(source), Ok(Some((false, Some(false)))));
+        let source = "export function main(args: [][]char) -> int { return 0; }";
+        assert_eq!(entry_point(source), Ok(Some((false, Some(true)))));
+        let source = "function main() -> u8 { return 0; }";
+        assert_eq!(entry_point(source), Ok(Some((false, Some(false)))));
+    }
+
+    #[test]
+    fn test_library_has_no_entry_point() {
+        assert_eq!(entry_point("export function f() {}"), Ok(None));
+    }
+
+    #[test]
+    fn test_invalid_main() {
+        let invalid = |source| match entry_point(source) {
+            Err(Error::InvalidEntryPoint { reason, .. }) => reason,
+            res => panic!("expected an invalid entry point, got {:?}", res),
+        };
+        assert_eq!(
+            invalid("export function main(code: int) {}"),
+            "`main` takes either no parameters or the arguments as a `[][]char`"
+        );
+        assert_eq!(
+            invalid("export function main() -> bool { return true; }"),
+            "`main` returns either nothing or an integer"
+        );
+        assert_eq!(
+            invalid("export function main() {} export function main(args: [][]char) {}"),
+            "`main` cannot be overloaded"
+        );
+        assert_eq!(
+            invalid("print(1); function main() {}"),
+            "a script with top-level statements cannot also declare `main`"
+        );
+    }
+
+    #[test]
+    fn test_imports_are_initialized_first() {
+        let imports: HashMap<&str, Vec<&str>> = vec![
+            ("app", vec!["shapes", "io"]),
+            ("shapes", vec!["math"]),
+            ("io", vec!["math"]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            initialization_order("app", &imports),
+            vec!["math", "shapes", "io", "app"]
+        );
+    }
+
+    #[test]
+    fn test_import_cycles_are_broken_where_they_close() {
+        let imports: HashMap<&str, Vec<&str>> =
+            vec![("app", vec!["a"]), ("a", vec!["b"]), ("b", vec!["a"])]
+                .into_iter()
+                .collect();
+        assert_eq!(initialization_order("app", &imports), vec!["b", "a", "app"]);
+    }
+
+    #[test]
+    fn test_constant_initializers() {
+        let constant = |source| {
+            let arena = Arena::new();
+            let mut p = Parser::new(source, &arena);
+            is_constant_initializer(&p.expression_node().unwrap())
+        };
+        assert!(constant("5"));
+        assert!(constant("-5"));
+        assert!(constant("\"hello\""));
+        assert!(!constant("5 + 2"));
+        assert!(!constant("f()"));
+    }
+}
@@ -0,0 +1,277 @@
+use std::ops::Range;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("unknown type {:?} at {}:{}", name, span.start, span.end)]
+    UnknownType { name: String, span: Range<usize> },
+    #[error("{:?} is not an interface at {}:{}", name, span.start, span.end)]
+    NotAnInterface { name: String, span: Range<usize> },
+    #[error("duplicate definition of {:?} at {}:{}", name, span.start, span.end)]
+    DuplicateDefinition { name: String, span: Range<usize> },
+    #[error("duplicate implementation of {} for {} at {}:{}", interface, implementor, span.start, span.end)]
+    DuplicateImplementation {
+        interface: String,
+        implementor: String,
+        span: Range<usize>,
+    },
+    #[error(
+        "implementation of {} for {} is missing method {:?}",
+        interface,
+        implementor,
+        method
+    )]
+    MissingInterfaceMethod {
+        interface: String,
+        implementor: String,
+        method: String,
+    },
+    #[error("method {:?} is not a member of interface {} at {}:{}", method, interface, span.start, span.end)]
+    UnknownInterfaceMethod {
+        interface: String,
+        method: String,
+        span: Range<usize>,
+    },
+    #[error("method {:?} does not match its signature in interface {} at {}:{}", method, interface, span.start, span.end)]
+    InterfaceMethodMismatch {
+        interface: String,
+        method: String,
+        span: Range<usize>,
+    },
+    #[error(
+        "interface {} cannot be used dynamically: method {:?} {}",
+        interface,
+        method,
+        reason
+    )]
+    NotObjectSafe {
+        interface: String,
+        method: String,
+        reason: &'static str,
+    },
+    #[error("cannot cast {} to {}: {}", from, to, reason)]
+    InvalidCast {
+        from: String,
+        to: String,
+        reason: &'static str,
+    },
+    #[error("no method {:?} on type {} at {}:{}", method, ty, span.start, span.end)]
+    UnknownMethod {
+        ty: String,
+        method: String,
+        span: Range<usize>,
+    },
+    #[error("type {} has no member {:?} at {}:{}", ty, member, span.start, span.end)]
+    UnknownMember {
+        ty: String,
+        member: String,
+        span: Range<usize>,
+    },
+    #[error("static method {}::{} cannot be called on an instance at {}:{}", ty, method, span.start, span.end)]
+    StaticMethodOnInstance {
+        ty: String,
+        method: String,
+        span: Range<usize>,
+    },
+    #[error("operator overloads cannot be static: {} at {}:{}", operator, span.start, span.end)]
+    StaticOperatorOverload {
+        operator: String,
+        span: Range<usize>,
+    },
+    #[error("{} must take {:?} parameters besides `this` at {}:{}", operator, expected, span.start, span.end)]
+    OperatorArity {
+        operator: String,
+        expected: Vec<usize>,
+        span: Range<usize>,
+    },
+    #[error("no overload of {} for {} at {}:{}", operator, operands, span.start, span.end)]
+    NoOperatorOverload {
+        operator: String,
+        operands: String,
+        span: Range<usize>,
+    },
+    #[error("unknown function {:?} at {}:{}", name, span.start, span.end)]
+    UnknownFunction { name: String, span: Range<usize> },
+    #[error("no overload of {}({}) matches; candidates are {:?} at {}:{}", name, arguments, candidates, span.start, span.end)]
+    NoMatchingOverload {
+        name: String,
+        arguments: String,
+        candidates: Vec<String>,
+        span: Range<usize>,
+    },
+    #[error("call to {} is ambiguous between {:?} at {}:{}", name, candidates, span.start, span.end)]
+    AmbiguousCall {
+        name: String,
+        candidates: Vec<String>,
+        span: Range<usize>,
+    },
+    #[error("{:?} is not exported by module {} at {}:{}", name, module, span.start, span.end)]
+    NotExported {
+        name: String,
+        module: String,
+        span: Range<usize>,
+    },
+    #[error("match does not handle {:?} at {}:{}", missing, span.start, span.end)]
+    NonExhaustiveMatch {
+        missing: Vec<String>,
+        span: Range<usize>,
+    },
+    #[error("unreachable pattern at {}:{}", span.start, span.end)]
+    UnreachablePattern { span: Range<usize> },
+    #[error("pattern cannot match a value of type {} at {}:{}", ty, span.start, span.end)]
+    PatternTypeMismatch { ty: String, span: Range<usize> },
+    #[error("enum {} has no variant {:?} at {}:{}", enumeration, variant, span.start, span.end)]
+    UnknownEnumVariant {
+        enumeration: String,
+        variant: String,
+        span: Range<usize>,
+    },
+    #[error("`try` needs a union with error and value members, found {} at {}:{}", ty, span.start, span.end)]
+    NotAResult { ty: String, span: Range<usize> },
+    #[error("cannot propagate {} from a function returning {} at {}:{}", error, return_type, span.start, span.end)]
+    UnpropagatedError {
+        error: String,
+        return_type: String,
+        span: Range<usize>,
+    },
+    #[error("cannot iterate over {} at {}:{}", ty, span.start, span.end)]
+    NotIterable { ty: String, span: Range<usize> },
+    #[error("type alias {:?} refers to itself at {}:{}", name, span.start, span.end)]
+    RecursiveTypeAlias { name: String, span: Range<usize> },
+    #[error("`typeof` types cannot be resolved here at {}:{}", span.start, span.end)]
+    UnresolvedTypeof { span: Range<usize> },
+    #[error("unknown variable {:?} at {}:{}", name, span.start, span.end)]
+    UnknownVariable { name: String, span: Range<usize> },
+    #[error("expected {} but found {} at {}:{}", expected, found, span.start, span.end)]
+    TypeMismatch {
+        expected: String,
+        found: String,
+        span: Range<usize>,
+    },
+    #[error("cannot assign to constant {:?} at {}:{}", name, span.start, span.end)]
+    AssignToConstant { name: String, span: Range<usize> },
+    #[error("function {:?} can end without returning a value at {}:{}", function, span.start, span.end)]
+    MissingReturn {
+        function: String,
+        span: Range<usize>,
+    },
+    #[error("`break` or `continue` outside of a loop at {}:{}", span.start, span.end)]
+    JumpOutsideLoop { span: Range<usize> },
+    #[error("invalid entry point: {} at {}:{}", reason, span.start, span.end)]
+    InvalidEntryPoint {
+        reason: &'static str,
+        span: Range<usize>,
+    },
+    #[error("expression cannot be evaluated at compile time at {}:{}", span.start, span.end)]
+    NotConstant { span: Range<usize> },
+    #[error("constant expression overflows {} at {}:{}", ty, span.start, span.end)]
+    ConstantOverflow { ty: String, span: Range<usize> },
+    #[error("division by zero in constant expression at {}:{}", span.start, span.end)]
+    DivisionByZero { span: Range<usize> },
+    #[error("constants refer to each other in a cycle: {} at {}:{}", path.join(" -> "), span.start, span.end)]
+    ConstantCycle {
+        path: Vec<String>,
+        span: Range<usize>,
+    },
+    #[error("array size must be a non-negative integer, found {} at {}:{}", size, span.start, span.end)]
+    InvalidArraySize { size: String, span: Range<usize> },
+    #[error("could not read C header {}: {} at {}:{}", path, reason, span.start, span.end)]
+    CouldNotReadHeader {
+        path: String,
+        reason: String,
+        span: Range<usize>,
+    },
+    #[error("cannot import {:?} from C: {} at {}:{}", name, reason, span.start, span.end)]
+    UnsupportedForeignDeclaration {
+        name: String,
+        reason: String,
+        span: Range<usize>,
+    },
+    #[error("invalid attribute {}: {} at {}:{}", name, reason, span.start, span.end)]
+    InvalidAttribute {
+        name: String,
+        reason: String,
+        span: Range<usize>,
+    },
+    #[error("cannot export {} to C: {} has no C equivalent at {}:{}", name, ty, span.start, span.end)]
+    NotRepresentableInC {
+        name: String,
+        ty: String,
+        span: Range<usize>,
+    },
+    #[error("cannot export {} to Rust: {} has no Rust equivalent at {}:{}", name, ty, span.start, span.end)]
+    NotRepresentableInRust {
+        name: String,
+        ty: String,
+        span: Range<usize>,
+    },
+}
+
+impl Error {
+    /// Where in the source the error is, if it is anywhere in particular.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Error::UnknownType { span, .. }
+            | Error::NotAnInterface { span, .. }
+            | Error::DuplicateDefinition { span, .. }
+            | Error::DuplicateImplementation { span, .. }
+            | Error::UnknownInterfaceMethod { span, .. }
+            | Error::InterfaceMethodMismatch { span, .. }
+            | Error::UnknownMethod { span, .. }
+            | Error::UnknownMember { span, .. }
+            | Error::StaticMethodOnInstance { span, .. }
+            | Error::StaticOperatorOverload { span, .. }
+            | Error::OperatorArity { span, .. }
+            | Error::NoOperatorOverload { span, .. }
+            | Error::UnknownFunction { span, .. }
+            | Error::NoMatchingOverload { span, .. }
+            | Error::AmbiguousCall { span, .. }
+            | Error::NotExported { span, .. }
+            | Error::NonExhaustiveMatch { span, .. }
+            | Error::UnreachablePattern { span }
+            | Error::PatternTypeMismatch { span, .. }
+            | Error::UnknownEnumVariant { span, .. }
+            | Error::NotAResult { span, .. }
+            | Error::UnpropagatedError { span, .. }
+            | Error::NotIterable { span, .. }
+            | Error::RecursiveTypeAlias { span, .. }
+            | Error::UnresolvedTypeof { span }
+            | Error::UnknownVariable { span, .. }
+            | Error::TypeMismatch { span, .. }
+            | Error::AssignToConstant { span, .. }
+            | Error::MissingReturn { span, .. }
+            | Error::JumpOutsideLoop { span }
+            | Error::InvalidEntryPoint { span, .. }
+            | Error::NotConstant { span }
+            | Error::ConstantOverflow { span, .. }
+            | Error::DivisionByZero { span }
+            | Error::ConstantCycle { span, .. }
+            | Error::InvalidArraySize { span, .. }
+            | Error::CouldNotReadHeader { span, .. }
+            | Error::UnsupportedForeignDeclaration { span, .. }
+            | Error::InvalidAttribute { span, .. }
+            | Error::NotRepresentableInC { span, .. }
+            | Error::NotRepresentableInRust { span, .. } => Some(span.clone()),
+            Error::MissingInterfaceMethod { .. }
+            | Error::NotObjectSafe { .. }
+            | Error::InvalidCast { .. } => None,
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Warning {
+    #[error("result of type {} is ignored and may hold an error at {}:{}", ty, span.start, span.end)]
+    UnusedResult { ty: String, span: Range<usize> },
+}
+
+impl Warning {
+    /// Where in the source the warning is.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Warning::UnusedResult { span, .. } => span.clone(),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,789 @@
+use crate::interop::c_header::{CFunction, CHeader, Symbol};
+use crate::interop::is_extern_c;
+use crate::semantic::error::*;
+use crate::semantic::module_declarations;
+use crate::semantic::types::*;
+use crate::syntax::ast::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// One function of an overload set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overload<'ast> {
+    pub name: &'ast str,
+    /// The symbol the overload is emitted under. It includes the defining
+    /// module and the parameter types, so every overload gets its own symbol
+    /// and keeps it when imported under another name.
+    pub symbol: String,
+    /// Whether the overload uses C's calling convention and is emitted under
+    /// its plain name, like functions declared `#[extern("C")]` and those
+    /// imported from C headers.
+    pub extern_c: bool,
+    pub generic_parameters: Vec<&'ast str>,
+    pub signature: FunctionSignature,
+    pub exported: bool,
+    pub span: Range<usize>,
+}
+
+impl<'ast> Overload<'ast> {
+    /// The conversion cost of passing `arguments` to this overload, or `None`
+    /// if they are not accepted.
+    fn cost(&self, arguments: &[Type]) -> Option<Vec<u8>> {
+        if arguments.len() != self.signature.parameters.len() {
+            return None;
+        }
+        let mut bindings = HashMap::new();
+        self.signature
+            .parameters
+            .iter()
+            .zip(arguments)
+            .map(|(parameter, argument)| conversion_cost(parameter, argument, &mut bindings))
+            .collect()
+    }
+}
+
+impl<'ast> std::fmt::Display for Overload<'ast> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}(", self.name)?;
+        for (i, parameter) in self.signature.parameters.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", parameter)?;
+        }
+        f.write_str(")")
+    }
+}
+
+const EXACT: u8 = 0;
+const WIDENING: u8 = 1;
+const INTEGER_TO_FLOAT: u8 = 2;
+const UNION_CONTAINMENT: u8 = 3;
+const GENERIC: u8 = 4;
+
+/// How much converting `argument` to `parameter` costs. Overloads needing
+/// cheaper conversions are preferred.
+fn conversion_cost(
+    parameter: &Type,
+    argument: &Type,
+    bindings: &mut HashMap<String, Type>,
+) -> Option<u8> {
+    if parameter.unqualified() == argument.unqualified() {
+        Some(EXACT)
+    } else if argument.widens_to(parameter) {
+        match (argument.unqualified(), parameter.unqualified()) {
+            (Type::Primitive(from), Type::Primitive(to))
+                if from.integer_layout().is_some() && to.float_width().is_some() =>
+            {
+                Some(INTEGER_TO_FLOAT)
+            }
+            _ => Some(WIDENING),
+        }
+    } else if let Type::Optional(inner) = parameter.unqualified() {
+        match argument.unqualified() {
+            Type::Primitive(PrimitiveType::Null) => Some(UNION_CONTAINMENT),
+            argument if inner.unqualified() == argument => Some(UNION_CONTAINMENT),
+            _ => None,
+        }
+    } else if matches!(parameter.unqualified(), Type::Union(_)) && parameter.contains(argument) {
+        Some(UNION_CONTAINMENT)
+    } else if parameter.unify(argument, bindings) {
+        Some(GENERIC)
+    } else {
+        None
+    }
+}
+
+/// The overload a C function is called through.
+fn foreign_overload<'ast>(
+    name: &'ast str,
+    function: &CFunction,
+    span: Range<usize>,
+) -> Overload<'ast> {
+    Overload {
+        name,
+        symbol: function.name.clone(),
+        extern_c: true,
+        generic_parameters: vec![],
+        signature: function.signature.clone(),
+        exported: false,
+        span,
+    }
+}
+
+/// Whether conversion costs `a` are at least as good as `b` for every
+/// argument, and better for at least one.
+fn better(a: &[u8], b: &[u8]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a <= b) && a.iter().zip(b).any(|(a, b)| a < b)
+}
+
+/// The functions of a module, including imported ones. Functions declared
+/// with the `function` syntax with the same name form an overload set.
+#[derive(Clone, Debug, Default)]
+pub struct FunctionTable<'ast> {
+    module: String,
+    functions: HashMap<&'ast str, Vec<Overload<'ast>>>,
+    /// Names bound by `const`, which cannot be overloaded.
+    constants: HashMap<&'ast str, Range<usize>>,
+    /// Overload sets exported with `export name;`, by exported name.
+    exports: HashMap<&'ast str, &'ast str>,
+    /// C headers imported with `import * as name`, by name.
+    namespaces: HashMap<&'ast str, CHeader>,
+    /// Catlang modules imported with `import * as name`, by name, holding
+    /// the overloads they export under the names they export them as.
+    modules: HashMap<&'ast str, FunctionTable<'ast>>,
+}
+
+impl<'ast> FunctionTable<'ast> {
+    /// Collects the functions `module` defines. `name` identifies the module
+    /// in symbols.
+    pub fn build(
+        name: &str,
+        module: &Module<'ast>,
+        env: &TypeEnvironment<'ast>,
+    ) -> std::result::Result<Self, Vec<Error>> {
+        let mut table = FunctionTable {
+            module: name.to_string(),
+            ..FunctionTable::default()
+        };
+        let mut errors = vec![];
+        let exported_declarations: Vec<DeclarationNode<'ast>> = module
+            .elements
+            .iter()
+            .filter_map(|element| match element.value {
+                ModuleElement::Export(export) => match export.value {
+                    Export::Declaration(declaration) => Some(declaration),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        for declaration in module_declarations(module) {
+            let exported = exported_declarations.contains(&declaration);
+            let res = match declaration.value.declarator {
+                Declarator::Function(f) if f.scope.is_none() => {
+                    is_extern_c(declaration.value.attributes)
+                        .and_then(|extern_c| table.define(env, f, exported, extern_c))
+                }
+                Declarator::Constant(c) => table.define_constant(c.identifier),
+                _ => Ok(()),
+            };
+            if let Err(e) = res {
+                errors.push(e);
+            }
+        }
+        table.export_functions(module);
+        match errors.len() {
+            0 => Ok(table),
+            _ => Err(errors),
+        }
+    }
+
+    /// Exports the overload sets `module` names in `export name;`
+    /// statements. Those of imported functions are exported once they have
+    /// been imported.
+    pub fn export_functions(&mut self, module: &Module<'ast>) {
+        for element in module.elements.iter() {
+            if let ModuleElement::Export(export) = element.value {
+                if let Export::Statement(statement) = export.value {
+                    let name = statement.identifier.value;
+                    if self.functions.contains_key(name) {
+                        let exported_as = statement.renamed_to.unwrap_or(statement.identifier);
+                        self.exports.insert(exported_as.value, name);
+                    }
+                }
+            }
+        }
+    }
+
+    fn define(
+        &mut self,
+        env: &TypeEnvironment<'ast>,
+        declarator: FunctionDeclarator<'ast>,
+        exported: bool,
+        extern_c: bool,
+    ) -> Result<()> {
+        let name = declarator.function_name;
+        let span = name.start as usize..name.end as usize;
+        if self.constants.contains_key(name.value) {
+            return Err(Error::DuplicateDefinition {
+                name: name.value.to_string(),
+                span,
+            });
+        }
+        // C has no overloading, so a plain name can only be used once.
+        let overloaded = self.overloads(name.value);
+        if (extern_c && !overloaded.is_empty()) || overloaded.iter().any(|o| o.extern_c) {
+            return Err(Error::InvalidAttribute {
+                name: "extern".to_string(),
+                reason: format!("{} is overloaded", name.value),
+                span,
+            });
+        }
+        if extern_c && !declarator.generic_parameters.is_empty() {
+            return Err(Error::InvalidAttribute {
+                name: "extern".to_string(),
+                reason: format!("{} is generic", name.value),
+                span,
+            });
+        }
+        let generic_parameters: Vec<&str> = declarator
+            .generic_parameters
+            .iter()
+            .map(|g| g.value)
+            .collect();
+        let signature = env.resolve_signature(
+            declarator.parameters,
+            declarator.return_type,
+            false,
+            &generic_parameters,
+        )?;
+        let mut overload = Overload {
+            name: name.value,
+            symbol: String::new(),
+            extern_c,
+            generic_parameters,
+            signature,
+            exported,
+            span,
+        };
+        overload.symbol = match extern_c {
+            true => name.value.to_string(),
+            false => format!("{}::{}", self.module, overload),
+        };
+        self.insert(name.value, overload)
+    }
+
+    fn define_constant(&mut self, identifier: IdentifierNode<'ast>) -> Result<()> {
+        let span = identifier.start as usize..identifier.end as usize;
+        if self.functions.contains_key(identifier.value)
+            || self.constants.contains_key(identifier.value)
+        {
+            return Err(Error::DuplicateDefinition {
+                name: identifier.value.to_string(),
+                span,
+            });
+        }
+        self.constants.insert(identifier.value, span);
+        Ok(())
+    }
+
+    /// Adds an overload to the set named `name`, unless one with the same
+    /// parameter types already exists.
+    fn insert(&mut self, name: &'ast str, overload: Overload<'ast>) -> Result<()> {
+        let overloads = self.functions.entry(name).or_default();
+        if overloads
+            .iter()
+            .any(|existing| existing.signature.parameters == overload.signature.parameters)
+        {
+            return Err(Error::DuplicateDefinition {
+                name: overload.to_string(),
+                span: overload.span,
+            });
+        }
+        overloads.push(overload);
+        Ok(())
+    }
+
+    pub fn overloads(&self, name: &str) -> &[Overload<'ast>] {
+        self.functions.get(name).map_or(&[], |overloads| overloads)
+    }
+
+    /// Every overload the module defines or imports.
+    pub fn iter(&self) -> impl Iterator<Item = &Overload<'ast>> {
+        self.functions.values().flatten()
+    }
+
+    /// The overloads other modules can import as `name`.
+    pub fn exported_overloads(&self, name: &str) -> Vec<&Overload<'ast>> {
+        match self.exports.get(name) {
+            Some(local) => self.overloads(local).iter().collect(),
+            None => self.overloads(name).iter().filter(|o| o.exported).collect(),
+        }
+    }
+
+    /// Imports the exported overloads named `identifier` from another module,
+    /// merging them into the local overload set of the same (or the renamed)
+    /// name. Names that are not functions of `from` are left alone.
+    pub fn import(
+        &mut self,
+        from: &FunctionTable<'ast>,
+        import: &ImportIdentifier<'ast>,
+    ) -> Result<()> {
+        let identifier = import.identifier;
+        let local_name = import.renamed_to.unwrap_or(identifier);
+        let span = identifier.start as usize..identifier.end as usize;
+        if !from.functions.contains_key(identifier.value)
+            && !from.exports.contains_key(identifier.value)
+        {
+            return Ok(());
+        }
+        let exported = from.exported_overloads(identifier.value);
+        if exported.is_empty() {
+            return Err(Error::NotExported {
+                name: identifier.value.to_string(),
+                module: from.module.clone(),
+                span,
+            });
+        }
+        if self.constants.contains_key(local_name.value) {
+            return Err(Error::DuplicateDefinition {
+                name: local_name.value.to_string(),
+                span,
+            });
+        }
+        for overload in exported {
+            let mut imported = overload.clone();
+            imported.name = local_name.value;
+            imported.exported = false;
+            imported.span = span.clone();
+            self.insert(local_name.value, imported)?;
+        }
+        Ok(())
+    }
+
+    /// Imports the function named `identifier` from a C header read from
+    /// `path`. Its symbol is its C name, so calls link against the C
+    /// definition. Other declarations of the header are left alone, and
+    /// names the header does not declare are errors.
+    pub fn import_foreign(
+        &mut self,
+        header: &CHeader,
+        path: &str,
+        import: &ImportIdentifier<'ast>,
+    ) -> Result<()> {
+        let identifier = import.identifier;
+        let local_name = import.renamed_to.unwrap_or(identifier);
+        let span = identifier.start as usize..identifier.end as usize;
+        if let Some(unsupported) = header.unsupported(identifier.value) {
+            return Err(Error::UnsupportedForeignDeclaration {
+                name: identifier.value.to_string(),
+                reason: unsupported.reason.clone(),
+                span,
+            });
+        }
+        let function = match header.lookup(identifier.value) {
+            Some(Symbol::Function(function)) => function,
+            Some(_) => return Ok(()),
+            None => {
+                return Err(Error::NotExported {
+                    name: identifier.value.to_string(),
+                    module: path.to_string(),
+                    span,
+                })
+            }
+        };
+        if self.constants.contains_key(local_name.value) {
+            return Err(Error::DuplicateDefinition {
+                name: local_name.value.to_string(),
+                span,
+            });
+        }
+        self.insert(
+            local_name.value,
+            foreign_overload(local_name.value, function, span),
+        )
+    }
+
+    /// Makes the functions of a C header callable as `name.function()` and
+    /// `name::function()`.
+    pub fn import_foreign_namespace(&mut self, name: &'ast str, header: CHeader) {
+        self.namespaces.insert(name, header);
+    }
+
+    /// The C header imported as `name`, if any.
+    pub fn foreign_namespace(&self, name: &str) -> Option<&CHeader> {
+        self.namespaces.get(name)
+    }
+
+    /// Makes the functions the catlang module `from` exports callable as
+    /// `name.function()` and `name::function()`.
+    pub fn import_namespace(&mut self, name: &'ast str, from: &FunctionTable<'ast>) {
+        let mut namespace = FunctionTable {
+            module: from.module.clone(),
+            ..FunctionTable::default()
+        };
+        for &exported_as in from.functions.keys().chain(from.exports.keys()) {
+            let overloads: Vec<Overload<'ast>> = from
+                .exported_overloads(exported_as)
+                .into_iter()
+                .map(|overload| Overload {
+                    name: exported_as,
+                    exported: false,
+                    ..overload.clone()
+                })
+                .collect();
+            if !overloads.is_empty() {
+                namespace.functions.insert(exported_as, overloads);
+            }
+        }
+        self.modules.insert(name, namespace);
+    }
+
+    /// The functions of the catlang module imported as `name`, if any.
+    pub fn namespace(&self, name: &str) -> Option<&FunctionTable<'ast>> {
+        self.modules.get(name)
+    }
+
+    /// Resolves a call to `function` of the catlang module imported as
+    /// `namespace`, or returns `None` if `namespace` names no module.
+    pub fn resolve_namespace_call(
+        &self,
+        namespace: &str,
+        function: IdentifierNode<'ast>,
+        arguments: &[Type],
+        span: Range<usize>,
+    ) -> Result<Option<&Overload<'ast>>> {
+        let module = match self.modules.get(namespace) {
+            Some(module) => module,
+            None => return Ok(None),
+        };
+        if module.overloads(function.value).is_empty() {
+            return Err(Error::UnknownFunction {
+                name: format!("{}.{}", namespace, function.value),
+                span,
+            });
+        }
+        module
+            .resolve_call(function.value, arguments, span)
+            .map(Some)
+    }
+
+    /// Resolves a call to `function` of the C header imported as
+    /// `namespace`, or returns `None` if `namespace` names no header.
+    pub fn resolve_foreign_call(
+        &self,
+        namespace: &str,
+        function: IdentifierNode<'ast>,
+        arguments: &[Type],
+        span: Range<usize>,
+    ) -> Result<Option<Overload<'ast>>> {
+        let header = match self.namespaces.get(namespace) {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let name = function.value;
+        let declared = header.functions.iter().find(|f| f.name == name);
+        let overload = match (declared, header.unsupported(name)) {
+            (Some(declared), _) => foreign_overload(name, declared, span.clone()),
+            (None, Some(unsupported)) => {
+                return Err(Error::UnsupportedForeignDeclaration {
+                    name: name.to_string(),
+                    reason: unsupported.reason.clone(),
+                    span,
+                })
+            }
+            (None, None) => {
+                return Err(Error::UnknownFunction {
+                    name: format!("{}.{}", namespace, name),
+                    span,
+                })
+            }
+        };
+        if overload.cost(arguments).is_none() {
+            return Err(Error::NoMatchingOverload {
+                name: name.to_string(),
+                arguments: arguments
+                    .iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                candidates: vec![overload.to_string()],
+                span,
+            });
+        }
+        Ok(Some(overload))
+    }
+
+    /// Picks the overload of `name` to call with `arguments`. The overload
+    /// needing the cheapest conversions wins; exact matches beat numeric
+    /// widening (integers to integers before integers to floats), which beats
+    /// union containment, which beats generic parameters.
+    pub fn resolve_call(
+        &self,
+        name: &str,
+        arguments: &[Type],
+        span: Range<usize>,
+    ) -> Result<&Overload<'ast>> {
+        let overloads = self.overloads(name);
+        if overloads.is_empty() {
+            return Err(Error::UnknownFunction {
+                name: name.to_string(),
+                span,
+            });
+        }
+        let viable: Vec<(&Overload<'ast>, Vec<u8>)> = overloads
+            .iter()
+            .filter_map(|overload| overload.cost(arguments).map(|cost| (overload, cost)))
+            .collect();
+        let best: Vec<&(&Overload<'ast>, Vec<u8>)> = viable
+            .iter()
+            .filter(|(_, cost)| !viable.iter().any(|(_, other)| better(other, cost)))
+            .collect();
+        match best.as_slice() {
+            [(overload, _)] => Ok(overload),
+            [] => Err(Error::NoMatchingOverload {
+                name: name.to_string(),
+                arguments: arguments
+                    .iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                candidates: overloads.iter().map(|o| o.to_string()).collect(),
+                span,
+            }),
+            ambiguous => Err(Error::AmbiguousCall {
+                name: name.to_string(),
+                candidates: ambiguous.iter().map(|(o, _)| o.to_string()).collect(),
+                span,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parser::parse;
+    use pretty_assertions::assert_eq;
+
+    const OVERLOADS: &str = "
+        function printMe() {}
+        function printMe(times: int) {}
+        function printMe(value: f64) {}
+        function scale(value: s64, factor: f32) {}
+        function scale(value: s32, factor: f64) {}
+        function show(value: int | bool) {}
+        export function area(side: int) -> int {}
+        export function area(width: int, height: int) -> int {}
+        export scale as resize;
+    ";
+
+    fn with_table<F>(source: &str, f: F)
+    where
+        F: FnOnce(std::result::Result<FunctionTable, Vec<Error>>),
+    {
+        let program = parse(source).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let env = TypeEnvironment::from_module(&module).unwrap();
+        f(FunctionTable::build("shapes", &module, &env))
+    }
+
+    fn primitive(pt: PrimitiveType) -> Type {
+        Type::Primitive(pt)
+    }
+
+    #[test]
+    fn test_exact_matches_and_widening() {
+        with_table(OVERLOADS, |table| {
+            let table = table.unwrap();
+            let call = table.resolve_call("printMe", &[], 0..0).unwrap();
+            assert_eq!(call.symbol, "shapes::printMe()");
+
+            let int = primitive(PrimitiveType::S32);
+            let call = table.resolve_call("printMe", &[int], 0..0).unwrap();
+            assert_eq!(call.symbol, "shapes::printMe(s32)");
+
+            let short = primitive(PrimitiveType::S16);
+            let call = table.resolve_call("printMe", &[short], 0..0).unwrap();
+            assert_eq!(call.symbol, "shapes::printMe(s32)");
+
+            let float = primitive(PrimitiveType::F32);
+            let call = table.resolve_call("printMe", &[float], 0..0).unwrap();
+            assert_eq!(call.symbol, "shapes::printMe(f64)");
+        });
+    }
+
+    #[test]
+    fn test_union_containment() {
+        with_table(OVERLOADS, |table| {
+            let table = table.unwrap();
+            let boolean = primitive(PrimitiveType::Bool);
+            assert!(table.resolve_call("show", &[boolean], 0..0).is_ok());
+            let float = primitive(PrimitiveType::F32);
+            assert_eq!(
+                table.resolve_call("show", &[float], 3..7),
+                Err(Error::NoMatchingOverload {
+                    name: "show".to_string(),
+                    arguments: "f32".to_string(),
+                    candidates: vec!["show((s32 | bool))".to_string()],
+                    span: 3..7,
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_ambiguous_calls_list_candidates() {
+        with_table(OVERLOADS, |table| {
+            let table = table.unwrap();
+            let arguments = [primitive(PrimitiveType::S32), primitive(PrimitiveType::F32)];
+            match table.resolve_call("scale", &arguments, 0..0) {
+                Err(Error::AmbiguousCall { candidates, .. }) => assert_eq!(
+                    candidates,
+                    vec!["scale(s64, f32)".to_string(), "scale(s32, f64)".to_string()]
+                ),
+                res => panic!("expected an ambiguous call, got {:?}", res),
+            }
+        });
+    }
+
+    #[test]
+    fn test_conflicting_overloads() {
+        let source = "
+            function printMe(times: int) {}
+            function printMe(count: s32) {}
+            const area = 3;
+            function area() {}
+        ";
+        with_table(source, |table| {
+            let errors = table.unwrap_err();
+            assert_eq!(errors.len(), 2);
+            assert!(matches!(
+                &errors[0],
+                Error::DuplicateDefinition { name, .. } if name == "printMe(s32)"
+            ));
+            assert!(matches!(
+                &errors[1],
+                Error::DuplicateDefinition { name, .. } if name == "area"
+            ));
+        });
+    }
+
+    #[test]
+    fn test_imports_merge_exported_overloads() {
+        with_table(OVERLOADS, |shapes| {
+            let shapes = shapes.unwrap();
+            let source = "
+                import { area, printMe, resize } from \"./shapes\";
+                function area(width: f64, height: f64) -> f64 {}
+            ";
+            let program = parse(source).unwrap();
+            let body = program.body();
+            let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+            let env = TypeEnvironment::from_module(&module).unwrap();
+            let mut main = FunctionTable::build("main", &module, &env).unwrap();
+            let imports: Vec<ImportIdentifier> = module
+                .elements
+                .iter()
+                .filter_map(|element| match element.value {
+                    ModuleElement::Import(import) => Some(import.value.import_list),
+                    _ => None,
+                })
+                .flat_map(|list| match list {
+                    ImportList::NamedImportList(named) => {
+                        named.imports.iter().map(|i| i.value).collect::<Vec<_>>()
+                    }
+                    ImportList::GlobImportList(_) => vec![],
+                })
+                .collect();
+
+            assert_eq!(main.import(&shapes, &imports[0]), Ok(()));
+            assert_eq!(main.overloads("area").len(), 3);
+            let int = primitive(PrimitiveType::S32);
+            let call = main
+                .resolve_call("area", &[int.clone(), int], 0..0)
+                .unwrap();
+            assert_eq!(call.symbol, "shapes::area(s32, s32)");
+
+            assert_eq!(main.import(&shapes, &imports[2]), Ok(()));
+            assert_eq!(main.overloads("resize").len(), 2);
+            assert_eq!(
+                main.overloads("resize")[0].symbol,
+                "shapes::scale(s64, f32)"
+            );
+
+            assert!(matches!(
+                main.import(&shapes, &imports[1]),
+                Err(Error::NotExported { .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn test_extern_c_functions() {
+        with_table(
+            "#[extern(\"C\")] export function area(side: s32) -> s32 {}",
+            |table| {
+                let table = table.unwrap();
+                assert_eq!(table.overloads("area")[0].symbol, "area");
+                assert!(table.overloads("area")[0].extern_c);
+            },
+        );
+        with_table(
+            "#[extern(\"C\")] function f(a: s32) {} function f(a: f32) {}",
+            |table| {
+                assert!(matches!(
+                    table.unwrap_err().as_slice(),
+                    [Error::InvalidAttribute { reason, .. }] if reason == "f is overloaded"
+                ));
+            },
+        );
+        with_table("#[extern(\"Rust\")] function f() {}", |table| {
+            assert!(matches!(
+                table.unwrap_err().as_slice(),
+                [Error::InvalidAttribute { .. }]
+            ));
+        });
+    }
+
+    #[test]
+    fn test_foreign_imports() {
+        let header = crate::interop::c_header::parse_header(
+            "void greet(const char *name);
+            int add(int a, int b);
+            int printf(const char *format, ...);
+            #define SIZE 4",
+        );
+        let source = "import { add as plus, SIZE, printf, missing } from \"math.h\";";
+        let program = parse(source).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let imports: Vec<ImportIdentifier> = match module.elements.iter().next().unwrap().value {
+            ModuleElement::Import(import) => match import.value.import_list {
+                ImportList::NamedImportList(named) => {
+                    named.imports.iter().map(|i| i.value).collect()
+                }
+                ImportList::GlobImportList(_) => vec![],
+            },
+            _ => vec![],
+        };
+        let mut table = FunctionTable::default();
+        assert_eq!(table.import_foreign(&header, "math.h", &imports[0]), Ok(()));
+        let int = primitive(PrimitiveType::CInt);
+        let call = table
+            .resolve_call("plus", &[int.clone(), int.clone()], 0..0)
+            .unwrap();
+        assert_eq!(call.symbol, "add");
+        assert_eq!(table.import_foreign(&header, "math.h", &imports[1]), Ok(()));
+        assert!(matches!(
+            table.import_foreign(&header, "math.h", &imports[2]),
+            Err(Error::UnsupportedForeignDeclaration { reason, .. })
+                if reason == "variadic functions are not supported"
+        ));
+        assert!(matches!(
+            table.import_foreign(&header, "math.h", &imports[3]),
+            Err(Error::NotExported { .. })
+        ));
+
+        table.import_foreign_namespace("math", header);
+        let add = imports[0].identifier;
+        let call = table
+            .resolve_foreign_call("math", add, &[int.clone(), int], 0..0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            call.signature.return_type,
+            Some(primitive(PrimitiveType::CInt))
+        );
+        assert!(matches!(
+            table.resolve_foreign_call("math", add, &[], 0..0),
+            Err(Error::NoMatchingOverload { .. })
+        ));
+        assert_eq!(
+            table.resolve_foreign_call("other", add, &[], 0..0),
+            Ok(None)
+        );
+    }
+}
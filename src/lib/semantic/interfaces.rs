@@ -0,0 +1,541 @@
+use crate::semantic::error::*;
+use crate::semantic::module_declarations;
+use crate::semantic::types::*;
+use crate::syntax::ast::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// An `impl` block attaching an interface to an implementing type. `methods`
+/// are stored in vtable slot order, i.e. the order the interface declares them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Implementation<'ast> {
+    pub interface: NamedTypeRef,
+    pub implementor: Type,
+    pub generic_parameters: Vec<&'ast str>,
+    pub methods: Vec<FunctionDeclarator<'ast>>,
+    pub span: Range<usize>,
+}
+
+impl<'ast> Implementation<'ast> {
+    /// The symbol the given method of this implementation is emitted under.
+    pub fn method_symbol(&self, method: &str) -> String {
+        format!("{}::{}::{}", self.implementor, self.interface, method)
+    }
+
+    /// The symbol of the vtable for this implementation.
+    pub fn vtable_symbol(&self) -> String {
+        format!("{}::{}::vtable", self.implementor, self.interface)
+    }
+
+    /// Whether this implementation covers `implementor` as `interface`.
+    /// Implementing an interface for a type union implements it for every
+    /// member of that union.
+    fn covers(
+        &self,
+        implementor: &Type,
+        interface: &NamedTypeRef,
+    ) -> Option<HashMap<String, Type>> {
+        self.implementor.members().iter().find_map(|pattern| {
+            let mut bindings = HashMap::new();
+            if pattern.unify(implementor, &mut bindings)
+                && self.interface.unify(interface, &mut bindings)
+            {
+                Some(bindings)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// The order of function pointers in the vtables of an interface.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VTableLayout {
+    pub interface: String,
+    pub slots: Vec<String>,
+}
+
+impl VTableLayout {
+    pub fn slot(&self, method: &str) -> Option<u32> {
+        self.slots
+            .iter()
+            .position(|slot| slot == method)
+            .map(|index| index as u32)
+    }
+}
+
+/// Every interface and `impl` block of a module.
+#[derive(Clone, Debug, Default)]
+pub struct InterfaceTable<'ast> {
+    interfaces: HashMap<&'ast str, InterfaceDeclarator<'ast>>,
+    implementations: Vec<Implementation<'ast>>,
+}
+
+impl<'ast> InterfaceTable<'ast> {
+    pub fn build(
+        module: &Module<'ast>,
+        env: &TypeEnvironment<'ast>,
+    ) -> std::result::Result<Self, Vec<Error>> {
+        let mut table = InterfaceTable::default();
+        let mut errors = vec![];
+        // Interfaces come from the environment, so that those of the prelude
+        // can be implemented too.
+        for (name, definition) in env.definitions() {
+            if let TypeDefinition::Interface(interface) = definition {
+                table.interfaces.insert(name, *interface);
+            }
+        }
+        let declarations = module_declarations(module);
+        for declaration in &declarations {
+            if let Declarator::Impl(implementation) = declaration.value.declarator {
+                let span = declaration.start as usize..declaration.end as usize;
+                match table.implementation(env, implementation, span) {
+                    Ok(implementation) => table.implementations.push(implementation),
+                    Err(mut e) => errors.append(&mut e),
+                }
+            }
+        }
+        match errors.len() {
+            0 => Ok(table),
+            _ => Err(errors),
+        }
+    }
+
+    pub fn interface(&self, name: &str) -> Option<&InterfaceDeclarator<'ast>> {
+        self.interfaces.get(name)
+    }
+
+    pub fn implementations(&self) -> &[Implementation<'ast>] {
+        &self.implementations
+    }
+
+    pub fn find_implementation(
+        &self,
+        implementor: &Type,
+        interface: &NamedTypeRef,
+    ) -> Option<&Implementation<'ast>> {
+        self.implementations
+            .iter()
+            .find(|implementation| implementation.covers(implementor, interface).is_some())
+    }
+
+    /// The interface `implementation` implements for `implementor`, with the
+    /// generic parameters of the `impl` block bound, if it covers that type.
+    pub fn implemented_interface(
+        &self,
+        implementation: &Implementation<'ast>,
+        implementor: &Type,
+    ) -> Option<NamedTypeRef> {
+        implementation
+            .implementor
+            .members()
+            .iter()
+            .find_map(|pattern| {
+                let mut bindings = HashMap::new();
+                if pattern.unify(implementor, &mut bindings) {
+                    Some(implementation.interface.substitute(&bindings))
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Resolves the method signatures of an interface instantiated with
+    /// concrete generic arguments, in vtable slot order.
+    pub fn method_signatures(
+        &self,
+        env: &TypeEnvironment<'ast>,
+        interface: &NamedTypeRef,
+    ) -> Result<Vec<(&'ast str, FunctionSignature)>> {
+        let declarator = self.declarator(interface)?;
+        let generics: Vec<&str> = declarator
+            .generic_parameters
+            .iter()
+            .map(|g| g.value)
+            .collect();
+        let bindings: HashMap<String, Type> = generics
+            .iter()
+            .map(|g| g.to_string())
+            .zip(interface.generic_arguments.iter().cloned())
+            .collect();
+        let mut signatures = vec![];
+        for method in declarator.methods.iter() {
+            let mut scope = generics.clone();
+            scope.extend(method.value.generic_parameters.iter().map(|g| g.value));
+            let signature = env.resolve_signature(
+                method.value.parameters,
+                method.value.return_type,
+                method.value.receiver.is_some(),
+                &scope,
+            )?;
+            signatures.push((
+                method.value.function_name.value,
+                FunctionSignature {
+                    receiver: signature.receiver,
+                    parameters: signature
+                        .parameters
+                        .iter()
+                        .map(|p| p.substitute(&bindings))
+                        .collect(),
+                    return_type: signature.return_type.map(|r| r.substitute(&bindings)),
+                },
+            ));
+        }
+        Ok(signatures)
+    }
+
+    pub fn vtable_layout(&self, interface: &str) -> Option<VTableLayout> {
+        self.interfaces
+            .get(interface)
+            .map(|declarator| VTableLayout {
+                interface: interface.to_string(),
+                slots: declarator
+                    .methods
+                    .iter()
+                    .map(|method| method.value.function_name.value.to_string())
+                    .collect(),
+            })
+    }
+
+    /// Checks that an interface can be used behind a pointer, i.e. that every
+    /// method can be called through a vtable without knowing the concrete type.
+    pub fn check_object_safety(&self, env: &TypeEnvironment<'ast>, interface: &str) -> Result<()> {
+        let declarator = self.declarator(&NamedTypeRef::new(interface))?;
+        let generics: Vec<&str> = declarator
+            .generic_parameters
+            .iter()
+            .map(|g| g.value)
+            .collect();
+        for method in declarator.methods.iter() {
+            let not_object_safe = |reason| Error::NotObjectSafe {
+                interface: interface.to_string(),
+                method: method.value.function_name.value.to_string(),
+                reason,
+            };
+            if method.value.receiver.is_none() {
+                return Err(not_object_safe("has no `this` receiver"));
+            }
+            if !method.value.generic_parameters.is_empty() {
+                return Err(not_object_safe("declares its own generic parameters"));
+            }
+            let signature = env.resolve_signature(
+                method.value.parameters,
+                method.value.return_type,
+                true,
+                &generics,
+            )?;
+            let by_value = signature
+                .parameters
+                .iter()
+                .chain(signature.return_type.iter())
+                .any(|ty| mentions_by_value(ty, interface));
+            if by_value {
+                return Err(not_object_safe("uses the interface by value"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a cast from `from` to `to` where `to` is an interface pointer.
+    /// Casts to other types are not restricted by interfaces.
+    pub fn check_cast(&self, env: &TypeEnvironment<'ast>, from: &Type, to: &Type) -> Result<()> {
+        let interface = match to.interface_pointer() {
+            Some(interface) => interface,
+            None => return Ok(()),
+        };
+        let invalid_cast = |reason| Error::InvalidCast {
+            from: from.to_string(),
+            to: to.to_string(),
+            reason,
+        };
+        let pointee = match from.unqualified() {
+            Type::Pointer(pointee) => pointee.unqualified(),
+            _ => return Err(invalid_cast("only pointers can point to an interface")),
+        };
+        if let Type::Interface(source) = pointee {
+            return if source == interface {
+                Ok(())
+            } else {
+                Err(invalid_cast("interface pointers cannot change interface"))
+            };
+        }
+        self.check_object_safety(env, &interface.name)?;
+        match self.find_implementation(pointee, interface) {
+            Some(_) => Ok(()),
+            None => Err(invalid_cast("the type does not implement the interface")),
+        }
+    }
+
+    fn declarator(&self, interface: &NamedTypeRef) -> Result<&InterfaceDeclarator<'ast>> {
+        self.interfaces
+            .get(interface.name.as_str())
+            .ok_or_else(|| Error::NotAnInterface {
+                name: interface.name.clone(),
+                span: 0..0,
+            })
+    }
+
+    fn implementation(
+        &self,
+        env: &TypeEnvironment<'ast>,
+        declarator: ImplDeclarator<'ast>,
+        span: Range<usize>,
+    ) -> std::result::Result<Implementation<'ast>, Vec<Error>> {
+        let generics: Vec<&str> = declarator
+            .generic_parameters
+            .iter()
+            .map(|g| g.value)
+            .collect();
+        let interface = match env.resolve(&declarator.interface, &generics) {
+            Ok(Type::Interface(interface)) => interface,
+            Ok(other) => {
+                return Err(vec![Error::NotAnInterface {
+                    name: other.to_string(),
+                    span: declarator.interface.start as usize..declarator.interface.end as usize,
+                }])
+            }
+            Err(e) => return Err(vec![e]),
+        };
+        let implementor = env
+            .resolve(&declarator.implementor, &generics)
+            .map_err(|e| vec![e])?;
+
+        let overlapping = self.implementations.iter().any(|existing| {
+            implementor.members().iter().any(|member| {
+                let generic_overlap = existing.interface.name == interface.name
+                    && existing
+                        .implementor
+                        .members()
+                        .iter()
+                        .any(|other| member.unify(other, &mut HashMap::new()));
+                generic_overlap || existing.covers(member, &interface).is_some()
+            })
+        });
+        if overlapping {
+            return Err(vec![Error::DuplicateImplementation {
+                interface: interface.to_string(),
+                implementor: implementor.to_string(),
+                span,
+            }]);
+        }
+
+        let required = self
+            .method_signatures(env, &interface)
+            .map_err(|e| vec![e])?;
+        let mut provided: HashMap<&str, FunctionDeclarator<'ast>> = HashMap::new();
+        let mut errors = vec![];
+        for method in declarator.methods.iter() {
+            let name = method.value.function_name.value;
+            let method_span =
+                method.value.function_name.start as usize..method.value.function_name.end as usize;
+            let expected = match required.iter().find(|(required, _)| *required == name) {
+                Some((_, signature)) => signature,
+                None => {
+                    errors.push(Error::UnknownInterfaceMethod {
+                        interface: interface.to_string(),
+                        method: name.to_string(),
+                        span: method_span,
+                    });
+                    continue;
+                }
+            };
+            let mut scope = generics.clone();
+            scope.extend(method.value.generic_parameters.iter().map(|g| g.value));
+            match env.resolve_signature(
+                method.value.parameters,
+                method.value.return_type,
+                method.value.receiver.is_some(),
+                &scope,
+            ) {
+                Ok(ref signature) if signature == expected => {}
+                Ok(_) => errors.push(Error::InterfaceMethodMismatch {
+                    interface: interface.to_string(),
+                    method: name.to_string(),
+                    span: method_span,
+                }),
+                Err(e) => errors.push(e),
+            }
+            if provided.insert(name, method.value).is_some() {
+                errors.push(Error::DuplicateDefinition {
+                    name: name.to_string(),
+                    span: method.value.function_name.start as usize
+                        ..method.value.function_name.end as usize,
+                });
+            }
+        }
+
+        let mut methods = vec![];
+        for (name, _) in &required {
+            match provided.get(name) {
+                Some(method) => methods.push(*method),
+                None => errors.push(Error::MissingInterfaceMethod {
+                    interface: interface.to_string(),
+                    implementor: implementor.to_string(),
+                    method: name.to_string(),
+                }),
+            }
+        }
+
+        match errors.len() {
+            0 => Ok(Implementation {
+                interface,
+                implementor,
+                generic_parameters: generics,
+                methods,
+                span,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+/// Whether `ty` holds a value of the named interface directly rather than
+/// behind a pointer.
+fn mentions_by_value(ty: &Type, interface: &str) -> bool {
+    match ty {
+        Type::Pointer(_) => false,
+        Type::Interface(named) => named.name == interface,
+        Type::SizedArray(inner)
+        | Type::UnsizedArray(inner)
+        | Type::Const(inner)
+        | Type::Volatile(inner)
+        | Type::Optional(inner) => mentions_by_value(inner, interface),
+        Type::Union(members) => members.iter().any(|m| mentions_by_value(m, interface)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parser::parse;
+    use pretty_assertions::assert_eq;
+
+    const SHAPES: &str = "
+        interface IDrawable {
+            function draw(this, scale: float) -> bool;
+            function area(this) -> float;
+        }
+        interface IFactory {
+            function create() -> *IFactory;
+        }
+        struct Circle { radius: float; }
+        struct Square { side: float; }
+        impl IDrawable for Circle {
+            function area(this) -> float {}
+            function draw(this, scale: float) -> bool {}
+        }
+    ";
+
+    fn with_table<F>(source: &str, f: F)
+    where
+        F: for<'ast> FnOnce(
+            &TypeEnvironment<'ast>,
+            std::result::Result<InterfaceTable<'ast>, Vec<Error>>,
+        ),
+    {
+        let program = parse(source).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let env = TypeEnvironment::from_module(&module).unwrap();
+        let table = InterfaceTable::build(&module, &env);
+        f(&env, table)
+    }
+
+    fn pointer_to(name: &str) -> Type {
+        Type::Pointer(Box::new(Type::Struct(NamedTypeRef::new(name))))
+    }
+
+    fn interface_pointer(name: &str) -> Type {
+        Type::Pointer(Box::new(Type::Interface(NamedTypeRef::new(name))))
+    }
+
+    #[test]
+    fn test_vtable_layout_follows_interface_order() {
+        with_table(SHAPES, |_, table| {
+            let table = table.unwrap();
+            let layout = table.vtable_layout("IDrawable").unwrap();
+            assert_eq!(layout.slots, vec!["draw", "area"]);
+            assert_eq!(layout.slot("area"), Some(1));
+
+            let implementation = &table.implementations()[0];
+            let order: Vec<&str> = implementation
+                .methods
+                .iter()
+                .map(|m| m.function_name.value)
+                .collect();
+            assert_eq!(order, vec!["draw", "area"]);
+            assert_eq!(
+                implementation.method_symbol("draw"),
+                "Circle::IDrawable::draw"
+            );
+        });
+    }
+
+    #[test]
+    fn test_object_safety() {
+        with_table(SHAPES, |env, table| {
+            let table = table.unwrap();
+            assert_eq!(table.check_object_safety(env, "IDrawable"), Ok(()));
+            assert_eq!(
+                table.check_object_safety(env, "IFactory"),
+                Err(Error::NotObjectSafe {
+                    interface: "IFactory".to_string(),
+                    method: "create".to_string(),
+                    reason: "has no `this` receiver",
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_interface_pointer_casts() {
+        with_table(SHAPES, |env, table| {
+            let table = table.unwrap();
+            let drawable = interface_pointer("IDrawable");
+            assert_eq!(
+                table.check_cast(env, &pointer_to("Circle"), &drawable),
+                Ok(())
+            );
+            assert_eq!(table.check_cast(env, &drawable, &drawable), Ok(()));
+            assert_eq!(
+                table.check_cast(env, &pointer_to("Square"), &drawable),
+                Err(Error::InvalidCast {
+                    from: "*Square".to_string(),
+                    to: "*IDrawable".to_string(),
+                    reason: "the type does not implement the interface",
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_missing_and_mismatched_methods() {
+        let source = "
+            interface IDrawable { function draw(this, scale: float) -> bool; }
+            struct Circle { radius: float; }
+            struct Square { side: float; }
+            impl IDrawable for Circle { }
+            impl IDrawable for Square { function draw(this, scale: int) -> bool {} }
+        ";
+        with_table(source, |_, table| {
+            let errors = table.unwrap_err();
+            assert_eq!(errors.len(), 2);
+            assert!(matches!(errors[0], Error::MissingInterfaceMethod { .. }));
+            assert!(matches!(errors[1], Error::InterfaceMethodMismatch { .. }));
+        });
+    }
+
+    #[test]
+    fn test_union_implementations() {
+        let source = "
+            interface ICollection<T> { function length(this) -> int; }
+            impl<T> ICollection<T> for ([]T | [..]T) { function length(this) -> int {} }
+            impl ICollection<int> for []int { function length(this) -> int {} }
+        ";
+        with_table(source, |_, table| {
+            let errors = table.unwrap_err();
+            assert!(matches!(errors[0], Error::DuplicateImplementation { .. }));
+        });
+    }
+}
@@ -0,0 +1,232 @@
+use crate::semantic::error::*;
+use crate::semantic::interfaces::InterfaceTable;
+use crate::semantic::methods::{MethodCall, MethodTable};
+use crate::semantic::prelude::{ITERATOR_INTERFACE, RANGE_STRUCT};
+use crate::semantic::types::*;
+use crate::syntax::ast::*;
+
+/// How a `for (x in expr)` loop steps through `expr`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Iteration {
+    /// Counts from the start of an integer range to its end. `inclusive` is
+    /// known for range expressions written in the loop itself; range values
+    /// carry it at runtime.
+    Range {
+        element: Type,
+        inclusive: Option<bool>,
+    },
+    /// Counts through the elements of a sized or growable array.
+    Array { element: Type },
+    /// Calls `next` until it returns `null`.
+    Iterator { element: Type, next: MethodCall },
+}
+
+impl Iteration {
+    /// The type of the loop variable.
+    pub fn element(&self) -> &Type {
+        match self {
+            Iteration::Range { element, .. }
+            | Iteration::Array { element }
+            | Iteration::Iterator { element, .. } => element,
+        }
+    }
+}
+
+/// The type of the range value `start..end` or `start...end`.
+pub fn range_type(bound: &Type) -> Type {
+    Type::Struct(NamedTypeRef {
+        name: RANGE_STRUCT.to_string(),
+        generic_arguments: vec![bound.unqualified().clone()],
+    })
+}
+
+/// The `Iterator<T>` interface.
+pub fn iterator_interface(element: &Type) -> NamedTypeRef {
+    NamedTypeRef {
+        name: ITERATOR_INTERFACE.to_string(),
+        generic_arguments: vec![element.clone()],
+    }
+}
+
+/// Decides how `for_loop` iterates over its range expression, which has type
+/// `iterated`. Ranges of integers and arrays become counted loops; anything
+/// else must have a `next(this) -> ?T` method, either directly or by
+/// implementing `Iterator<T>`.
+pub fn resolve_iteration<'ast>(
+    methods: &MethodTable<'ast>,
+    interfaces: &InterfaceTable<'ast>,
+    env: &TypeEnvironment<'ast>,
+    for_loop: &ForLoop<'ast>,
+    iterated: &Type,
+) -> Result<Iteration> {
+    let span = for_loop.range.start as usize..for_loop.range.end as usize;
+    let not_iterable = || Error::NotIterable {
+        ty: iterated.to_string(),
+        span: span.clone(),
+    };
+    match iterated.unqualified() {
+        Type::Struct(named) if named.name == RANGE_STRUCT && named.generic_arguments.len() == 1 => {
+            let element = named.generic_arguments[0].clone();
+            let counted = match element.unqualified() {
+                Type::Primitive(pt) => pt.integer_layout().is_some(),
+                _ => false,
+            };
+            if !counted {
+                return Err(not_iterable());
+            }
+            let inclusive = match for_loop.range.value {
+                Expression::BinaryExpression(BinaryExpression {
+                    operator: BinaryOperator::RangeExclusive,
+                    ..
+                }) => Some(false),
+                Expression::BinaryExpression(BinaryExpression {
+                    operator: BinaryOperator::RangeInclusive,
+                    ..
+                }) => Some(true),
+                _ => None,
+            };
+            return Ok(Iteration::Range { element, inclusive });
+        }
+        Type::SizedArray(element) | Type::UnsizedArray(element) => {
+            return Ok(Iteration::Array {
+                element: (**element).clone(),
+            })
+        }
+        _ => {}
+    }
+    let next =
+        match methods.resolve_instance(interfaces, env, iterated, "next", Some(0), span.clone()) {
+            Ok(next) => next,
+            Err(Error::UnknownMethod { .. }) => return Err(not_iterable()),
+            Err(e) => return Err(e),
+        };
+    match &next.signature.return_type {
+        Some(Type::Optional(element)) => Ok(Iteration::Iterator {
+            element: (**element).clone(),
+            next,
+        }),
+        _ => Err(not_iterable()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::methods::Dispatch;
+    use crate::semantic::prelude::PRELUDE;
+    use crate::syntax::parser::{parse, Parser};
+    use pretty_assertions::assert_eq;
+    use toolshed::Arena;
+
+    const ITERATORS: &str = "
+        struct Counter { count: int; }
+        struct Chars { text: []char; }
+        struct Point { x: int; y: int; }
+        impl Iterator<int> for Counter {
+            function next(this) -> ?int {}
+        }
+        function Chars::next(this) -> ?char {}
+    ";
+
+    fn resolve(source: &str, iterated: Type) -> Result<Iteration> {
+        let prelude = parse(PRELUDE).unwrap();
+        let prelude_body = prelude.body();
+        let SourceUnit::Module(prelude) = prelude_body.iter().next().unwrap().value;
+        let program = parse(ITERATORS).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+
+        let mut env = TypeEnvironment::from_module(&module).unwrap();
+        env.define_module(&prelude).unwrap();
+        let interfaces = InterfaceTable::build(&module, &env).unwrap();
+        let methods = MethodTable::build(&module, &env).unwrap();
+
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let statement = p.statement_node().unwrap();
+        match statement.value {
+            Statement::Loop(LoopStatement::ForLoop(for_loop)) => {
+                resolve_iteration(&methods, &interfaces, &env, &for_loop, &iterated)
+            }
+            s => panic!("expected a for loop, got {:?}", s),
+        }
+    }
+
+    fn int() -> Type {
+        Type::Primitive(PrimitiveType::S32)
+    }
+
+    fn named(name: &str) -> Type {
+        Type::Struct(NamedTypeRef::new(name))
+    }
+
+    #[test]
+    fn test_ranges_are_counted() {
+        assert_eq!(
+            resolve("for (i in 0..10) {}", range_type(&int())),
+            Ok(Iteration::Range {
+                element: int(),
+                inclusive: Some(false),
+            })
+        );
+        assert_eq!(
+            resolve("for (i in 0...10) {}", range_type(&int())),
+            Ok(Iteration::Range {
+                element: int(),
+                inclusive: Some(true),
+            })
+        );
+        let u8_type = Type::Primitive(PrimitiveType::U8);
+        assert_eq!(
+            resolve("for (i in bytes) {}", range_type(&u8_type)),
+            Ok(Iteration::Range {
+                element: u8_type,
+                inclusive: None,
+            })
+        );
+        let float = Type::Primitive(PrimitiveType::F32);
+        assert_eq!(
+            resolve("for (x in 0..1) {}", range_type(&float)),
+            Err(Error::NotIterable {
+                ty: "Range<f32>".to_string(),
+                span: 10..14,
+            })
+        );
+    }
+
+    #[test]
+    fn test_arrays_are_counted() {
+        let array = Type::UnsizedArray(Box::new(int()));
+        assert_eq!(
+            resolve("for (x in values) {}", array),
+            Ok(Iteration::Array { element: int() })
+        );
+    }
+
+    #[test]
+    fn test_user_iterators() {
+        match resolve("for (x in counter) {}", named("Counter")) {
+            Ok(Iteration::Iterator { element, next }) => {
+                assert_eq!(element, int());
+                assert!(matches!(next.dispatch, Dispatch::Static(_)));
+            }
+            res => panic!("expected an iterator, got {:?}", res),
+        }
+        let chars = resolve("for (c in chars) {}", named("Chars")).unwrap();
+        assert_eq!(chars.element(), &Type::Primitive(PrimitiveType::Char));
+        let dynamic = Type::Pointer(Box::new(Type::Interface(iterator_interface(&int()))));
+        match resolve("for (x in it) {}", dynamic) {
+            Ok(Iteration::Iterator { next, .. }) => {
+                assert!(matches!(next.dispatch, Dispatch::Dynamic { slot: 0, .. }))
+            }
+            res => panic!("expected an iterator, got {:?}", res),
+        }
+        assert_eq!(
+            resolve("for (p in point) {}", named("Point")),
+            Err(Error::NotIterable {
+                ty: "Point".to_string(),
+                span: 10..15,
+            })
+        );
+    }
+}
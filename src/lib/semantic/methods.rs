@@ -0,0 +1,501 @@
+use crate::semantic::error::*;
+use crate::semantic::interfaces::InterfaceTable;
+use crate::semantic::module_declarations;
+use crate::semantic::types::*;
+use crate::syntax::ast::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A function defined in the scope of a type, as in `function Vector3::dot(...)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Method<'ast> {
+    pub owner: &'ast str,
+    pub owner_generics: Vec<&'ast str>,
+    pub declarator: FunctionDeclarator<'ast>,
+    pub signature: FunctionSignature,
+    /// Whether the owner has other methods of the same name, which take a
+    /// different number of parameters.
+    pub overloaded: bool,
+}
+
+impl<'ast> Method<'ast> {
+    pub fn name(&self) -> &'ast str {
+        self.declarator.function_name.value
+    }
+
+    /// Static methods have no `this` receiver and are only callable through
+    /// their scope, as in `Vector3::dot(v1, v2)`.
+    pub fn is_static(&self) -> bool {
+        !self.signature.receiver
+    }
+
+    /// The symbol the method is emitted under. Prefix `+` and `-` overloads
+    /// are told apart from the binary ones by a suffix, and other methods
+    /// overloaded by arity by the number of parameters they take.
+    pub fn symbol(&self) -> String {
+        let operator = OverloadableOperator::from_method_name(self.name());
+        let prefix_overload = matches!(
+            operator,
+            Some(OverloadableOperator::Add) | Some(OverloadableOperator::Sub)
+        ) && self.signature.parameters.is_empty();
+        if prefix_overload {
+            format!("{}::{}.prefix", self.owner, self.name())
+        } else if self.overloaded && operator.is_none() {
+            let arity = self.signature.parameters.len();
+            format!("{}::{}.{}", self.owner, self.name(), arity)
+        } else {
+            format!("{}::{}", self.owner, self.name())
+        }
+    }
+
+    /// The signature with the owner's generic parameters bound to the
+    /// generic arguments of `owner`.
+    fn instantiate(&self, owner: &NamedTypeRef) -> FunctionSignature {
+        let bindings: HashMap<String, Type> = self
+            .owner_generics
+            .iter()
+            .map(|g| g.to_string())
+            .zip(owner.generic_arguments.iter().cloned())
+            .collect();
+        FunctionSignature {
+            receiver: self.signature.receiver,
+            parameters: self
+                .signature
+                .parameters
+                .iter()
+                .map(|p| p.substitute(&bindings))
+                .collect(),
+            return_type: self
+                .signature
+                .return_type
+                .as_ref()
+                .map(|r| r.substitute(&bindings)),
+        }
+    }
+}
+
+/// How the object of `obj.method()` is passed as `this`, which is always a
+/// pointer to the object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiverAdjustment {
+    /// The object already is a pointer and is passed as is.
+    None,
+    /// The object is a value; its address is passed instead.
+    AutoRef,
+}
+
+/// Which function an instance method call ends up calling.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Dispatch {
+    /// A call to a known symbol.
+    Static(String),
+    /// A call through the vtable of an interface pointer.
+    Dynamic { interface: NamedTypeRef, slot: u32 },
+}
+
+/// A resolved `obj.method(...)` call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MethodCall {
+    pub dispatch: Dispatch,
+    pub receiver: ReceiverAdjustment,
+    pub signature: FunctionSignature,
+}
+
+/// Every scoped function of a module, grouped by the type they belong to.
+#[derive(Clone, Debug, Default)]
+pub struct MethodTable<'ast> {
+    methods: HashMap<&'ast str, Vec<Method<'ast>>>,
+}
+
+impl<'ast> MethodTable<'ast> {
+    pub fn build(
+        module: &Module<'ast>,
+        env: &TypeEnvironment<'ast>,
+    ) -> std::result::Result<Self, Vec<Error>> {
+        let mut table = MethodTable::default();
+        let mut errors = vec![];
+        for declaration in module_declarations(module) {
+            let declarator = match declaration.value.declarator {
+                Declarator::Function(f) => f,
+                _ => continue,
+            };
+            if let Err(e) = table.define(env, declarator) {
+                errors.push(e);
+            }
+        }
+        match errors.len() {
+            0 => Ok(table),
+            _ => Err(errors),
+        }
+    }
+
+    fn define(
+        &mut self,
+        env: &TypeEnvironment<'ast>,
+        declarator: FunctionDeclarator<'ast>,
+    ) -> Result<()> {
+        let name = declarator.function_name;
+        let operator = OverloadableOperator::from_method_name(name.value);
+        if operator.is_some() && (declarator.scope.is_none() || declarator.receiver.is_none()) {
+            return Err(Error::StaticOperatorOverload {
+                operator: name.value.to_string(),
+                span: name.start as usize..name.end as usize,
+            });
+        }
+        let scope = match declarator.scope {
+            Some(scope) => scope,
+            None => return Ok(()),
+        };
+        let owner = scope.value.identifier;
+        match env.get(owner.value) {
+            Some(TypeDefinition::Struct(_)) | Some(TypeDefinition::Enum(_)) => {}
+            _ => {
+                return Err(Error::UnknownType {
+                    name: owner.value.to_string(),
+                    span: owner.start as usize..owner.end as usize,
+                })
+            }
+        }
+        let owner_generics: Vec<&str> = scope
+            .value
+            .generic_parameters
+            .iter()
+            .map(|g| g.value)
+            .collect();
+        let mut generics = owner_generics.clone();
+        generics.extend(declarator.generic_parameters.iter().map(|g| g.value));
+        let signature = env.resolve_signature(
+            declarator.parameters,
+            declarator.return_type,
+            declarator.receiver.is_some(),
+            &generics,
+        )?;
+        if let Some(operator) = operator {
+            if !operator.arities().contains(&signature.parameters.len()) {
+                return Err(Error::OperatorArity {
+                    operator: name.value.to_string(),
+                    expected: operator.arities().to_vec(),
+                    span: name.start as usize..name.end as usize,
+                });
+            }
+        }
+
+        let methods = self.methods.entry(owner.value).or_default();
+        let duplicate = methods.iter().any(|method| {
+            method.name() == name.value
+                && method.signature.parameters.len() == signature.parameters.len()
+        });
+        if duplicate {
+            return Err(Error::DuplicateDefinition {
+                name: format!("{}::{}", owner.value, name.value),
+                span: name.start as usize..name.end as usize,
+            });
+        }
+        let mut overloaded = false;
+        for method in methods
+            .iter_mut()
+            .filter(|method| method.name() == name.value)
+        {
+            method.overloaded = true;
+            overloaded = true;
+        }
+        methods.push(Method {
+            owner: owner.value,
+            owner_generics,
+            declarator,
+            signature,
+            overloaded,
+        });
+        Ok(())
+    }
+
+    pub fn get(&self, owner: &str, name: &str) -> Option<&Method<'ast>> {
+        self.find(owner, name, None)
+    }
+
+    /// The method a scoped function declaration defines.
+    pub fn defined_by(&self, declarator: &FunctionDeclarator<'ast>) -> Option<&Method<'ast>> {
+        let owner = declarator.scope?.value.identifier.value;
+        self.methods.get(owner).and_then(|methods| {
+            methods
+                .iter()
+                .find(|method| method.declarator.function_name == declarator.function_name)
+        })
+    }
+
+    /// Looks up a method, optionally only one taking `arity` parameters
+    /// besides `this`.
+    fn find(&self, owner: &str, name: &str, arity: Option<usize>) -> Option<&Method<'ast>> {
+        self.methods.get(owner).and_then(|methods| {
+            methods.iter().find(|method| {
+                method.name() == name
+                    && (arity.is_none() || arity == Some(method.signature.parameters.len()))
+            })
+        })
+    }
+
+    /// Resolves `Owner::name(...)`, preferring the method that takes
+    /// `arguments` arguments if it is given. Instance methods may be called
+    /// this way too, taking the receiver as their first argument.
+    pub fn resolve_static_call(
+        &self,
+        owner: &str,
+        name: &str,
+        arguments: Option<usize>,
+        span: Range<usize>,
+    ) -> Result<&Method<'ast>> {
+        let takes_arguments = |method: &&Method<'ast>| {
+            let receiver = method.signature.receiver as usize;
+            method.name() == name && arguments == Some(method.signature.parameters.len() + receiver)
+        };
+        let by_arity = self
+            .methods
+            .get(owner)
+            .and_then(|methods| methods.iter().find(takes_arguments));
+        by_arity
+            .or_else(|| self.get(owner, name))
+            .ok_or_else(|| Error::UnknownMethod {
+                ty: owner.to_string(),
+                method: name.to_string(),
+                span,
+            })
+    }
+
+    /// Resolves `object.name(...)` where `object` has type `receiver`.
+    ///
+    /// Methods defined on the type itself take precedence over methods of the
+    /// interfaces it implements. Calls on interface pointers go through the
+    /// vtable.
+    pub fn resolve_instance_call(
+        &self,
+        interfaces: &InterfaceTable<'ast>,
+        env: &TypeEnvironment<'ast>,
+        receiver: &Type,
+        name: &str,
+        span: Range<usize>,
+    ) -> Result<MethodCall> {
+        self.resolve_instance(interfaces, env, receiver, name, None, span)
+    }
+
+    /// `resolve_instance_call`, optionally restricted to methods taking
+    /// `arity` parameters besides `this`.
+    pub(crate) fn resolve_instance(
+        &self,
+        interfaces: &InterfaceTable<'ast>,
+        env: &TypeEnvironment<'ast>,
+        receiver: &Type,
+        name: &str,
+        arity: Option<usize>,
+        span: Range<usize>,
+    ) -> Result<MethodCall> {
+        let unknown_method = || Error::UnknownMethod {
+            ty: receiver.to_string(),
+            method: name.to_string(),
+            span: span.clone(),
+        };
+
+        if let Some(interface) = receiver.interface_pointer() {
+            let slot = interfaces
+                .vtable_layout(&interface.name)
+                .and_then(|layout| layout.slot(name))
+                .ok_or_else(unknown_method)?;
+            let (_, signature) = interfaces
+                .method_signatures(env, interface)?
+                .swap_remove(slot as usize);
+            return Ok(MethodCall {
+                dispatch: Dispatch::Dynamic {
+                    interface: interface.clone(),
+                    slot,
+                },
+                receiver: ReceiverAdjustment::None,
+                signature,
+            });
+        }
+
+        let (object, adjustment) = match receiver.unqualified() {
+            Type::Pointer(inner) => (inner.unqualified(), ReceiverAdjustment::None),
+            value => (value, ReceiverAdjustment::AutoRef),
+        };
+        let owner = match object {
+            Type::Struct(named) => named.clone(),
+            Type::Enum(name) => NamedTypeRef::new(name),
+            _ => return Err(unknown_method()),
+        };
+
+        if let Some(method) = self.find(&owner.name, name, arity) {
+            if method.is_static() {
+                return Err(Error::StaticMethodOnInstance {
+                    ty: owner.to_string(),
+                    method: name.to_string(),
+                    span,
+                });
+            }
+            return Ok(MethodCall {
+                dispatch: Dispatch::Static(method.symbol()),
+                receiver: adjustment,
+                signature: method.instantiate(&owner),
+            });
+        }
+
+        for implementation in interfaces.implementations() {
+            let interface = match interfaces.implemented_interface(implementation, object) {
+                Some(interface) => interface,
+                None => continue,
+            };
+            let signatures = interfaces.method_signatures(env, &interface)?;
+            let found = signatures.into_iter().find(|(method, signature)| {
+                *method == name && (arity.is_none() || arity == Some(signature.parameters.len()))
+            });
+            if let Some((_, signature)) = found {
+                return Ok(MethodCall {
+                    dispatch: Dispatch::Static(implementation.method_symbol(name)),
+                    receiver: adjustment,
+                    signature,
+                });
+            }
+        }
+        Err(unknown_method())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parser::parse;
+    use pretty_assertions::assert_eq;
+
+    const VECTORS: &str = "
+        interface IDrawable {
+            function draw(this, scale: float) -> bool;
+        }
+        struct Vector3 { x: float; y: float; z: float; }
+        struct Tree<K, V> { root: *K; }
+        function Vector3::scaled(this) -> Vector3 {}
+        function Vector3::dot(a: Vector3, b: Vector3) -> float {}
+        function Tree<K, V>::get(this, key: K) -> ?V {}
+        impl IDrawable for Vector3 {
+            function draw(this, scale: float) -> bool {}
+        }
+    ";
+
+    fn with_tables<F>(f: F)
+    where
+        F: for<'ast> FnOnce(&TypeEnvironment<'ast>, &InterfaceTable<'ast>, &MethodTable<'ast>),
+    {
+        let program = parse(VECTORS).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let env = TypeEnvironment::from_module(&module).unwrap();
+        let interfaces = InterfaceTable::build(&module, &env).unwrap();
+        let methods = MethodTable::build(&module, &env).unwrap();
+        f(&env, &interfaces, &methods)
+    }
+
+    fn vector3() -> Type {
+        Type::Struct(NamedTypeRef::new("Vector3"))
+    }
+
+    #[test]
+    fn test_instance_call_auto_refs_values() {
+        with_tables(|env, interfaces, methods| {
+            let call = methods
+                .resolve_instance_call(interfaces, env, &vector3(), "scaled", 0..0)
+                .unwrap();
+            assert_eq!(
+                call.dispatch,
+                Dispatch::Static("Vector3::scaled".to_string())
+            );
+            assert_eq!(call.receiver, ReceiverAdjustment::AutoRef);
+
+            let pointer = Type::Pointer(Box::new(vector3()));
+            let call = methods
+                .resolve_instance_call(interfaces, env, &pointer, "scaled", 0..0)
+                .unwrap();
+            assert_eq!(call.receiver, ReceiverAdjustment::None);
+        });
+    }
+
+    #[test]
+    fn test_instance_call_binds_owner_generics() {
+        with_tables(|env, interfaces, methods| {
+            let tree = Type::Struct(NamedTypeRef {
+                name: "Tree".to_string(),
+                generic_arguments: vec![
+                    Type::Primitive(PrimitiveType::S32),
+                    Type::Primitive(PrimitiveType::Bool),
+                ],
+            });
+            let call = methods
+                .resolve_instance_call(interfaces, env, &tree, "get", 0..0)
+                .unwrap();
+            assert_eq!(
+                call.signature.parameters,
+                vec![Type::Primitive(PrimitiveType::S32)]
+            );
+            assert_eq!(
+                call.signature.return_type,
+                Some(Type::Optional(Box::new(Type::Primitive(
+                    PrimitiveType::Bool
+                ))))
+            );
+        });
+    }
+
+    #[test]
+    fn test_static_methods() {
+        with_tables(|env, interfaces, methods| {
+            let dot = methods
+                .resolve_static_call("Vector3", "dot", Some(2), 0..0)
+                .unwrap();
+            assert!(dot.is_static());
+            assert_eq!(dot.symbol(), "Vector3::dot");
+            assert_eq!(
+                methods.resolve_instance_call(interfaces, env, &vector3(), "dot", 3..6),
+                Err(Error::StaticMethodOnInstance {
+                    ty: "Vector3".to_string(),
+                    method: "dot".to_string(),
+                    span: 3..6,
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_interface_methods() {
+        with_tables(|env, interfaces, methods| {
+            let call = methods
+                .resolve_instance_call(interfaces, env, &vector3(), "draw", 0..0)
+                .unwrap();
+            assert_eq!(
+                call.dispatch,
+                Dispatch::Static("Vector3::IDrawable::draw".to_string())
+            );
+
+            let drawable = Type::Pointer(Box::new(Type::Interface(NamedTypeRef::new("IDrawable"))));
+            let call = methods
+                .resolve_instance_call(interfaces, env, &drawable, "draw", 0..0)
+                .unwrap();
+            assert_eq!(
+                call.dispatch,
+                Dispatch::Dynamic {
+                    interface: NamedTypeRef::new("IDrawable"),
+                    slot: 0,
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn test_unknown_method() {
+        with_tables(|env, interfaces, methods| {
+            assert_eq!(
+                methods.resolve_instance_call(interfaces, env, &vector3(), "length", 1..2),
+                Err(Error::UnknownMethod {
+                    ty: "Vector3".to_string(),
+                    method: "length".to_string(),
+                    span: 1..2,
+                })
+            );
+        });
+    }
+}
@@ -1 +1,32 @@
+pub mod cfg;
+pub mod constants;
+pub mod entry;
+pub mod error;
+pub mod functions;
 mod hir;
+pub mod interfaces;
+pub mod iteration;
+pub mod methods;
+pub mod operators;
+pub mod patterns;
+pub mod prelude;
+pub mod results;
+pub mod types;
+
+use crate::syntax::ast::*;
+
+/// Collects the declarations of a module, including exported ones.
+pub fn module_declarations<'ast>(module: &Module<'ast>) -> Vec<DeclarationNode<'ast>> {
+    module
+        .elements
+        .iter()
+        .filter_map(|element| match element.value {
+            ModuleElement::Declaration(declaration) => Some(declaration),
+            ModuleElement::Export(export) => match export.value {
+                Export::Declaration(declaration) => Some(declaration),
+                _ => None,
+            },
+            ModuleElement::Import(_) | ModuleElement::Statement(_) => None,
+        })
+        .collect()
+}
@@ -0,0 +1,406 @@
+use crate::semantic::error::*;
+use crate::semantic::interfaces::InterfaceTable;
+use crate::semantic::methods::{MethodCall, MethodTable};
+use crate::semantic::types::*;
+use crate::syntax::ast::*;
+use std::ops::Range;
+use toolshed::Arena;
+
+/// Whether operators on `ty` are looked up among the overloads. Structs and
+/// interface pointers have no built-in operators, so a missing overload is an
+/// error; enums fall back to the built-in operators.
+fn overload_required(ty: &Type) -> Option<bool> {
+    if ty.interface_pointer().is_some() {
+        return Some(true);
+    }
+    match ty.unqualified() {
+        Type::Struct(_) => Some(true),
+        Type::Enum(_) => Some(false),
+        _ => None,
+    }
+}
+
+impl<'ast> MethodTable<'ast> {
+    /// Resolves `left <operator> right`. Returns `None` when the built-in
+    /// operator applies.
+    pub fn resolve_binary_operator(
+        &self,
+        interfaces: &InterfaceTable<'ast>,
+        env: &TypeEnvironment<'ast>,
+        left: &Type,
+        operator: BinaryOperator,
+        right: &Type,
+        span: Range<usize>,
+    ) -> Result<Option<MethodCall>> {
+        match OverloadableOperator::from_binary(operator) {
+            Some(operator) => self.resolve_operator(
+                interfaces,
+                env,
+                operator,
+                left,
+                std::slice::from_ref(right),
+                span,
+            ),
+            None => Ok(None),
+        }
+    }
+
+    pub fn resolve_prefix_operator(
+        &self,
+        interfaces: &InterfaceTable<'ast>,
+        env: &TypeEnvironment<'ast>,
+        operator: PrefixOperator,
+        operand: &Type,
+        span: Range<usize>,
+    ) -> Result<Option<MethodCall>> {
+        match OverloadableOperator::from_prefix(operator) {
+            Some(operator) => self.resolve_operator(interfaces, env, operator, operand, &[], span),
+            None => Ok(None),
+        }
+    }
+
+    /// Resolves reading `array[index]`.
+    pub fn resolve_index(
+        &self,
+        interfaces: &InterfaceTable<'ast>,
+        env: &TypeEnvironment<'ast>,
+        array: &Type,
+        index: &Type,
+        span: Range<usize>,
+    ) -> Result<Option<MethodCall>> {
+        self.resolve_operator(
+            interfaces,
+            env,
+            OverloadableOperator::Index,
+            array,
+            std::slice::from_ref(index),
+            span,
+        )
+    }
+
+    /// Resolves assigning `value` to `array[index]`.
+    pub fn resolve_index_assign(
+        &self,
+        interfaces: &InterfaceTable<'ast>,
+        env: &TypeEnvironment<'ast>,
+        array: &Type,
+        index: &Type,
+        value: &Type,
+        span: Range<usize>,
+    ) -> Result<Option<MethodCall>> {
+        self.resolve_operator(
+            interfaces,
+            env,
+            OverloadableOperator::IndexAssign,
+            array,
+            &[index.clone(), value.clone()],
+            span,
+        )
+    }
+
+    fn resolve_operator(
+        &self,
+        interfaces: &InterfaceTable<'ast>,
+        env: &TypeEnvironment<'ast>,
+        operator: OverloadableOperator,
+        operand: &Type,
+        arguments: &[Type],
+        span: Range<usize>,
+    ) -> Result<Option<MethodCall>> {
+        let required = match overload_required(operand) {
+            Some(required) => required,
+            None => return Ok(None),
+        };
+        let no_overload = || Error::NoOperatorOverload {
+            operator: operator.method_name().to_string(),
+            operands: std::iter::once(operand)
+                .chain(arguments)
+                .map(|ty| ty.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            span: span.clone(),
+        };
+        let name = operator.method_name();
+        let call = match self.resolve_instance(
+            interfaces,
+            env,
+            operand,
+            name,
+            Some(arguments.len()),
+            span.clone(),
+        ) {
+            Ok(call) => call,
+            Err(Error::UnknownMethod { .. }) if !required => return Ok(None),
+            Err(Error::UnknownMethod { .. }) => return Err(no_overload()),
+            Err(e) => return Err(e),
+        };
+        let accepts = call.signature.parameters.len() == arguments.len()
+            && call
+                .signature
+                .parameters
+                .iter()
+                .zip(arguments)
+                .all(|(parameter, argument)| parameter.contains(argument));
+        if accepts {
+            Ok(Some(call))
+        } else {
+            Err(no_overload())
+        }
+    }
+}
+
+/// The value `a op b` that `a op= b` assigns, or `None` for plain
+/// assignments.
+pub fn compound_assignment_value<'ast>(
+    assignment: &AssignmentExpression<'ast>,
+) -> Option<BinaryExpression<'ast>> {
+    let operator = assignment.operator.binary_operator()?;
+    Some(BinaryExpression {
+        left: assignment.left,
+        operator,
+        right: assignment.right,
+    })
+}
+
+/// Rewrites `a op= b` into `a = a op b` so that compound assignments use the
+/// overloads of `op`. Other expressions are returned unchanged.
+///
+/// The target is shared between both sides, so it is evaluated twice.
+pub fn desugar_compound_assignment<'ast>(
+    arena: &'ast Arena,
+    expression: ExpressionNode<'ast>,
+) -> ExpressionNode<'ast> {
+    let assignment = match expression.value {
+        Expression::AssignmentExpression(assignment) => assignment,
+        _ => return expression,
+    };
+    let value = match compound_assignment_value(&assignment) {
+        Some(value) => value,
+        None => return expression,
+    };
+    let value = Node::new(arena.alloc(NodeInner::new(
+        expression.start,
+        expression.end,
+        Expression::from(value),
+    )));
+    let assignment = AssignmentExpression {
+        left: assignment.left,
+        operator: AssignmentOperator::Plain,
+        right: value,
+    };
+    Node::new(arena.alloc(NodeInner::new(
+        expression.start,
+        expression.end,
+        Expression::from(assignment),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::methods::{Dispatch, ReceiverAdjustment};
+    use crate::syntax::parser::{parse, Parser};
+    use pretty_assertions::assert_eq;
+
+    const VECTORS: &str = "
+        struct Vector2 { x: float; y: float; }
+        enum Direction { Up, Down }
+        function Vector2::operator +(this, rhs: Vector2) -> Vector2 {}
+        function Vector2::operator -(this) -> Vector2 {}
+        function Vector2::operator -(this, rhs: Vector2) -> Vector2 {}
+        function Vector2::operator [](this, index: int) -> float {}
+        function Vector2::operator []=(this, index: int, value: float) {}
+    ";
+
+    fn build(source: &str) -> std::result::Result<(), Vec<Error>> {
+        let program = parse(source).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let env = TypeEnvironment::from_module(&module).unwrap();
+        MethodTable::build(&module, &env).map(|_| ())
+    }
+
+    fn with_tables<F>(f: F)
+    where
+        F: for<'ast> FnOnce(&TypeEnvironment<'ast>, &InterfaceTable<'ast>, &MethodTable<'ast>),
+    {
+        let program = parse(VECTORS).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let env = TypeEnvironment::from_module(&module).unwrap();
+        let interfaces = InterfaceTable::build(&module, &env).unwrap();
+        let methods = MethodTable::build(&module, &env).unwrap();
+        f(&env, &interfaces, &methods)
+    }
+
+    fn vector2() -> Type {
+        Type::Struct(NamedTypeRef::new("Vector2"))
+    }
+
+    #[test]
+    fn test_binary_operator_overloads() {
+        with_tables(|env, interfaces, methods| {
+            let call = methods
+                .resolve_binary_operator(
+                    interfaces,
+                    env,
+                    &vector2(),
+                    BinaryOperator::Addition,
+                    &vector2(),
+                    0..0,
+                )
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                call.dispatch,
+                Dispatch::Static("Vector2::operator+".to_string())
+            );
+            assert_eq!(call.receiver, ReceiverAdjustment::AutoRef);
+
+            let int = Type::Primitive(PrimitiveType::S32);
+            assert_eq!(
+                methods.resolve_binary_operator(
+                    interfaces,
+                    env,
+                    &int,
+                    BinaryOperator::Addition,
+                    &int,
+                    0..0
+                ),
+                Ok(None)
+            );
+            assert_eq!(
+                methods.resolve_binary_operator(
+                    interfaces,
+                    env,
+                    &vector2(),
+                    BinaryOperator::Multiplication,
+                    &vector2(),
+                    4..9
+                ),
+                Err(Error::NoOperatorOverload {
+                    operator: "operator*".to_string(),
+                    operands: "Vector2, Vector2".to_string(),
+                    span: 4..9,
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_prefix_and_binary_overloads_are_distinct() {
+        with_tables(|env, interfaces, methods| {
+            let prefix = methods
+                .resolve_prefix_operator(interfaces, env, PrefixOperator::Minus, &vector2(), 0..0)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                prefix.dispatch,
+                Dispatch::Static("Vector2::operator-.prefix".to_string())
+            );
+            let binary = methods
+                .resolve_binary_operator(
+                    interfaces,
+                    env,
+                    &vector2(),
+                    BinaryOperator::Subtraction,
+                    &vector2(),
+                    0..0,
+                )
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                binary.dispatch,
+                Dispatch::Static("Vector2::operator-".to_string())
+            );
+        });
+    }
+
+    #[test]
+    fn test_subscript_overloads() {
+        with_tables(|env, interfaces, methods| {
+            let int = Type::Primitive(PrimitiveType::S32);
+            let float = Type::Primitive(PrimitiveType::F32);
+            let read = methods
+                .resolve_index(interfaces, env, &vector2(), &int, 0..0)
+                .unwrap()
+                .unwrap();
+            assert_eq!(read.signature.return_type, Some(float.clone()));
+            let write = methods
+                .resolve_index_assign(interfaces, env, &vector2(), &int, &float, 0..0)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                write.dispatch,
+                Dispatch::Static("Vector2::operator[]=".to_string())
+            );
+            assert!(methods
+                .resolve_index(interfaces, env, &vector2(), &vector2(), 0..0)
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_enums_fall_back_to_builtin_operators() {
+        with_tables(|env, interfaces, methods| {
+            let direction = Type::Enum("Direction".to_string());
+            assert_eq!(
+                methods.resolve_binary_operator(
+                    interfaces,
+                    env,
+                    &direction,
+                    BinaryOperator::Equals,
+                    &direction,
+                    0..0
+                ),
+                Ok(None)
+            );
+        });
+    }
+
+    #[test]
+    fn test_operator_overloads_cannot_be_static() {
+        let source = "
+            struct Vector2 { x: float; y: float; }
+            function Vector2::operator +(lhs: Vector2, rhs: Vector2) -> Vector2 {}
+        ";
+        assert_eq!(
+            build(source),
+            Err(vec![Error::StaticOperatorOverload {
+                operator: "operator+".to_string(),
+                span: 82..92,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_operator_arity() {
+        let source = "
+            struct Vector2 { x: float; y: float; }
+            function Vector2::operator [](this) -> float {}
+        ";
+        assert!(matches!(
+            build(source),
+            Err(errors) if matches!(errors[0], Error::OperatorArity { .. })
+        ));
+    }
+
+    #[test]
+    fn test_desugar_compound_assignment() {
+        let arena = Arena::new();
+        let mut p = Parser::new("v += w", &arena);
+        let expression = desugar_compound_assignment(&arena, p.expression_node().unwrap());
+        let assignment = match expression.value {
+            Expression::AssignmentExpression(assignment) => assignment,
+            e => panic!("expected an assignment, got {:?}", e),
+        };
+        assert_eq!(assignment.operator, AssignmentOperator::Plain);
+        match assignment.right.value {
+            Expression::BinaryExpression(binary) => {
+                assert_eq!(binary.operator, BinaryOperator::Addition);
+                assert_eq!(binary.left, assignment.left);
+            }
+            e => panic!("expected a binary expression, got {:?}", e),
+        }
+    }
+}
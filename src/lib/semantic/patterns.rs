@@ -0,0 +1,608 @@
+use crate::semantic::constants::Constant;
+use crate::semantic::error::*;
+use crate::semantic::types::*;
+use crate::syntax::ast::*;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Ranges with more values than this are tested with comparisons instead of
+/// being expanded into `switch` cases.
+const MAX_SWITCH_RANGE: i128 = 64;
+
+/// How a checked `match` statement is lowered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MatchLowering {
+    /// An LLVM `switch` on the scrutinee. Each case maps a value to the index
+    /// of the arm handling it. Values without a case go to the `default` arm;
+    /// without one, the cases are exhaustive.
+    Switch {
+        cases: Vec<(i128, usize)>,
+        default: Option<usize>,
+    },
+    /// The arms are tested one after another.
+    Chain,
+}
+
+/// The values of the scrutinee that no arm has handled yet.
+enum Space<'ast> {
+    /// Integers, `char`s and `bool`s, as inclusive intervals.
+    Integers(Vec<(i128, i128)>),
+    Variants {
+        enumeration: String,
+        all: Vec<&'ast str>,
+        remaining: Vec<&'ast str>,
+    },
+    /// The members of a union or optional type.
+    Members(Vec<Type>),
+    /// Types whose values can only be exhausted by a wildcard. Literal
+    /// patterns are only tracked to find repeated ones.
+    Opaque {
+        exhausted: bool,
+        seen: Vec<LiteralPattern<'ast>>,
+    },
+}
+
+impl<'ast> Space<'ast> {
+    fn of(env: &TypeEnvironment<'ast>, ty: &Type) -> Self {
+        if let Some(bounds) = integer_bounds(ty) {
+            return Space::Integers(vec![bounds]);
+        }
+        match ty.unqualified() {
+            Type::Enum(name) => match env.get(name) {
+                Some(TypeDefinition::Enum(declarator)) => {
+                    let variants: Vec<&str> = declarator
+                        .values
+                        .iter()
+                        .map(|value| value.value.identifier.value)
+                        .collect();
+                    Space::Variants {
+                        enumeration: name.clone(),
+                        all: variants.clone(),
+                        remaining: variants,
+                    }
+                }
+                _ => Space::opaque(),
+            },
+            Type::Union(_) | Type::Optional(_) => Space::Members(members(ty)),
+            _ => Space::opaque(),
+        }
+    }
+
+    fn opaque() -> Self {
+        Space::Opaque {
+            exhausted: false,
+            seen: vec![],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Space::Integers(remaining) => remaining.is_empty(),
+            Space::Variants { remaining, .. } => remaining.is_empty(),
+            Space::Members(remaining) => remaining.is_empty(),
+            Space::Opaque { exhausted, .. } => *exhausted,
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Space::Integers(remaining) => remaining.clear(),
+            Space::Variants { remaining, .. } => remaining.clear(),
+            Space::Members(remaining) => remaining.clear(),
+            Space::Opaque { exhausted, .. } => *exhausted = true,
+        }
+    }
+
+    /// Describes the values that are left, for non-exhaustive matches.
+    fn missing(&self, ty: &Type) -> Vec<String> {
+        let is_bool = is_bool(ty);
+        let describe = |value: i128| match (is_bool, value) {
+            (true, 0) => "false".to_string(),
+            (true, _) => "true".to_string(),
+            (false, value) => value.to_string(),
+        };
+        match self {
+            Space::Integers(remaining) => remaining
+                .iter()
+                .flat_map(|&(start, end)| match (is_bool, start == end) {
+                    (_, true) => vec![describe(start)],
+                    (true, false) => vec![describe(start), describe(end)],
+                    (false, false) => vec![format!("{}...{}", start, end)],
+                })
+                .collect(),
+            Space::Variants { remaining, .. } => remaining
+                .iter()
+                .map(|variant| format!(".{}", variant))
+                .collect(),
+            Space::Members(remaining) => remaining.iter().map(|m| m.to_string()).collect(),
+            Space::Opaque {
+                exhausted: false, ..
+            } => vec!["_".to_string()],
+            Space::Opaque {
+                exhausted: true, ..
+            } => vec![],
+        }
+    }
+
+    /// Checks `pattern` against the scrutinee type and returns whether it can
+    /// match anything that earlier arms did not. Unless the arm is guarded,
+    /// what it matches is removed from the space.
+    fn apply(
+        &mut self,
+        env: &TypeEnvironment<'ast>,
+        scrutinee: &Type,
+        pattern: &PatternNode<'ast>,
+        guarded: bool,
+    ) -> Result<bool> {
+        let span = pattern.start as usize..pattern.end as usize;
+        let mismatch = || Error::PatternTypeMismatch {
+            ty: scrutinee.to_string(),
+            span: span.clone(),
+        };
+        let resolved = match pattern.value {
+            Pattern::Type(type_pattern) => Some(env.resolve(&type_pattern.type_expression, &[])?),
+            _ => None,
+        };
+        let everything = match &resolved {
+            None => pattern.value == Pattern::Wildcard,
+            Some(ty) if ty.unqualified() == scrutinee.unqualified() || ty.contains(scrutinee) => {
+                true
+            }
+            Some(_) => false,
+        };
+        if everything {
+            let reachable = !self.is_empty();
+            if !guarded {
+                self.clear();
+            }
+            return Ok(reachable);
+        }
+        match (self, pattern.value) {
+            (Space::Integers(remaining), Pattern::Literal(literal)) => {
+                let value = integer_value(scrutinee, &literal).ok_or_else(mismatch)?;
+                Ok(cover_interval(remaining, (value, value), guarded))
+            }
+            (Space::Integers(remaining), Pattern::Range(range)) => {
+                let (start, end) = range_bounds(scrutinee, &range).ok_or_else(mismatch)?;
+                Ok(cover_interval(remaining, (start, end), guarded))
+            }
+            (
+                Space::Variants {
+                    enumeration,
+                    all,
+                    remaining,
+                },
+                Pattern::EnumVariant(variant_pattern),
+            ) => {
+                if let Some(named) = variant_pattern.enumeration {
+                    if named.value != enumeration {
+                        return Err(mismatch());
+                    }
+                }
+                let variant = variant_pattern.variant;
+                if !all.contains(&variant.value) {
+                    return Err(Error::UnknownEnumVariant {
+                        enumeration: enumeration.clone(),
+                        variant: variant.value.to_string(),
+                        span: variant.start as usize..variant.end as usize,
+                    });
+                }
+                let reachable = remaining.contains(&variant.value);
+                if !guarded {
+                    remaining.retain(|remaining| *remaining != variant.value);
+                }
+                Ok(reachable)
+            }
+            (Space::Members(remaining), Pattern::Type(_)) => {
+                let ty = resolved.unwrap();
+                if !members(scrutinee).iter().any(|member| ty.contains(member)) {
+                    return Err(mismatch());
+                }
+                let reachable = remaining.iter().any(|member| ty.contains(member));
+                if !guarded {
+                    remaining.retain(|member| !ty.contains(member));
+                }
+                Ok(reachable)
+            }
+            (
+                Space::Members(remaining),
+                Pattern::Literal(LiteralPattern {
+                    value: Primitive::Null,
+                    ..
+                }),
+            ) => {
+                let null = Type::Primitive(PrimitiveType::Null);
+                if !members(scrutinee).contains(&null) {
+                    return Err(mismatch());
+                }
+                let reachable = remaining.contains(&null);
+                if !guarded {
+                    remaining.retain(|member| *member != null);
+                }
+                Ok(reachable)
+            }
+            // Other literals only match some values of a member, so they never
+            // exhaust it.
+            (Space::Members(remaining), Pattern::Literal(_))
+            | (Space::Members(remaining), Pattern::Range(_)) => {
+                let accepts = |member: &Type| match pattern.value {
+                    Pattern::Literal(literal) => integer_value(member, &literal).is_some(),
+                    Pattern::Range(range) => range_bounds(member, &range).is_some(),
+                    _ => false,
+                };
+                if !members(scrutinee).iter().any(accepts) {
+                    return Err(mismatch());
+                }
+                Ok(remaining.iter().any(accepts))
+            }
+            (Space::Opaque { exhausted, seen }, Pattern::Literal(literal)) => {
+                let reachable = !*exhausted && !seen.contains(&literal);
+                if !guarded {
+                    seen.push(literal);
+                }
+                Ok(reachable)
+            }
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+/// Checks the arms of a `match` statement on a value of type `scrutinee`:
+/// every pattern must fit the type, every arm must be reachable, and the
+/// unguarded arms together must handle every value.
+pub fn check_match<'ast>(
+    env: &TypeEnvironment<'ast>,
+    scrutinee: &Type,
+    statement: &MatchStatement<'ast>,
+    span: Range<usize>,
+) -> std::result::Result<MatchLowering, Vec<Error>> {
+    let mut space = Space::of(env, scrutinee);
+    let mut errors = vec![];
+    for arm in statement.arms.iter() {
+        let pattern = arm.value.pattern;
+        match space.apply(env, scrutinee, &pattern, arm.value.guard.is_some()) {
+            Ok(true) => {}
+            Ok(false) => errors.push(Error::UnreachablePattern {
+                span: pattern.start as usize..pattern.end as usize,
+            }),
+            Err(e) => errors.push(e),
+        }
+    }
+    let missing = space.missing(scrutinee);
+    if !missing.is_empty() {
+        errors.push(Error::NonExhaustiveMatch { missing, span });
+    }
+    match errors.len() {
+        0 => Ok(lower(env, scrutinee, statement)),
+        _ => Err(errors),
+    }
+}
+
+/// Picks how a checked `match` statement is lowered. Unguarded literal,
+/// small range and enum variant patterns on integers and enums become a
+/// `switch`; anything else is tested arm by arm.
+fn lower<'ast>(
+    env: &TypeEnvironment<'ast>,
+    scrutinee: &Type,
+    statement: &MatchStatement<'ast>,
+) -> MatchLowering {
+    let discriminants = match scrutinee.unqualified() {
+        Type::Enum(name) => match env.get(name).and_then(|definition| match definition {
+            TypeDefinition::Enum(declarator) => enum_discriminants(env, declarator),
+            _ => None,
+        }) {
+            Some(discriminants) => discriminants,
+            None => return MatchLowering::Chain,
+        },
+        _ if integer_bounds(scrutinee).is_some() => vec![],
+        _ => return MatchLowering::Chain,
+    };
+    let mut cases = vec![];
+    let mut seen = HashSet::new();
+    let mut default = None;
+    for (index, arm) in statement.arms.iter().enumerate() {
+        if arm.value.guard.is_some() {
+            return MatchLowering::Chain;
+        }
+        let values: Vec<i128> = match arm.value.pattern.value {
+            Pattern::Wildcard => {
+                default = Some(index);
+                break;
+            }
+            Pattern::Literal(literal) => integer_value(scrutinee, &literal).into_iter().collect(),
+            Pattern::Range(range) => match range_bounds(scrutinee, &range) {
+                Some((start, end)) if end - start < MAX_SWITCH_RANGE => (start..=end).collect(),
+                _ => return MatchLowering::Chain,
+            },
+            Pattern::EnumVariant(variant) => discriminants
+                .iter()
+                .filter(|(name, _)| *name == variant.variant.value)
+                .map(|(_, value)| *value)
+                .collect(),
+            // Type patterns bind the value.
+            Pattern::Type(_) => return MatchLowering::Chain,
+        };
+        for value in values {
+            if seen.insert(value) {
+                cases.push((value, index));
+            }
+        }
+    }
+    MatchLowering::Switch { cases, default }
+}
+
+/// The values of the variants of an enum of the module `env` was built
+/// from, if they can be computed.
+pub fn enum_discriminants<'ast>(
+    env: &TypeEnvironment<'ast>,
+    declarator: &EnumDeclarator<'ast>,
+) -> Option<Vec<(&'ast str, i128)>> {
+    let values = env
+        .constants()
+        .ok()?
+        .enum_values(LOCAL_MODULE, declarator.identifier.value)
+        .ok()?;
+    values
+        .into_iter()
+        .map(|(variant, value)| match value {
+            Constant::Integer { value, .. } => Some((variant, value)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The members of a union, an optional's type and `null`, or the type
+/// itself.
+fn members(ty: &Type) -> Vec<Type> {
+    match ty.unqualified() {
+        Type::Optional(inner) => vec![
+            inner.unqualified().clone(),
+            Type::Primitive(PrimitiveType::Null),
+        ],
+        ty => ty.members().to_vec(),
+    }
+}
+
+fn is_bool(ty: &Type) -> bool {
+    *ty.unqualified() == Type::Primitive(PrimitiveType::Bool)
+}
+
+/// The smallest and largest values of integer-like types.
+pub(crate) fn integer_bounds(ty: &Type) -> Option<(i128, i128)> {
+    match ty.unqualified() {
+        Type::Primitive(PrimitiveType::Bool) => Some((0, 1)),
+        Type::Primitive(pt) => match pt.integer_layout()? {
+            (true, bits) => Some((-(1 << (bits - 1)), (1 << (bits - 1)) - 1)),
+            (false, bits) => Some((0, (1 << bits) - 1)),
+        },
+        _ => None,
+    }
+}
+
+/// The value of a literal pattern, if it is a valid value of `ty`.
+pub(crate) fn integer_value(ty: &Type, literal: &LiteralPattern) -> Option<i128> {
+    let (min, max) = integer_bounds(ty)?;
+    if is_bool(ty) != matches!(literal.value, Primitive::Bool(_)) {
+        return None;
+    }
+    let value = match literal.value {
+        Primitive::Bool(b) => b as i128,
+        Primitive::DecimalNumber(digits) => digits.parse().ok()?,
+        Primitive::HexadecimalNumber(digits) => i128::from_str_radix(&digits[2..], 16).ok()?,
+        Primitive::OctalNumber(digits) => i128::from_str_radix(&digits[2..], 8).ok()?,
+        Primitive::BinaryNumber(digits) => i128::from_str_radix(&digits[2..], 2).ok()?,
+        Primitive::Char(c) => char_value(c)?,
+        _ => return None,
+    };
+    let value = if literal.negative { -value } else { value };
+    if value < min || value > max {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// The inclusive bounds of a range pattern.
+pub(crate) fn range_bounds(ty: &Type, range: &RangePattern) -> Option<(i128, i128)> {
+    let start = integer_value(ty, &range.start.value)?;
+    let end = integer_value(ty, &range.end.value)?;
+    Some((start, if range.inclusive { end } else { end - 1 }))
+}
+
+/// The value of a character literal, including its quotes.
+pub(crate) fn char_value(literal: &str) -> Option<i128> {
+    let inner = literal.get(1..literal.len() - 1)?;
+    let mut chars = inner.chars();
+    let value = match chars.next()? {
+        '\\' => match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            c @ '\\' | c @ '\'' | c @ '"' => c,
+            _ => return None,
+        },
+        c => c,
+    };
+    Some(value as i128)
+}
+
+/// Removes `[start, end]` from the remaining intervals unless `guarded`, and
+/// returns whether any of it was still remaining.
+fn cover_interval(
+    remaining: &mut Vec<(i128, i128)>,
+    (start, end): (i128, i128),
+    guarded: bool,
+) -> bool {
+    let reachable = remaining.iter().any(|&(a, b)| a <= end && start <= b);
+    if reachable && !guarded {
+        let mut uncovered = vec![];
+        for &(a, b) in remaining.iter() {
+            if b < start || end < a {
+                uncovered.push((a, b));
+                continue;
+            }
+            if a < start {
+                uncovered.push((a, start - 1));
+            }
+            if end < b {
+                uncovered.push((end + 1, b));
+            }
+        }
+        *remaining = uncovered;
+    }
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parser::{parse, Parser};
+    use pretty_assertions::assert_eq;
+    use toolshed::Arena;
+
+    const ALIGNMENT: &str = "
+        enum Alignment {
+            Left,
+            Right = 12,
+            Center,
+        }
+    ";
+
+    fn check(scrutinee: Type, source: &str) -> std::result::Result<MatchLowering, Vec<Error>> {
+        let program = parse(ALIGNMENT).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let env = TypeEnvironment::from_module(&module).unwrap();
+
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let statement = p.statement_node().unwrap();
+        match statement.value {
+            Statement::Match(statement) => check_match(&env, &scrutinee, &statement, 0..1),
+            s => panic!("expected a match statement, got {:?}", s),
+        }
+    }
+
+    fn alignment() -> Type {
+        Type::Enum("Alignment".to_string())
+    }
+
+    #[test]
+    fn test_exhaustive_enum_match_is_a_switch() {
+        let source = "match (a) { .Left => x = 1; Alignment.Right => x = 2; .Center => x = 3; }";
+        assert_eq!(
+            check(alignment(), source),
+            Ok(MatchLowering::Switch {
+                cases: vec![(0, 0), (12, 1), (13, 2)],
+                default: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_enum_variant() {
+        let source = "match (a) { .Left => x = 1; .Right => x = 2; }";
+        assert_eq!(
+            check(alignment(), source),
+            Err(vec![Error::NonExhaustiveMatch {
+                missing: vec![".Center".to_string()],
+                span: 0..1,
+            }])
+        );
+        let source = "match (a) { .Left => x = 1; .Up => x = 2; _ => x = 3; }";
+        assert_eq!(
+            check(alignment(), source),
+            Err(vec![Error::UnknownEnumVariant {
+                enumeration: "Alignment".to_string(),
+                variant: "Up".to_string(),
+                span: 29..31,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_integer_ranges() {
+        let u8_type = Type::Primitive(PrimitiveType::U8);
+        let source = "match (c) { 0 => x = 1; 1..10 => x = 2; 5 => x = 3; _ => x = 4; }";
+        assert_eq!(
+            check(u8_type.clone(), source),
+            Err(vec![Error::UnreachablePattern { span: 40..41 }])
+        );
+        let source = "match (c) { 0...99 => x = 1; 200...255 => x = 2; }";
+        assert_eq!(
+            check(u8_type.clone(), source),
+            Err(vec![Error::NonExhaustiveMatch {
+                missing: vec!["100...199".to_string()],
+                span: 0..1,
+            }])
+        );
+        let source = "match (c) { 'a'...'c' => x = 1; 0...9 => x = 2; _ => x = 3; }";
+        match check(u8_type, source) {
+            Ok(MatchLowering::Switch { cases, default }) => {
+                assert_eq!(&cases[..3], &[(97, 0), (98, 0), (99, 0)]);
+                assert_eq!(cases.len(), 13);
+                assert_eq!(default, Some(2));
+            }
+            res => panic!("expected a switch, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_guards_do_not_exhaust() {
+        let source = "match (b) { true if y => x = 1; false => x = 2; }";
+        assert_eq!(
+            check(Type::Primitive(PrimitiveType::Bool), source),
+            Err(vec![Error::NonExhaustiveMatch {
+                missing: vec!["true".to_string()],
+                span: 0..1,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_union_type_patterns() {
+        let union = Type::union(vec![
+            Type::Primitive(PrimitiveType::S32),
+            Type::Primitive(PrimitiveType::Bool),
+            Type::Primitive(PrimitiveType::Null),
+        ]);
+        let source = "match (v) { n: int => x = n; b: bool => x = 0; }";
+        assert_eq!(
+            check(union.clone(), source),
+            Err(vec![Error::NonExhaustiveMatch {
+                missing: vec!["null".to_string()],
+                span: 0..1,
+            }])
+        );
+        let source = "match (v) { 0...9 => x = 0; n: int => x = n; null => x = 0; _ => x = 1; }";
+        assert_eq!(check(union, source), Ok(MatchLowering::Chain));
+    }
+
+    #[test]
+    fn test_optional_patterns() {
+        let optional = Type::Optional(Box::new(Type::Primitive(PrimitiveType::S32)));
+        let source = "match (v) { 0 => x = 0; n: int => x = n; }";
+        assert_eq!(
+            check(optional.clone(), source),
+            Err(vec![Error::NonExhaustiveMatch {
+                missing: vec!["null".to_string()],
+                span: 0..1,
+            }])
+        );
+        let source = "match (v) { null => x = 0; n: int => x = n; }";
+        assert_eq!(check(optional, source), Ok(MatchLowering::Chain));
+    }
+
+    #[test]
+    fn test_pattern_type_mismatch() {
+        let source = "match (a) { 3 => x = 1; _ => x = 2; }";
+        assert_eq!(
+            check(alignment(), source),
+            Err(vec![Error::PatternTypeMismatch {
+                ty: "Alignment".to_string(),
+                span: 12..13,
+            }])
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Declarations every module can use without importing them.
+
+use crate::semantic::types::TypeEnvironment;
+use crate::syntax::ast::{Program, SourceUnit};
+use crate::syntax::parser::parse;
+
+/// The source of the prelude. It is parsed like any other module and defined
+/// in each module's `TypeEnvironment` by `define_prelude`.
+pub const PRELUDE: &str = "
+interface Error {
+    function message(this) -> []char;
+}
+
+interface Iterator<T> {
+    function next(this) -> ?T;
+}
+
+struct Range<T> {
+    start: T;
+    end: T;
+    inclusive: bool;
+}
+";
+
+/// The interface error types implement.
+pub const ERROR_INTERFACE: &str = "Error";
+
+/// The interface `for` loops use to step through values.
+pub const ITERATOR_INTERFACE: &str = "Iterator";
+
+/// The type of range values such as `0..10`.
+pub const RANGE_STRUCT: &str = "Range";
+
+thread_local! {
+    /// The parsed prelude. It is never freed, so that every module's AST can
+    /// refer to the declarations in it.
+    static PROGRAM: &'static Program<'static> =
+        Box::leak(Box::new(parse(PRELUDE).expect("the prelude parses")));
+}
+
+/// Defines the types of the prelude in `env`. Types the module declares
+/// itself shadow them.
+pub fn define_prelude(env: &mut TypeEnvironment) {
+    let program = PROGRAM.with(|program| *program);
+    for unit in program.body().iter() {
+        let SourceUnit::Module(module) = unit.value;
+        env.define_missing(&module);
+    }
+}
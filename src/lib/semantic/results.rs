@@ -0,0 +1,234 @@
+use crate::semantic::error::*;
+use crate::semantic::interfaces::InterfaceTable;
+use crate::semantic::prelude::ERROR_INTERFACE;
+use crate::semantic::types::*;
+use crate::syntax::ast::*;
+use std::ops::Range;
+
+/// How `try operand` unwraps a result union.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Propagation {
+    /// The type of the `try` expression: the members that are not errors.
+    pub value: Type,
+    /// The union tags of the error members, which return early.
+    pub error_tags: Vec<u32>,
+}
+
+/// Whether values of `ty` are errors: pointers to the `Error` interface and
+/// types implementing it, or pointers to them.
+pub fn is_error_type(interfaces: &InterfaceTable, ty: &Type) -> bool {
+    let error = NamedTypeRef::new(ERROR_INTERFACE);
+    if ty.interface_pointer() == Some(&error) {
+        return true;
+    }
+    let implementor = match ty.unqualified() {
+        Type::Pointer(inner) => inner.unqualified(),
+        ty => ty,
+    };
+    matches!(implementor, Type::Struct(_) | Type::Enum(_))
+        && interfaces
+            .find_implementation(implementor, &error)
+            .is_some()
+}
+
+/// Whether `ty` is a union with error members, such as `int | *Error`.
+pub fn is_result_type(interfaces: &InterfaceTable, ty: &Type) -> bool {
+    match ty.unqualified() {
+        Type::Union(members) => members.iter().any(|m| is_error_type(interfaces, m)),
+        _ => false,
+    }
+}
+
+/// Checks `try operand` in a function returning `return_type`. The operand
+/// must be a result union, and the function must be able to return each of
+/// its errors.
+pub fn check_try(
+    interfaces: &InterfaceTable,
+    operand: &Type,
+    return_type: Option<&Type>,
+    span: Range<usize>,
+) -> Result<Propagation> {
+    let mut values = vec![];
+    let mut error_tags = vec![];
+    let mut errors = vec![];
+    for (tag, member) in operand.unqualified().members().iter().enumerate() {
+        if is_error_type(interfaces, member) {
+            error_tags.push(tag as u32);
+            errors.push(member);
+        } else {
+            values.push(member.clone());
+        }
+    }
+    if errors.is_empty() || values.is_empty() || !matches!(operand.unqualified(), Type::Union(_)) {
+        return Err(Error::NotAResult {
+            ty: operand.to_string(),
+            span,
+        });
+    }
+    let error_pointer = Type::Pointer(Box::new(Type::Interface(NamedTypeRef::new(
+        ERROR_INTERFACE,
+    ))));
+    for error in errors {
+        // Pointers to error types convert to `*Error`.
+        let returnable = match return_type {
+            Some(return_type) => {
+                return_type.contains(error)
+                    || (return_type.contains(&error_pointer) && matches!(error, Type::Pointer(_)))
+            }
+            None => false,
+        };
+        if !returnable {
+            return Err(Error::UnpropagatedError {
+                error: error.to_string(),
+                return_type: return_type.map_or("nothing".to_string(), |ty| ty.to_string()),
+                span,
+            });
+        }
+    }
+    Ok(Propagation {
+        value: Type::union(values),
+        error_tags,
+    })
+}
+
+/// Warns about an expression statement that discards a result union, since
+/// the error it may hold would go unnoticed.
+pub fn check_discarded_result(
+    interfaces: &InterfaceTable,
+    expression: &ExpressionNode,
+    ty: &Type,
+) -> Option<Warning> {
+    match expression.value {
+        Expression::AssignmentExpression(_) => None,
+        _ if is_result_type(interfaces, ty) => Some(Warning::UnusedResult {
+            ty: ty.to_string(),
+            span: expression.start as usize..expression.end as usize,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::prelude::PRELUDE;
+    use crate::syntax::parser::{parse, Parser};
+    use pretty_assertions::assert_eq;
+    use toolshed::Arena;
+
+    const PARSING: &str = "
+        struct ParseError { position: int; }
+        struct Token { length: int; }
+        impl Error for ParseError {
+            function message(this) -> []char {}
+        }
+    ";
+
+    fn with_interfaces<F>(f: F)
+    where
+        F: FnOnce(&InterfaceTable),
+    {
+        let prelude = parse(PRELUDE).unwrap();
+        let prelude_body = prelude.body();
+        let SourceUnit::Module(prelude) = prelude_body.iter().next().unwrap().value;
+        let program = parse(PARSING).unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+
+        let mut env = TypeEnvironment::from_module(&module).unwrap();
+        env.define_module(&prelude).unwrap();
+        let interfaces = InterfaceTable::build(&module, &env).unwrap();
+        f(&interfaces)
+    }
+
+    fn named(name: &str) -> Type {
+        Type::Struct(NamedTypeRef::new(name))
+    }
+
+    fn pointer(ty: Type) -> Type {
+        Type::Pointer(Box::new(ty))
+    }
+
+    fn error_pointer() -> Type {
+        pointer(Type::Interface(NamedTypeRef::new("Error")))
+    }
+
+    #[test]
+    fn test_error_types() {
+        with_interfaces(|interfaces| {
+            assert!(is_error_type(interfaces, &error_pointer()));
+            assert!(is_error_type(interfaces, &named("ParseError")));
+            assert!(is_error_type(interfaces, &pointer(named("ParseError"))));
+            assert!(!is_error_type(interfaces, &named("Token")));
+        });
+    }
+
+    #[test]
+    fn test_try_unwraps_the_value_members() {
+        with_interfaces(|interfaces| {
+            let operand = Type::union(vec![
+                named("Token"),
+                pointer(named("ParseError")),
+                Type::Primitive(PrimitiveType::Null),
+            ]);
+            let return_type =
+                Type::union(vec![Type::Primitive(PrimitiveType::S32), error_pointer()]);
+            assert_eq!(
+                check_try(interfaces, &operand, Some(&return_type), 0..0),
+                Ok(Propagation {
+                    value: Type::union(vec![named("Token"), Type::Primitive(PrimitiveType::Null)]),
+                    error_tags: vec![1],
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_try_requires_a_returnable_error() {
+        with_interfaces(|interfaces| {
+            let operand = Type::union(vec![named("Token"), named("ParseError")]);
+            let return_type =
+                Type::union(vec![Type::Primitive(PrimitiveType::S32), error_pointer()]);
+            assert_eq!(
+                check_try(interfaces, &operand, Some(&return_type), 3..9),
+                Err(Error::UnpropagatedError {
+                    error: "ParseError".to_string(),
+                    return_type: "(s32 | *Error)".to_string(),
+                    span: 3..9,
+                })
+            );
+            assert_eq!(
+                check_try(interfaces, &named("Token"), Some(&return_type), 3..9),
+                Err(Error::NotAResult {
+                    ty: "Token".to_string(),
+                    span: 3..9,
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_discarded_results_warn() {
+        with_interfaces(|interfaces| {
+            let result = Type::union(vec![named("Token"), error_pointer()]);
+            let arena = Arena::new();
+            let call = Parser::new("tokenize(input)", &arena)
+                .expression_node()
+                .unwrap();
+            assert_eq!(
+                check_discarded_result(interfaces, &call, &result),
+                Some(Warning::UnusedResult {
+                    ty: "(Token | *Error)".to_string(),
+                    span: 0..15,
+                })
+            );
+            let assignment = Parser::new("token = tokenize(input)", &arena)
+                .expression_node()
+                .unwrap();
+            assert_eq!(
+                check_discarded_result(interfaces, &assignment, &result),
+                None
+            );
+        });
+    }
+}
@@ -0,0 +1,602 @@
+use crate::semantic::constants::ConstantEvaluator;
+use crate::semantic::error::*;
+use crate::semantic::module_declarations;
+use crate::syntax::ast::*;
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A fully-resolved catlang type. Unlike `TypeExpression`, named types have
+/// been looked up and aliases have been expanded.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Type {
+    Primitive(PrimitiveType),
+    Pointer(Box<Type>),
+    SizedArray(Box<Type>),
+    UnsizedArray(Box<Type>),
+    /// An array of a size known at compile time, stored inline.
+    Array(Box<Type>, u64),
+    Const(Box<Type>),
+    Volatile(Box<Type>),
+    Optional(Box<Type>),
+    Union(Vec<Type>),
+    Struct(NamedTypeRef),
+    Enum(String),
+    Interface(NamedTypeRef),
+    Generic(String),
+    Any,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NamedTypeRef {
+    pub name: String,
+    pub generic_arguments: Vec<Type>,
+}
+
+/// The resolved signature of a function or method.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FunctionSignature {
+    pub receiver: bool,
+    pub parameters: Vec<Type>,
+    pub return_type: Option<Type>,
+}
+
+impl NamedTypeRef {
+    pub fn new(name: &str) -> Self {
+        NamedTypeRef {
+            name: name.to_string(),
+            generic_arguments: vec![],
+        }
+    }
+}
+
+impl Type {
+    /// Builds a type union, flattening nested unions and dropping duplicates.
+    /// A union of a single type is that type.
+    pub fn union<I>(types: I) -> Type
+    where
+        I: IntoIterator<Item = Type>,
+    {
+        let mut members: Vec<Type> = vec![];
+        for ty in types {
+            let flattened = match ty {
+                Type::Union(inner) => inner,
+                ty => vec![ty],
+            };
+            for member in flattened {
+                if !members.contains(&member) {
+                    members.push(member);
+                }
+            }
+        }
+        if members.len() == 1 {
+            members.pop().unwrap()
+        } else {
+            Type::Union(members)
+        }
+    }
+
+    /// Strips `const` and `volatile` qualifiers.
+    pub fn unqualified(&self) -> &Type {
+        match self {
+            Type::Const(inner) | Type::Volatile(inner) => inner.unqualified(),
+            ty => ty,
+        }
+    }
+
+    /// The members of a union, or the type itself for non-union types.
+    pub fn members(&self) -> &[Type] {
+        match self {
+            Type::Union(members) => members,
+            ty => std::slice::from_ref(ty),
+        }
+    }
+
+    /// Returns the interface behind a `*Interface` fat pointer type.
+    pub fn interface_pointer(&self) -> Option<&NamedTypeRef> {
+        match self.unqualified() {
+            Type::Pointer(inner) => match inner.unqualified() {
+                Type::Interface(interface) => Some(interface),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether every value of `other` is also a value of this type, as used
+    /// for coercions into type unions.
+    pub fn contains(&self, other: &Type) -> bool {
+        let members = self.unqualified().members();
+        other
+            .unqualified()
+            .members()
+            .iter()
+            .all(|member| members.contains(member))
+    }
+
+    /// Whether a value of this type implicitly converts to `target` without
+    /// losing information: integers to wider integers (unsigned ones also to
+    /// wider signed ones), integers to floats that represent them exactly, and
+    /// floats to wider floats.
+    pub fn widens_to(&self, target: &Type) -> bool {
+        let (from, to) = match (self.unqualified(), target.unqualified()) {
+            (Type::Primitive(from), Type::Primitive(to)) if from != to => (*from, *to),
+            _ => return false,
+        };
+        match (from.integer_layout(), to.integer_layout()) {
+            (Some((from_signed, from_bits)), Some((to_signed, to_bits))) => {
+                return if from_signed == to_signed {
+                    from_bits <= to_bits
+                } else {
+                    !from_signed && from_bits < to_bits
+                };
+            }
+            (Some((_, from_bits)), None) => {
+                // Only integers that fit the mantissa convert exactly.
+                return match to.float_width() {
+                    Some(32) => from_bits <= 16,
+                    Some(_) => from_bits <= 32,
+                    None => false,
+                };
+            }
+            _ => {}
+        }
+        match (from.float_width(), to.float_width()) {
+            (Some(from_bits), Some(to_bits)) => from_bits <= to_bits,
+            _ => false,
+        }
+    }
+
+    /// Replaces generic parameters with the types bound to them.
+    pub fn substitute(&self, bindings: &HashMap<String, Type>) -> Type {
+        match self {
+            Type::Generic(name) => bindings.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Type::Pointer(inner) => Type::Pointer(Box::new(inner.substitute(bindings))),
+            Type::SizedArray(inner) => Type::SizedArray(Box::new(inner.substitute(bindings))),
+            Type::UnsizedArray(inner) => Type::UnsizedArray(Box::new(inner.substitute(bindings))),
+            Type::Array(inner, size) => Type::Array(Box::new(inner.substitute(bindings)), *size),
+            Type::Const(inner) => Type::Const(Box::new(inner.substitute(bindings))),
+            Type::Volatile(inner) => Type::Volatile(Box::new(inner.substitute(bindings))),
+            Type::Optional(inner) => Type::Optional(Box::new(inner.substitute(bindings))),
+            Type::Union(members) => Type::union(members.iter().map(|m| m.substitute(bindings))),
+            Type::Struct(named) => Type::Struct(named.substitute(bindings)),
+            Type::Interface(named) => Type::Interface(named.substitute(bindings)),
+            Type::Primitive(_) | Type::Enum(_) | Type::Any => self.clone(),
+        }
+    }
+
+    /// Matches `self`, which may contain generic parameters, against a
+    /// concrete type, recording what each generic parameter is bound to.
+    pub fn unify(&self, concrete: &Type, bindings: &mut HashMap<String, Type>) -> bool {
+        match (self, concrete) {
+            (Type::Generic(name), _) => match bindings.get(name) {
+                Some(bound) => bound == concrete,
+                None => {
+                    bindings.insert(name.clone(), concrete.clone());
+                    true
+                }
+            },
+            (Type::Pointer(a), Type::Pointer(b))
+            | (Type::SizedArray(a), Type::SizedArray(b))
+            | (Type::UnsizedArray(a), Type::UnsizedArray(b))
+            | (Type::Const(a), Type::Const(b))
+            | (Type::Volatile(a), Type::Volatile(b))
+            | (Type::Optional(a), Type::Optional(b)) => a.unify(b, bindings),
+            (Type::Array(a, a_size), Type::Array(b, b_size)) => {
+                a_size == b_size && a.unify(b, bindings)
+            }
+            (Type::Union(a), Type::Union(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.unify(b, bindings))
+            }
+            (Type::Struct(a), Type::Struct(b)) | (Type::Interface(a), Type::Interface(b)) => {
+                a.unify(b, bindings)
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl NamedTypeRef {
+    pub fn substitute(&self, bindings: &HashMap<String, Type>) -> NamedTypeRef {
+        NamedTypeRef {
+            name: self.name.clone(),
+            generic_arguments: self
+                .generic_arguments
+                .iter()
+                .map(|arg| arg.substitute(bindings))
+                .collect(),
+        }
+    }
+
+    pub fn unify(&self, concrete: &NamedTypeRef, bindings: &mut HashMap<String, Type>) -> bool {
+        self.name == concrete.name
+            && self.generic_arguments.len() == concrete.generic_arguments.len()
+            && self
+                .generic_arguments
+                .iter()
+                .zip(&concrete.generic_arguments)
+                .all(|(a, b)| a.unify(b, bindings))
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Primitive(pt) => f.write_str(pt.keyword()),
+            Type::Pointer(inner) => write!(f, "*{}", inner),
+            Type::SizedArray(inner) => write!(f, "[]{}", inner),
+            Type::UnsizedArray(inner) => write!(f, "[..]{}", inner),
+            Type::Array(inner, size) => write!(f, "[{}]{}", size, inner),
+            Type::Const(inner) => write!(f, "const {}", inner),
+            Type::Volatile(inner) => write!(f, "volatile {}", inner),
+            Type::Optional(inner) => write!(f, "?{}", inner),
+            Type::Union(members) => {
+                f.write_str("(")?;
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{}", member)?;
+                }
+                f.write_str(")")
+            }
+            Type::Struct(named) | Type::Interface(named) => write!(f, "{}", named),
+            Type::Enum(name) | Type::Generic(name) => f.write_str(name),
+            Type::Any => f.write_str("any"),
+        }
+    }
+}
+
+impl fmt::Display for NamedTypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.generic_arguments.is_empty() {
+            f.write_str("<")?;
+            for (i, arg) in self.generic_arguments.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", arg)?;
+            }
+            f.write_str(">")?;
+        }
+        Ok(())
+    }
+}
+
+/// A user-defined type that named types can refer to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TypeDefinition<'ast> {
+    Struct(StructDeclarator<'ast>),
+    Enum(EnumDeclarator<'ast>),
+    Interface(InterfaceDeclarator<'ast>),
+    Alias(TypeDeclarator<'ast>),
+}
+
+/// The name `TypeEnvironment::constants` knows the environment's module by.
+pub const LOCAL_MODULE: &str = "self";
+
+/// Upper bound on alias expansion, so that `type a = b; type b = a;` is
+/// reported instead of overflowing the stack.
+const MAX_ALIAS_DEPTH: usize = 64;
+
+/// The named type a declaration defines, if it defines one.
+fn type_definition<'ast>(
+    declaration: &DeclarationNode<'ast>,
+) -> Option<(IdentifierNode<'ast>, TypeDefinition<'ast>)> {
+    match declaration.value.declarator {
+        Declarator::Struct(s) => Some((s.identifier, TypeDefinition::Struct(s))),
+        Declarator::Enum(e) => Some((e.identifier, TypeDefinition::Enum(e))),
+        Declarator::Interface(i) => Some((i.identifier, TypeDefinition::Interface(i))),
+        Declarator::Type(t) => Some((t.identifier, TypeDefinition::Alias(t))),
+        _ => None,
+    }
+}
+
+/// Every named type visible in a module.
+#[derive(Clone, Debug, Default)]
+pub struct TypeEnvironment<'ast> {
+    definitions: HashMap<&'ast str, TypeDefinition<'ast>>,
+    /// Types imported from C headers, by the name they are imported as.
+    foreign: HashMap<&'ast str, Type>,
+    /// The module the environment was built from, whose constants array
+    /// sizes and enum values may refer to.
+    module: Option<Module<'ast>>,
+    /// Evaluates the constants of `module`, built on first use so that
+    /// each is evaluated once however many types refer to it.
+    constants: RefCell<Option<ConstantEvaluator<'ast>>>,
+}
+
+impl<'ast> TypeEnvironment<'ast> {
+    pub fn new() -> Self {
+        TypeEnvironment::default()
+    }
+
+    pub fn from_module(module: &Module<'ast>) -> std::result::Result<Self, Vec<Error>> {
+        let mut env = TypeEnvironment::new();
+        env.define_module(module)?;
+        env.module = Some(*module);
+        Ok(env)
+    }
+
+    /// Defines every named type `module` declares.
+    pub fn define_module(&mut self, module: &Module<'ast>) -> std::result::Result<(), Vec<Error>> {
+        let mut errors = vec![];
+        for declaration in module_declarations(module) {
+            if let Some((identifier, definition)) = type_definition(&declaration) {
+                if let Err(e) = self.define(identifier, definition) {
+                    errors.push(e);
+                }
+            }
+        }
+        match errors.len() {
+            0 => Ok(()),
+            _ => Err(errors),
+        }
+    }
+
+    /// Defines the named types `module` declares that this environment does
+    /// not define yet, which the module's own declarations shadow.
+    pub fn define_missing(&mut self, module: &Module<'ast>) {
+        for declaration in module_declarations(module) {
+            if let Some((identifier, definition)) = type_definition(&declaration) {
+                if !self.definitions.contains_key(identifier.value) {
+                    self.definitions.insert(identifier.value, definition);
+                }
+            }
+        }
+    }
+
+    pub fn define(
+        &mut self,
+        identifier: IdentifierNode<'ast>,
+        definition: TypeDefinition<'ast>,
+    ) -> Result<()> {
+        if self.definitions.contains_key(identifier.value) {
+            return Err(Error::DuplicateDefinition {
+                name: identifier.value.to_string(),
+                span: identifier.start as usize..identifier.end as usize,
+            });
+        }
+        self.definitions.insert(identifier.value, definition);
+        Ok(())
+    }
+
+    /// Defines a type imported from a C header.
+    pub fn define_foreign(&mut self, identifier: IdentifierNode<'ast>, ty: Type) -> Result<()> {
+        if self.definitions.contains_key(identifier.value)
+            || self.foreign.contains_key(identifier.value)
+        {
+            return Err(Error::DuplicateDefinition {
+                name: identifier.value.to_string(),
+                span: identifier.start as usize..identifier.end as usize,
+            });
+        }
+        self.foreign.insert(identifier.value, ty);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TypeDefinition<'ast>> {
+        self.definitions.get(name)
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = (&'ast str, &TypeDefinition<'ast>)> {
+        self.definitions
+            .iter()
+            .map(|(name, definition)| (*name, definition))
+    }
+
+    /// The evaluator for the constants of the module the environment was
+    /// built from, which it knows as `LOCAL_MODULE`. It is shared by every
+    /// use of the environment, and must not be held while resolving types.
+    pub fn constants(
+        &self,
+    ) -> std::result::Result<RefMut<'_, ConstantEvaluator<'ast>>, Vec<Error>> {
+        let mut constants = self.constants.borrow_mut();
+        if constants.is_none() {
+            let mut evaluator = ConstantEvaluator::new();
+            if let Some(module) = &self.module {
+                evaluator.add_module(LOCAL_MODULE, module)?;
+            }
+            *constants = Some(evaluator);
+        }
+        Ok(RefMut::map(constants, |constants| {
+            constants.as_mut().expect("the evaluator was just built")
+        }))
+    }
+
+    fn array_size(&self, size: &ExpressionNode<'ast>) -> Result<u64> {
+        self.constants()
+            .map_err(|mut errors| errors.remove(0))?
+            .array_size(LOCAL_MODULE, size)
+    }
+
+    /// Resolves a type expression. `generics` lists the generic parameters
+    /// that are in scope where the expression appears.
+    pub fn resolve(&self, ty: &TypeExpressionNode<'ast>, generics: &[&str]) -> Result<Type> {
+        self.resolve_at_depth(ty, generics, 0)
+    }
+
+    fn resolve_at_depth(
+        &self,
+        ty: &TypeExpressionNode<'ast>,
+        generics: &[&str],
+        depth: usize,
+    ) -> Result<Type> {
+        match ty.value {
+            TypeExpression::Binary(binary) => match binary.op {
+                BinaryTypeOperator::TypeUnion => Ok(Type::union(vec![
+                    self.resolve_at_depth(&binary.left, generics, depth)?,
+                    self.resolve_at_depth(&binary.right, generics, depth)?,
+                ])),
+            },
+            TypeExpression::Unary(unary) => {
+                let inner = Box::new(self.resolve_at_depth(&unary.inner, generics, depth)?);
+                Ok(match unary.op {
+                    UnaryTypeOperator::PointerTo => Type::Pointer(inner),
+                    UnaryTypeOperator::SizedArray => Type::SizedArray(inner),
+                    UnaryTypeOperator::UnsizedArray => Type::UnsizedArray(inner),
+                    UnaryTypeOperator::Const => Type::Const(inner),
+                    UnaryTypeOperator::Volatile => Type::Volatile(inner),
+                    UnaryTypeOperator::Optional => Type::Optional(inner),
+                })
+            }
+            TypeExpression::Array(array) => {
+                let inner = Box::new(self.resolve_at_depth(&array.inner, generics, depth)?);
+                Ok(Type::Array(inner, self.array_size(&array.size)?))
+            }
+            TypeExpression::Simple(simple) => match simple {
+                SimpleTypeExpression::Typeof(_) => Err(Error::UnresolvedTypeof {
+                    span: ty.start as usize..ty.end as usize,
+                }),
+                SimpleTypeExpression::PrimitiveType(pt) => Ok(Type::Primitive(pt.canonical())),
+                SimpleTypeExpression::SubExpression(inner) => {
+                    self.resolve_at_depth(&inner, generics, depth)
+                }
+                SimpleTypeExpression::Any => Ok(Type::Any),
+                SimpleTypeExpression::NamedType(named) => {
+                    self.resolve_named(ty, named, generics, depth)
+                }
+            },
+        }
+    }
+
+    fn resolve_named(
+        &self,
+        ty: &TypeExpressionNode<'ast>,
+        named: NamedType<'ast>,
+        generics: &[&str],
+        depth: usize,
+    ) -> Result<Type> {
+        let name = named.identifier.value;
+        if generics.contains(&name) {
+            return Ok(Type::Generic(name.to_string()));
+        }
+        let mut generic_arguments = vec![];
+        for argument in named.generic_parameters.iter() {
+            generic_arguments.push(self.resolve_at_depth(argument, generics, depth)?);
+        }
+        let named_ref = NamedTypeRef {
+            name: name.to_string(),
+            generic_arguments,
+        };
+        match self.definitions.get(name) {
+            Some(TypeDefinition::Struct(_)) => Ok(Type::Struct(named_ref)),
+            Some(TypeDefinition::Enum(_)) => Ok(Type::Enum(named_ref.name)),
+            Some(TypeDefinition::Interface(_)) => Ok(Type::Interface(named_ref)),
+            Some(TypeDefinition::Alias(alias)) => {
+                if depth >= MAX_ALIAS_DEPTH {
+                    return Err(Error::RecursiveTypeAlias {
+                        name: name.to_string(),
+                        span: ty.start as usize..ty.end as usize,
+                    });
+                }
+                self.resolve_at_depth(&alias.type_expression, generics, depth + 1)
+            }
+            None if self.foreign.contains_key(name) => Ok(self.foreign[name].clone()),
+            None => Err(Error::UnknownType {
+                name: name.to_string(),
+                span: ty.start as usize..ty.end as usize,
+            }),
+        }
+    }
+
+    /// Resolves the signature of a function declarator.
+    pub fn resolve_signature(
+        &self,
+        parameters: NodeList<'ast, Parameter<'ast>>,
+        return_type: Option<TypeExpressionNode<'ast>>,
+        receiver: bool,
+        generics: &[&str],
+    ) -> Result<FunctionSignature> {
+        let mut resolved = vec![];
+        for parameter in parameters.iter() {
+            resolved.push(self.resolve(&parameter.value.type_expression, generics)?);
+        }
+        let return_type = match return_type {
+            Some(ty) => Some(self.resolve(&ty, generics)?),
+            None => None,
+        };
+        Ok(FunctionSignature {
+            receiver,
+            parameters: resolved,
+            return_type,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::parser::Parser;
+    use pretty_assertions::assert_eq;
+    use toolshed::Arena;
+
+    fn resolve(source: &str) -> Result<Type> {
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let ty = p.type_node().unwrap();
+        TypeEnvironment::new().resolve(&ty, &["T"])
+    }
+
+    #[test]
+    fn test_resolve_primitive_alias() {
+        assert_eq!(resolve("int"), Ok(Type::Primitive(PrimitiveType::S32)));
+    }
+
+    #[test]
+    fn test_resolve_flattens_unions() {
+        assert_eq!(
+            resolve("int | (s32 | T)"),
+            Ok(Type::Union(vec![
+                Type::Primitive(PrimitiveType::S32),
+                Type::Generic("T".to_string()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_resolve_array_sizes_from_constants() {
+        let arena = Arena::new();
+        let program = crate::syntax::parser::parse("const n = 4;").unwrap();
+        let body = program.body();
+        let SourceUnit::Module(module) = body.iter().next().unwrap().value;
+        let env = TypeEnvironment::from_module(&module).unwrap();
+        let resolve = |source| {
+            let mut p = Parser::new(source, &arena);
+            env.resolve(&p.type_node().unwrap(), &[])
+        };
+        assert_eq!(
+            resolve("[n * 2]s32"),
+            Ok(Type::Array(
+                Box::new(Type::Primitive(PrimitiveType::S32)),
+                8
+            ))
+        );
+        assert!(matches!(
+            resolve("[n - 5]s32"),
+            Err(Error::InvalidArraySize { .. })
+        ));
+    }
+
+    #[test]
+    fn test_resolve_unknown_type() {
+        assert_eq!(
+            resolve("*Missing"),
+            Err(Error::UnknownType {
+                name: "Missing".to_string(),
+                span: 1..8,
+            })
+        );
+    }
+
+    #[test]
+    fn test_union_containment() {
+        let int = Type::Primitive(PrimitiveType::S32);
+        let boolean = Type::Primitive(PrimitiveType::Bool);
+        let union = Type::union(vec![int.clone(), boolean.clone()]);
+        assert!(union.contains(&int));
+        assert!(union.contains(&union));
+        assert!(!int.contains(&union));
+    }
+}
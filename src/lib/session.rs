@@ -0,0 +1,763 @@
+//! Compiles catlang held in memory and calls it from Rust, for programs
+//! that run catlang scripts:
+//!
+//! ```
+//! use catlang::{Context, Session};
+//!
+//! let context = Context::create();
+//! let mut session = Session::new();
+//! session.add_source(
+//!     "scripts/fib.cat",
+//!     "function fibIter(n: s64) -> s64 {
+//!         let a: s64 = 0;
+//!         let b: s64 = 1;
+//!         for (x in 0..n) {
+//!             const temp = a + b;
+//!             a = b;
+//!             b = temp;
+//!         }
+//!         return a;
+//!     }",
+//! );
+//! let compiled = session.compile(&context).unwrap();
+//! let fib = compiled.get_function::<fn(i64) -> i64>("fibIter").unwrap();
+//! assert_eq!(fib(10), 55);
+//! ```
+
+use crate::build::import_path;
+use crate::codegen::entry::Start;
+use crate::codegen::error::CodeGenError;
+use crate::codegen::module::{exported_items, ExportedItems, ModuleCompiler};
+use crate::codegen::optimize::{OptLevel, PassDebugging};
+use crate::codegen::{jit, target_machine, Checks, CodeGen, DEFAULT_TARGET_TRIPLE};
+use crate::semantic::cfg::{strip, Config};
+use crate::semantic::entry::initialization_order;
+use crate::semantic::types::{FunctionSignature, Type};
+use crate::syntax::ast::*;
+use crate::syntax::parser::parse;
+use inkwell::context::Context;
+use inkwell::execution_engine::{ExecutionEngine, JitFunction, UnsafeFunctionPointer};
+use inkwell::targets::{InitializationConfig, Target, TargetTriple};
+use inkwell::values::FunctionValue;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use thiserror::Error;
+use toolshed::Arena;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SessionError {
+    #[error("unknown function {:?}", .0)]
+    UnknownFunction(String),
+    #[error(
+        "no overload of {} has the signature {}; candidates are {:?}",
+        name,
+        signature,
+        candidates
+    )]
+    SignatureMismatch {
+        name: String,
+        signature: &'static str,
+        candidates: Vec<String>,
+    },
+    #[error("{} is ambiguous between {:?}", name, candidates)]
+    AmbiguousFunction {
+        name: String,
+        candidates: Vec<String>,
+    },
+    #[error("{} was not compiled", .0)]
+    NotCompiled(String),
+}
+
+pub type Result<T> = std::result::Result<T, SessionError>;
+
+/// An error in one of a session's sources.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The path the source was added under, or `""` for errors that are in
+    /// no source in particular.
+    pub path: String,
+    /// The byte range of the error in the source.
+    pub span: Option<Range<usize>>,
+    /// The line and column the error starts at, both counted from 1.
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new<M: ToString>(
+        path: &str,
+        source: &str,
+        span: Option<Range<usize>>,
+        message: M,
+    ) -> Self {
+        let location = span.as_ref().map(|span| {
+            let before = &source[..span.start.min(source.len())];
+            let line = before.matches('\n').count() + 1;
+            let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+            (line, column)
+        });
+        Diagnostic {
+            path: path.to_string(),
+            span,
+            location,
+            message: message.to_string(),
+        }
+    }
+
+    fn from_codegen(path: &str, source: &str, error: CodeGenError) -> Vec<Self> {
+        match error {
+            CodeGenError::SemanticErrors(errors) => errors
+                .iter()
+                .map(|error| Diagnostic::new(path, source, error.span(), error))
+                .collect(),
+            error => vec![Diagnostic::new(path, source, error.span(), error)],
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => {
+                write!(f, "{}:{}:{}: {}", self.path, line, column, self.message)
+            }
+            None if self.path.is_empty() => f.write_str(&self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// The sources of a program, compiled together into a JIT or an object
+/// file.
+#[derive(Clone, Debug)]
+pub struct Session {
+    sources: Vec<(String, String)>,
+    opt_level: OptLevel,
+    passes: PassDebugging,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            sources: vec![],
+            opt_level: OptLevel::None,
+            passes: PassDebugging::default(),
+        }
+    }
+}
+
+/// The sources of a session compiled into one LLVM module, which is yet to
+/// be finished into a JIT or an object file.
+struct Built<'ctx> {
+    codegen: CodeGen<'ctx>,
+    functions: Vec<Function>,
+    initializers: Vec<FunctionValue<'ctx>>,
+    /// Where the program starts: in the first source added that can.
+    start: Option<Start<'ctx>>,
+    warnings: Vec<Diagnostic>,
+}
+
+/// An object file a session's sources were compiled into.
+#[derive(Clone, Debug)]
+pub struct Object {
+    /// The object's LLVM IR, as optimized.
+    pub ir: String,
+    /// What compiling the sources found suspicious but not wrong.
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// Adds a module under a virtual path, which names it in diagnostics and
+    /// symbols, and which other sources import it by relative to their own.
+    /// Adding a path again replaces its source.
+    pub fn add_source<P: Into<String>, S: Into<String>>(
+        &mut self,
+        path: P,
+        source: S,
+    ) -> &mut Self {
+        let path = path.into();
+        let source = source.into();
+        match self.sources.iter_mut().find(|(p, _)| *p == path) {
+            Some(existing) => existing.1 = source,
+            None => self.sources.push((path, source)),
+        }
+        self
+    }
+
+    /// Sets the pipeline the sources are optimized with, `OptLevel::None`
+    /// by default.
+    pub fn opt_level(&mut self, opt_level: OptLevel) -> &mut Self {
+        self.opt_level = opt_level;
+        self
+    }
+
+    /// Sets the hooks for looking into what the optimizer does.
+    pub fn passes(&mut self, passes: PassDebugging) -> &mut Self {
+        self.passes = passes;
+        self
+    }
+
+    /// Compiles the sources and runs their module initializers, or returns
+    /// every error found in them. Sources are parsed before any is compiled,
+    /// so syntax errors are reported for all of them at once, and each is
+    /// compiled and initialized after the sources it imports.
+    pub fn compile<'ctx>(
+        &self,
+        context: &'ctx Context,
+    ) -> std::result::Result<Compilation<'ctx>, Vec<Diagnostic>> {
+        let error = |message: String| vec![Diagnostic::new("", "", None, message)];
+        Target::initialize_native(&InitializationConfig::default()).map_err(error)?;
+        let Built {
+            codegen,
+            functions,
+            initializers,
+            warnings,
+            ..
+        } = self.build(
+            context,
+            &DEFAULT_TARGET_TRIPLE,
+            &jit::config(),
+            Checks::all(),
+        )?;
+        if !initializers.is_empty() {
+            codegen.build_constructor(&initializers);
+        }
+        codegen.module.verify().map_err(|e| error(e.to_string()))?;
+        target_machine(&DEFAULT_TARGET_TRIPLE, self.opt_level)
+            .and_then(|machine| codegen.optimize(self.opt_level, &machine))
+            .map_err(|e| error(e.to_string()))?;
+        let engine = codegen
+            .module
+            .create_jit_execution_engine(self.opt_level.codegen_level())
+            .map_err(|e| error(e.to_string()))?;
+        engine.run_static_constructors();
+        Ok(Compilation {
+            codegen,
+            engine,
+            functions,
+            warnings,
+        })
+    }
+
+    /// Compiles the sources into the object file `path` for `target`, or
+    /// returns every error found in them. A program gets a `main` that runs
+    /// the module initializers and then the entry point of the first source
+    /// added that has one; otherwise a constructor runs the initializers.
+    pub fn write_object<P: AsRef<Path>>(
+        &self,
+        context: &Context,
+        target: &str,
+        path: P,
+    ) -> std::result::Result<Object, Vec<Diagnostic>> {
+        let error = |message: String| vec![Diagnostic::new("", "", None, message)];
+        Target::initialize_all(&InitializationConfig::default());
+        let Built {
+            codegen,
+            initializers,
+            start,
+            warnings,
+            ..
+        } = self.build(
+            context,
+            target,
+            &Config::for_target(target),
+            Checks::default(),
+        )?;
+        match start {
+            Some(start) => {
+                codegen
+                    .build_entry_point(&start, &initializers)
+                    .ok_or_else(|| error("could not build the entry point".to_string()))?;
+            }
+            None if !initializers.is_empty() => {
+                codegen.build_constructor(&initializers);
+            }
+            None => {}
+        }
+        codegen.module.verify().map_err(|e| error(e.to_string()))?;
+        codegen
+            .write_output_file(self.opt_level, target, path)
+            .map_err(|e| error(e.to_string()))?;
+        Ok(Object {
+            ir: codegen.write_to_string(),
+            warnings,
+        })
+    }
+
+    /// Parses the sources and compiles each after those it imports, with
+    /// the `#[cfg]` settings `config`, into one LLVM module for `target`.
+    fn build<'ctx>(
+        &self,
+        context: &'ctx Context,
+        target: &str,
+        config: &Config,
+        checks: Checks,
+    ) -> std::result::Result<Built<'ctx>, Vec<Diagnostic>> {
+        let mut diagnostics = vec![];
+        let mut programs = vec![];
+        for (path, source) in &self.sources {
+            match parse(source) {
+                Ok(program) => programs.push((path, source, program)),
+                Err(errors) => diagnostics.extend(
+                    errors
+                        .iter()
+                        .map(|error| Diagnostic::new(path, source, error.span(), error)),
+                ),
+            }
+        }
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+
+        // Imports of other sources, by the index of the importing source.
+        // A source's own path is resolved as if imported from the root, so
+        // that `main` and `./main.cat` name the same module.
+        let modules: Vec<_> = programs
+            .iter()
+            .map(|(path, _, _)| import_path(Path::new(""), path))
+            .collect();
+        let mut imports: HashMap<usize, Vec<(&str, usize)>> = HashMap::new();
+        for (index, (path, _, program)) in programs.iter().enumerate() {
+            for unit in program.body().iter() {
+                let SourceUnit::Module(module) = unit.value;
+                for element in module.elements.iter() {
+                    if let ModuleElement::Import(import) = element.value {
+                        let import = import.value.path.value.trim_matches('"');
+                        let imported = import_path(Path::new(path), import);
+                        if let Some(imported) = modules.iter().position(|path| *path == imported) {
+                            imports.entry(index).or_default().push((import, imported));
+                        }
+                    }
+                }
+            }
+        }
+        let graph: HashMap<&str, Vec<&str>> = imports
+            .iter()
+            .map(|(index, imported)| {
+                let imported = imported
+                    .iter()
+                    .map(|(_, imported)| programs[*imported].0.as_str())
+                    .collect();
+                (programs[*index].0.as_str(), imported)
+            })
+            .collect();
+        let mut order = vec![];
+        for (path, _, _) in &programs {
+            for module in initialization_order(path, &graph) {
+                let index = programs.iter().position(|(path, _, _)| *path == module);
+                if !order.contains(&index) {
+                    order.push(index);
+                }
+            }
+        }
+
+        let mut codegen = CodeGen::named(context, "session");
+        codegen.checks = checks;
+        codegen.passes = self.passes.clone();
+        // The target decides the layout of some C types, like `long double`.
+        codegen.module.set_triple(&TargetTriple::create(target));
+        let arena = Arena::new();
+        let mut functions = vec![];
+        let mut initializers = vec![];
+        let mut start = None;
+        let mut warnings = vec![];
+        let mut exports = HashMap::new();
+        for index in order.into_iter().flatten() {
+            let (path, source, program) = &programs[index];
+            for unit in program.body().iter() {
+                let SourceUnit::Module(module) = unit.value;
+                let compiled = strip(&arena, &module, config)
+                    .map_err(CodeGenError::from)
+                    .and_then(|module| {
+                        let imported: Vec<(&str, &ExportedItems)> = imports
+                            .get(&index)
+                            .into_iter()
+                            .flatten()
+                            .flat_map(|(import, imported)| {
+                                let items = exports.get(imported).into_iter().flatten();
+                                items.map(move |items| (*import, items))
+                            })
+                            .collect();
+                        let mut compiler =
+                            ModuleCompiler::with_imports(&codegen, path, &module, &imported)?;
+                        let compiled = compiler.compile(&module)?;
+                        let items = exported_items(path, &module, &imported)?;
+                        Ok((compiler, compiled, items))
+                    });
+                let (compiler, compiled, items) = match compiled {
+                    Ok(compiled) => compiled,
+                    Err(e) => {
+                        diagnostics.extend(Diagnostic::from_codegen(path, source, e));
+                        continue;
+                    }
+                };
+                exports.entry(index).or_insert_with(Vec::new).push(items);
+                initializers.extend(compiled.initializer);
+                if let Some(compiled_start) = compiled.start {
+                    match start {
+                        Some((first, _)) if first < index => {}
+                        _ => start = Some((index, compiled_start)),
+                    }
+                }
+                warnings.extend(
+                    compiler.warnings().iter().map(|warning| {
+                        Diagnostic::new(path, source, Some(warning.span()), warning)
+                    }),
+                );
+                functions.extend(
+                    compiler
+                        .functions()
+                        .iter()
+                        .filter(|overload| overload.generic_parameters.is_empty())
+                        .map(|overload| Function {
+                            name: overload.name.to_string(),
+                            description: format!("{} in {}", overload, path),
+                            symbol: overload.symbol.clone(),
+                            signature: overload.signature.clone(),
+                        }),
+                );
+            }
+        }
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        Ok(Built {
+            codegen,
+            functions,
+            initializers,
+            start: start.map(|(_, start)| start),
+            warnings,
+        })
+    }
+}
+
+/// A function compiled by a session.
+#[derive(Clone, Debug)]
+struct Function {
+    name: String,
+    /// The overload and the source defining it, for errors.
+    description: String,
+    symbol: String,
+    signature: FunctionSignature,
+}
+
+/// The compiled sources of a session, whose functions can be called.
+pub struct Compilation<'ctx> {
+    codegen: CodeGen<'ctx>,
+    engine: ExecutionEngine<'ctx>,
+    functions: Vec<Function>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl<'ctx> Compilation<'ctx> {
+    /// The function called `name` as a Rust closure with the signature `F`,
+    /// such as `fn(i64, f64) -> f64`. `F` picks among the overloads of
+    /// `name`, and must match the catlang signature exactly: `s64` is `i64`,
+    /// `f32` is `f32` and so on, and functions returning nothing return `()`.
+    pub fn get_function<F: Signature<'ctx>>(&self, name: &str) -> Result<F::Function> {
+        let overloads: Vec<&Function> = self.functions.iter().filter(|f| f.name == name).collect();
+        if overloads.is_empty() {
+            return Err(SessionError::UnknownFunction(name.to_string()));
+        }
+        let candidates = |functions: &[&Function]| {
+            functions
+                .iter()
+                .map(|function| function.description.clone())
+                .collect()
+        };
+        let matching: Vec<&Function> = overloads
+            .iter()
+            .copied()
+            .filter(|function| F::accepts(&function.signature))
+            .collect();
+        let function = match matching.as_slice() {
+            [function] => function,
+            [] => {
+                return Err(SessionError::SignatureMismatch {
+                    name: name.to_string(),
+                    signature: std::any::type_name::<F>(),
+                    candidates: candidates(&overloads),
+                })
+            }
+            _ => {
+                return Err(SessionError::AmbiguousFunction {
+                    name: name.to_string(),
+                    candidates: candidates(&matching),
+                })
+            }
+        };
+        // The signature was checked against the function's above.
+        let function = unsafe { self.engine.get_function::<F::Pointer>(&function.symbol) }
+            .map_err(|_| SessionError::NotCompiled(function.description.clone()))?;
+        Ok(F::wrap(function))
+    }
+
+    /// The LLVM IR the sources were compiled to.
+    pub fn ir(&self) -> String {
+        self.codegen.write_to_string()
+    }
+
+    /// What compiling the sources found suspicious but not wrong, such as
+    /// discarded result unions.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+}
+
+/// A Rust type catlang passes the same way, so it can be a parameter or
+/// return type of a function called from Rust.
+pub trait Value: Copy {
+    /// Whether values of the catlang type `ty` are values of this type.
+    fn accepts(ty: &Type) -> bool;
+}
+
+macro_rules! value {
+    ($rust:ty, $($primitive:path)|+) => {
+        impl Value for $rust {
+            fn accepts(ty: &Type) -> bool {
+                matches!(ty, Type::Primitive(pt) if matches!(pt.canonical(), $($primitive)|+))
+            }
+        }
+    };
+}
+
+value!(i8, PrimitiveType::S8);
+value!(u8, PrimitiveType::U8 | PrimitiveType::Char);
+value!(i16, PrimitiveType::S16);
+value!(u16, PrimitiveType::U16);
+value!(i32, PrimitiveType::S32);
+value!(u32, PrimitiveType::U32);
+value!(i64, PrimitiveType::S64);
+value!(u64, PrimitiveType::U64);
+value!(f32, PrimitiveType::F32);
+value!(f64, PrimitiveType::F64);
+
+/// A Rust type a function called from Rust can return: a [`Value`], or `()`
+/// for functions returning nothing.
+pub trait ReturnValue {
+    fn accepts(ty: Option<&Type>) -> bool;
+}
+
+impl<T: Value> ReturnValue for T {
+    fn accepts(ty: Option<&Type>) -> bool {
+        matches!(ty, Some(ty) if T::accepts(ty))
+    }
+}
+
+impl ReturnValue for () {
+    fn accepts(ty: Option<&Type>) -> bool {
+        matches!(ty, None | Some(Type::Primitive(PrimitiveType::CVoid)))
+    }
+}
+
+/// The signature of a catlang function called from Rust, written as a Rust
+/// function pointer type like `fn(i64) -> i64`.
+pub trait Signature<'ctx> {
+    /// The pointer the JIT hands back.
+    type Pointer: UnsafeFunctionPointer;
+    /// The closure calling the function.
+    type Function;
+
+    /// Whether a catlang function with `signature` can be called as this.
+    fn accepts(signature: &FunctionSignature) -> bool;
+
+    fn wrap(function: JitFunction<'ctx, Self::Pointer>) -> Self::Function;
+}
+
+macro_rules! signature {
+    ($($parameter:ident: $ty:ident),*) => {
+        impl<'ctx, $($ty: Value + 'ctx,)* R: ReturnValue + 'ctx> Signature<'ctx> for fn($($ty),*) -> R {
+            type Pointer = unsafe extern "C" fn($($ty),*) -> R;
+            type Function = Box<dyn Fn($($ty),*) -> R + 'ctx>;
+
+            fn accepts(signature: &FunctionSignature) -> bool {
+                let mut parameters = signature.parameters.iter();
+                !signature.receiver
+                    $(&& matches!(parameters.next(), Some(parameter) if $ty::accepts(parameter)))*
+                    && parameters.next().is_none()
+                    && R::accepts(signature.return_type.as_ref())
+            }
+
+            fn wrap(function: JitFunction<'ctx, Self::Pointer>) -> Self::Function {
+                Box::new(move |$($parameter),*| unsafe { function.call($($parameter),*) })
+            }
+        }
+    };
+}
+
+signature!();
+signature!(a: A);
+signature!(a: A, b: B);
+signature!(a: A, b: B, c: C);
+signature!(a: A, b: B, c: C, d: D);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn signature(
+        parameters: Vec<PrimitiveType>,
+        return_type: Option<PrimitiveType>,
+    ) -> FunctionSignature {
+        FunctionSignature {
+            receiver: false,
+            parameters: parameters.into_iter().map(Type::Primitive).collect(),
+            return_type: return_type.map(Type::Primitive),
+        }
+    }
+
+    #[test]
+    fn test_signatures() {
+        let fib = signature(vec![PrimitiveType::S64], Some(PrimitiveType::S64));
+        assert!(<fn(i64) -> i64>::accepts(&fib));
+        assert!(!<fn(u64) -> i64>::accepts(&fib));
+        assert!(!<fn(i64, i64) -> i64>::accepts(&fib));
+        assert!(!<fn(i64)>::accepts(&fib));
+
+        let log = signature(vec![PrimitiveType::Long, PrimitiveType::Double], None);
+        assert!(<fn(i64, f64)>::accepts(&log));
+        assert!(!<fn(i64, f64) -> f64>::accepts(&log));
+    }
+
+    #[test]
+    fn test_syntax_errors_are_diagnosed() {
+        let context = Context::create();
+        let mut session = Session::new();
+        session
+            .add_source("ok.cat", "function one() -> s64 { return 1; }")
+            .add_source("broken.cat", "function one() -> s64 {\n    return 1 +;\n}");
+        let diagnostics = match session.compile(&context) {
+            Ok(_) => panic!("compiled a syntax error"),
+            Err(diagnostics) => diagnostics,
+        };
+
+        let locations: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.path.as_str(), diagnostic.location))
+            .collect();
+        assert_eq!(
+            locations,
+            vec![("broken.cat", Some((2, 15))), ("broken.cat", Some((3, 1)))]
+        );
+        assert_eq!(diagnostics[0].span, Some(38..39));
+    }
+
+    #[test]
+    fn test_discarded_results_are_warned_about() {
+        let context = Context::create();
+        let mut session = Session::new();
+        session.add_source(
+            "check.cat",
+            "function check(value: s64) -> s64 | *Error { return value; }\n\
+             function run() {\n    check(1);\n    let checked = check(2);\n}",
+        );
+        let compiled = session.compile(&context).unwrap();
+        let warnings: Vec<_> = compiled
+            .warnings()
+            .iter()
+            .map(|warning| (warning.location, warning.message.as_str()))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![(
+                Some((3, 5)),
+                "result of type (s64 | *Error) is ignored and may hold an error at 82:90"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_sources_import_each_other() {
+        let context = Context::create();
+        let mut session = Session::new();
+        session
+            .add_source(
+                "main.cat",
+                "import { square, offset } from \"./lib/math\";\n\
+                 function run(n: s64) -> s64 { return square(n) + offset; }",
+            )
+            .add_source(
+                "lib/math.cat",
+                "export let offset: s64 = 9;\n\
+                 export function square(n: s64) -> s64 { return n * n; }",
+            );
+        let compiled = session.compile(&context).unwrap();
+        let run = compiled.get_function::<fn(i64) -> i64>("run").unwrap();
+        assert_eq!(run(4), 25);
+    }
+
+    #[test]
+    fn test_imported_constants_size_arrays() {
+        let context = Context::create();
+        let mut session = Session::new();
+        session
+            .add_source(
+                "main.cat",
+                "import { SIZE as N } from \"./config\";\n\
+                 const TOTAL = N * 2;\n\
+                 let squares: [TOTAL]s64;\n\
+                 function run() -> s64 { return squares.length as s64 * 10 + N; }",
+            )
+            .add_source("config.cat", "export const SIZE = 2 + 2;");
+        let compiled = session.compile(&context).unwrap();
+        let run = compiled.get_function::<fn() -> i64>("run").unwrap();
+        assert_eq!(run(), 84);
+    }
+
+    #[test]
+    fn test_write_object_starts_in_the_first_source() {
+        let object = std::env::temp_dir().join("catlang-session-test-write-object.o");
+        let _ = std::fs::remove_file(&object);
+        let context = Context::create();
+        let mut session = Session::new();
+        session
+            .add_source(
+                "answer.cat",
+                "import { base } from \"./base\";\n\
+                 function main() -> s32 { return base + 2; }",
+            )
+            .add_source(
+                "base.cat",
+                "export let base: s32 = 40;\nfunction main() -> s32 { return 1; }",
+            );
+        let written = session
+            .write_object(&context, &DEFAULT_TARGET_TRIPLE, &object)
+            .unwrap();
+        let entry = &written.ir[written.ir.find("define i32 @main(").unwrap()..];
+        let entry = &entry[..entry.find("\n}").unwrap()];
+        assert!(entry.contains("answer.cat::main"), "{}", entry);
+        assert!(std::fs::metadata(&object).unwrap().len() > 0);
+        std::fs::remove_file(object).unwrap();
+
+        let mut session = Session::new();
+        session.add_source("broken.cat", "function main() -> s32 { return 1 +; }");
+        let errors = session
+            .write_object(&context, &DEFAULT_TARGET_TRIPLE, "broken.o")
+            .unwrap_err();
+        assert_eq!(errors[0].location, Some((1, 36)));
+    }
+
+    #[test]
+    fn test_opt_level_runs_the_pass_pipeline() {
+        let context = Context::create();
+        let mut session = Session::new();
+        session.opt_level(OptLevel::Default).add_source(
+            "sum.cat",
+            "function sum(n: s64) -> s64 {\n\
+             let total: s64 = 0;\n\
+             for (x in 0..n) { total += x; }\n\
+             return total;\n}",
+        );
+        let compiled = session.compile(&context).unwrap();
+        assert!(!compiled.ir().contains("alloca"));
+        let sum = compiled.get_function::<fn(i64) -> i64>("sum").unwrap();
+        assert_eq!(sum(5), 10);
+    }
+}
@@ -14,6 +14,8 @@ pub enum Declarator<'ast> {
     Function(FunctionDeclarator<'ast>),
     Struct(StructDeclarator<'ast>),
     Enum(EnumDeclarator<'ast>),
+    Interface(InterfaceDeclarator<'ast>),
+    Impl(ImplDeclarator<'ast>),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -38,13 +40,155 @@ pub struct VariableDeclarator<'ast> {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct FunctionDeclarator<'ast> {
-    pub function_name: IdentifierNode<'ast>, // TODO: Scoped values?
+    pub scope: Option<FunctionScopeNode<'ast>>,
+    pub function_name: IdentifierNode<'ast>,
     pub generic_parameters: IdentifierList<'ast>,
+    pub receiver: Option<FlagNode<'ast>>,
     pub parameters: NodeList<'ast, Parameter<'ast>>,
     pub return_type: Option<TypeExpressionNode<'ast>>,
     pub block: BlockNode<'ast>,
 }
 
+/// The type a method is defined on, as in `function Tree<K, V>::get(...)`.
+/// The generic parameters are bound by the method.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FunctionScope<'ast> {
+    pub identifier: IdentifierNode<'ast>,
+    pub generic_parameters: IdentifierList<'ast>,
+}
+
+/// An operator that can be overloaded with a `function Type::operator ...`
+/// method. Overloads are stored under their method name, e.g. `operator+`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OverloadableOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitShiftLeft,
+    BitShiftRight,
+    Equals,
+    NotEquals,
+    LessThan,
+    LessThanEquals,
+    GreaterThan,
+    GreaterThanEquals,
+    Not,
+    BitNot,
+    Index,
+    IndexAssign,
+}
+
+const OVERLOADABLE_OPERATORS: [OverloadableOperator; 20] = [
+    OverloadableOperator::Add,
+    OverloadableOperator::Sub,
+    OverloadableOperator::Mul,
+    OverloadableOperator::Div,
+    OverloadableOperator::Rem,
+    OverloadableOperator::BitAnd,
+    OverloadableOperator::BitOr,
+    OverloadableOperator::BitXor,
+    OverloadableOperator::BitShiftLeft,
+    OverloadableOperator::BitShiftRight,
+    OverloadableOperator::Equals,
+    OverloadableOperator::NotEquals,
+    OverloadableOperator::LessThan,
+    OverloadableOperator::LessThanEquals,
+    OverloadableOperator::GreaterThan,
+    OverloadableOperator::GreaterThanEquals,
+    OverloadableOperator::Not,
+    OverloadableOperator::BitNot,
+    OverloadableOperator::Index,
+    OverloadableOperator::IndexAssign,
+];
+
+impl OverloadableOperator {
+    /// The name the overload is declared and looked up under.
+    pub fn method_name(self) -> &'static str {
+        match self {
+            OverloadableOperator::Add => "operator+",
+            OverloadableOperator::Sub => "operator-",
+            OverloadableOperator::Mul => "operator*",
+            OverloadableOperator::Div => "operator/",
+            OverloadableOperator::Rem => "operator%",
+            OverloadableOperator::BitAnd => "operator&",
+            OverloadableOperator::BitOr => "operator|",
+            OverloadableOperator::BitXor => "operator^",
+            OverloadableOperator::BitShiftLeft => "operator<<",
+            OverloadableOperator::BitShiftRight => "operator>>",
+            OverloadableOperator::Equals => "operator==",
+            OverloadableOperator::NotEquals => "operator!=",
+            OverloadableOperator::LessThan => "operator<",
+            OverloadableOperator::LessThanEquals => "operator<=",
+            OverloadableOperator::GreaterThan => "operator>",
+            OverloadableOperator::GreaterThanEquals => "operator>=",
+            OverloadableOperator::Not => "operator!",
+            OverloadableOperator::BitNot => "operator~",
+            OverloadableOperator::Index => "operator[]",
+            OverloadableOperator::IndexAssign => "operator[]=",
+        }
+    }
+
+    pub fn from_method_name(name: &str) -> Option<Self> {
+        OVERLOADABLE_OPERATORS
+            .iter()
+            .copied()
+            .find(|op| op.method_name() == name)
+    }
+
+    /// The number of parameters besides `this` an overload may take. `+` and
+    /// `-` can be overloaded both as prefix and as binary operators.
+    pub fn arities(self) -> &'static [usize] {
+        match self {
+            OverloadableOperator::Add | OverloadableOperator::Sub => &[0, 1],
+            OverloadableOperator::Not | OverloadableOperator::BitNot => &[0],
+            OverloadableOperator::IndexAssign => &[2],
+            _ => &[1],
+        }
+    }
+
+    pub fn from_binary(operator: BinaryOperator) -> Option<Self> {
+        let res = match operator {
+            BinaryOperator::Addition => OverloadableOperator::Add,
+            BinaryOperator::Subtraction => OverloadableOperator::Sub,
+            BinaryOperator::Multiplication => OverloadableOperator::Mul,
+            BinaryOperator::Division => OverloadableOperator::Div,
+            BinaryOperator::Remainder => OverloadableOperator::Rem,
+            BinaryOperator::BitAnd => OverloadableOperator::BitAnd,
+            BinaryOperator::BitOr => OverloadableOperator::BitOr,
+            BinaryOperator::BitXor => OverloadableOperator::BitXor,
+            BinaryOperator::BitShiftLeft => OverloadableOperator::BitShiftLeft,
+            BinaryOperator::BitShiftRight => OverloadableOperator::BitShiftRight,
+            BinaryOperator::Equals => OverloadableOperator::Equals,
+            BinaryOperator::NotEquals => OverloadableOperator::NotEquals,
+            BinaryOperator::LessThan => OverloadableOperator::LessThan,
+            BinaryOperator::LessThanEquals => OverloadableOperator::LessThanEquals,
+            BinaryOperator::GreaterThan => OverloadableOperator::GreaterThan,
+            BinaryOperator::GreaterThanEquals => OverloadableOperator::GreaterThanEquals,
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    pub fn from_prefix(operator: PrefixOperator) -> Option<Self> {
+        let res = match operator {
+            PrefixOperator::Plus => OverloadableOperator::Add,
+            PrefixOperator::Minus => OverloadableOperator::Sub,
+            PrefixOperator::LogicalNot => OverloadableOperator::Not,
+            PrefixOperator::BitNot => OverloadableOperator::BitNot,
+            PrefixOperator::Increment
+            | PrefixOperator::Decrement
+            | PrefixOperator::AddressOf
+            | PrefixOperator::Dereference => return None,
+        };
+        Some(res)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Parameter<'ast> {
     pub identifier: IdentifierNode<'ast>,
@@ -91,7 +235,34 @@ pub struct EnumValue<'ast> {
     pub value: Option<ExpressionNode<'ast>>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InterfaceDeclarator<'ast> {
+    pub identifier: IdentifierNode<'ast>,
+    pub generic_parameters: IdentifierList<'ast>,
+    pub methods: NodeList<'ast, InterfaceMethod<'ast>>,
+}
+
+/// A method signature required by an interface. The `receiver` flag marks
+/// methods that take `this` as their first parameter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InterfaceMethod<'ast> {
+    pub function_name: IdentifierNode<'ast>,
+    pub generic_parameters: IdentifierList<'ast>,
+    pub receiver: Option<FlagNode<'ast>>,
+    pub parameters: NodeList<'ast, Parameter<'ast>>,
+    pub return_type: Option<TypeExpressionNode<'ast>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImplDeclarator<'ast> {
+    pub generic_parameters: IdentifierList<'ast>,
+    pub interface: TypeExpressionNode<'ast>,
+    pub implementor: TypeExpressionNode<'ast>,
+    pub methods: NodeList<'ast, FunctionDeclarator<'ast>>,
+}
+
 pub type DeclarationNode<'ast> = Node<'ast, Declaration<'ast>>;
+pub type FunctionScopeNode<'ast> = Node<'ast, FunctionScope<'ast>>;
 
 impl_from! {
     ConstantDeclarator => Declarator::Constant,
@@ -100,4 +271,6 @@ impl_from! {
     FunctionDeclarator => Declarator::Function,
     StructDeclarator => Declarator::Struct,
     EnumDeclarator => Declarator::Enum,
+    InterfaceDeclarator => Declarator::Interface,
+    ImplDeclarator => Declarator::Impl,
 }
@@ -14,6 +14,8 @@ pub enum Expression<'ast> {
     IndexExpression(IndexExpression<'ast>),
     CastExpression(CastExpression<'ast>),
     MemberAccessExpression(MemberAccessExpression<'ast>),
+    ScopedExpression(ScopedExpression<'ast>),
+    TryExpression(TryExpression<'ast>),
     IdentifierExpression(IdentifierNode<'ast>),
     LambdaExpression(LambdaExpression<'ast>),
 }
@@ -77,6 +79,29 @@ pub enum AssignmentOperator {
     NullCoalesce,
 }
 
+impl AssignmentOperator {
+    /// The binary operator a compound assignment applies, e.g. `+` for `+=`.
+    pub fn binary_operator(self) -> Option<BinaryOperator> {
+        let res = match self {
+            AssignmentOperator::Plain => return None,
+            AssignmentOperator::Addition => BinaryOperator::Addition,
+            AssignmentOperator::Subtraction => BinaryOperator::Subtraction,
+            AssignmentOperator::Multiplication => BinaryOperator::Multiplication,
+            AssignmentOperator::Division => BinaryOperator::Division,
+            AssignmentOperator::Remainder => BinaryOperator::Remainder,
+            AssignmentOperator::BitShiftLeft => BinaryOperator::BitShiftLeft,
+            AssignmentOperator::BitShiftRight => BinaryOperator::BitShiftRight,
+            AssignmentOperator::BitAnd => BinaryOperator::BitAnd,
+            AssignmentOperator::BitXor => BinaryOperator::BitXor,
+            AssignmentOperator::BitOr => BinaryOperator::BitOr,
+            AssignmentOperator::LogicalAnd => BinaryOperator::LogicalAnd,
+            AssignmentOperator::LogicalOr => BinaryOperator::LogicalOr,
+            AssignmentOperator::NullCoalesce => BinaryOperator::NullCoalesce,
+        };
+        Some(res)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PrefixOperator {
     LogicalNot,
@@ -85,6 +110,10 @@ pub enum PrefixOperator {
     Decrement,
     Plus,
     Minus,
+    /// `@x`, the address of `x`.
+    AddressOf,
+    /// `*p`, the value `p` points to.
+    Dereference,
 }
 
 impl From<Token> for PrefixOperator {
@@ -96,6 +125,8 @@ impl From<Token> for PrefixOperator {
             Token::Decrement => PrefixOperator::Decrement,
             Token::Add => PrefixOperator::Plus,
             Token::Sub => PrefixOperator::Minus,
+            Token::At => PrefixOperator::AddressOf,
+            Token::Mul => PrefixOperator::Dereference,
             t => panic!("invalid prefix operator {:?}", t),
         }
     }
@@ -187,6 +218,19 @@ pub struct MemberAccessExpression<'ast> {
     pub member: IdentifierNode<'ast>,
 }
 
+/// A member of a type's scope, such as the static method in `Vector3::dot`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScopedExpression<'ast> {
+    pub scope: IdentifierNode<'ast>,
+    pub member: IdentifierNode<'ast>,
+}
+
+/// `try operand`, which returns early if `operand` holds an error.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TryExpression<'ast> {
+    pub operand: ExpressionNode<'ast>,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct LambdaExpression<'ast> {
     pub parameters: NodeList<'ast, Parameter<'ast>>,
@@ -208,5 +252,7 @@ impl_from! {
     IndexExpression => Expression::IndexExpression,
     CastExpression => Expression::CastExpression,
     MemberAccessExpression => Expression::MemberAccessExpression,
+    ScopedExpression => Expression::ScopedExpression,
+    TryExpression => Expression::TryExpression,
     IdentifierNode => Expression::IdentifierExpression,
 }
@@ -7,6 +7,7 @@ mod declaration;
 mod expression;
 mod module;
 mod node;
+mod pattern;
 mod source;
 mod statement;
 mod types;
@@ -22,6 +23,7 @@ pub use self::declaration::*;
 pub use self::expression::*;
 pub use self::module::*;
 pub use self::node::{Node, NodeInner, OptionalLocation};
+pub use self::pattern::*;
 pub use self::source::*;
 pub use self::statement::*;
 pub use self::types::*;
@@ -11,6 +11,8 @@ pub enum ModuleElement<'ast> {
     Import(ImportNode<'ast>),
     Export(ExportNode<'ast>),
     Declaration(DeclarationNode<'ast>),
+    /// A top-level statement, run in order when a script is executed.
+    Statement(StatementNode<'ast>),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -67,6 +69,7 @@ impl_from! {
     ImportNode => ModuleElement::Import,
     ExportNode => ModuleElement::Export,
     DeclarationNode => ModuleElement::Declaration,
+    StatementNode => ModuleElement::Statement,
 
     NamedImportList => ImportList::NamedImportList,
     GlobImportList => ImportList::GlobImportList,
@@ -0,0 +1,47 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern<'ast> {
+    Wildcard,
+    Literal(LiteralPattern<'ast>),
+    Range(RangePattern<'ast>),
+    EnumVariant(EnumVariantPattern<'ast>),
+    Type(TypePattern<'ast>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LiteralPattern<'ast> {
+    pub negative: bool,
+    pub value: Primitive<'ast>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RangePattern<'ast> {
+    pub start: LiteralPatternNode<'ast>,
+    pub end: LiteralPatternNode<'ast>,
+    pub inclusive: bool,
+}
+
+/// `.Variant` or `Enum.Variant`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnumVariantPattern<'ast> {
+    pub enumeration: Option<IdentifierNode<'ast>>,
+    pub variant: IdentifierNode<'ast>,
+}
+
+/// `x: T`, which binds the matched value as `x` if it is a `T`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TypePattern<'ast> {
+    pub binding: IdentifierNode<'ast>,
+    pub type_expression: TypeExpressionNode<'ast>,
+}
+
+pub type PatternNode<'ast> = Node<'ast, Pattern<'ast>>;
+pub type LiteralPatternNode<'ast> = Node<'ast, LiteralPattern<'ast>>;
+
+impl_from! {
+    LiteralPattern => Pattern::Literal,
+    RangePattern => Pattern::Range,
+    EnumVariantPattern => Pattern::EnumVariant,
+    TypePattern => Pattern::Type,
+}
@@ -4,6 +4,7 @@ use super::*;
 pub enum Statement<'ast> {
     InnerBlock(BlockNode<'ast>),
     If(IfStatement<'ast>),
+    Match(MatchStatement<'ast>),
     Loop(LoopStatement<'ast>),
     Jump(JumpStatement<'ast>),
     Expression(ExpressionNode<'ast>),
@@ -17,6 +18,19 @@ pub struct IfStatement<'ast> {
     pub else_block: Option<StatementNode<'ast>>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchStatement<'ast> {
+    pub scrutinee: ExpressionNode<'ast>,
+    pub arms: NodeList<'ast, MatchArm<'ast>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchArm<'ast> {
+    pub pattern: PatternNode<'ast>,
+    pub guard: Option<ExpressionNode<'ast>>,
+    pub body: StatementNode<'ast>,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LoopStatement<'ast> {
     ForLoop(ForLoop<'ast>),
@@ -56,10 +70,12 @@ pub struct DeleteStatement<'ast> {
 }
 
 pub type StatementNode<'ast> = Node<'ast, Statement<'ast>>;
+pub type MatchArmNode<'ast> = Node<'ast, MatchArm<'ast>>;
 
 impl_from! {
     BlockNode => Statement::InnerBlock,
     IfStatement => Statement::If,
+    MatchStatement => Statement::Match,
     LoopStatement => Statement::Loop,
     JumpStatement => Statement::Jump,
     ExpressionNode => Statement::Expression,
@@ -4,6 +4,7 @@ use super::*;
 pub enum TypeExpression<'ast> {
     Binary(BinaryTypeExpression<'ast>),
     Unary(UnaryTypeExpression<'ast>),
+    Array(ArrayTypeExpression<'ast>),
     Simple(SimpleTypeExpression<'ast>),
 }
 
@@ -35,6 +36,13 @@ pub struct UnaryTypeExpression<'ast> {
     pub inner: TypeExpressionNode<'ast>,
 }
 
+/// An array of a size known at compile time, as in `[4]s32`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArrayTypeExpression<'ast> {
+    pub size: ExpressionNode<'ast>,
+    pub inner: TypeExpressionNode<'ast>,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SimpleTypeExpression<'ast> {
     Typeof(ExpressionNode<'ast>),
@@ -50,7 +58,7 @@ pub struct NamedType<'ast> {
     pub generic_parameters: TypeExpressionList<'ast>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PrimitiveType {
     S8,
     U8,
@@ -87,12 +95,89 @@ pub enum PrimitiveType {
     CVoid,
 }
 
+impl PrimitiveType {
+    /// The keyword used to spell this type in catlang source code.
+    pub fn keyword(self) -> &'static str {
+        match self {
+            PrimitiveType::S8 => "s8",
+            PrimitiveType::U8 => "u8",
+            PrimitiveType::S16 => "s16",
+            PrimitiveType::U16 => "u16",
+            PrimitiveType::S32 => "s32",
+            PrimitiveType::U32 => "u32",
+            PrimitiveType::S64 => "s64",
+            PrimitiveType::U64 => "u64",
+            PrimitiveType::Char => "char",
+            PrimitiveType::Short => "short",
+            PrimitiveType::Int => "int",
+            PrimitiveType::Long => "long",
+            PrimitiveType::CShort => "c_short",
+            PrimitiveType::CUShort => "c_ushort",
+            PrimitiveType::CInt => "c_int",
+            PrimitiveType::CUInt => "c_uint",
+            PrimitiveType::CLong => "c_long",
+            PrimitiveType::CULong => "c_ulong",
+            PrimitiveType::CLongLong => "c_longlong",
+            PrimitiveType::CULongLong => "c_ulonglong",
+            PrimitiveType::CLongDouble => "c_longdouble",
+            PrimitiveType::Bool => "bool",
+            PrimitiveType::F32 => "f32",
+            PrimitiveType::F64 => "f64",
+            PrimitiveType::Float => "float",
+            PrimitiveType::Double => "double",
+            PrimitiveType::Null => "null",
+            PrimitiveType::NoReturn => "noreturn",
+            PrimitiveType::CVoid => "c_void",
+        }
+    }
+
+    /// The signedness and width in bits of integer types.
+    pub fn integer_layout(self) -> Option<(bool, u32)> {
+        let res = match self.canonical() {
+            PrimitiveType::S8 => (true, 8),
+            PrimitiveType::U8 | PrimitiveType::Char => (false, 8),
+            PrimitiveType::S16 | PrimitiveType::CShort => (true, 16),
+            PrimitiveType::U16 | PrimitiveType::CUShort => (false, 16),
+            PrimitiveType::S32 | PrimitiveType::CInt => (true, 32),
+            PrimitiveType::U32 | PrimitiveType::CUInt => (false, 32),
+            PrimitiveType::S64 | PrimitiveType::CLong | PrimitiveType::CLongLong => (true, 64),
+            PrimitiveType::U64 | PrimitiveType::CULong | PrimitiveType::CULongLong => (false, 64),
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    /// The width in bits of floating point types.
+    pub fn float_width(self) -> Option<u32> {
+        match self.canonical() {
+            PrimitiveType::F32 => Some(32),
+            PrimitiveType::F64 => Some(64),
+            PrimitiveType::CLongDouble => Some(80),
+            _ => None,
+        }
+    }
+
+    /// Maps the documented aliases (`int`, `float`, ...) onto the type they
+    /// are equivalent to.
+    pub fn canonical(self) -> Self {
+        match self {
+            PrimitiveType::Short => PrimitiveType::S16,
+            PrimitiveType::Int => PrimitiveType::S32,
+            PrimitiveType::Long => PrimitiveType::S64,
+            PrimitiveType::Float => PrimitiveType::F32,
+            PrimitiveType::Double => PrimitiveType::F64,
+            pt => pt,
+        }
+    }
+}
+
 pub type TypeExpressionNode<'ast> = Node<'ast, TypeExpression<'ast>>;
 pub type TypeExpressionList<'ast> = NodeList<'ast, TypeExpression<'ast>>;
 
 impl_from! {
     BinaryTypeExpression => TypeExpression::Binary,
     UnaryTypeExpression => TypeExpression::Unary,
+    ArrayTypeExpression => TypeExpression::Array,
     SimpleTypeExpression => TypeExpression::Simple,
 
     ExpressionNode => SimpleTypeExpression::Typeof,
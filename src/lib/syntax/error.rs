@@ -21,6 +21,13 @@ pub enum Error {
         span: Range<usize>,
     },
 
+    #[error("expected an expression but got {:?} ({:?}) at {}:{}", token, raw, span.start, span.end)]
+    ExpectedExpressionButGot {
+        token: Token,
+        raw: Box<str>,
+        span: Range<usize>,
+    },
+
     #[error("parser requested token beyond end of file")]
     ExtendedBeyondEndOfFile,
     #[error("duplicate flag error at {}:{}", span.start, span.end)]
@@ -29,4 +36,17 @@ pub enum Error {
     ExpectedFunctionButGot { token: Token },
 }
 
+impl Error {
+    /// Where in the source the error is, if it is anywhere in particular.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Error::ExpectedButGot { span, .. }
+            | Error::ExpectedOneOfButGot { span, .. }
+            | Error::ExpectedExpressionButGot { span, .. }
+            | Error::DuplicateFlagError { span } => Some(span.clone()),
+            Error::ExtendedBeyondEndOfFile | Error::ExpectedFunctionButGot { .. } => None,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
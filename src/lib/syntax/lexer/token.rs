@@ -123,6 +123,8 @@ pub enum Token {
 
     #[token("->")]
     Arrow,
+    #[token("=>")]
+    FatArrow,
     #[token("?")]
     Question,
     #[token("?.")]
@@ -166,12 +168,20 @@ pub enum Token {
     Function,
     #[token("return")]
     Return,
+    #[token("try")]
+    Try,
     #[token("struct")]
     Struct,
     #[token("type")]
     Type,
     #[token("enum")]
     Enum,
+    #[token("interface")]
+    Interface,
+    #[token("impl")]
+    Impl,
+    #[token("operator")]
+    Operator,
     #[token("SOA")]
     Soa,
     #[token("owned")]
@@ -194,6 +204,8 @@ pub enum Token {
     If,
     #[token("else")]
     Else,
+    #[token("match")]
+    Match,
     #[token("break")]
     Break,
     #[token("continue")]
@@ -1,9 +1,83 @@
+use toolshed::list::GrowableList;
+
 use crate::syntax::ast::*;
 use crate::syntax::error::*;
+use crate::syntax::lexer::Token;
 use crate::syntax::parser::Parser;
 
 impl<'ast> Parser<'ast> {
+    /// Parses the (possibly empty) attributes before a declaration, as in
+    /// `#[SOA] #[extern("C"), inline]`.
+    pub fn attribute_list(&mut self) -> Result<AttributeList<'ast>> {
+        let attributes = GrowableList::new();
+        while self.eat(Token::LAttr) {
+            loop {
+                attributes.push(self.arena, self.attribute_node()?);
+                if !self.eat(Token::Comma) || self.current_token == Token::RSquareB {
+                    break;
+                }
+            }
+            self.expect(Token::RSquareB);
+        }
+        Ok(attributes.as_list())
+    }
+
     pub fn attribute_node(&mut self) -> Result<AttributeNode<'ast>> {
-        unimplemented!()
+        // `SOA` is a keyword, but also the name of an attribute.
+        let identifier = if self.current_token == Token::Soa {
+            let (start, end) = self.loc();
+            let value = self.current_slice;
+            self.bump();
+            self.node_at(start, end, value)
+        } else {
+            self.identifier_node()?
+        };
+        let parameters = GrowableList::new();
+        if self.eat(Token::LParen) {
+            while self.current_token != Token::RParen && self.current_token != Token::EndOfFile {
+                parameters.push(self.arena, self.expression_node()?);
+                self.expect_one_of(&[Token::Comma], &[Token::RParen]);
+            }
+            self.expect(Token::RParen);
+        }
+        let end = self.last_span.end as u32;
+        Ok(self.node_at(
+            identifier.start,
+            end,
+            Attribute {
+                identifier,
+                parameters: parameters.as_list(),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+    use toolshed::Arena;
+
+    #[test]
+    fn test_attribute_with_parameters() {
+        let source = "#[extern(\"C\"), SOA]";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.attribute_list().unwrap();
+
+        assert!(p.errors.is_empty());
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_attributed_export() {
+        let source =
+            "#[extern(\"C\")]\nexport function area(side: s32) -> s32 { return side * side; }";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.source_unit();
+
+        assert!(p.errors.is_empty(), "{:?}", p.errors);
+        assert_debug_snapshot!(res);
     }
 }
@@ -7,15 +7,25 @@ use crate::syntax::parser::Parser;
 
 impl<'ast> Parser<'ast> {
     pub fn declaration_node(&mut self) -> Result<DeclarationNode<'ast>> {
-        // TODO: Possible attributes before this
         let start = self.loc().0;
+        let attributes = self.attribute_list()?;
+        self.attributed_declaration_node(start, attributes)
+    }
+
+    /// Parses a declaration whose attributes, starting at `start`, have
+    /// already been parsed.
+    pub fn attributed_declaration_node(
+        &mut self,
+        start: u32,
+        attributes: AttributeList<'ast>,
+    ) -> Result<DeclarationNode<'ast>> {
         let declarator = self.declarator()?;
         let end = self.last_span.end as u32;
         Ok(self.node_at(
             start,
             end,
             Declaration {
-                attributes: GrowableList::new().as_list(),
+                attributes,
                 declarator,
             },
         ))
@@ -29,6 +39,8 @@ impl<'ast> Parser<'ast> {
             Token::Function => self.function_declarator(),
             Token::Struct => self.struct_declarator(),
             Token::Enum => self.enum_declarator(),
+            Token::Interface => self.interface_declarator(),
+            Token::Impl => self.impl_declarator(),
             _ => Err(Error::ExpectedOneOfButGot {
                 expected_tokens: vec![
                     Token::Const,
@@ -37,6 +49,8 @@ impl<'ast> Parser<'ast> {
                     Token::Function,
                     Token::Struct,
                     Token::Enum,
+                    Token::Interface,
+                    Token::Impl,
                 ],
                 token: self.current_token,
                 raw: self.current_slice.into(),
@@ -97,19 +111,28 @@ impl<'ast> Parser<'ast> {
     }
 
     fn function_declarator(&mut self) -> Result<Declarator<'ast>> {
+        Ok(self.function_definition()?.into())
+    }
+
+    fn function_definition(&mut self) -> Result<FunctionDeclarator<'ast>> {
         let _start = self.start_then_advance();
-        let function_name = self.identifier_node()?;
-        let generic_parameters = GrowableList::new();
-        if self.eat(Token::LessThan) {
-            while self.current_token != Token::GreaterThan && self.current_token != Token::EndOfFile
-            {
-                generic_parameters.push(self.arena, self.identifier_node()?);
-                self.expect_one_of(&[Token::Comma], &[Token::GreaterThan]);
-            }
-            self.expect(Token::GreaterThan);
-        }
+        let mut function_name = self.identifier_node()?;
+        let mut generic_parameters = self.generic_parameter_list()?;
+        let scope_end = self.last_span.end as u32;
+        let scope = if self.eat(Token::DoubleColon) {
+            let scope = FunctionScope {
+                identifier: function_name,
+                generic_parameters,
+            };
+            let scope = self.node_at(function_name.start, scope_end, scope);
+            function_name = self.function_name_node()?;
+            generic_parameters = self.generic_parameter_list()?;
+            Some(scope)
+        } else {
+            None
+        };
         self.expect(Token::LParen);
-        let parameters = self.formal_parameter_list()?;
+        let (receiver, parameters) = self.formal_parameter_list()?;
         self.expect(Token::RParen);
         let return_type = if self.eat(Token::Arrow) {
             match self.current_token {
@@ -122,16 +145,117 @@ impl<'ast> Parser<'ast> {
         let block = self.block_node()?;
 
         Ok(FunctionDeclarator {
+            scope,
             function_name,
-            generic_parameters: generic_parameters.as_list(),
+            generic_parameters,
+            receiver,
             parameters,
             return_type,
             block,
+        })
+    }
+
+    /// Parses a function name, which may also be an operator overload such as
+    /// `operator +` or `operator []=`. Overloads are named after the operator,
+    /// see `OverloadableOperator::method_name`.
+    fn function_name_node(&mut self) -> Result<IdentifierNode<'ast>> {
+        if self.current_token != Token::Operator {
+            return self.identifier_node();
         }
-        .into())
+        let start = self.loc().0;
+        self.bump();
+        let operator = match self.current_token {
+            Token::Add => OverloadableOperator::Add,
+            Token::Sub => OverloadableOperator::Sub,
+            Token::Mul => OverloadableOperator::Mul,
+            Token::Quo => OverloadableOperator::Div,
+            Token::Mod => OverloadableOperator::Rem,
+            Token::BitAnd => OverloadableOperator::BitAnd,
+            Token::BitOr => OverloadableOperator::BitOr,
+            Token::Xor => OverloadableOperator::BitXor,
+            Token::ShiftL => OverloadableOperator::BitShiftLeft,
+            Token::ShiftR => OverloadableOperator::BitShiftRight,
+            Token::Equals => OverloadableOperator::Equals,
+            Token::NotEquals => OverloadableOperator::NotEquals,
+            Token::LessThan => OverloadableOperator::LessThan,
+            Token::LessThanEquals => OverloadableOperator::LessThanEquals,
+            Token::GreaterThan => OverloadableOperator::GreaterThan,
+            Token::GreaterThanEquals => OverloadableOperator::GreaterThanEquals,
+            Token::Not => OverloadableOperator::Not,
+            Token::BitNot => OverloadableOperator::BitNot,
+            Token::LSquareB => {
+                self.bump();
+                if self.current_token != Token::RSquareB {
+                    return Err(Error::ExpectedButGot {
+                        expected_token: Token::RSquareB,
+                        token: self.current_token,
+                        raw: self.current_slice.into(),
+                        span: self.current_span.clone(),
+                    });
+                }
+                if self.peek_token == Token::Assign {
+                    self.bump();
+                    OverloadableOperator::IndexAssign
+                } else {
+                    OverloadableOperator::Index
+                }
+            }
+            t => {
+                return Err(Error::ExpectedOneOfButGot {
+                    expected_tokens: vec![
+                        Token::Add,
+                        Token::Sub,
+                        Token::Mul,
+                        Token::Quo,
+                        Token::Mod,
+                        Token::BitAnd,
+                        Token::BitOr,
+                        Token::Xor,
+                        Token::ShiftL,
+                        Token::ShiftR,
+                        Token::Equals,
+                        Token::NotEquals,
+                        Token::LessThan,
+                        Token::LessThanEquals,
+                        Token::GreaterThan,
+                        Token::GreaterThanEquals,
+                        Token::Not,
+                        Token::BitNot,
+                        Token::LSquareB,
+                    ],
+                    token: t,
+                    raw: self.current_slice.into(),
+                    span: self.current_span.clone(),
+                })
+            }
+        };
+        self.bump();
+        let end = self.last_span.end as u32;
+        Ok(self.node_at(start, end, operator.method_name()))
     }
 
-    fn formal_parameter_list(&mut self) -> Result<NodeList<'ast, Parameter<'ast>>> {
+    fn generic_parameter_list(&mut self) -> Result<IdentifierList<'ast>> {
+        let generic_parameters = GrowableList::new();
+        if self.eat(Token::LessThan) {
+            while self.current_token != Token::GreaterThan && self.current_token != Token::EndOfFile
+            {
+                generic_parameters.push(self.arena, self.identifier_node()?);
+                self.expect_one_of(&[Token::Comma], &[Token::GreaterThan]);
+            }
+            self.expect(Token::GreaterThan);
+        }
+        Ok(generic_parameters.as_list())
+    }
+
+    /// Parses the parameters between a function's parentheses. A leading `this`
+    /// is returned separately as the receiver flag.
+    fn formal_parameter_list(
+        &mut self,
+    ) -> Result<(Option<FlagNode<'ast>>, NodeList<'ast, Parameter<'ast>>)> {
+        let receiver = self.allow_flag_node(Token::This);
+        if receiver.is_some() {
+            self.expect_one_of(&[Token::Comma], &[Token::RParen]);
+        }
         let param_list = GrowableList::new();
         while self.current_token != Token::RParen && self.current_token != Token::EndOfFile {
             let identifier = self.identifier_node()?;
@@ -150,26 +274,19 @@ impl<'ast> Parser<'ast> {
             );
             self.expect_one_of(&[Token::Comma], &[Token::RParen]);
         }
-        Ok(param_list.as_list())
+        Ok((receiver, param_list.as_list()))
     }
 
     fn struct_declarator(&mut self) -> Result<Declarator<'ast>> {
         let _start = self.start_then_advance();
         let identifier = self.identifier_node()?;
-        let generic_parameters = GrowableList::new();
-        if self.eat(Token::LessThan) {
-            while self.current_token != Token::GreaterThan {
-                generic_parameters.push(self.arena, self.identifier_node()?);
-                self.expect_one_of(&[Token::Comma], &[Token::GreaterThan]);
-            }
-            self.expect(Token::GreaterThan);
-        }
+        let generic_parameters = self.generic_parameter_list()?;
         self.expect(Token::LCurlyB);
         let members = self.struct_member_list()?;
         self.expect(Token::RCurlyB);
         Ok(StructDeclarator {
             identifier,
-            generic_parameters: generic_parameters.as_list(),
+            generic_parameters,
             members,
         }
         .into())
@@ -281,6 +398,77 @@ impl<'ast> Parser<'ast> {
 
         Ok(member_list.as_list())
     }
+
+    fn interface_declarator(&mut self) -> Result<Declarator<'ast>> {
+        let _start = self.start_then_advance();
+        let identifier = self.identifier_node()?;
+        let generic_parameters = self.generic_parameter_list()?;
+        self.expect(Token::LCurlyB);
+        let methods = GrowableList::new();
+        while self.current_token != Token::RCurlyB && self.current_token != Token::EndOfFile {
+            let start = self.loc().0;
+            self.expect(Token::Function);
+            let function_name = self.function_name_node()?;
+            let method_generics = self.generic_parameter_list()?;
+            self.expect(Token::LParen);
+            let (receiver, parameters) = self.formal_parameter_list()?;
+            self.expect(Token::RParen);
+            let mut end = self.last_span.end as u32;
+            let return_type = if self.eat(Token::Arrow) {
+                let return_type = self.type_node()?;
+                end = return_type.end;
+                Some(return_type)
+            } else {
+                None
+            };
+            self.eat(Token::Semicolon);
+            methods.push(
+                self.arena,
+                self.node_at(
+                    start,
+                    end,
+                    InterfaceMethod {
+                        function_name,
+                        generic_parameters: method_generics,
+                        receiver,
+                        parameters,
+                        return_type,
+                    },
+                ),
+            );
+        }
+        self.expect(Token::RCurlyB);
+        Ok(InterfaceDeclarator {
+            identifier,
+            generic_parameters,
+            methods: methods.as_list(),
+        }
+        .into())
+    }
+
+    fn impl_declarator(&mut self) -> Result<Declarator<'ast>> {
+        let _start = self.start_then_advance();
+        let generic_parameters = self.generic_parameter_list()?;
+        let interface = self.type_node()?;
+        self.expect(Token::For);
+        let implementor = self.type_node()?;
+        self.expect(Token::LCurlyB);
+        let methods = GrowableList::new();
+        while self.current_token != Token::RCurlyB && self.current_token != Token::EndOfFile {
+            let start = self.loc().0;
+            let method = self.function_definition()?;
+            let end = method.block.end;
+            methods.push(self.arena, self.node_at(start, end, method));
+        }
+        self.expect(Token::RCurlyB);
+        Ok(ImplDeclarator {
+            generic_parameters,
+            interface,
+            implementor,
+            methods: methods.as_list(),
+        }
+        .into())
+    }
 }
 
 #[cfg(test)]
@@ -369,6 +557,26 @@ mod tests {
         assert_debug_snapshot!(res);
     }
 
+    #[test]
+    fn test_method_declarator() {
+        let source = "function Tree<K, V>::get(this, key: K) -> V {}";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.declaration_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_operator_declarator() {
+        let source = "function Vector2::operator []=(this, index: int, value: float) {}";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.declaration_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
     #[test]
     fn test_struct_declarator() {
         let source = "struct Vector2 { x: f32 = 0; y: f32 = 0; }";
@@ -418,4 +626,24 @@ mod tests {
 
         assert_debug_snapshot!(res);
     }
+
+    #[test]
+    fn test_interface_declarator() {
+        let source = "interface IDrawable { function draw(this, scale: float) -> bool; }";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.declaration_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_impl_declarator() {
+        let source = "impl IDrawable for Circle { function draw(this, scale: float) -> bool {} }";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.declaration_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
 }
@@ -2,6 +2,7 @@ use crate::syntax::ast::*;
 use crate::syntax::error::*;
 use crate::syntax::lexer::Token;
 use crate::syntax::parser::Parser;
+use toolshed::list::GrowableList;
 
 // Pratt parsing! Note that if the return of .0 < .1, the operator will be left-associative,
 // and if .0 > .1, the operator will be right-associative.
@@ -96,16 +97,41 @@ impl<'ast> Parser<'ast> {
         let mut lhs: ExpressionNode<'ast> = match self.current_token {
             Token::Ident => {
                 let identifier = self.identifier_node()?;
+                if self.eat(Token::DoubleColon) {
+                    let member = self.identifier_node()?;
+                    self.node_at(
+                        identifier.start,
+                        member.end,
+                        ScopedExpression {
+                            scope: identifier,
+                            member,
+                        },
+                    )
+                } else {
+                    self.node_at(identifier.start, identifier.end, identifier)
+                }
+            }
+            Token::This => {
+                let identifier: IdentifierNode<'ast> = self.node_from_slice(|slice| slice);
                 self.node_at(identifier.start, identifier.end, identifier)
             }
             Token::Integer(_) => self.node_from_slice(Primitive::DecimalNumber),
             Token::Bool(b) => self.node_at_token(Primitive::Bool(b)),
+            Token::LiteralString => self.node_from_slice(Primitive::String),
+            Token::LiteralChar => self.node_from_slice(Primitive::Char),
+            Token::Null => self.node_at_token(Primitive::Null),
             Token::LParen => {
                 self.bump();
                 let lhs = self.expression_bp(0)?;
                 self.expect(Token::RParen);
                 lhs
             }
+            Token::Try => {
+                let start = self.loc().0;
+                self.bump();
+                let operand = self.expression_bp(29)?;
+                self.node_at(start, operand.end, TryExpression { operand })
+            }
             t => match prefix_binding_power(t) {
                 Some(((), r_bp)) => {
                     let start = self.start_then_advance();
@@ -119,7 +145,13 @@ impl<'ast> Parser<'ast> {
                         },
                     )
                 }
-                None => unimplemented!(),
+                None => {
+                    return Err(Error::ExpectedExpressionButGot {
+                        token: t,
+                        raw: self.current_slice.into(),
+                        span: self.current_span.clone(),
+                    })
+                }
             },
         };
 
@@ -148,9 +180,43 @@ impl<'ast> Parser<'ast> {
                         operand: lhs,
                         operator: PostfixOperator::NullForgiving,
                     }),
-                    Token::LParen => unimplemented!(), // function call
-                    Token::LSquareB => unimplemented!(), // index
-                    Token::Dot => unimplemented!(),    // member access
+                    Token::LParen => {
+                        let arguments = GrowableList::new();
+                        while self.current_token != Token::RParen
+                            && self.current_token != Token::EndOfFile
+                        {
+                            arguments.push(self.arena, self.expression_node()?);
+                            self.expect_one_of(&[Token::Comma], &[Token::RParen]);
+                        }
+                        self.expect(Token::RParen);
+                        let end = self.last_span.end as u32;
+                        self.node_at(
+                            lhs.start,
+                            end,
+                            CallExpression {
+                                callee: lhs,
+                                arguments: arguments.as_list(),
+                            },
+                        )
+                    }
+                    Token::LSquareB => {
+                        let index = self.expression_node()?;
+                        self.expect(Token::RSquareB);
+                        let end = self.last_span.end as u32;
+                        self.node_at(lhs.start, end, IndexExpression { array: lhs, index })
+                    }
+                    Token::Dot => {
+                        let member = self.identifier_node()?;
+                        self.node_at(
+                            lhs.start,
+                            member.end,
+                            MemberAccessExpression {
+                                object: lhs,
+                                null_condition: false,
+                                member,
+                            },
+                        )
+                    }
                     Token::NullConditional => self.node_at_token(PostfixExpression {
                         operand: lhs,
                         operator: PostfixOperator::NullConditional,
@@ -622,19 +688,15 @@ impl<'ast> Parser<'ast> {
                     }
                     // Casting, null coalesce
                     Token::As => {
-                        let operator = if self.eat(Token::Not) {
-                            BinaryOperator::ForcedCast
-                        } else {
-                            BinaryOperator::Cast
-                        };
-                        let rhs = self.expression_bp(r_bp)?;
+                        let forced = self.eat(Token::Not);
+                        let cast_to = self.type_node()?;
                         self.node_at(
                             lhs.start,
-                            rhs.end,
-                            BinaryExpression {
+                            cast_to.end,
+                            CastExpression {
                                 left: lhs,
-                                operator,
-                                right: rhs,
+                                forced,
+                                cast_to,
                             },
                         )
                     }
@@ -677,6 +739,56 @@ mod tests {
         assert_debug_snapshot!(res);
     }
 
+    #[test]
+    fn test_cast_expression() {
+        let source = "circle as! *IDrawable";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.expression_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_method_call_expression() {
+        let source = "v1.scaled()";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.expression_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_static_call_expression() {
+        let source = "Vector3::dot(v1, this)";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.expression_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_try_expression() {
+        let source = "try parse(input) + 1";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.expression_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_index_expression() {
+        let source = "values[i + 1]";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.expression_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
     #[test]
     fn test_ternary_associativity() {
         let source = "1 == 1 ? 2 : 3 == 4 ? 5 : 6";
@@ -686,4 +798,14 @@ mod tests {
 
         assert_debug_snapshot!(res);
     }
+
+    #[test]
+    fn test_pointer_expression() {
+        let source = "*@x";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.expression_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
 }
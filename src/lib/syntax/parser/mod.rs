@@ -3,6 +3,7 @@ mod block;
 mod declaration;
 mod expression;
 mod module;
+mod pattern;
 mod primitive;
 mod source;
 mod statement;
@@ -289,3 +290,19 @@ pub fn parse<'src, 'ast>(source: &'src str) -> std::result::Result<Program<'ast>
         _ => Err(errors),
     }
 }
+
+/// Parses `source` as a single expression, allocated in `arena`.
+pub fn parse_expression<'ast>(
+    source: &str,
+    arena: &'ast Arena,
+) -> std::result::Result<ExpressionNode<'ast>, Vec<Error>> {
+    let mut parser = Parser::new(source, arena);
+    let expression = parser.expression_node();
+    parser.expect_eof();
+
+    match expression {
+        Ok(expression) if parser.errors.is_empty() => Ok(expression),
+        Ok(_) => Err(parser.errors),
+        Err(e) => Err(vec![e]),
+    }
+}
@@ -62,10 +62,27 @@ impl<'ast> Parser<'ast> {
     }
 
     pub fn export(&mut self) -> Result<ExportNode<'ast>> {
-        let start = self.start_then_advance();
-        if is_declaration_starter(self.current_token) {
+        let start = self.loc().0;
+        let attributes = self.attribute_list()?;
+        self.attributed_export(start, attributes)
+    }
+
+    /// Parses an export whose attributes, starting at `start`, have already
+    /// been parsed. Only declarations can have attributes.
+    pub fn attributed_export(
+        &mut self,
+        start: u32,
+        attributes: AttributeList<'ast>,
+    ) -> Result<ExportNode<'ast>> {
+        let export_start = self.start_then_advance();
+        let start = match attributes.is_empty() {
+            true => export_start,
+            false => start,
+        };
+        if is_declaration_starter(self.current_token) || !attributes.is_empty() {
             // Export declaration
-            let declaration = self.declaration_node()?;
+            let declaration_start = self.loc().0;
+            let declaration = self.attributed_declaration_node(declaration_start, attributes)?;
             Ok(self.node_at(start, declaration.end, declaration))
         } else if self.current_token == Token::LCurlyB || self.current_token == Token::Mul {
             // Re-export
@@ -103,7 +120,15 @@ impl<'ast> Parser<'ast> {
 pub fn is_declaration_starter(t: Token) -> bool {
     matches!(
         t,
-        Token::Const | Token::Type | Token::Let | Token::Function | Token::Struct | Token::Enum
+        Token::Const
+            | Token::Type
+            | Token::Let
+            | Token::Function
+            | Token::Struct
+            | Token::Enum
+            | Token::Interface
+            | Token::Impl
+            | Token::LAttr
     )
 }
 
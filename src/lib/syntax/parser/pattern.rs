@@ -0,0 +1,149 @@
+use crate::syntax::ast::*;
+use crate::syntax::error::*;
+use crate::syntax::lexer::Token;
+use crate::syntax::parser::Parser;
+
+const LITERAL_PATTERN_STARTERS: &[Token] = &[
+    Token::Integer(0),
+    Token::Bool(false),
+    Token::LiteralChar,
+    Token::LiteralString,
+    Token::Null,
+    Token::Sub,
+];
+
+impl<'ast> Parser<'ast> {
+    pub fn pattern_node(&mut self) -> Result<PatternNode<'ast>> {
+        match self.current_token {
+            Token::Ident if self.current_slice == "_" && self.peek_token != Token::Colon => {
+                Ok(self.node_at_token(Pattern::Wildcard))
+            }
+            Token::Ident if self.peek_token == Token::Colon => {
+                let binding = self.identifier_node()?;
+                self.expect(Token::Colon);
+                let type_expression = self.type_node()?;
+                Ok(self.node_at(
+                    binding.start,
+                    type_expression.end,
+                    TypePattern {
+                        binding,
+                        type_expression,
+                    },
+                ))
+            }
+            Token::Ident => {
+                let enumeration = self.identifier_node()?;
+                self.expect(Token::Dot);
+                let variant = self.identifier_node()?;
+                Ok(self.node_at(
+                    enumeration.start,
+                    variant.end,
+                    EnumVariantPattern {
+                        enumeration: Some(enumeration),
+                        variant,
+                    },
+                ))
+            }
+            Token::Dot => {
+                let start = self.loc().0;
+                self.bump();
+                let variant = self.identifier_node()?;
+                Ok(self.node_at(
+                    start,
+                    variant.end,
+                    EnumVariantPattern {
+                        enumeration: None,
+                        variant,
+                    },
+                ))
+            }
+            _ => {
+                let start = self.literal_pattern_node()?;
+                let inclusive = match self.current_token {
+                    Token::DotDot => false,
+                    Token::DotDotDot => true,
+                    _ => return Ok(self.node_at(start.start, start.end, start.value)),
+                };
+                self.bump();
+                let end = self.literal_pattern_node()?;
+                Ok(self.node_at(
+                    start.start,
+                    end.end,
+                    RangePattern {
+                        start,
+                        end,
+                        inclusive,
+                    },
+                ))
+            }
+        }
+    }
+
+    fn literal_pattern_node(&mut self) -> Result<LiteralPatternNode<'ast>> {
+        let start = self.loc().0;
+        let negative = self.eat(Token::Sub);
+        let value = match self.current_token {
+            Token::Integer(_) => Primitive::DecimalNumber(self.current_slice),
+            Token::Bool(b) => Primitive::Bool(b),
+            Token::LiteralChar => Primitive::Char(self.current_slice),
+            Token::LiteralString => Primitive::String(self.current_slice),
+            Token::Null => Primitive::Null,
+            t => {
+                let mut expected_tokens = LITERAL_PATTERN_STARTERS.to_vec();
+                expected_tokens.extend(&[Token::Ident, Token::Dot]);
+                return Err(Error::ExpectedOneOfButGot {
+                    expected_tokens,
+                    token: t,
+                    raw: self.current_slice.into(),
+                    span: self.current_span.clone(),
+                });
+            }
+        };
+        let end = self.loc().1;
+        self.bump();
+        Ok(self.node_at(start, end, LiteralPattern { negative, value }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+    use toolshed::Arena;
+
+    #[test]
+    fn test_range_pattern() {
+        let source = "-5...'z'";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.pattern_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_enum_variant_patterns() {
+        let source = ".Left";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.pattern_node().unwrap();
+
+        assert_debug_snapshot!(res);
+
+        let source = "Alignment.Left";
+        let mut p = Parser::new(source, &arena);
+        let res = p.pattern_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_type_pattern() {
+        let source = "x: int | bool";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.pattern_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+}
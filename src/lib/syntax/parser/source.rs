@@ -3,6 +3,7 @@ use toolshed::list::GrowableList;
 use crate::syntax::ast::*;
 use crate::syntax::error::Result;
 use crate::syntax::lexer::Token;
+use crate::syntax::parser::module::is_declaration_starter;
 use crate::syntax::parser::Parser;
 
 impl<'ast> Parser<'ast> {
@@ -16,25 +17,20 @@ impl<'ast> Parser<'ast> {
                 Token::EndOfFile => break,
                 Token::Semicolon => self.bump(),
                 Token::Export => {
-                    // TODO: =BUG= If this is an export declaration, it might have attributes before it.
                     is_script = false;
-                    let res = self.module_element_node();
-                    if let Ok(element_node) = res {
-                        elements.push(self.arena, element_node);
+                    self.push_module_element(&elements);
+                }
+                Token::LAttr => {
+                    if self.push_module_element(&elements) {
+                        is_script = false;
                     }
                 }
                 Token::Import => {
                     is_script = false;
-                    let res = self.module_element_node();
-                    if let Ok(element_node) = res {
-                        elements.push(self.arena, element_node);
-                    }
+                    self.push_module_element(&elements);
                 }
                 _ => {
-                    let res = self.module_element_node();
-                    if let Ok(element_node) = res {
-                        elements.push(self.arena, element_node);
-                    }
+                    self.push_module_element(&elements);
                 }
             }
         }
@@ -50,6 +46,29 @@ impl<'ast> Parser<'ast> {
         )
     }
 
+    /// Parses a module element, recording the error and skipping a token if
+    /// it cannot be parsed so the rest of the file is still checked. Returns
+    /// whether the element was an export.
+    fn push_module_element(
+        &mut self,
+        elements: &GrowableList<'ast, Node<'ast, ModuleElement<'ast>>>,
+    ) -> bool {
+        let start = self.current_span.start;
+        match self.module_element_node() {
+            Ok(element_node) => {
+                elements.push(self.arena, element_node);
+                matches!(element_node.value, ModuleElement::Export(_))
+            }
+            Err(e) => {
+                self.errors.push(e);
+                if self.current_span.start == start {
+                    self.bump();
+                }
+                false
+            }
+        }
+    }
+
     fn module_element_node(&mut self) -> Result<Node<'ast, ModuleElement<'ast>>> {
         let (start, end, element): (u32, u32, ModuleElement) = match self.current_token {
             Token::Export => {
@@ -60,12 +79,56 @@ impl<'ast> Parser<'ast> {
                 let element = self.import()?;
                 (element.start, element.end, element.into())
             }
-            _ => {
+            Token::LAttr => {
+                let start = self.loc().0;
+                let attributes = self.attribute_list()?;
+                if self.current_token == Token::Export {
+                    let element = self.attributed_export(start, attributes)?;
+                    (element.start, element.end, element.into())
+                } else {
+                    let element = self.attributed_declaration_node(start, attributes)?;
+                    (element.start, element.end, element.into())
+                }
+            }
+            t if is_declaration_starter(t) => {
                 let element = self.declaration_node()?;
                 (element.start, element.end, element.into())
             }
+            _ => {
+                let element = self.statement_node()?;
+                (element.start, element.end, element.into())
+            }
         };
 
         Ok(self.node_at(start, end, element))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+    use toolshed::Arena;
+
+    #[test]
+    fn test_script_statements() {
+        let source = "let total = 0;\nfor (i in 1...3) { total += i; }\nprint(\"done\");";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.source_unit();
+
+        assert!(p.errors.is_empty());
+        assert_debug_snapshot!(res);
+    }
+
+    #[test]
+    fn test_module_is_not_script() {
+        let source = "import * as io from \"io\";\nfunction main() {}";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let SourceUnit::Module(module) = p.source_unit().value;
+
+        assert!(p.errors.is_empty());
+        assert!(!module.is_script);
+    }
+}
@@ -2,12 +2,14 @@ use crate::syntax::ast::*;
 use crate::syntax::error::*;
 use crate::syntax::lexer::Token;
 use crate::syntax::parser::Parser;
+use toolshed::list::GrowableList;
 
 impl<'ast> Parser<'ast> {
     pub fn statement_node(&mut self) -> Result<StatementNode<'ast>> {
         match self.current_token {
             Token::LCurlyB => self.inner_block(),
             Token::If => self.if_statement(),
+            Token::Match => self.match_statement(),
             Token::While | Token::Do | Token::For | Token::Loop => self.loop_statement(),
             Token::Break | Token::Continue | Token::Return => self.jump_statement(),
             Token::Delete => self.delete_statement(),
@@ -45,6 +47,50 @@ impl<'ast> Parser<'ast> {
         ))
     }
 
+    fn match_statement(&mut self) -> Result<StatementNode<'ast>> {
+        let start = self.loc().0;
+        self.bump();
+        self.expect(Token::LParen);
+        let scrutinee = self.expression_node()?;
+        self.expect(Token::RParen);
+        self.expect(Token::LCurlyB);
+        let arms = GrowableList::new();
+        while self.current_token != Token::RCurlyB && self.current_token != Token::EndOfFile {
+            arms.push(self.arena, self.match_arm_node()?);
+        }
+        self.expect(Token::RCurlyB);
+        let end = self.last_span.end as u32;
+        Ok(self.node_at(
+            start,
+            end,
+            MatchStatement {
+                scrutinee,
+                arms: arms.as_list(),
+            },
+        ))
+    }
+
+    fn match_arm_node(&mut self) -> Result<MatchArmNode<'ast>> {
+        let pattern = self.pattern_node()?;
+        let guard = if self.eat(Token::If) {
+            Some(self.expression_node()?)
+        } else {
+            None
+        };
+        self.expect(Token::FatArrow);
+        let body = self.statement_node()?;
+        self.eat(Token::Comma);
+        Ok(self.node_at(
+            pattern.start,
+            body.end,
+            MatchArm {
+                pattern,
+                guard,
+                body,
+            },
+        ))
+    }
+
     fn loop_statement(&mut self) -> Result<StatementNode<'ast>> {
         match self.current_token {
             Token::For => {
@@ -199,6 +245,16 @@ mod tests {
         assert_debug_snapshot!(res);
     }
 
+    #[test]
+    fn test_match_statement() {
+        let source = "match (x) {\n  0 => y = 1;\n  1..10 if y > 2 => { }\n  _ => y = 0;\n}";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.statement_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
     #[test]
     fn test_for_loop_statement() {
         let source = "for (x in 0..3) { }";
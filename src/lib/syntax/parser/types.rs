@@ -68,7 +68,13 @@ impl<'ast> Parser<'ast> {
                             },
                         ))
                     }
-                    _ => unimplemented!(),
+                    _ => {
+                        // fixed-size type
+                        let size = self.expression_node()?;
+                        self.expect(Token::RSquareB);
+                        let inner = self.unary_type()?;
+                        Ok(self.node_at(start, inner.end, ArrayTypeExpression { size, inner }))
+                    }
                 }
             }
             Token::Const => {
@@ -229,6 +235,7 @@ impl<'ast> Parser<'ast> {
                         && self.current_token != Token::EndOfFile
                     {
                         generic_parameters.push(self.arena, self.type_node()?);
+                        self.expect_one_of(&[Token::Comma], &[Token::GreaterThan]);
                     }
                     end = self.expect_end(Token::GreaterThan);
                 }
@@ -281,6 +288,16 @@ mod tests {
         assert_debug_snapshot!(res);
     }
 
+    #[test]
+    fn test_fixed_array_type() {
+        let source = "[SIZE * 2]float";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.type_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
+
     #[test]
     fn test_unsized_array_type() {
         let source = "[..]float";
@@ -320,4 +337,14 @@ mod tests {
 
         assert_debug_snapshot!(res);
     }
+
+    #[test]
+    fn test_interface_pointer_type() {
+        let source = "[]*ICollection<int, float>";
+        let arena = Arena::new();
+        let mut p = Parser::new(source, &arena);
+        let res = p.type_node().unwrap();
+
+        assert_debug_snapshot!(res);
+    }
 }